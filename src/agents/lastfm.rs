@@ -0,0 +1,474 @@
+//! Last.fm metadata agent and scrobbler.
+//!
+//! Uses the `artist.getInfo` and `album.getInfo` methods of the Last.fm web
+//! service API for metadata, and `auth.getSession`, `track.updateNowPlaying`
+//! and `track.scrobble` for scrobbling. The base URL is configurable, so any
+//! server implementing those methods can stand in for Last.fm.
+
+use chrono::NaiveDateTime;
+use md5::{Digest, Md5};
+use serde_json::Value;
+
+use super::scrobbling::{LinkedAccount, Scrobbler, SubmitFailure, classify_http_error};
+use super::{AgentError, AgentFuture, MetadataAgent, http_client};
+use crate::models::metadata::{AlbumMetadata, ArtistMetadata};
+use crate::models::scrobbling::{ScrobbleService, ScrobbleTrack};
+
+/// Last.fm web service API endpoint.
+pub const DEFAULT_BASE_URL: &str = "https://ws.audioscrobbler.com/2.0/";
+
+/// Error code Last.fm returns for unknown artists and albums.
+const ERROR_NOT_FOUND: i64 = 6;
+
+/// Last.fm's placeholder image, returned for artists without a picture.
+const PLACEHOLDER_IMAGE: &str = "2a96cbd8b46e442fc41c2b86b821562f";
+
+/// Metadata agent for the Last.fm API.
+pub struct LastFmAgent {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: String,
+}
+
+impl LastFmAgent {
+    /// Create a Last.fm agent with the given API key.
+    pub fn new(api_key: impl Into<String>) -> Self {
+        Self {
+            client: http_client(),
+            base_url: DEFAULT_BASE_URL.to_string(),
+            api_key: api_key.into(),
+        }
+    }
+
+    /// Use a different API endpoint.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Call an API method, returning `None` if the item is unknown.
+    async fn call(&self, params: &[(&str, &str)]) -> Result<Option<Value>, AgentError> {
+        let response = self
+            .client
+            .get(&self.base_url)
+            .query(&[
+                ("api_key", self.api_key.as_str()),
+                ("format", "json"),
+                ("autocorrect", "1"),
+            ])
+            .query(params)
+            .send()
+            .await?;
+
+        match check_response(response).await {
+            Err(AgentError::Api {
+                code: ERROR_NOT_FOUND,
+                ..
+            }) => Ok(None),
+            result => result.map(Some),
+        }
+    }
+
+    /// Call an API method by MusicBrainz ID if there is one, else or if that
+    /// finds nothing by name.
+    async fn lookup(
+        &self,
+        params: &[(&str, &str)],
+        musicbrainz_id: Option<&str>,
+    ) -> Result<Option<Value>, AgentError> {
+        if let Some(mbid) = musicbrainz_id {
+            let mut by_mbid = params.to_vec();
+            by_mbid.push(("mbid", mbid));
+            if let Some(body) = self.call(&by_mbid).await? {
+                return Ok(Some(body));
+            }
+        }
+        self.call(params).await
+    }
+}
+
+impl MetadataAgent for LastFmAgent {
+    fn name(&self) -> &'static str {
+        "lastfm"
+    }
+
+    fn artist_info<'a>(
+        &'a self,
+        name: &'a str,
+        musicbrainz_id: Option<&'a str>,
+    ) -> AgentFuture<'a, Option<ArtistMetadata>> {
+        Box::pin(async move {
+            let params = [("method", "artist.getinfo"), ("artist", name)];
+            let body = self.lookup(&params, musicbrainz_id).await?;
+            Ok(body.as_ref().and_then(parse_artist_info))
+        })
+    }
+
+    fn album_info<'a>(
+        &'a self,
+        artist: &'a str,
+        album: &'a str,
+        musicbrainz_id: Option<&'a str>,
+    ) -> AgentFuture<'a, Option<AlbumMetadata>> {
+        Box::pin(async move {
+            let params = [
+                ("method", "album.getinfo"),
+                ("artist", artist),
+                ("album", album),
+            ];
+            let body = self.lookup(&params, musicbrainz_id).await?;
+            Ok(body.as_ref().and_then(parse_album_info))
+        })
+    }
+}
+
+/// Scrobbler for the Last.fm API.
+///
+/// Unlike lookups, scrobbling calls must be signed with the API secret.
+pub struct LastFmScrobbler {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: String,
+    secret: String,
+}
+
+impl LastFmScrobbler {
+    /// Create a Last.fm scrobbler with the given API key and secret.
+    pub fn new(api_key: impl Into<String>, secret: impl Into<String>) -> Self {
+        Self {
+            client: http_client(),
+            base_url: DEFAULT_BASE_URL.to_string(),
+            api_key: api_key.into(),
+            secret: secret.into(),
+        }
+    }
+
+    /// Use a different API endpoint.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Call a signed API method.
+    async fn call_signed(&self, params: &[(&str, &str)]) -> Result<Value, AgentError> {
+        let mut signed = params.to_vec();
+        signed.push(("api_key", &self.api_key));
+        let signature = sign(&signed, &self.secret);
+
+        let mut form = signed;
+        form.push(("api_sig", &signature));
+        form.push(("format", "json"));
+        let response = self.client.post(&self.base_url).form(&form).send().await?;
+        check_response(response).await
+    }
+
+    /// Call a track method for a user's session.
+    async fn call_track(
+        &self,
+        method: &str,
+        session_key: &str,
+        track: &ScrobbleTrack,
+        played_at: Option<NaiveDateTime>,
+    ) -> Result<(), AgentError> {
+        let duration = track.duration.to_string();
+        let track_number = track.track_number.map(|n| n.to_string());
+        let timestamp = played_at.map(|t| t.and_utc().timestamp().to_string());
+
+        let mut params = vec![
+            ("method", method),
+            ("sk", session_key),
+            ("artist", track.artist.as_str()),
+            ("track", track.title.as_str()),
+        ];
+        if let Some(album) = &track.album {
+            params.push(("album", album));
+        }
+        if track.duration > 0 {
+            params.push(("duration", &duration));
+        }
+        if let Some(track_number) = &track_number {
+            params.push(("trackNumber", track_number));
+        }
+        if let Some(mbid) = &track.musicbrainz_id {
+            params.push(("mbid", mbid));
+        }
+        if let Some(timestamp) = &timestamp {
+            params.push(("timestamp", timestamp));
+        }
+
+        self.call_signed(&params).await?;
+        Ok(())
+    }
+}
+
+impl Scrobbler for LastFmScrobbler {
+    fn service(&self) -> ScrobbleService {
+        ScrobbleService::LastFm
+    }
+
+    fn link<'a>(&'a self, token: &'a str) -> AgentFuture<'a, LinkedAccount> {
+        Box::pin(async move {
+            let body = self
+                .call_signed(&[("method", "auth.getSession"), ("token", token)])
+                .await?;
+            let key = non_empty(body.pointer("/session/key")).ok_or_else(|| AgentError::Api {
+                code: 0,
+                message: "No session key in response".into(),
+            })?;
+            Ok(LinkedAccount {
+                token: key,
+                username: non_empty(body.pointer("/session/name")),
+            })
+        })
+    }
+
+    fn now_playing<'a>(&'a self, token: &'a str, track: &'a ScrobbleTrack) -> AgentFuture<'a, ()> {
+        Box::pin(self.call_track("track.updateNowPlaying", token, track, None))
+    }
+
+    fn scrobble<'a>(
+        &'a self,
+        token: &'a str,
+        track: &'a ScrobbleTrack,
+        played_at: NaiveDateTime,
+    ) -> AgentFuture<'a, ()> {
+        Box::pin(self.call_track("track.scrobble", token, track, Some(played_at)))
+    }
+
+    fn classify(&self, error: &AgentError) -> SubmitFailure {
+        match error {
+            // Authentication failed or the session key was revoked
+            AgentError::Api { code: 4 | 9, .. } => SubmitFailure::Unauthorized,
+            // Temporary failures, and API key problems that affect every account
+            AgentError::Api {
+                code: 8 | 10 | 11 | 16 | 26 | 29,
+                ..
+            } => SubmitFailure::Retry,
+            AgentError::Api { code: 0..100, .. } => SubmitFailure::Rejected,
+            _ => classify_http_error(error),
+        }
+    }
+}
+
+/// Read an API response, turning Last.fm and HTTP errors into agent errors.
+async fn check_response(response: reqwest::Response) -> Result<Value, AgentError> {
+    let status = response.status();
+    let body: Value = response.json().await?;
+
+    if let Some(code) = body.get("error").and_then(Value::as_i64) {
+        let message = body
+            .get("message")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string();
+        return Err(AgentError::Api { code, message });
+    }
+
+    if !status.is_success() {
+        return Err(AgentError::Api {
+            code: i64::from(status.as_u16()),
+            message: status.to_string(),
+        });
+    }
+
+    Ok(body)
+}
+
+/// Compute the signature of a call: the MD5 hash of its parameters, sorted by
+/// name and concatenated without separators, followed by the API secret.
+fn sign(params: &[(&str, &str)], secret: &str) -> String {
+    let mut sorted = params.to_vec();
+    sorted.sort_by_key(|(name, _)| *name);
+
+    let mut hasher = Md5::new();
+    for (name, value) in sorted {
+        hasher.update(name.as_bytes());
+        hasher.update(value.as_bytes());
+    }
+    hasher.update(secret.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+/// Parse an `artist.getInfo` response.
+fn parse_artist_info(body: &Value) -> Option<ArtistMetadata> {
+    let artist = body.get("artist")?;
+    let (small_image_url, medium_image_url, large_image_url) = parse_images(artist);
+
+    Some(ArtistMetadata {
+        biography: artist
+            .pointer("/bio/summary")
+            .and_then(Value::as_str)
+            .and_then(clean_summary),
+        musicbrainz_id: non_empty(artist.get("mbid")),
+        last_fm_url: non_empty(artist.get("url")),
+        small_image_url,
+        medium_image_url,
+        large_image_url,
+        similar_artists: one_or_many(artist.pointer("/similar/artist"))
+            .filter_map(|similar| non_empty(similar.get("name")))
+            .collect(),
+    })
+}
+
+/// Parse an `album.getInfo` response.
+fn parse_album_info(body: &Value) -> Option<AlbumMetadata> {
+    let album = body.get("album")?;
+    let (small_image_url, medium_image_url, large_image_url) = parse_images(album);
+
+    Some(AlbumMetadata {
+        notes: album
+            .pointer("/wiki/summary")
+            .and_then(Value::as_str)
+            .and_then(clean_summary),
+        musicbrainz_id: non_empty(album.get("mbid")),
+        last_fm_url: non_empty(album.get("url")),
+        small_image_url,
+        medium_image_url,
+        large_image_url,
+    })
+}
+
+/// Get the small, medium and large image URLs, skipping the placeholder image.
+fn parse_images(item: &Value) -> (Option<String>, Option<String>, Option<String>) {
+    let image = |sizes: &[&str]| {
+        sizes.iter().find_map(|size| {
+            one_or_many(item.get("image"))
+                .find(|image| image.get("size").and_then(Value::as_str) == Some(*size))
+                .and_then(|image| non_empty(image.get("#text")))
+                .filter(|url| !url.contains(PLACEHOLDER_IMAGE))
+        })
+    };
+
+    (
+        image(&["small"]),
+        image(&["medium"]),
+        image(&["extralarge", "large"]),
+    )
+}
+
+/// Drop the "Read more on Last.fm" link that ends every summary.
+fn clean_summary(summary: &str) -> Option<String> {
+    let text = summary
+        .split("<a href=\"https://www.last.fm")
+        .next()
+        .unwrap_or_default()
+        .trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// Iterate a JSON value that is an array, a single object, or missing.
+fn one_or_many(value: Option<&Value>) -> impl Iterator<Item = &Value> {
+    let items: &[Value] = match value {
+        Some(Value::Array(items)) => items,
+        Some(item @ Value::Object(_)) => std::slice::from_ref(item),
+        _ => &[],
+    };
+    items.iter()
+}
+
+/// Get a string value, treating empty strings as missing.
+fn non_empty(value: Option<&Value>) -> Option<String> {
+    value
+        .and_then(Value::as_str)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_artist_info() {
+        let body = json!({
+            "artist": {
+                "name": "Artist",
+                "mbid": "b10bbbfc-cf9e-42e0-be17-e2c3e1d2600d",
+                "url": "https://www.last.fm/music/Artist",
+                "image": [
+                    {"#text": "https://lastfm.freetls.fastly.net/i/u/34s/2a96cbd8b46e442fc41c2b86b821562f.png", "size": "small"},
+                    {"#text": "https://example.com/medium.png", "size": "medium"},
+                    {"#text": "https://example.com/large.png", "size": "large"},
+                ],
+                "similar": {"artist": [{"name": "Other"}, {"name": "Another"}]},
+                "bio": {
+                    "summary": "A band. <a href=\"https://www.last.fm/music/Artist\">Read more on Last.fm</a>"
+                }
+            }
+        });
+
+        let info = parse_artist_info(&body).unwrap();
+        assert_eq!(info.biography.as_deref(), Some("A band."));
+        assert_eq!(info.small_image_url, None);
+        assert_eq!(
+            info.medium_image_url.as_deref(),
+            Some("https://example.com/medium.png")
+        );
+        assert_eq!(
+            info.large_image_url.as_deref(),
+            Some("https://example.com/large.png")
+        );
+        assert_eq!(info.similar_artists, vec!["Other", "Another"]);
+    }
+
+    #[test]
+    fn test_parse_album_info() {
+        let body = json!({
+            "album": {
+                "name": "Album",
+                "mbid": "",
+                "image": {"#text": "https://example.com/small.png", "size": "small"},
+                "wiki": {"summary": "Notes"}
+            }
+        });
+
+        let info = parse_album_info(&body).unwrap();
+        assert_eq!(info.notes.as_deref(), Some("Notes"));
+        assert_eq!(info.musicbrainz_id, None);
+        assert_eq!(
+            info.small_image_url.as_deref(),
+            Some("https://example.com/small.png")
+        );
+    }
+
+    #[test]
+    fn test_sign_sorts_params_and_appends_secret() {
+        let params = [
+            ("token", "tok"),
+            ("method", "auth.getSession"),
+            ("api_key", "key"),
+        ];
+        assert_eq!(sign(&params, "secret"), "04e870be4bb79756721b7bc1937fe83d");
+    }
+
+    #[tokio::test]
+    async fn test_lookup_against_stand_in_server() {
+        let app = axum::Router::new().route(
+            "/2.0/",
+            axum::routing::get(
+                |axum::extract::Query(params): axum::extract::Query<
+                    std::collections::HashMap<String, String>,
+                >| async move {
+                    let body = match params.get("artist").map(String::as_str) {
+                        Some("Known") => json!({"artist": {"bio": {"summary": "Bio"}}}),
+                        Some("Broken") => json!({"error": 29, "message": "Rate limit exceeded"}),
+                        _ => json!({"error": 6, "message": "The artist you supplied could not be found"}),
+                    };
+                    axum::Json(body)
+                },
+            ),
+        );
+        let addr = crate::agents::serve_stand_in(app).await;
+
+        let agent = LastFmAgent::new("key").with_base_url(format!("http://{}/2.0/", addr));
+
+        let known = agent.artist_info("Known", None).await.unwrap().unwrap();
+        assert_eq!(known.biography.as_deref(), Some("Bio"));
+        assert!(agent.artist_info("Unknown", None).await.unwrap().is_none());
+        assert!(matches!(
+            agent.artist_info("Broken", None).await,
+            Err(AgentError::Api { code: 29, .. })
+        ));
+    }
+}
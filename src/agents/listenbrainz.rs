@@ -0,0 +1,261 @@
+//! ListenBrainz scrobbler.
+//!
+//! Uses the `validate-token` and `submit-listens` endpoints of the
+//! ListenBrainz API, authenticated with each user's own token. The base URL
+//! is configurable, so any server implementing those endpoints can stand in
+//! for ListenBrainz.
+
+use chrono::NaiveDateTime;
+use serde_json::{Value, json};
+
+use super::scrobbling::{LinkedAccount, Scrobbler};
+use super::{AgentError, AgentFuture, http_client};
+use crate::models::scrobbling::{ScrobbleService, ScrobbleTrack};
+
+/// ListenBrainz API endpoint.
+pub const DEFAULT_BASE_URL: &str = "https://api.listenbrainz.org/";
+
+/// Scrobbler for the ListenBrainz API.
+pub struct ListenBrainzScrobbler {
+    client: reqwest::Client,
+    base_url: String,
+}
+
+impl ListenBrainzScrobbler {
+    /// Create a ListenBrainz scrobbler.
+    pub fn new() -> Self {
+        Self {
+            client: http_client(),
+            base_url: DEFAULT_BASE_URL.to_string(),
+        }
+    }
+
+    /// Use a different API endpoint.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    fn url(&self, endpoint: &str) -> String {
+        format!("{}/1/{}", self.base_url.trim_end_matches('/'), endpoint)
+    }
+
+    /// Submit a listen of the given type ("single" or "playing_now").
+    async fn submit(
+        &self,
+        token: &str,
+        listen_type: &str,
+        listen: Value,
+    ) -> Result<(), AgentError> {
+        let response = self
+            .client
+            .post(self.url("submit-listens"))
+            .header("Authorization", format!("Token {}", token))
+            .json(&json!({ "listen_type": listen_type, "payload": [listen] }))
+            .send()
+            .await?;
+        check_response(response).await?;
+        Ok(())
+    }
+}
+
+impl Default for ListenBrainzScrobbler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Scrobbler for ListenBrainzScrobbler {
+    fn service(&self) -> ScrobbleService {
+        ScrobbleService::ListenBrainz
+    }
+
+    fn link<'a>(&'a self, token: &'a str) -> AgentFuture<'a, LinkedAccount> {
+        Box::pin(async move {
+            let response = self
+                .client
+                .get(self.url("validate-token"))
+                .header("Authorization", format!("Token {}", token))
+                .send()
+                .await?;
+            let body = check_response(response).await?;
+
+            if body.get("valid").and_then(Value::as_bool) != Some(true) {
+                return Err(AgentError::Api {
+                    code: 401,
+                    message: "Invalid user token".into(),
+                });
+            }
+            Ok(LinkedAccount {
+                token: token.to_string(),
+                username: body
+                    .get("user_name")
+                    .and_then(Value::as_str)
+                    .map(str::to_string),
+            })
+        })
+    }
+
+    fn now_playing<'a>(&'a self, token: &'a str, track: &'a ScrobbleTrack) -> AgentFuture<'a, ()> {
+        Box::pin(self.submit(token, "playing_now", listen(track, None)))
+    }
+
+    fn scrobble<'a>(
+        &'a self,
+        token: &'a str,
+        track: &'a ScrobbleTrack,
+        played_at: NaiveDateTime,
+    ) -> AgentFuture<'a, ()> {
+        Box::pin(self.submit(token, "single", listen(track, Some(played_at))))
+    }
+}
+
+/// Build a listen, with its time unless it is playing now.
+fn listen(track: &ScrobbleTrack, played_at: Option<NaiveDateTime>) -> Value {
+    let mut additional_info = json!({
+        "submission_client": "subsonic-rs",
+        "submission_client_version": env!("CARGO_PKG_VERSION"),
+    });
+    if track.duration > 0 {
+        additional_info["duration_ms"] = json!(i64::from(track.duration) * 1000);
+    }
+    if let Some(track_number) = track.track_number {
+        additional_info["tracknumber"] = json!(track_number);
+    }
+    if let Some(mbid) = &track.musicbrainz_id {
+        additional_info["recording_mbid"] = json!(mbid);
+    }
+
+    let mut track_metadata = json!({
+        "artist_name": track.artist,
+        "track_name": track.title,
+        "additional_info": additional_info,
+    });
+    if let Some(album) = &track.album {
+        track_metadata["release_name"] = json!(album);
+    }
+
+    let mut listen = json!({ "track_metadata": track_metadata });
+    if let Some(played_at) = played_at {
+        listen["listened_at"] = json!(played_at.and_utc().timestamp());
+    }
+    listen
+}
+
+/// Read an API response, turning HTTP errors into agent errors.
+async fn check_response(response: reqwest::Response) -> Result<Value, AgentError> {
+    let status = response.status();
+    let body: Value = response.json().await.unwrap_or(Value::Null);
+
+    if !status.is_success() {
+        let message = body
+            .get("error")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .unwrap_or_else(|| status.to_string());
+        return Err(AgentError::Api {
+            code: i64::from(status.as_u16()),
+            message,
+        });
+    }
+
+    Ok(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use axum::http::HeaderMap;
+
+    use super::*;
+
+    fn track() -> ScrobbleTrack {
+        ScrobbleTrack {
+            artist: "Artist".into(),
+            title: "Title".into(),
+            album: None,
+            duration: 200,
+            track_number: Some(3),
+            musicbrainz_id: None,
+        }
+    }
+
+    #[test]
+    fn test_listen_payload() {
+        let played_at = chrono::DateTime::from_timestamp(1_700_000_000, 0)
+            .unwrap()
+            .naive_utc();
+
+        let listen = listen(&track(), Some(played_at));
+        assert_eq!(listen["listened_at"], 1_700_000_000);
+        assert_eq!(listen["track_metadata"]["artist_name"], "Artist");
+        assert_eq!(listen["track_metadata"]["track_name"], "Title");
+        assert_eq!(
+            listen["track_metadata"]["additional_info"]["duration_ms"],
+            200_000
+        );
+        assert!(listen["track_metadata"].get("release_name").is_none());
+
+        // Now playing listens have no time
+        assert!(super::listen(&track(), None).get("listened_at").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_submit_against_stand_in_server() {
+        let submitted = Arc::new(Mutex::new(Vec::new()));
+        let app = axum::Router::new()
+            .route(
+                "/1/validate-token",
+                axum::routing::get(|headers: HeaderMap| async move {
+                    let valid = headers.get("Authorization").unwrap() == "Token good";
+                    axum::Json(json!({"code": 200, "valid": valid, "user_name": "listener"}))
+                }),
+            )
+            .route(
+                "/1/submit-listens",
+                axum::routing::post({
+                    let submitted = submitted.clone();
+                    move |headers: HeaderMap, axum::Json(body): axum::Json<Value>| async move {
+                        if headers.get("Authorization").unwrap() != "Token good" {
+                            return (
+                                axum::http::StatusCode::UNAUTHORIZED,
+                                axum::Json(
+                                    json!({"code": 401, "error": "Invalid authorization token."}),
+                                ),
+                            );
+                        }
+                        submitted.lock().unwrap().push(body);
+                        (
+                            axum::http::StatusCode::OK,
+                            axum::Json(json!({"status": "ok"})),
+                        )
+                    }
+                }),
+            );
+        let addr = crate::agents::serve_stand_in(app).await;
+
+        let scrobbler = ListenBrainzScrobbler::new().with_base_url(format!("http://{}/", addr));
+
+        let linked = scrobbler.link("good").await.unwrap();
+        assert_eq!(linked.token, "good");
+        assert_eq!(linked.username.as_deref(), Some("listener"));
+        assert!(scrobbler.link("bad").await.is_err());
+
+        let played_at = chrono::Utc::now().naive_utc();
+        scrobbler.now_playing("good", &track()).await.unwrap();
+        scrobbler
+            .scrobble("good", &track(), played_at)
+            .await
+            .unwrap();
+        assert!(matches!(
+            scrobbler.scrobble("bad", &track(), played_at).await,
+            Err(AgentError::Api { code: 401, .. })
+        ));
+
+        let submitted = submitted.lock().unwrap();
+        assert_eq!(submitted.len(), 2);
+        assert_eq!(submitted[0]["listen_type"], "playing_now");
+        assert_eq!(submitted[1]["listen_type"], "single");
+    }
+}
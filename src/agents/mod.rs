@@ -0,0 +1,284 @@
+//! External metadata agents.
+//!
+//! Agents look up artist biographies, similar artists, images and album notes
+//! from online services. Results are cached in the database and refreshed in
+//! the background, so a slow or unreachable agent never holds up a request.
+//! Similar artists and song recommendations are also derived from the server's
+//! own listening history. Plays can also be forwarded to the scrobbling
+//! services users have linked accounts on, and internet radio playlist links
+//! are resolved to the streams they list. Podcast feeds are fetched and their
+//! episodes downloaded here too.
+
+pub mod lastfm;
+pub mod listenbrainz;
+pub mod podcast;
+pub mod radio;
+pub mod recommend;
+pub mod scrobbling;
+pub mod similarity;
+
+use std::collections::HashSet;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+use chrono::{Duration, Utc};
+use thiserror::Error;
+
+use crate::db::{DbPool, MetadataCacheRepository};
+use crate::models::metadata::{AlbumMetadata, ArtistMetadata};
+use crate::models::music::{Album, Artist};
+
+pub use lastfm::{LastFmAgent, LastFmScrobbler};
+pub use listenbrainz::ListenBrainzScrobbler;
+pub use podcast::{PodcastService, PodcastServiceHandle};
+pub use recommend::Recommender;
+pub use scrobbling::{ScrobbleForwarder, ScrobbleForwarderHandle, Scrobbler};
+pub use similarity::{SimilarityRefreshHandle, SimilarityRefresher};
+
+/// Default time metadata stays cached before it is refreshed (one week).
+pub const DEFAULT_CACHE_TTL_HOURS: i64 = 7 * 24;
+
+/// Time before looking up an artist or album the agent did not know again.
+const NOT_FOUND_TTL: Duration = Duration::days(1);
+
+/// Time before retrying a lookup that failed.
+const RETRY_AFTER: Duration = Duration::hours(1);
+
+/// Errors from metadata agents.
+#[derive(Debug, Error)]
+pub enum AgentError {
+    #[error("HTTP error: {0}")]
+    Http(reqwest::Error),
+
+    #[error("Agent error {code}: {message}")]
+    Api { code: i64, message: String },
+}
+
+impl From<reqwest::Error> for AgentError {
+    fn from(e: reqwest::Error) -> Self {
+        // Request URLs carry the API key, so keep them out of error messages
+        Self::Http(e.without_url())
+    }
+}
+
+/// Boxed future returned by metadata agents.
+pub type AgentFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, AgentError>> + Send + 'a>>;
+
+/// Time allowed for a single request to an external service.
+const REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Serve `app` on a free local port, standing in for an external service in tests.
+#[cfg(test)]
+pub(crate) async fn serve_stand_in(app: axum::Router) -> std::net::SocketAddr {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    addr
+}
+
+/// Build the HTTP client used to call external services.
+fn http_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .user_agent(concat!("subsonic-rs/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .unwrap_or_default()
+}
+
+/// A source of artist and album details.
+pub trait MetadataAgent: Send + Sync {
+    /// Name stored with the metadata cached from this agent.
+    fn name(&self) -> &'static str;
+
+    /// Look up an artist, returning `None` if the agent does not know it.
+    fn artist_info<'a>(
+        &'a self,
+        name: &'a str,
+        musicbrainz_id: Option<&'a str>,
+    ) -> AgentFuture<'a, Option<ArtistMetadata>>;
+
+    /// Look up an album, returning `None` if the agent does not know it.
+    fn album_info<'a>(
+        &'a self,
+        artist: &'a str,
+        album: &'a str,
+        musicbrainz_id: Option<&'a str>,
+    ) -> AgentFuture<'a, Option<AlbumMetadata>>;
+}
+
+/// An artist or album with a refresh in progress.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum RefreshKey {
+    Artist(i32),
+    Album(i32),
+}
+
+/// Serves cached agent metadata and refreshes it in the background.
+#[derive(Clone)]
+pub struct MetadataService {
+    agent: Option<Arc<dyn MetadataAgent>>,
+    cache: MetadataCacheRepository,
+    ttl: Duration,
+    refreshing: Arc<Mutex<HashSet<RefreshKey>>>,
+}
+
+impl MetadataService {
+    /// Create a metadata service without an agent, serving only cached metadata.
+    pub fn new(pool: DbPool) -> Self {
+        Self {
+            agent: None,
+            cache: MetadataCacheRepository::new(pool),
+            ttl: Duration::hours(DEFAULT_CACHE_TTL_HOURS),
+            refreshing: Arc::new(Mutex::new(HashSet::new())),
+        }
+    }
+
+    /// Set the agent used to fetch missing and expired metadata.
+    pub fn with_agent(mut self, agent: Arc<dyn MetadataAgent>) -> Self {
+        self.agent = Some(agent);
+        self
+    }
+
+    /// Set how long fetched metadata stays cached.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Get the cached metadata of an artist.
+    ///
+    /// Missing or expired metadata is fetched in the background, and expired
+    /// metadata is still returned until the fetch replaces it.
+    pub fn artist_info(&self, artist: &Artist) -> Option<ArtistMetadata> {
+        let cached = self.cache.find_artist(artist.id).ok().flatten();
+        if cached
+            .as_ref()
+            .is_none_or(|c| c.is_expired(Utc::now().naive_utc()))
+        {
+            self.refresh_artist(artist);
+        }
+        cached.map(|c| c.value)
+    }
+
+    /// Get the cached metadata of an album.
+    ///
+    /// Missing or expired metadata is fetched in the background, and expired
+    /// metadata is still returned until the fetch replaces it.
+    pub fn album_info(&self, album: &Album) -> Option<AlbumMetadata> {
+        let cached = self.cache.find_album(album.id).ok().flatten();
+        if cached
+            .as_ref()
+            .is_none_or(|c| c.is_expired(Utc::now().naive_utc()))
+        {
+            self.refresh_album(album);
+        }
+        cached.map(|c| c.value)
+    }
+
+    /// Claim a refresh of an item, unless there is no agent or runtime or it is already refreshing.
+    fn start_refresh(
+        &self,
+        key: RefreshKey,
+    ) -> Option<(Arc<dyn MetadataAgent>, tokio::runtime::Handle)> {
+        let agent = self.agent.clone()?;
+        let runtime = tokio::runtime::Handle::try_current().ok()?;
+        self.refreshing
+            .lock()
+            .unwrap()
+            .insert(key)
+            .then_some((agent, runtime))
+    }
+
+    fn refresh_artist(&self, artist: &Artist) {
+        let key = RefreshKey::Artist(artist.id);
+        let Some((agent, runtime)) = self.start_refresh(key) else {
+            return;
+        };
+
+        let service = self.clone();
+        let artist = artist.clone();
+        runtime.spawn(async move {
+            let result = agent
+                .artist_info(&artist.name, artist.musicbrainz_id.as_deref())
+                .await;
+            let now = Utc::now().naive_utc();
+            let saved = match result {
+                Ok(Some(metadata)) => {
+                    service
+                        .cache
+                        .save_artist(artist.id, agent.name(), &metadata, now + service.ttl)
+                }
+                Ok(None) => service.cache.save_artist(
+                    artist.id,
+                    agent.name(),
+                    &ArtistMetadata::default(),
+                    now + NOT_FOUND_TTL,
+                ),
+                Err(e) => {
+                    tracing::warn!(
+                        "{} lookup of artist {} failed: {}",
+                        agent.name(),
+                        artist.name,
+                        e
+                    );
+                    service
+                        .cache
+                        .postpone_artist(artist.id, agent.name(), now + RETRY_AFTER)
+                }
+            };
+            if let Err(e) = saved {
+                tracing::warn!("Failed to cache metadata of artist {}: {}", artist.name, e);
+            }
+            service.refreshing.lock().unwrap().remove(&key);
+        });
+    }
+
+    fn refresh_album(&self, album: &Album) {
+        // Agents look albums up by artist and title
+        let Some(artist_name) = album.artist_name.clone() else {
+            return;
+        };
+        let key = RefreshKey::Album(album.id);
+        let Some((agent, runtime)) = self.start_refresh(key) else {
+            return;
+        };
+
+        let service = self.clone();
+        let album = album.clone();
+        runtime.spawn(async move {
+            let result = agent
+                .album_info(&artist_name, &album.name, album.musicbrainz_id.as_deref())
+                .await;
+            let now = Utc::now().naive_utc();
+            let saved = match result {
+                Ok(Some(metadata)) => {
+                    service
+                        .cache
+                        .save_album(album.id, agent.name(), &metadata, now + service.ttl)
+                }
+                Ok(None) => service.cache.save_album(
+                    album.id,
+                    agent.name(),
+                    &AlbumMetadata::default(),
+                    now + NOT_FOUND_TTL,
+                ),
+                Err(e) => {
+                    tracing::warn!(
+                        "{} lookup of album {} failed: {}",
+                        agent.name(),
+                        album.name,
+                        e
+                    );
+                    service
+                        .cache
+                        .postpone_album(album.id, agent.name(), now + RETRY_AFTER)
+                }
+            };
+            if let Err(e) = saved {
+                tracing::warn!("Failed to cache metadata of album {}: {}", album.name, e);
+            }
+            service.refreshing.lock().unwrap().remove(&key);
+        });
+    }
+}
@@ -0,0 +1,767 @@
+//! Podcast feeds and episode downloads.
+//!
+//! Channels are subscribed to by the URL of their RSS or Atom feed. A
+//! background worker refreshes the feeds periodically (and on request) and
+//! downloads the episodes users ask for into the podcast folder, one at a
+//! time, so requests never wait on a feed or a download.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use quick_xml::Reader;
+use quick_xml::escape::resolve_predefined_entity;
+use quick_xml::events::{BytesStart, Event};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{Notify, watch};
+
+use super::AgentError;
+use crate::db::{DbPool, MusicRepoError, PodcastRepository};
+use crate::models::podcast::{FeedEpisode, PodcastChannel, PodcastFeed, PodcastStatus};
+
+/// Default time between refreshes of all feeds.
+pub const DEFAULT_REFRESH_INTERVAL_HOURS: u64 = 24;
+
+/// Default podcast folder, below the user's data directory.
+const PODCAST_DIR: &str = "subsonic/podcasts";
+
+/// Largest feed that is fetched.
+const MAX_FEED_SIZE: usize = 16 * 1024 * 1024;
+
+/// Largest episode that is downloaded.
+const MAX_EPISODE_SIZE: u64 = 2 * 1024 * 1024 * 1024;
+
+/// Time allowed to connect to a feed or download server.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Time allowed for a feed, or between two reads of a download.
+const READ_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Get the default podcast folder.
+pub fn default_podcast_folder() -> PathBuf {
+    dirs::data_dir()
+        .map(|d| d.join(PODCAST_DIR))
+        .unwrap_or_else(|| PathBuf::from("podcasts"))
+}
+
+/// Build the HTTP client used for feeds and downloads. Unlike the agents'
+/// client it has no overall timeout, which long episodes would exceed.
+fn download_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .connect_timeout(CONNECT_TIMEOUT)
+        .read_timeout(READ_TIMEOUT)
+        .user_agent(concat!("subsonic-rs/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .unwrap_or_default()
+}
+
+/// An open element and the text read inside it so far.
+#[derive(Default)]
+struct Element {
+    name: String,
+    text: String,
+}
+
+/// Parse an RSS 2.0 or Atom feed. Returns `None` if the content is not a feed.
+///
+/// Entries are kept only if they link to audio: an RSS `<enclosure>` or an
+/// Atom `<link rel="enclosure">`. Parsing stops at the first malformed
+/// element, keeping what was read before it.
+pub fn parse_feed(content: &str) -> Option<PodcastFeed> {
+    let mut reader = Reader::from_str(content);
+    let mut feed = PodcastFeed::default();
+    let mut is_feed = false;
+    let mut stack: Vec<Element> = Vec::new();
+    let mut item: Option<FeedEpisode> = None;
+
+    loop {
+        let event = reader.read_event();
+        match event {
+            Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) => {
+                let name = String::from_utf8_lossy(e.name().as_ref()).to_lowercase();
+                if stack.is_empty() {
+                    is_feed = matches!(name.as_str(), "rss" | "feed" | "rdf:rdf");
+                    if !is_feed {
+                        break;
+                    }
+                }
+                match (item.as_mut(), name.as_str()) {
+                    (None, "item" | "entry") => item = Some(FeedEpisode::default()),
+                    (Some(episode), "enclosure") => read_enclosure(episode, e, "url"),
+                    (Some(episode), "link")
+                        if attribute(e, "rel").is_some_and(|rel| rel == "enclosure") =>
+                    {
+                        read_enclosure(episode, e, "href")
+                    }
+                    (None, "itunes:image") if feed.image_url.is_none() => {
+                        feed.image_url = attribute(e, "href");
+                    }
+                    _ => {}
+                }
+                if matches!(event, Ok(Event::Start(_))) {
+                    stack.push(Element {
+                        name,
+                        text: String::new(),
+                    });
+                }
+            }
+            Ok(Event::End(_)) => {
+                let Some(element) = stack.pop() else {
+                    break;
+                };
+                let text = element.text.trim();
+                let parent = stack.last().map(|p| p.name.as_str()).unwrap_or("");
+
+                if let Some(episode) = item.as_mut() {
+                    if matches!(element.name.as_str(), "item" | "entry") {
+                        let mut episode = item.take().unwrap_or_default();
+                        if !episode.enclosure_url.is_empty() {
+                            if episode.guid.is_empty() {
+                                episode.guid = episode.enclosure_url.clone();
+                            }
+                            if episode.title.is_empty() {
+                                episode.title = episode.guid.clone();
+                            }
+                            feed.episodes.push(episode);
+                        }
+                    } else if !text.is_empty() && matches!(parent, "item" | "entry") {
+                        read_episode_field(episode, &element.name, text);
+                    }
+                } else if !text.is_empty() {
+                    read_channel_field(&mut feed, parent, &element.name, text);
+                }
+            }
+            Ok(Event::Text(e)) => {
+                if let (Some(element), Ok(s)) = (stack.last_mut(), e.xml_content()) {
+                    element.text.push_str(&s);
+                }
+            }
+            Ok(Event::CData(e)) => {
+                if let (Some(element), Ok(s)) = (stack.last_mut(), e.decode()) {
+                    element.text.push_str(&s);
+                }
+            }
+            Ok(Event::GeneralRef(e)) => {
+                if let Some(element) = stack.last_mut() {
+                    if let Ok(Some(ch)) = e.resolve_char_ref() {
+                        element.text.push(ch);
+                    } else if let Ok(name) = e.decode()
+                        && let Some(resolved) = resolve_predefined_entity(&name)
+                    {
+                        element.text.push_str(resolved);
+                    }
+                }
+            }
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+    }
+
+    is_feed.then_some(feed)
+}
+
+/// Get the unescaped value of an attribute.
+fn attribute(e: &BytesStart, name: &str) -> Option<String> {
+    e.try_get_attribute(name)
+        .ok()
+        .flatten()
+        .and_then(|a| a.unescape_value().ok())
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+/// Read the audio link of an entry, keeping the first one.
+fn read_enclosure(episode: &mut FeedEpisode, e: &BytesStart, url_attribute: &str) {
+    if !episode.enclosure_url.is_empty() {
+        return;
+    }
+    let Some(url) = attribute(e, url_attribute) else {
+        return;
+    };
+    episode.enclosure_url = url;
+    episode.content_type = attribute(e, "type");
+    episode.size = attribute(e, "length")
+        .and_then(|l| l.parse().ok())
+        .filter(|&l: &i64| l > 0);
+}
+
+fn read_episode_field(episode: &mut FeedEpisode, name: &str, text: &str) {
+    match name {
+        "title" => episode.title = text.to_string(),
+        "guid" | "id" => episode.guid = text.to_string(),
+        "description" | "summary" | "itunes:summary" | "content" => {
+            episode.description.get_or_insert_with(|| text.to_string());
+        }
+        "pubdate" | "published" | "dc:date" => episode.publish_date = parse_date(text),
+        "updated" => episode.publish_date = episode.publish_date.or_else(|| parse_date(text)),
+        "itunes:duration" => episode.duration = parse_duration(text),
+        _ => {}
+    }
+}
+
+fn read_channel_field(feed: &mut PodcastFeed, parent: &str, name: &str, text: &str) {
+    match (parent, name) {
+        ("channel" | "feed", "title") => feed.title = Some(text.to_string()),
+        ("channel" | "feed", "description" | "subtitle" | "itunes:summary") => {
+            feed.description.get_or_insert_with(|| text.to_string());
+        }
+        ("image", "url") | ("feed", "logo" | "icon") => {
+            feed.image_url.get_or_insert_with(|| text.to_string());
+        }
+        _ => {}
+    }
+}
+
+/// Parse an RFC 2822 (RSS) or RFC 3339 (Atom) date.
+fn parse_date(text: &str) -> Option<NaiveDateTime> {
+    DateTime::parse_from_rfc2822(text)
+        .or_else(|_| DateTime::parse_from_rfc3339(text))
+        .ok()
+        .map(|date| date.with_timezone(&Utc).naive_utc())
+}
+
+/// Parse an `itunes:duration` given in seconds, `MM:SS` or `HH:MM:SS`.
+fn parse_duration(text: &str) -> Option<i32> {
+    let mut seconds = 0f64;
+    for part in text.split(':') {
+        seconds = seconds * 60.0 + part.trim().parse::<f64>().ok()?;
+    }
+    (seconds.is_finite() && seconds >= 0.0).then_some(seconds.round() as i32)
+}
+
+/// Send a GET request, failing on error statuses.
+async fn get(client: &reqwest::Client, url: &str) -> Result<reqwest::Response, AgentError> {
+    let response = client.get(url).send().await?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(AgentError::Api {
+            code: i64::from(status.as_u16()),
+            message: format!("Request failed: {}", status),
+        });
+    }
+    Ok(response)
+}
+
+/// Fetch and parse a podcast feed.
+pub async fn fetch_feed(client: &reqwest::Client, url: &str) -> Result<PodcastFeed, AgentError> {
+    let too_large = || AgentError::Api {
+        code: 0,
+        message: "Feed is too large".into(),
+    };
+
+    let mut response = tokio::time::timeout(READ_TIMEOUT, get(client, url))
+        .await
+        .map_err(|_| AgentError::Api {
+            code: 0,
+            message: "Timed out fetching feed".into(),
+        })??;
+    if response
+        .content_length()
+        .is_some_and(|len| len > MAX_FEED_SIZE as u64)
+    {
+        return Err(too_large());
+    }
+
+    let mut bytes = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        bytes.extend_from_slice(&chunk);
+        if bytes.len() > MAX_FEED_SIZE {
+            return Err(too_large());
+        }
+    }
+
+    parse_feed(&String::from_utf8_lossy(&bytes)).ok_or_else(|| AgentError::Api {
+        code: 0,
+        message: "Not an RSS or Atom feed".into(),
+    })
+}
+
+/// Download a URL to a file, going through a temporary file so a failed
+/// download never leaves a partial file at `path`. Downloads larger than
+/// `max_size` bytes are abandoned. Returns the file size.
+pub async fn download_file(
+    client: &reqwest::Client,
+    url: &str,
+    path: &Path,
+    max_size: u64,
+) -> Result<i64, AgentError> {
+    let io_error = |e: std::io::Error| AgentError::Api {
+        code: 0,
+        message: format!("Failed to write {}: {}", path.display(), e),
+    };
+    let too_large = || AgentError::Api {
+        code: 0,
+        message: format!("Download is larger than {} bytes", max_size),
+    };
+
+    let mut response = get(client, url).await?;
+    if response.content_length().is_some_and(|len| len > max_size) {
+        return Err(too_large());
+    }
+    if let Some(dir) = path.parent() {
+        tokio::fs::create_dir_all(dir).await.map_err(io_error)?;
+    }
+    let partial = path.with_extension("part");
+    let mut file = tokio::fs::File::create(&partial).await.map_err(io_error)?;
+
+    let mut size = 0u64;
+    let result = async {
+        while let Some(chunk) = response.chunk().await? {
+            size += chunk.len() as u64;
+            if size > max_size {
+                return Err(too_large());
+            }
+            file.write_all(&chunk).await.map_err(io_error)?;
+        }
+        file.flush().await.map_err(io_error)
+    }
+    .await;
+
+    match result {
+        Ok(()) => {
+            tokio::fs::rename(&partial, path).await.map_err(io_error)?;
+            Ok(size as i64)
+        }
+        Err(e) => {
+            let _ = tokio::fs::remove_file(&partial).await;
+            Err(e)
+        }
+    }
+}
+
+/// Remove a downloaded file, ignoring files that are already gone.
+fn remove_download(path: &Path) {
+    match std::fs::remove_file(path) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => tracing::warn!("Failed to remove {}: {}", path.display(), e),
+    }
+}
+
+/// Keeps podcast channels up to date and downloads their episodes.
+#[derive(Clone)]
+pub struct PodcastService {
+    repo: PodcastRepository,
+    folder: PathBuf,
+    client: reqwest::Client,
+    interval: Duration,
+    refresh_requested: Arc<AtomicBool>,
+    wake: Arc<Notify>,
+}
+
+impl PodcastService {
+    /// Create a service storing episodes in the given folder.
+    pub fn new(pool: DbPool, folder: PathBuf) -> Self {
+        Self {
+            repo: PodcastRepository::new(pool),
+            folder,
+            client: download_client(),
+            interval: Duration::from_secs(DEFAULT_REFRESH_INTERVAL_HOURS * 60 * 60),
+            refresh_requested: Arc::new(AtomicBool::new(false)),
+            wake: Arc::new(Notify::new()),
+        }
+    }
+
+    /// Set the time between refreshes of all feeds.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// The folder episodes are downloaded to.
+    pub fn folder(&self) -> &Path {
+        &self.folder
+    }
+
+    /// Subscribe to a feed, which is fetched in the background.
+    pub fn create_channel(&self, url: &str) -> Result<PodcastChannel, MusicRepoError> {
+        let channel = self.repo.create_channel(url)?;
+        self.wake.notify_one();
+        Ok(channel)
+    }
+
+    /// Unsubscribe from a channel, deleting its downloaded episodes.
+    /// Returns false if the channel does not exist.
+    pub fn delete_channel(&self, channel_id: i32) -> Result<bool, MusicRepoError> {
+        let episodes = self.repo.find_episodes(channel_id)?;
+        if !self.repo.delete_channel(channel_id)? {
+            return Ok(false);
+        }
+        for path in episodes.iter().filter_map(|e| e.file_path.as_deref()) {
+            remove_download(Path::new(path));
+        }
+        let _ = std::fs::remove_dir(self.folder.join(channel_id.to_string()));
+        Ok(true)
+    }
+
+    /// Refresh all feeds in the background.
+    pub fn refresh(&self) {
+        self.refresh_requested.store(true, Ordering::SeqCst);
+        self.wake.notify_one();
+    }
+
+    /// Queue an episode for download. Returns false if it does not exist.
+    pub fn download_episode(&self, episode_id: i32) -> Result<bool, MusicRepoError> {
+        let Some(episode) = self.repo.find_episode(episode_id)? else {
+            return Ok(false);
+        };
+        if matches!(
+            episode.status,
+            PodcastStatus::Completed | PodcastStatus::Downloading
+        ) {
+            return Ok(true);
+        }
+        self.repo
+            .set_episode_status(episode_id, PodcastStatus::Downloading, None, None)?;
+        self.wake.notify_one();
+        Ok(true)
+    }
+
+    /// Delete an episode's download. The episode is kept, marked deleted, so
+    /// refreshes do not bring it back. Returns false if it does not exist.
+    pub fn delete_episode(&self, episode_id: i32) -> Result<bool, MusicRepoError> {
+        let Some(episode) = self.repo.find_episode(episode_id)? else {
+            return Ok(false);
+        };
+        self.repo
+            .set_episode_status(episode_id, PodcastStatus::Deleted, None, None)?;
+        if let Some(path) = &episode.file_path {
+            remove_download(Path::new(path));
+        }
+        Ok(true)
+    }
+
+    /// Fetch a channel's feed and store its new episodes.
+    async fn refresh_channel(&self, channel: &PodcastChannel) {
+        let result = match fetch_feed(&self.client, &channel.url).await {
+            Ok(feed) => self
+                .repo
+                .save_feed(channel.id, &feed)
+                .map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+
+        match result {
+            Ok(added) => {
+                tracing::info!("Refreshed podcast {}: {} new episodes", channel.url, added)
+            }
+            Err(message) => {
+                tracing::warn!("Failed to refresh podcast {}: {}", channel.url, message);
+                if let Err(e) = self.repo.set_channel_error(channel.id, &message) {
+                    tracing::error!("Failed to record podcast error: {}", e);
+                }
+            }
+        }
+    }
+
+    /// Refresh the channels that were never refreshed, or all of them.
+    async fn refresh_channels(&self, all: bool) {
+        let channels = match self.repo.find_channels() {
+            Ok(channels) => channels,
+            Err(e) => {
+                tracing::error!("Failed to load podcast channels: {}", e);
+                return;
+            }
+        };
+        for channel in channels
+            .iter()
+            .filter(|c| all || c.status == PodcastStatus::New)
+        {
+            self.refresh_channel(channel).await;
+        }
+    }
+
+    /// Download queued episodes until none are left.
+    async fn download_queued(&self) {
+        loop {
+            let episode = match self.repo.next_queued_episode() {
+                Ok(Some(episode)) => episode,
+                Ok(None) => return,
+                Err(e) => {
+                    tracing::error!("Failed to load queued podcast episodes: {}", e);
+                    return;
+                }
+            };
+
+            let path = self
+                .folder
+                .join(episode.channel_id.to_string())
+                .join(format!(
+                    "{}.{}",
+                    episode.id,
+                    episode.suffix().unwrap_or_else(|| "mp3".into())
+                ));
+            let result = match download_file(
+                &self.client,
+                &episode.enclosure_url,
+                &path,
+                MAX_EPISODE_SIZE,
+            )
+            .await
+            {
+                Ok(size) => self
+                    .repo
+                    .complete_episode(episode.id, &path.to_string_lossy(), size),
+                Err(e) => {
+                    tracing::warn!(
+                        "Failed to download podcast episode {}: {}",
+                        episode.enclosure_url,
+                        e
+                    );
+                    self.repo.set_episode_status(
+                        episode.id,
+                        PodcastStatus::Error,
+                        None,
+                        Some(&e.to_string()),
+                    )
+                }
+            };
+
+            match result {
+                Ok(true) => {}
+                // Deleted while downloading
+                Ok(false) => remove_download(&path),
+                Err(e) => {
+                    tracing::error!("Failed to record podcast download: {}", e);
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Start refreshing feeds and downloading episodes in the background.
+    /// Returns a handle that can be used to stop the service.
+    pub fn start(&self) -> PodcastServiceHandle {
+        let (shutdown_tx, mut shutdown_rx) = watch::channel(false);
+        let service = self.clone();
+
+        tokio::spawn(async move {
+            tracing::info!(
+                "Podcast service started with folder {} and interval {:?}",
+                service.folder.display(),
+                service.interval
+            );
+            let mut last_refresh: Option<Instant> = None;
+
+            loop {
+                let due = last_refresh.is_none_or(|at| at.elapsed() >= service.interval);
+                let all = service.refresh_requested.swap(false, Ordering::SeqCst) || due;
+                service.refresh_channels(all).await;
+                if all {
+                    last_refresh = Some(Instant::now());
+                }
+                service.download_queued().await;
+
+                let next_refresh = last_refresh
+                    .map(|at| service.interval.saturating_sub(at.elapsed()))
+                    .unwrap_or_default();
+                tokio::select! {
+                    _ = tokio::time::sleep(next_refresh) => {}
+                    _ = service.wake.notified() => {}
+                    _ = shutdown_rx.changed() => {
+                        if *shutdown_rx.borrow() {
+                            break;
+                        }
+                    }
+                }
+            }
+
+            tracing::info!("Podcast service stopped");
+        });
+
+        PodcastServiceHandle { shutdown_tx }
+    }
+}
+
+/// Handle for controlling the podcast service.
+pub struct PodcastServiceHandle {
+    shutdown_tx: watch::Sender<bool>,
+}
+
+impl PodcastServiceHandle {
+    /// Stop the podcast service.
+    pub fn stop(&self) {
+        let _ = self.shutdown_tx.send(true);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RSS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0" xmlns:itunes="http://www.itunes.com/dtds/podcast-1.0.dtd">
+  <channel>
+    <title>Rust &amp; Friends</title>
+    <description><![CDATA[A show about <b>Rust</b>]]></description>
+    <itunes:image href="http://example.com/cover.jpg"/>
+    <item>
+      <title>Episode 2</title>
+      <guid isPermaLink="false">ep-2</guid>
+      <pubDate>Tue, 02 Jan 2024 10:00:00 +0100</pubDate>
+      <itunes:duration>1:02:03</itunes:duration>
+      <enclosure url="http://example.com/ep2.mp3?x=1&amp;y=2" length="1234" type="audio/mpeg"/>
+    </item>
+    <item>
+      <title>Show notes only</title>
+      <guid>notes</guid>
+    </item>
+    <item>
+      <enclosure url="http://example.com/ep1.m4a" type="audio/mp4"/>
+      <itunes:duration>95</itunes:duration>
+    </item>
+  </channel>
+</rss>"#;
+
+    const ATOM: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <title>Atom Cast</title>
+  <subtitle>Entries with enclosures</subtitle>
+  <logo>http://example.com/logo.png</logo>
+  <entry>
+    <title>First</title>
+    <id>urn:uuid:1</id>
+    <updated>2024-03-01T12:00:00Z</updated>
+    <link rel="alternate" href="http://example.com/first"/>
+    <link rel="enclosure" href="http://example.com/first.ogg" type="audio/ogg" length="42"/>
+    <summary>The first entry</summary>
+  </entry>
+</feed>"#;
+
+    #[test]
+    fn test_parse_rss_feed() {
+        let feed = parse_feed(RSS).unwrap();
+        assert_eq!(feed.title.as_deref(), Some("Rust & Friends"));
+        assert_eq!(
+            feed.description.as_deref(),
+            Some("A show about <b>Rust</b>")
+        );
+        assert_eq!(
+            feed.image_url.as_deref(),
+            Some("http://example.com/cover.jpg")
+        );
+
+        // Entries without audio are skipped
+        assert_eq!(feed.episodes.len(), 2);
+        let episode = &feed.episodes[0];
+        assert_eq!(episode.guid, "ep-2");
+        assert_eq!(episode.title, "Episode 2");
+        assert_eq!(episode.enclosure_url, "http://example.com/ep2.mp3?x=1&y=2");
+        assert_eq!(episode.content_type.as_deref(), Some("audio/mpeg"));
+        assert_eq!(episode.size, Some(1234));
+        assert_eq!(episode.duration, Some(3723));
+        assert_eq!(
+            episode.publish_date.unwrap().to_string(),
+            "2024-01-02 09:00:00"
+        );
+
+        // Missing guid and title fall back to the enclosure URL
+        let episode = &feed.episodes[1];
+        assert_eq!(episode.guid, "http://example.com/ep1.m4a");
+        assert_eq!(episode.title, "http://example.com/ep1.m4a");
+        assert_eq!(episode.duration, Some(95));
+    }
+
+    #[test]
+    fn test_parse_atom_feed() {
+        let feed = parse_feed(ATOM).unwrap();
+        assert_eq!(feed.title.as_deref(), Some("Atom Cast"));
+        assert_eq!(feed.description.as_deref(), Some("Entries with enclosures"));
+        assert_eq!(
+            feed.image_url.as_deref(),
+            Some("http://example.com/logo.png")
+        );
+        assert_eq!(feed.episodes.len(), 1);
+        let episode = &feed.episodes[0];
+        assert_eq!(episode.guid, "urn:uuid:1");
+        assert_eq!(episode.enclosure_url, "http://example.com/first.ogg");
+        assert_eq!(episode.size, Some(42));
+        assert_eq!(episode.description.as_deref(), Some("The first entry"));
+        assert_eq!(
+            episode.publish_date.unwrap().to_string(),
+            "2024-03-01 12:00:00"
+        );
+    }
+
+    #[test]
+    fn test_parse_non_feed() {
+        assert!(parse_feed("<html><body>Not a feed</body></html>").is_none());
+        assert!(parse_feed("plain text").is_none());
+        assert_eq!(parse_duration("12:34"), Some(754));
+        assert_eq!(parse_duration("soon"), None);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_and_download_from_stand_in_server() {
+        let app = axum::Router::new()
+            .route("/feed.xml", axum::routing::get(|| async { RSS }))
+            .route(
+                "/page.html",
+                axum::routing::get(|| async { "<html></html>" }),
+            )
+            .route(
+                "/episode.mp3",
+                axum::routing::get(|| async { vec![7u8; 100_000] }),
+            )
+            .route(
+                "/endless.mp3",
+                axum::routing::get(|| async {
+                    let stream = tokio_util::io::ReaderStream::new(tokio::io::repeat(7));
+                    axum::body::Body::from_stream(stream)
+                }),
+            );
+        let addr = crate::agents::serve_stand_in(app).await;
+        let base = format!("http://{}", addr);
+        let client = download_client();
+
+        let feed = fetch_feed(&client, &format!("{}/feed.xml", base))
+            .await
+            .unwrap();
+        assert_eq!(feed.episodes.len(), 2);
+        assert!(
+            fetch_feed(&client, &format!("{}/page.html", base))
+                .await
+                .is_err()
+        );
+        assert!(
+            fetch_feed(&client, &format!("{}/missing.xml", base))
+                .await
+                .is_err()
+        );
+
+        let dir = std::env::temp_dir().join(format!("subsonic-podcast-test-{}", addr.port()));
+        let path = dir.join("1").join("2.mp3");
+        let size = download_file(&client, &format!("{}/episode.mp3", base), &path, 100_000)
+            .await
+            .unwrap();
+        assert_eq!(size, 100_000);
+        assert_eq!(std::fs::read(&path).unwrap(), vec![7u8; 100_000]);
+
+        // Failed downloads leave no file behind
+        let missing = dir.join("1").join("3.mp3");
+        assert!(
+            download_file(&client, &format!("{}/missing.mp3", base), &missing, 100_000)
+                .await
+                .is_err()
+        );
+        assert!(!missing.exists());
+        assert!(!missing.with_extension("part").exists());
+
+        // Downloads over the size limit are abandoned, whether or not the
+        // server declares their length
+        for name in ["episode.mp3", "endless.mp3"] {
+            let large = dir.join("1").join("4.mp3");
+            assert!(
+                download_file(&client, &format!("{}/{}", base, name), &large, 50_000)
+                    .await
+                    .is_err()
+            );
+            assert!(!large.exists());
+            assert!(!large.with_extension("part").exists());
+        }
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,299 @@
+//! Internet radio stream URLs.
+//!
+//! Stations are often published as links to `.pls` or `.m3u` playlists rather
+//! than to the stream itself, which many clients cannot play. Such links are
+//! fetched and replaced by the first stream they list. Station lists in the
+//! same formats can be imported along with the station names they carry.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use reqwest::Url;
+
+use super::{AgentError, http_client};
+use crate::scanner::playlists::decode_text;
+
+/// Largest playlist fetched when resolving a stream URL.
+const MAX_PLAYLIST_SIZE: usize = 64 * 1024;
+
+/// A station read from a station list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StationEntry {
+    /// Station name, if the list gives one.
+    pub name: Option<String>,
+    pub stream_url: String,
+}
+
+impl StationEntry {
+    /// The station name, or the host of its stream URL if it has none.
+    pub fn name_or_host(&self) -> String {
+        self.name.clone().unwrap_or_else(|| {
+            Url::parse(&self.stream_url)
+                .ok()
+                .and_then(|url| url.host_str().map(str::to_string))
+                .unwrap_or_else(|| self.stream_url.clone())
+        })
+    }
+}
+
+/// Check that a URL is an absolute HTTP(S) URL.
+pub fn is_http_url(url: &str) -> bool {
+    Url::parse(url).is_ok_and(|url| matches!(url.scheme(), "http" | "https"))
+}
+
+/// Whether a path names a PLS playlist (`Some(true)`), an M3U playlist
+/// (`Some(false)`), or neither.
+fn playlist_kind(path: &str) -> Option<bool> {
+    let extension = path.rsplit_once('.')?.1.to_ascii_lowercase();
+    match extension.as_str() {
+        "pls" => Some(true),
+        "m3u" | "m3u8" => Some(false),
+        _ => None,
+    }
+}
+
+/// Parse a station list in M3U format (names from `#EXTINF` lines) or PLS
+/// format (names from `TitleN` keys).
+pub fn parse_station_list(content: &str, is_pls: bool) -> Vec<StationEntry> {
+    if is_pls {
+        parse_pls_stations(content)
+    } else {
+        parse_m3u_stations(content)
+    }
+}
+
+fn parse_m3u_stations(content: &str) -> Vec<StationEntry> {
+    let mut stations = Vec::new();
+    let mut name = None;
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(info) = line.strip_prefix("#EXTINF:") {
+            name = info
+                .split_once(',')
+                .map(|(_, title)| title.trim().to_string())
+                .filter(|title| !title.is_empty());
+            continue;
+        }
+        if line.starts_with('#') {
+            continue;
+        }
+        stations.push(StationEntry {
+            name: name.take(),
+            stream_url: line.to_string(),
+        });
+    }
+
+    stations
+}
+
+fn parse_pls_stations(content: &str) -> Vec<StationEntry> {
+    // Entries are keyed by the index in their `FileN` and `TitleN` keys
+    let mut entries: BTreeMap<u32, (Option<String>, Option<String>)> = BTreeMap::new();
+
+    for line in content.lines() {
+        let Some((key, value)) = line.trim().split_once('=') else {
+            continue;
+        };
+        let key = key.trim().to_ascii_lowercase();
+        let value = value.trim();
+        if value.is_empty() {
+            continue;
+        }
+        if let Some(index) = key.strip_prefix("file").and_then(|i| i.parse().ok()) {
+            entries.entry(index).or_default().0 = Some(value.to_string());
+        } else if let Some(index) = key.strip_prefix("title").and_then(|i| i.parse().ok()) {
+            entries.entry(index).or_default().1 = Some(value.to_string());
+        }
+    }
+
+    entries
+        .into_values()
+        .filter_map(|(file, title)| {
+            Some(StationEntry {
+                name: title,
+                stream_url: file?,
+            })
+        })
+        .collect()
+}
+
+/// Read a station list file, in PLS format if it has a `.pls` extension
+/// and M3U format otherwise.
+pub fn read_station_list(path: &Path) -> std::io::Result<Vec<StationEntry>> {
+    let content = decode_text(&std::fs::read(path)?);
+    let is_pls = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("pls"));
+    Ok(parse_station_list(&content, is_pls))
+}
+
+/// Resolve a link to a `.pls` or `.m3u` playlist to the first stream it
+/// lists. Other URLs, and HLS playlists (which clients play directly), are
+/// returned unchanged.
+pub async fn resolve_stream_url(url: &str) -> Result<String, AgentError> {
+    let Ok(parsed) = Url::parse(url) else {
+        return Ok(url.to_string());
+    };
+    let Some(is_pls) = playlist_kind(parsed.path()) else {
+        return Ok(url.to_string());
+    };
+
+    let too_large = || AgentError::Api {
+        code: 0,
+        message: "Playlist is too large".into(),
+    };
+
+    let mut response = http_client().get(parsed.clone()).send().await?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(AgentError::Api {
+            code: i64::from(status.as_u16()),
+            message: format!("Failed to fetch playlist: {}", status),
+        });
+    }
+    if response
+        .content_length()
+        .is_some_and(|len| len > MAX_PLAYLIST_SIZE as u64)
+    {
+        return Err(too_large());
+    }
+
+    // Links that turn out to be streams themselves never end, so stop at the cap
+    let mut bytes = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        bytes.extend_from_slice(&chunk);
+        if bytes.len() > MAX_PLAYLIST_SIZE {
+            return Err(too_large());
+        }
+    }
+    let content = decode_text(&bytes);
+
+    if !is_pls && content.contains("#EXT-X-") {
+        return Ok(url.to_string());
+    }
+
+    // Entries may be relative to the playlist
+    parse_station_list(&content, is_pls)
+        .into_iter()
+        .filter_map(|entry| parsed.join(&entry.stream_url).ok())
+        .map(String::from)
+        .find(|stream_url| is_http_url(stream_url))
+        .ok_or_else(|| AgentError::Api {
+            code: 0,
+            message: "Playlist lists no streams".into(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_station_lists() {
+        let m3u = "#EXTM3U\n\
+                   #EXTINF:-1,Jazz FM\n\
+                   http://jazz.example/stream\n\
+                   \n\
+                   http://plain.example/live.mp3\n";
+        assert_eq!(
+            parse_station_list(m3u, false),
+            vec![
+                StationEntry {
+                    name: Some("Jazz FM".into()),
+                    stream_url: "http://jazz.example/stream".into(),
+                },
+                StationEntry {
+                    name: None,
+                    stream_url: "http://plain.example/live.mp3".into(),
+                },
+            ]
+        );
+
+        let pls = "[playlist]\n\
+                   File2=http://two.example/\n\
+                   Title1=One\n\
+                   File1=http://one.example/\n\
+                   NumberOfEntries=2\n";
+        let stations = parse_station_list(pls, true);
+        assert_eq!(stations.len(), 2);
+        assert_eq!(stations[0].name.as_deref(), Some("One"));
+        assert_eq!(stations[0].stream_url, "http://one.example/");
+        assert_eq!(stations[1].name_or_host(), "two.example");
+    }
+
+    #[tokio::test]
+    async fn test_resolve_against_stand_in_server() {
+        let app = axum::Router::new()
+            .route(
+                "/station.pls",
+                axum::routing::get(|| async {
+                    "[playlist]\nFile1=http://stream.example/live\nTitle1=Live\n"
+                }),
+            )
+            .route(
+                "/relative.m3u",
+                axum::routing::get(|| async { "#EXTM3U\n#EXTINF:-1,Live\nlive.mp3\n" }),
+            )
+            .route(
+                "/hls.m3u8",
+                axum::routing::get(|| async {
+                    "#EXTM3U\n#EXT-X-TARGETDURATION:10\n#EXTINF:10,\nsegment0.ts\n"
+                }),
+            )
+            .route(
+                "/empty.pls",
+                axum::routing::get(|| async { "[playlist]\n" }),
+            )
+            .route(
+                "/endless.m3u",
+                axum::routing::get(|| async {
+                    let stream = tokio_util::io::ReaderStream::new(tokio::io::repeat(b'#'));
+                    axum::body::Body::from_stream(stream)
+                }),
+            );
+        let addr = crate::agents::serve_stand_in(app).await;
+        let base = format!("http://{}", addr);
+
+        assert_eq!(
+            resolve_stream_url(&format!("{}/station.pls", base))
+                .await
+                .unwrap(),
+            "http://stream.example/live"
+        );
+        assert_eq!(
+            resolve_stream_url(&format!("{}/relative.m3u", base))
+                .await
+                .unwrap(),
+            format!("{}/live.mp3", base)
+        );
+
+        // HLS playlists and direct streams are left alone
+        let hls = format!("{}/hls.m3u8", base);
+        assert_eq!(resolve_stream_url(&hls).await.unwrap(), hls);
+        let direct = format!("{}/live.mp3", base);
+        assert_eq!(resolve_stream_url(&direct).await.unwrap(), direct);
+
+        assert!(
+            resolve_stream_url(&format!("{}/empty.pls", base))
+                .await
+                .is_err()
+        );
+        // A stream behind a playlist link is cut off rather than read until the timeout
+        let endless = format!("{}/endless.m3u", base);
+        let resolved = tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            resolve_stream_url(&endless),
+        );
+        assert!(resolved.await.unwrap().is_err());
+        assert!(
+            resolve_stream_url(&format!("{}/missing.pls", base))
+                .await
+                .is_err()
+        );
+    }
+}
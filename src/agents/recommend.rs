@@ -0,0 +1,486 @@
+//! Song recommendations for getSimilarSongs.
+//!
+//! Candidates are gathered from the seed songs' artists, similar artists,
+//! genres, shared playlists and listening sessions, then scored on each of
+//! those signals plus the user's ratings and stars. The list is picked
+//! greedily, with every further song from an artist or album already chosen
+//! scoring less, so that a radio station does not get stuck on one artist.
+
+use std::collections::{HashMap, HashSet};
+
+use super::similarity::SESSION_GAP;
+use crate::db::{
+    AlbumRepository, ArtistSimilarityRepository, DbPool, MusicRepoError, PlaylistRepository,
+    RatingRepository, ScrobbleRepository, SongRepository, StarredRepository,
+};
+use crate::models::music::Song;
+
+// Weights of the signals in a song's score
+const GENRE_WEIGHT: f64 = 2.0;
+const YEAR_WEIGHT: f64 = 1.0;
+const SHARED_ARTIST_WEIGHT: f64 = 1.0;
+const SIMILAR_ARTIST_WEIGHT: f64 = 2.0;
+const PLAYLIST_WEIGHT: f64 = 1.5;
+const SESSION_WEIGHT: f64 = 2.0;
+const RATING_WEIGHT: f64 = 1.0;
+const STARRED_WEIGHT: f64 = 0.5;
+
+/// Years apart at which release years stop counting as close.
+const YEAR_SPAN: f64 = 10.0;
+
+/// Score multiplier for each song already picked from the same artist.
+const ARTIST_REPEAT_PENALTY: f64 = 0.6;
+
+/// Score multiplier for each song already picked from the same album.
+const ALBUM_REPEAT_PENALTY: f64 = 0.7;
+
+/// Songs the user rated this low are never recommended.
+const DISLIKED_RATING: i32 = 1;
+
+/// Candidates gathered from each source.
+const CANDIDATES_PER_SOURCE: i64 = 200;
+
+/// Most similar artists whose songs are gathered as candidates.
+const MAX_SIMILAR_ARTISTS: usize = 20;
+
+/// Recommends songs similar to a song, album or artist.
+#[derive(Clone)]
+pub struct Recommender {
+    songs: SongRepository,
+    albums: AlbumRepository,
+    playlists: PlaylistRepository,
+    scrobbles: ScrobbleRepository,
+    ratings: RatingRepository,
+    starred: StarredRepository,
+    similarity: ArtistSimilarityRepository,
+}
+
+impl Recommender {
+    /// Create a new recommender.
+    pub fn new(pool: DbPool) -> Self {
+        Self {
+            songs: SongRepository::new(pool.clone()),
+            albums: AlbumRepository::new(pool.clone()),
+            playlists: PlaylistRepository::new(pool.clone()),
+            scrobbles: ScrobbleRepository::new(pool.clone()),
+            ratings: RatingRepository::new(pool.clone()),
+            starred: StarredRepository::new(pool.clone()),
+            similarity: ArtistSimilarityRepository::new(pool),
+        }
+    }
+
+    /// Recommend up to `count` songs like the seed songs to a user.
+    pub fn similar_songs(
+        &self,
+        user_id: i32,
+        seeds: &[Song],
+        count: usize,
+    ) -> Result<Vec<Song>, MusicRepoError> {
+        if seeds.is_empty() || count == 0 {
+            return Ok(Vec::new());
+        }
+
+        let seed_ids: Vec<i32> = seeds.iter().map(|s| s.id).collect();
+        let mut evidence = Evidence::default();
+        self.load_album_artists(seeds, &mut evidence)?;
+
+        let mut artist_ids: Vec<i32> = seeds
+            .iter()
+            .filter_map(|s| s.artist_id)
+            .chain(seeds.iter().filter_map(|s| evidence.album_artist(s)))
+            .collect();
+        artist_ids.sort_unstable();
+        artist_ids.dedup();
+
+        // Keep each artist's strongest link to any seed artist
+        for similarity in self.similarity.find_scores(&artist_ids)? {
+            if artist_ids
+                .binary_search(&similarity.similar_artist_id)
+                .is_ok()
+            {
+                continue;
+            }
+            let score = evidence
+                .similar_artists
+                .entry(similarity.similar_artist_id)
+                .or_default();
+            *score = score.max(similarity.score);
+        }
+        let mut similar_artists: Vec<(i32, f64)> = evidence
+            .similar_artists
+            .iter()
+            .map(|(&id, &score)| (id, score))
+            .collect();
+        similar_artists.sort_by(|a, b| b.1.total_cmp(&a.1));
+        let similar_artist_ids: Vec<i32> = similar_artists
+            .iter()
+            .take(MAX_SIMILAR_ARTISTS)
+            .map(|(id, _)| *id)
+            .collect();
+
+        evidence.playlist_counts = self
+            .playlists
+            .count_co_occurrences(&seed_ids, CANDIDATES_PER_SOURCE)?
+            .into_iter()
+            .collect();
+        evidence.session_counts = self
+            .scrobbles
+            .count_session_co_occurrences(
+                &seed_ids,
+                SESSION_GAP.num_minutes(),
+                CANDIDATES_PER_SOURCE,
+            )?
+            .into_iter()
+            .collect();
+
+        let mut seed_genres: Vec<String> = seeds.iter().filter_map(|s| s.genre.clone()).collect();
+        seed_genres.sort_unstable();
+        seed_genres.dedup();
+
+        let co_occurring_ids: Vec<i32> = evidence
+            .playlist_counts
+            .keys()
+            .chain(evidence.session_counts.keys())
+            .copied()
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+
+        let mut candidates = self.songs.find_by_ids(&co_occurring_ids)?;
+        candidates.extend(
+            self.songs
+                .find_random_by_artists(&similar_artist_ids, CANDIDATES_PER_SOURCE)?,
+        );
+        candidates.extend(
+            self.songs
+                .find_random_by_artists(&artist_ids, CANDIDATES_PER_SOURCE)?,
+        );
+        candidates.extend(
+            self.songs
+                .find_random_by_genres(&seed_genres, CANDIDATES_PER_SOURCE)?,
+        );
+        // Pad with random songs so that sparse libraries still fill the list
+        if candidates.len() < count * 2 {
+            candidates.extend(
+                self.songs
+                    .find_random(count as i64, None, None, None, None)?,
+            );
+        }
+
+        self.load_album_artists(&candidates, &mut evidence)?;
+        let candidate_ids: Vec<i32> = candidates.iter().map(|s| s.id).collect();
+        evidence.ratings = self
+            .ratings
+            .get_song_ratings_batch(user_id, &candidate_ids)?;
+        evidence.starred = self
+            .starred
+            .get_starred_at_for_songs_batch(user_id, &candidate_ids)?
+            .into_keys()
+            .collect();
+
+        Ok(rank(seeds, candidates, &evidence, count))
+    }
+
+    /// Look up the album artists of songs whose albums are not yet known.
+    fn load_album_artists(
+        &self,
+        songs: &[Song],
+        evidence: &mut Evidence,
+    ) -> Result<(), MusicRepoError> {
+        let album_ids: Vec<i32> = songs
+            .iter()
+            .filter_map(|s| s.album_id)
+            .filter(|id| !evidence.album_artists.contains_key(id))
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        if album_ids.is_empty() {
+            return Ok(());
+        }
+
+        for album in self.albums.find_by_ids(&album_ids)? {
+            if let Some(artist_id) = album.artist_id {
+                evidence.album_artists.insert(album.id, artist_id);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// What is known about candidate songs besides their tags.
+#[derive(Debug, Default)]
+struct Evidence {
+    /// Album artist of each album.
+    album_artists: HashMap<i32, i32>,
+    /// Similarity of other artists to the seed artists.
+    similar_artists: HashMap<i32, f64>,
+    /// Number of playlists each song shares with the seeds.
+    playlist_counts: HashMap<i32, i64>,
+    /// Number of seed plays each song was played near by the same user.
+    session_counts: HashMap<i32, i64>,
+    /// The user's song ratings.
+    ratings: HashMap<i32, i32>,
+    /// Songs the user starred.
+    starred: HashSet<i32>,
+}
+
+impl Evidence {
+    fn album_artist(&self, song: &Song) -> Option<i32> {
+        song.album_id
+            .and_then(|id| self.album_artists.get(&id))
+            .copied()
+    }
+}
+
+/// Features of the seed songs that candidates are compared with.
+struct Seed {
+    artist_ids: HashSet<i32>,
+    album_artist_ids: HashSet<i32>,
+    genres: HashSet<String>,
+    years: Vec<i32>,
+}
+
+impl Seed {
+    fn new(songs: &[Song], evidence: &Evidence) -> Self {
+        Self {
+            artist_ids: songs.iter().filter_map(|s| s.artist_id).collect(),
+            album_artist_ids: songs
+                .iter()
+                .filter_map(|s| evidence.album_artist(s))
+                .collect(),
+            genres: songs.iter().flat_map(genres).collect(),
+            years: songs.iter().filter_map(|s| s.year).collect(),
+        }
+    }
+}
+
+/// Split a song's genre tag into lowercase genres.
+fn genres(song: &Song) -> HashSet<String> {
+    song.genre
+        .iter()
+        .flat_map(|genre| genre.split([';', '/', ',']))
+        .map(|genre| genre.trim().to_lowercase())
+        .filter(|genre| !genre.is_empty())
+        .collect()
+}
+
+/// Identify a recording by title and artist, to skip copies of the same track.
+fn track_key(song: &Song) -> (String, String) {
+    (
+        song.title.trim().to_lowercase(),
+        song.artist_name
+            .as_deref()
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase(),
+    )
+}
+
+/// Map a count onto 0..1, each further occurrence adding less.
+fn saturate(count: Option<&i64>) -> f64 {
+    let count = count.copied().unwrap_or(0) as f64;
+    count / (count + 2.0)
+}
+
+/// Score how well a candidate song matches the seeds.
+fn score(seed: &Seed, song: &Song, evidence: &Evidence) -> f64 {
+    let mut score = 0.0;
+
+    let song_genres = genres(song);
+    if !song_genres.is_empty() && !seed.genres.is_empty() {
+        let shared = song_genres.intersection(&seed.genres).count() as f64;
+        let all = song_genres.union(&seed.genres).count() as f64;
+        score += GENRE_WEIGHT * shared / all;
+    }
+
+    if let Some(year) = song.year
+        && let Some(closest) = seed.years.iter().map(|y| (y - year).abs()).min()
+    {
+        score += YEAR_WEIGHT * (1.0 - closest as f64 / YEAR_SPAN).max(0.0);
+    }
+
+    let shares_artist = song
+        .artist_id
+        .is_some_and(|id| seed.artist_ids.contains(&id))
+        || evidence
+            .album_artist(song)
+            .is_some_and(|id| seed.album_artist_ids.contains(&id));
+    if shares_artist {
+        score += SHARED_ARTIST_WEIGHT;
+    }
+
+    if let Some(similarity) = song
+        .artist_id
+        .and_then(|id| evidence.similar_artists.get(&id))
+    {
+        score += SIMILAR_ARTIST_WEIGHT * similarity;
+    }
+
+    score += PLAYLIST_WEIGHT * saturate(evidence.playlist_counts.get(&song.id));
+    score += SESSION_WEIGHT * saturate(evidence.session_counts.get(&song.id));
+
+    if let Some(&rating) = evidence.ratings.get(&song.id) {
+        score += RATING_WEIGHT * f64::from(rating - 3) / 2.0;
+    }
+    if evidence.starred.contains(&song.id) {
+        score += STARRED_WEIGHT;
+    }
+
+    score.max(0.0)
+}
+
+/// Score the candidates and pick a diversified list of up to `count` songs.
+///
+/// Seeds, duplicate candidates, other copies of an already picked recording
+/// and songs the user disliked are left out.
+fn rank(seeds: &[Song], candidates: Vec<Song>, evidence: &Evidence, count: usize) -> Vec<Song> {
+    let seed = Seed::new(seeds, evidence);
+    let mut seen: HashSet<i32> = seeds.iter().map(|s| s.id).collect();
+    let mut picked_tracks: HashSet<(String, String)> = seeds.iter().map(track_key).collect();
+
+    let mut scored: Vec<(Song, f64, (String, String))> = Vec::new();
+    for song in candidates {
+        if !seen.insert(song.id) || evidence.ratings.get(&song.id) == Some(&DISLIKED_RATING) {
+            continue;
+        }
+        let score = score(&seed, &song, evidence);
+        let key = track_key(&song);
+        scored.push((song, score, key));
+    }
+
+    let mut artist_picks: HashMap<i32, i32> = HashMap::new();
+    let mut album_picks: HashMap<i32, i32> = HashMap::new();
+    let mut picked = Vec::new();
+    while picked.len() < count {
+        let best = scored
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, _, key))| !picked_tracks.contains(key))
+            .map(|(i, (song, score, _))| {
+                let artist_repeats = song
+                    .artist_id
+                    .and_then(|id| artist_picks.get(&id))
+                    .copied()
+                    .unwrap_or(0);
+                let album_repeats = song
+                    .album_id
+                    .and_then(|id| album_picks.get(&id))
+                    .copied()
+                    .unwrap_or(0);
+                let adjusted = score
+                    * ARTIST_REPEAT_PENALTY.powi(artist_repeats)
+                    * ALBUM_REPEAT_PENALTY.powi(album_repeats);
+                (i, adjusted)
+            })
+            // Ties go to the earlier candidate
+            .reduce(|best, next| if next.1 > best.1 { next } else { best });
+        let Some((index, _)) = best else {
+            break;
+        };
+
+        let (song, _, key) = scored.remove(index);
+        if let Some(artist_id) = song.artist_id {
+            *artist_picks.entry(artist_id).or_default() += 1;
+        }
+        if let Some(album_id) = song.album_id {
+            *album_picks.entry(album_id).or_default() += 1;
+        }
+        picked_tracks.insert(key);
+        picked.push(song);
+    }
+    picked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDateTime;
+
+    fn song(id: i32, title: &str, artist_id: i32, genre: &str, year: i32) -> Song {
+        Song {
+            id,
+            title: title.to_string(),
+            sort_name: None,
+            album_id: Some(artist_id * 100),
+            artist_id: Some(artist_id),
+            artist_name: Some(format!("Artist {}", artist_id)),
+            album_name: None,
+            music_folder_id: 1,
+            path: format!("{}.mp3", id),
+            parent_path: String::new(),
+            file_size: 0,
+            content_type: "audio/mpeg".to_string(),
+            suffix: "mp3".to_string(),
+            duration: 180,
+            bit_rate: None,
+            bit_depth: None,
+            sampling_rate: None,
+            channel_count: None,
+            track_number: None,
+            disc_number: None,
+            year: Some(year),
+            genre: Some(genre.to_string()),
+            cover_art: None,
+            musicbrainz_id: None,
+            created_at: NaiveDateTime::default(),
+            updated_at: NaiveDateTime::default(),
+            cue_start_ms: None,
+            cue_end_ms: None,
+        }
+    }
+
+    fn ids(songs: &[Song]) -> Vec<i32> {
+        songs.iter().map(|s| s.id).collect()
+    }
+
+    #[test]
+    fn test_signals_raise_score() {
+        let seeds = [song(1, "Seed", 1, "Rock; Blues", 1970)];
+        let mut evidence = Evidence::default();
+        let seed = Seed::new(&seeds, &evidence);
+
+        let unrelated = song(2, "Unrelated", 2, "Techno", 2020);
+        let same_genre = song(3, "Same Genre", 3, "Rock", 1972);
+        assert_eq!(score(&seed, &unrelated, &evidence), 0.0);
+        assert!(score(&seed, &same_genre, &evidence) > 0.0);
+
+        // Co-listening and stars lift an otherwise unrelated song
+        evidence.session_counts.insert(2, 4);
+        evidence.starred.insert(2);
+        assert!(score(&seed, &unrelated, &evidence) > score(&seed, &same_genre, &evidence));
+
+        // A poor rating lowers a song, but never below zero
+        evidence.ratings.insert(3, 2);
+        let rated = score(&seed, &same_genre, &evidence);
+        evidence.ratings.remove(&3);
+        assert!(rated < score(&seed, &same_genre, &evidence));
+        evidence.ratings.insert(2, 2);
+        evidence.session_counts.clear();
+        evidence.starred.clear();
+        assert_eq!(score(&seed, &unrelated, &evidence), 0.0);
+    }
+
+    #[test]
+    fn test_rank_diversifies_and_deduplicates() {
+        let seeds = [song(1, "Seed", 1, "Rock", 1970)];
+        let candidates = vec![
+            song(1, "Seed", 1, "Rock", 1970),
+            song(2, "Seed", 1, "Rock", 1970),
+            song(3, "A", 1, "Rock", 1970),
+            song(4, "B", 1, "Rock", 1970),
+            song(4, "B", 1, "Rock", 1970),
+            song(5, "C", 2, "Rock", 1975),
+            song(6, "D", 3, "Rock", 1970),
+            song(7, "E", 4, "Rock", 1970),
+        ];
+        let mut evidence = Evidence::default();
+        evidence.ratings.insert(6, DISLIKED_RATING);
+
+        let picked = rank(&seeds, candidates.clone(), &evidence, 3);
+        // Another song by artist 1 falls behind weaker matches by other artists
+        assert_eq!(ids(&picked), vec![3, 7, 5]);
+
+        let picked = rank(&seeds, candidates, &evidence, 10);
+        assert_eq!(ids(&picked), vec![3, 7, 5, 4]);
+    }
+}
@@ -0,0 +1,615 @@
+//! Scrobble forwarding to external services.
+//!
+//! Users link accounts on services such as ListenBrainz and Last.fm, and their
+//! plays are mirrored there. Now playing notifications are sent right away and
+//! dropped if they fail, since they are stale soon after. Completed plays are
+//! written to an outbox table first and submitted in the background; a play
+//! stays in the outbox until the service accepts it, and failed submissions
+//! are retried with increasing delays, across restarts too. Plays the service
+//! rejects outright are dropped, and accounts whose credentials were revoked
+//! are unlinked.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{NaiveDateTime, TimeDelta, Utc};
+use thiserror::Error;
+use tokio::sync::{Notify, watch};
+
+use super::{AgentError, AgentFuture};
+use crate::db::{
+    DbPool, MusicRepoError, ScrobbleAccountRepository, ScrobbleOutboxRepository, SongRepository,
+};
+use crate::models::scrobbling::{PendingScrobble, ScrobbleAccount, ScrobbleService, ScrobbleTrack};
+
+/// Failed submissions after which a play is dropped from the outbox.
+pub const MAX_ATTEMPTS: i32 = 20;
+
+/// Delay before the first retry of a failed submission.
+const FIRST_RETRY_DELAY: TimeDelta = TimeDelta::minutes(1);
+
+/// Longest delay between retries.
+const MAX_RETRY_DELAY: TimeDelta = TimeDelta::hours(6);
+
+/// Most plays submitted before the outbox is checked again.
+const BATCH_SIZE: i64 = 50;
+
+/// Shortest pause between outbox checks.
+const MIN_WAIT: Duration = Duration::from_secs(1);
+
+/// Longest pause between outbox checks.
+const MAX_WAIT: Duration = Duration::from_secs(60 * 60);
+
+/// Time to wait before retrying a play that failed `attempts` times.
+///
+/// Doubles with every attempt, from one minute up to six hours.
+pub fn retry_delay(attempts: i32) -> TimeDelta {
+    let doublings = attempts.clamp(1, 16) - 1;
+    (FIRST_RETRY_DELAY * 2i32.pow(doublings as u32)).min(MAX_RETRY_DELAY)
+}
+
+/// How a failed submission is handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubmitFailure {
+    /// The service may accept the play later, so it is retried.
+    Retry,
+    /// The service will never accept the play, so it is dropped.
+    Rejected,
+    /// The account's credentials are no longer valid, so it is unlinked.
+    Unauthorized,
+}
+
+/// Classify an error by its HTTP status: 401 and 403 mean the credentials
+/// are invalid, other client errors except 429 mean the play is rejected,
+/// and anything else is retried.
+pub fn classify_http_error(error: &AgentError) -> SubmitFailure {
+    match error {
+        AgentError::Api {
+            code: 401 | 403, ..
+        } => SubmitFailure::Unauthorized,
+        AgentError::Api { code: 429, .. } => SubmitFailure::Retry,
+        AgentError::Api {
+            code: 400..=499, ..
+        } => SubmitFailure::Rejected,
+        _ => SubmitFailure::Retry,
+    }
+}
+
+/// Errors from linking a scrobbling account.
+#[derive(Debug, Error)]
+pub enum LinkError {
+    #[error("Scrobbling to {0} is not enabled")]
+    NotEnabled(ScrobbleService),
+
+    #[error("{0} rejected the credentials: {1}")]
+    Rejected(ScrobbleService, AgentError),
+
+    #[error("Database error: {0}")]
+    Database(#[from] MusicRepoError),
+}
+
+/// Credentials to store for a linked account.
+#[derive(Debug, Clone)]
+pub struct LinkedAccount {
+    /// Token used to submit plays for the user.
+    pub token: String,
+    /// Username on the service, if known.
+    pub username: Option<String>,
+}
+
+/// A scrobbling service plays can be submitted to.
+pub trait Scrobbler: Send + Sync {
+    /// The service this scrobbler submits to.
+    fn service(&self) -> ScrobbleService;
+
+    /// Check the credentials a user supplied, returning those to store.
+    fn link<'a>(&'a self, token: &'a str) -> AgentFuture<'a, LinkedAccount>;
+
+    /// Tell the service a track started playing.
+    fn now_playing<'a>(&'a self, token: &'a str, track: &'a ScrobbleTrack) -> AgentFuture<'a, ()>;
+
+    /// Submit a completed play.
+    fn scrobble<'a>(
+        &'a self,
+        token: &'a str,
+        track: &'a ScrobbleTrack,
+        played_at: NaiveDateTime,
+    ) -> AgentFuture<'a, ()>;
+
+    /// Decide how to handle an error from submitting a play.
+    fn classify(&self, error: &AgentError) -> SubmitFailure {
+        classify_http_error(error)
+    }
+}
+
+/// Forwards plays to the scrobbling services users have linked accounts on.
+#[derive(Clone)]
+pub struct ScrobbleForwarder {
+    scrobblers: Vec<Arc<dyn Scrobbler>>,
+    accounts: ScrobbleAccountRepository,
+    outbox: ScrobbleOutboxRepository,
+    songs: SongRepository,
+    wake: Arc<Notify>,
+}
+
+impl ScrobbleForwarder {
+    /// Create a forwarder without scrobblers, which forwards nothing.
+    pub fn new(pool: DbPool) -> Self {
+        Self {
+            scrobblers: Vec::new(),
+            accounts: ScrobbleAccountRepository::new(pool.clone()),
+            outbox: ScrobbleOutboxRepository::new(pool.clone()),
+            songs: SongRepository::new(pool),
+            wake: Arc::new(Notify::new()),
+        }
+    }
+
+    /// Add a service plays can be forwarded to.
+    pub fn with_scrobbler(mut self, scrobbler: Arc<dyn Scrobbler>) -> Self {
+        self.scrobblers
+            .retain(|s| s.service() != scrobbler.service());
+        self.scrobblers.push(scrobbler);
+        self
+    }
+
+    fn scrobbler(&self, service: ScrobbleService) -> Option<&Arc<dyn Scrobbler>> {
+        self.scrobblers.iter().find(|s| s.service() == service)
+    }
+
+    fn services(&self) -> Vec<ScrobbleService> {
+        self.scrobblers.iter().map(|s| s.service()).collect()
+    }
+
+    /// Get a user's linked accounts, with the number of plays waiting to be
+    /// submitted to each.
+    pub fn accounts(&self, user_id: i32) -> Result<Vec<(ScrobbleAccount, i64)>, MusicRepoError> {
+        let pending = self.outbox.count_by_user(user_id)?;
+        Ok(self
+            .accounts
+            .find_by_user(user_id)?
+            .into_iter()
+            .map(|account| {
+                let count = pending.get(&account.service).copied().unwrap_or(0);
+                (account, count)
+            })
+            .collect())
+    }
+
+    /// Check a user's credentials with a service and link the account.
+    pub async fn link(
+        &self,
+        user_id: i32,
+        service: ScrobbleService,
+        token: &str,
+    ) -> Result<ScrobbleAccount, LinkError> {
+        let scrobbler = self
+            .scrobbler(service)
+            .ok_or(LinkError::NotEnabled(service))?;
+        let linked = scrobbler
+            .link(token)
+            .await
+            .map_err(|e| LinkError::Rejected(service, e))?;
+        Ok(self
+            .accounts
+            .save(user_id, service, &linked.token, linked.username.as_deref())?)
+    }
+
+    /// Unlink a user's account, returning false if there was none.
+    pub fn unlink(&self, user_id: i32, service: ScrobbleService) -> Result<bool, MusicRepoError> {
+        self.accounts.delete(user_id, service)
+    }
+
+    /// Get the user's accounts on the services plays are forwarded to.
+    fn forwarded_accounts(&self, user_id: i32) -> Vec<ScrobbleAccount> {
+        if self.scrobblers.is_empty() {
+            return Vec::new();
+        }
+        match self.accounts.find_by_user(user_id) {
+            Ok(accounts) => accounts
+                .into_iter()
+                .filter(|a| self.scrobbler(a.service).is_some())
+                .collect(),
+            Err(e) => {
+                tracing::warn!(
+                    "Failed to load scrobbling accounts of user {}: {}",
+                    user_id,
+                    e
+                );
+                Vec::new()
+            }
+        }
+    }
+
+    fn track(&self, song_id: i32) -> Option<ScrobbleTrack> {
+        let song = self.songs.find_by_id(song_id).ok().flatten()?;
+        ScrobbleTrack::from_song(&song)
+    }
+
+    /// Tell the user's linked services a song started playing, in the background.
+    pub fn now_playing(&self, user_id: i32, song_id: i32) {
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let accounts = self.forwarded_accounts(user_id);
+        if accounts.is_empty() {
+            return;
+        }
+        let Some(track) = self.track(song_id) else {
+            return;
+        };
+
+        for account in accounts {
+            let Some(scrobbler) = self.scrobbler(account.service).cloned() else {
+                continue;
+            };
+            let track = track.clone();
+            runtime.spawn(async move {
+                if let Err(e) = scrobbler.now_playing(&account.token, &track).await {
+                    tracing::warn!(
+                        "{} now playing update for user {} failed: {}",
+                        account.service,
+                        account.user_id,
+                        e
+                    );
+                }
+            });
+        }
+    }
+
+    /// Queue a completed play for submission to the user's linked services.
+    pub fn scrobble(&self, user_id: i32, song_id: i32, played_at: NaiveDateTime) {
+        let accounts = self.forwarded_accounts(user_id);
+        for account in &accounts {
+            if let Err(e) = self
+                .outbox
+                .enqueue(user_id, account.service, song_id, played_at)
+            {
+                tracing::warn!(
+                    "Failed to queue play of song {} for {}: {}",
+                    song_id,
+                    account.service,
+                    e
+                );
+            }
+        }
+        if !accounts.is_empty() {
+            self.wake.notify_one();
+        }
+    }
+
+    /// Submit a queued play. Returns false if it could not be submitted and
+    /// should be dropped, because the account or song is gone.
+    async fn submit(&self, pending: &PendingScrobble) -> Result<bool, AgentError> {
+        let Some(scrobbler) = self.scrobbler(pending.service) else {
+            return Ok(false);
+        };
+        let Some(account) = self
+            .accounts
+            .find(pending.user_id, pending.service)
+            .ok()
+            .flatten()
+        else {
+            return Ok(false);
+        };
+        let Some(track) = self.track(pending.song_id) else {
+            return Ok(false);
+        };
+
+        scrobbler
+            .scrobble(&account.token, &track, pending.played_at)
+            .await?;
+        Ok(true)
+    }
+
+    /// Submit the plays that are due, oldest first.
+    ///
+    /// Once a submission for an account fails, all of the account's due plays
+    /// are postponed along with the failed one, so a service that is down gets
+    /// one request per account and retry delay. Other accounts on the service
+    /// keep their own delays.
+    async fn submit_due(&self) {
+        let now = Utc::now().naive_utc();
+        let due = match self.outbox.find_due(&self.services(), now, BATCH_SIZE) {
+            Ok(due) => due,
+            Err(e) => {
+                tracing::warn!("Failed to load scrobble outbox: {}", e);
+                return;
+            }
+        };
+
+        let mut failing = HashSet::new();
+        for pending in due {
+            let account = (pending.user_id, pending.service);
+            if failing.contains(&account) {
+                continue;
+            }
+
+            let saved = match self.submit(&pending).await {
+                Ok(_) => self.outbox.remove(pending.id),
+                Err(e) => {
+                    let failure = self
+                        .scrobbler(pending.service)
+                        .map_or(SubmitFailure::Retry, |s| s.classify(&e));
+                    match failure {
+                        SubmitFailure::Unauthorized => {
+                            failing.insert(account);
+                            tracing::warn!(
+                                "{} rejected the credentials of user {}, unlinking the account: {}",
+                                pending.service,
+                                pending.user_id,
+                                e
+                            );
+                            self.accounts
+                                .delete(pending.user_id, pending.service)
+                                .map(|_| ())
+                        }
+                        SubmitFailure::Rejected => {
+                            tracing::warn!(
+                                "{} rejected play {}, dropping it: {}",
+                                pending.service,
+                                pending.id,
+                                e
+                            );
+                            self.outbox.remove(pending.id)
+                        }
+                        SubmitFailure::Retry => {
+                            failing.insert(account);
+                            self.retry_later(&pending, now, &e)
+                        }
+                    }
+                }
+            };
+            if let Err(e) = saved {
+                tracing::warn!("Failed to update scrobble outbox: {}", e);
+            }
+        }
+    }
+
+    /// Postpone a play that failed to submit, and the account's other due
+    /// plays with it, or give up on it after too many attempts.
+    fn retry_later(
+        &self,
+        pending: &PendingScrobble,
+        now: NaiveDateTime,
+        error: &AgentError,
+    ) -> Result<(), MusicRepoError> {
+        let attempts = pending.attempts + 1;
+        let next_attempt_at = Utc::now().naive_utc() + retry_delay(attempts);
+        self.outbox
+            .postpone_account(pending.user_id, pending.service, now, next_attempt_at)?;
+
+        if attempts >= MAX_ATTEMPTS {
+            tracing::warn!(
+                "Giving up on play {} for {} after {} attempts: {}",
+                pending.id,
+                pending.service,
+                attempts,
+                error
+            );
+            self.outbox.remove(pending.id)
+        } else {
+            tracing::info!(
+                "Submitting play {} to {} failed, will retry: {}",
+                pending.id,
+                pending.service,
+                error
+            );
+            self.outbox
+                .postpone(pending.id, attempts, next_attempt_at, &error.to_string())
+        }
+    }
+
+    /// Time until the next queued play is due.
+    fn time_until_next(&self) -> Duration {
+        match self.outbox.next_attempt_at(&self.services()) {
+            Ok(Some(next)) => (next - Utc::now().naive_utc())
+                .to_std()
+                .unwrap_or_default()
+                .clamp(MIN_WAIT, MAX_WAIT),
+            Ok(None) => MAX_WAIT,
+            // Check again soon in case the database recovers
+            Err(_) => Duration::from_secs(60),
+        }
+    }
+
+    /// Start submitting queued plays in the background.
+    /// Returns a handle that can be used to stop the forwarder.
+    pub fn start(&self) -> ScrobbleForwarderHandle {
+        let (shutdown_tx, mut shutdown_rx) = watch::channel(false);
+        let forwarder = self.clone();
+
+        tokio::spawn(async move {
+            let services: Vec<_> = forwarder
+                .services()
+                .iter()
+                .map(ToString::to_string)
+                .collect();
+            tracing::info!("Scrobble forwarder started for {}", services.join(", "));
+
+            loop {
+                forwarder.submit_due().await;
+
+                tokio::select! {
+                    _ = tokio::time::sleep(forwarder.time_until_next()) => {}
+                    _ = forwarder.wake.notified() => {}
+                    _ = shutdown_rx.changed() => {
+                        if *shutdown_rx.borrow() {
+                            break;
+                        }
+                    }
+                }
+            }
+
+            tracing::info!("Scrobble forwarder stopped");
+        });
+
+        ScrobbleForwarderHandle { shutdown_tx }
+    }
+}
+
+/// Handle for controlling the scrobble forwarder.
+pub struct ScrobbleForwarderHandle {
+    shutdown_tx: watch::Sender<bool>,
+}
+
+impl ScrobbleForwarderHandle {
+    /// Stop the scrobble forwarder.
+    pub fn stop(&self) {
+        let _ = self.shutdown_tx.send(true);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use diesel::RunQueryDsl;
+
+    use super::*;
+    use crate::db::{DbConfig, MusicFolderRepository, NewUser, UserRepository, run_migrations};
+    use crate::models::music::NewMusicFolder;
+
+    /// Scrobbler that fails for the tokens "revoked" and "down", and records
+    /// the tokens of the plays it accepts.
+    #[derive(Default)]
+    struct FakeScrobbler {
+        accepted: Mutex<Vec<String>>,
+    }
+
+    impl Scrobbler for FakeScrobbler {
+        fn service(&self) -> ScrobbleService {
+            ScrobbleService::ListenBrainz
+        }
+
+        fn link<'a>(&'a self, token: &'a str) -> AgentFuture<'a, LinkedAccount> {
+            Box::pin(async move {
+                Ok(LinkedAccount {
+                    token: token.to_string(),
+                    username: None,
+                })
+            })
+        }
+
+        fn now_playing<'a>(&'a self, _: &'a str, _: &'a ScrobbleTrack) -> AgentFuture<'a, ()> {
+            Box::pin(async { Ok(()) })
+        }
+
+        fn scrobble<'a>(
+            &'a self,
+            token: &'a str,
+            _: &'a ScrobbleTrack,
+            _: NaiveDateTime,
+        ) -> AgentFuture<'a, ()> {
+            Box::pin(async move {
+                let code = match token {
+                    "revoked" => 401,
+                    "down" => 503,
+                    _ => {
+                        self.accepted.lock().unwrap().push(token.to_string());
+                        return Ok(());
+                    }
+                };
+                Err(AgentError::Api {
+                    code,
+                    message: token.to_string(),
+                })
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failing_account_does_not_hold_back_others() {
+        let dir =
+            std::env::temp_dir().join(format!("subsonic-scrobbling-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let pool = DbConfig::new(dir.join("test.db").to_string_lossy())
+            .build_pool()
+            .unwrap();
+        run_migrations(&mut pool.get().unwrap()).unwrap();
+
+        let folder = MusicFolderRepository::new(pool.clone())
+            .create(&NewMusicFolder::new("Music", dir.to_string_lossy()))
+            .unwrap();
+        diesel::sql_query(format!(
+            "INSERT INTO songs (id, title, artist_name, music_folder_id, path, parent_path, \
+             content_type, suffix) \
+             VALUES (1, 'Song', 'Artist', {}, '/music/song.mp3', '/music', 'audio/mpeg', 'mp3')",
+            folder.id
+        ))
+        .execute(&mut pool.get().unwrap())
+        .unwrap();
+
+        let scrobbler = Arc::new(FakeScrobbler::default());
+        let forwarder = ScrobbleForwarder::new(pool.clone()).with_scrobbler(scrobbler.clone());
+        let users = UserRepository::new(pool.clone());
+        let service = ScrobbleService::ListenBrainz;
+        let played_at = Utc::now().naive_utc() - TimeDelta::hours(1);
+
+        // The broken accounts' plays are the oldest, so they are submitted first
+        let mut ids = Vec::new();
+        for (i, token) in ["revoked", "down", "good"].into_iter().enumerate() {
+            let user = users
+                .create(&NewUser::regular(token, "hash", "secret"))
+                .unwrap();
+            forwarder
+                .accounts
+                .save(user.id, service, token, None)
+                .unwrap();
+            for minute in 0..2 {
+                let at = played_at + TimeDelta::minutes(i as i64 * 2 + minute);
+                forwarder.outbox.enqueue(user.id, service, 1, at).unwrap();
+            }
+            ids.push(user.id);
+        }
+        let [revoked, down, good] = ids[..] else {
+            unreachable!()
+        };
+
+        forwarder.submit_due().await;
+
+        assert_eq!(*scrobbler.accepted.lock().unwrap(), ["good", "good"]);
+        assert!(forwarder.outbox.count_by_user(good).unwrap().is_empty());
+
+        // The revoked account is unlinked along with its plays
+        assert!(forwarder.accounts.find(revoked, service).unwrap().is_none());
+        assert!(forwarder.outbox.count_by_user(revoked).unwrap().is_empty());
+
+        // Plays of the account whose service is down wait for the retry delay
+        assert_eq!(forwarder.outbox.count_by_user(down).unwrap()[&service], 2);
+        let now = Utc::now().naive_utc();
+        assert!(
+            forwarder
+                .outbox
+                .find_due(&[service], now, BATCH_SIZE)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_retry_delay_doubles_up_to_cap() {
+        assert_eq!(retry_delay(1), TimeDelta::minutes(1));
+        assert_eq!(retry_delay(2), TimeDelta::minutes(2));
+        assert_eq!(retry_delay(5), TimeDelta::minutes(16));
+        assert_eq!(retry_delay(9), TimeDelta::minutes(256));
+        assert_eq!(retry_delay(10), TimeDelta::hours(6));
+        assert_eq!(retry_delay(MAX_ATTEMPTS), TimeDelta::hours(6));
+    }
+
+    #[test]
+    fn test_classify_http_errors() {
+        let error = |code| AgentError::Api {
+            code,
+            message: String::new(),
+        };
+        assert_eq!(
+            classify_http_error(&error(401)),
+            SubmitFailure::Unauthorized
+        );
+        assert_eq!(classify_http_error(&error(400)), SubmitFailure::Rejected);
+        assert_eq!(classify_http_error(&error(429)), SubmitFailure::Retry);
+        assert_eq!(classify_http_error(&error(503)), SubmitFailure::Retry);
+    }
+}
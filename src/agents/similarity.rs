@@ -0,0 +1,242 @@
+//! Artist similarity from local co-listening.
+//!
+//! Plays are split into listening sessions per user. Artists heard in the same
+//! session are related, with sessions spanning many artists counting for less,
+//! and the totals are normalized by how often each artist is played so that
+//! popular artists do not dominate every list.
+
+use std::collections::{BTreeSet, HashMap};
+use std::time::Duration;
+
+use chrono::TimeDelta;
+use tokio::sync::watch;
+
+use crate::db::{ArtistSimilarityRepository, DbPool, MusicRepoError};
+use crate::models::metadata::{ArtistPlay, ArtistSimilarity};
+
+/// Default time between similarity refreshes, in hours.
+pub const DEFAULT_REFRESH_INTERVAL_HOURS: u64 = 24;
+
+/// Longest pause between two plays of the same listening session.
+pub const SESSION_GAP: TimeDelta = TimeDelta::minutes(30);
+
+/// Most similar artists kept for each artist.
+const MAX_SIMILAR_ARTISTS: usize = 50;
+
+/// Compute artist similarity from plays.
+///
+/// Scores range from 0 to 1, where 1 means the two artists were only ever
+/// played in the same sessions, and only with each other.
+pub fn compute_similarity(plays: &[ArtistPlay]) -> Vec<ArtistSimilarity> {
+    let mut plays = plays.to_vec();
+    plays.sort_by_key(|p| (p.user_id, p.played_at));
+
+    let mut session_counts: HashMap<i32, f64> = HashMap::new();
+    let mut pair_weights: HashMap<(i32, i32), f64> = HashMap::new();
+    let mut close_session = |artists: &BTreeSet<i32>| {
+        for &artist_id in artists {
+            *session_counts.entry(artist_id).or_default() += 1.0;
+        }
+        if artists.len() < 2 {
+            return;
+        }
+        let weight = 1.0 / (artists.len() - 1) as f64;
+        for (i, &a) in artists.iter().enumerate() {
+            for &b in artists.iter().skip(i + 1) {
+                *pair_weights.entry((a, b)).or_default() += weight;
+            }
+        }
+    };
+
+    let mut session = BTreeSet::new();
+    let mut previous: Option<&ArtistPlay> = None;
+    for play in &plays {
+        if let Some(prev) = previous
+            && (prev.user_id != play.user_id || play.played_at - prev.played_at > SESSION_GAP)
+        {
+            close_session(&session);
+            session.clear();
+        }
+        session.insert(play.artist_id);
+        previous = Some(play);
+    }
+    close_session(&session);
+
+    let mut by_artist: HashMap<i32, Vec<ArtistSimilarity>> = HashMap::new();
+    for (&(a, b), &weight) in &pair_weights {
+        let score = weight / (session_counts[&a] * session_counts[&b]).sqrt();
+        for (artist_id, similar_artist_id) in [(a, b), (b, a)] {
+            by_artist
+                .entry(artist_id)
+                .or_default()
+                .push(ArtistSimilarity {
+                    artist_id,
+                    similar_artist_id,
+                    score,
+                });
+        }
+    }
+
+    let mut similarities = Vec::new();
+    for mut similar in by_artist.into_values() {
+        similar.sort_by(|x, y| {
+            y.score
+                .total_cmp(&x.score)
+                .then(x.similar_artist_id.cmp(&y.similar_artist_id))
+        });
+        similar.truncate(MAX_SIMILAR_ARTISTS);
+        similarities.extend(similar);
+    }
+    similarities
+}
+
+/// Recompute the artist similarity table from the scrobble history.
+///
+/// Returns the number of similarity pairs stored.
+pub fn refresh_similarity(pool: DbPool) -> Result<usize, MusicRepoError> {
+    let repo = ArtistSimilarityRepository::new(pool);
+    let similarities = compute_similarity(&repo.find_artist_plays()?);
+    repo.replace_all(&similarities)?;
+    Ok(similarities.len())
+}
+
+/// Refreshes artist similarity in the background on a schedule.
+pub struct SimilarityRefresher {
+    pool: DbPool,
+    interval: Duration,
+}
+
+impl SimilarityRefresher {
+    /// Create a similarity refresher with the default interval (24 hours).
+    pub fn new(pool: DbPool) -> Self {
+        Self {
+            pool,
+            interval: Duration::from_secs(DEFAULT_REFRESH_INTERVAL_HOURS * 60 * 60),
+        }
+    }
+
+    /// Set the time between refreshes.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Start refreshing in the background, beginning immediately.
+    /// Returns a handle that can be used to stop the refresher.
+    pub fn start(self) -> SimilarityRefreshHandle {
+        let (shutdown_tx, mut shutdown_rx) = watch::channel(false);
+
+        tokio::spawn(async move {
+            tracing::info!(
+                "Similarity refresher started with interval {:?}",
+                self.interval
+            );
+
+            loop {
+                let pool = self.pool.clone();
+                match tokio::task::spawn_blocking(move || refresh_similarity(pool)).await {
+                    Ok(Ok(count)) => {
+                        tracing::info!("Artist similarity refreshed: {} pairs", count);
+                    }
+                    Ok(Err(e)) => tracing::error!("Artist similarity refresh failed: {}", e),
+                    Err(e) => tracing::error!("Artist similarity refresh panicked: {}", e),
+                }
+
+                tokio::select! {
+                    _ = tokio::time::sleep(self.interval) => {}
+                    _ = shutdown_rx.changed() => {
+                        if *shutdown_rx.borrow() {
+                            break;
+                        }
+                    }
+                }
+            }
+
+            tracing::info!("Similarity refresher stopped");
+        });
+
+        SimilarityRefreshHandle { shutdown_tx }
+    }
+}
+
+/// Handle for controlling the similarity refresher.
+pub struct SimilarityRefreshHandle {
+    shutdown_tx: watch::Sender<bool>,
+}
+
+impl SimilarityRefreshHandle {
+    /// Stop the similarity refresher.
+    pub fn stop(&self) {
+        let _ = self.shutdown_tx.send(true);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDateTime;
+
+    fn play(user_id: i32, artist_id: i32, minute: i64) -> ArtistPlay {
+        ArtistPlay {
+            user_id,
+            artist_id,
+            played_at: NaiveDateTime::default() + TimeDelta::minutes(minute),
+        }
+    }
+
+    fn score(similarities: &[ArtistSimilarity], a: i32, b: i32) -> Option<f64> {
+        similarities
+            .iter()
+            .find(|s| s.artist_id == a && s.similar_artist_id == b)
+            .map(|s| s.score)
+    }
+
+    #[test]
+    fn test_sessions_split_by_gap_and_user() {
+        let plays = [
+            // User 1 plays artists 1 and 2 together, then artist 3 much later
+            play(1, 1, 0),
+            play(1, 2, 5),
+            play(1, 3, 60),
+            // User 2 plays artist 3 while user 1 plays artist 1
+            play(2, 3, 1),
+        ];
+
+        let similarities = compute_similarity(&plays);
+        assert_eq!(score(&similarities, 1, 2), Some(1.0));
+        assert_eq!(score(&similarities, 2, 1), Some(1.0));
+        assert_eq!(score(&similarities, 1, 3), None);
+        assert_eq!(similarities.len(), 2);
+    }
+
+    #[test]
+    fn test_scores_are_weighted_and_normalized() {
+        let plays = [
+            // Artists 1 and 2 are played together twice
+            play(1, 1, 0),
+            play(1, 2, 5),
+            play(1, 1, 100),
+            play(1, 2, 105),
+            // A session with four artists relates each pair only weakly
+            play(2, 1, 0),
+            play(2, 3, 5),
+            play(2, 4, 10),
+            play(2, 5, 15),
+        ];
+
+        let similarities = compute_similarity(&plays);
+        let close = score(&similarities, 1, 2).unwrap();
+        let weak = score(&similarities, 1, 3).unwrap();
+        assert!(close > weak);
+        assert!((close - 2.0 / (3.0f64 * 2.0).sqrt()).abs() < 1e-9);
+        assert!((weak - (1.0 / 3.0) / 3.0f64.sqrt()).abs() < 1e-9);
+
+        // Artist 1's list is ordered by score
+        let order: Vec<i32> = similarities
+            .iter()
+            .filter(|s| s.artist_id == 1)
+            .map(|s| s.similar_artist_id)
+            .collect();
+        assert_eq!(order, vec![2, 3, 4, 5]);
+    }
+}
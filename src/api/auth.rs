@@ -21,6 +21,9 @@
 //! - Form body (POST requests with application/x-www-form-urlencoded)
 //! - Or a combination of both (query params take precedence)
 
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
 use std::sync::Arc;
 
 use axum::{
@@ -34,18 +37,31 @@ use serde::Deserialize;
 
 use super::error::ApiError;
 use super::response::{Format, error_response};
+use crate::agents::podcast::default_podcast_folder;
+use crate::agents::{MetadataService, PodcastService, Recommender, ScrobbleForwarder, radio};
 use crate::crypto::hash_password;
 use crate::db::{
-    AlbumRepository, ArtistRepository, DbPool, MusicFolderRepository, NewUser, NowPlayingEntry,
-    NowPlayingRepository, PlayQueue, PlayQueueRepository, Playlist, PlaylistRepository,
-    RatingRepository, ScrobbleRepository, SongRepository, StarredRepository, UserRepository,
-    UserUpdate,
+    AlbumRepository, ArtistRepository, ArtistSimilarityRepository, Bookmark, BookmarkRepository,
+    ChatMessageRepository, DbPool, DirectoryRepository, InternetRadioRepository, LyricsRepository,
+    MusicFolderRepository, NewUser, NowPlayingEntry, NowPlayingRepository, PlayQueue,
+    PlayQueueRepository, Playlist, PlaylistRepository, PodcastRepository, RatingRepository,
+    ScanHistoryRepository, ScrobbleRepository, Share, ShareRepository, SongRepository,
+    StarredRepository, StatisticsRepository, UserRepository, UserUpdate,
 };
 use crate::models::User;
-use crate::models::music::{Album, Artist, MusicFolder, Song};
+use crate::models::chat::ChatMessage;
+use crate::models::metadata::{AlbumMetadata, ArtistMetadata};
+use crate::models::music::{Album, Artist, Directory, MusicFolder, PlayStats, Song};
+use crate::models::podcast::{PodcastChannel, PodcastEpisode};
+use crate::models::radio::InternetRadioStation;
+use crate::models::scan::{ScanRun, ScanRunError};
+use crate::models::scrobbling::{ScrobbleAccount, ScrobbleService};
+use crate::models::stats::{StatsFilter, UserListening};
 use crate::scanner::ScanState;
 use crate::scanner::lyrics::ExtractedLyrics;
-use chrono::NaiveDateTime;
+use crate::scanner::playlists::PlaylistImportConfig;
+use crate::scanner::smart_playlists::DEFAULT_REFRESH_TTL_SECONDS;
+use chrono::{NaiveDateTime, TimeDelta};
 
 /// Application state that must be available for auth.
 pub trait AuthState: Send + Sync + 'static {
@@ -59,6 +75,16 @@ pub trait AuthState: Send + Sync + 'static {
     fn get_artists(&self) -> Vec<Artist>;
     /// Get the last modified time for artists.
     fn get_artists_last_modified(&self) -> Option<NaiveDateTime>;
+    /// Get a directory by ID.
+    fn get_directory(&self, directory_id: i32) -> Option<Directory>;
+    /// Get the top-level directories, optionally limited to one music folder.
+    fn get_top_level_directories(&self, music_folder_id: Option<i32>) -> Vec<Directory>;
+    /// Get the subdirectories of a directory.
+    fn get_child_directories(&self, directory_id: i32) -> Vec<Directory>;
+    /// Get the songs directly inside a directory of a music folder.
+    fn get_songs_by_directory(&self, music_folder_id: i32, path: &str) -> Vec<Song>;
+    /// Get the last modified time for directories.
+    fn get_directories_last_modified(&self) -> Option<NaiveDateTime>;
     /// Get album count for an artist.
     fn get_artist_album_count(&self, artist_id: i32) -> i64;
     /// Get a song by ID.
@@ -67,8 +93,18 @@ pub trait AuthState: Send + Sync + 'static {
     fn get_album(&self, album_id: i32) -> Option<Album>;
     /// Get an artist by ID.
     fn get_artist(&self, artist_id: i32) -> Option<Artist>;
+    /// Get the library artists with any of the given names.
+    fn get_artists_by_names(&self, names: &[String]) -> Vec<Artist>;
+    /// Get the artists most often played alongside an artist, most similar first.
+    fn get_similar_artists(&self, artist_id: i32, limit: i64) -> Vec<Artist>;
+    /// Get agent metadata of an artist, fetching it in the background if missing or stale.
+    fn get_artist_metadata(&self, artist: &Artist) -> Option<ArtistMetadata>;
+    /// Get agent metadata of an album, fetching it in the background if missing or stale.
+    fn get_album_metadata(&self, album: &Album) -> Option<AlbumMetadata>;
     /// Get songs by album ID.
     fn get_songs_by_album(&self, album_id: i32) -> Vec<Song>;
+    /// Get songs by artist ID.
+    fn get_songs_by_artist(&self, artist_id: i32) -> Vec<Song>;
     /// Get albums by artist ID.
     fn get_albums_by_artist(&self, artist_id: i32) -> Vec<Album>;
 
@@ -79,10 +115,10 @@ pub trait AuthState: Send + Sync + 'static {
     fn get_albums_alphabetical_by_artist(&self, offset: i64, limit: i64) -> Vec<Album>;
     /// Get newest albums.
     fn get_albums_newest(&self, offset: i64, limit: i64) -> Vec<Album>;
-    /// Get most frequently played albums.
-    fn get_albums_frequent(&self, offset: i64, limit: i64) -> Vec<Album>;
-    /// Get recently played albums.
-    fn get_albums_recent(&self, offset: i64, limit: i64) -> Vec<Album>;
+    /// Get the albums a user has played most often.
+    fn get_albums_frequent(&self, user_id: i32, offset: i64, limit: i64) -> Vec<Album>;
+    /// Get the albums a user has played most recently.
+    fn get_albums_recent(&self, user_id: i32, offset: i64, limit: i64) -> Vec<Album>;
     /// Get random albums.
     fn get_albums_random(&self, limit: i64) -> Vec<Album>;
     /// Get albums by year range.
@@ -156,6 +192,18 @@ pub trait AuthState: Send + Sync + 'static {
         user_id: i32,
         album_ids: &[i32],
     ) -> std::collections::HashMap<i32, NaiveDateTime>;
+    /// Get a user's play stats for multiple songs in a single query.
+    fn get_song_play_stats_batch(
+        &self,
+        user_id: i32,
+        song_ids: &[i32],
+    ) -> std::collections::HashMap<i32, PlayStats>;
+    /// Get a user's play stats for multiple albums in a single query.
+    fn get_album_play_stats_batch(
+        &self,
+        user_id: i32,
+        album_ids: &[i32],
+    ) -> std::collections::HashMap<i32, PlayStats>;
     /// Get starred_at timestamps for multiple artists in a single query.
     fn get_starred_at_for_artists_batch(
         &self,
@@ -164,7 +212,8 @@ pub trait AuthState: Send + Sync + 'static {
     ) -> std::collections::HashMap<i32, NaiveDateTime>;
 
     // Scrobble/now playing methods
-    /// Record a scrobble (song play).
+    /// Record a scrobble (song play), forwarding it to the user's linked
+    /// scrobbling accounts.
     fn scrobble(
         &self,
         user_id: i32,
@@ -172,16 +221,43 @@ pub trait AuthState: Send + Sync + 'static {
         time: Option<i64>,
         submission: bool,
     ) -> Result<(), String>;
-    /// Set a song as now playing for a user.
+    /// Set a song as now playing on a user's player, with the playback
+    /// position in milliseconds if the player reported one.
     fn set_now_playing(
         &self,
         user_id: i32,
         song_id: i32,
         player_id: Option<&str>,
+        position_ms: Option<i64>,
+    ) -> Result<(), String>;
+    /// Clear a song from a user's player once it has been played.
+    fn clear_now_playing(
+        &self,
+        user_id: i32,
+        song_id: i32,
+        player_id: Option<&str>,
     ) -> Result<(), String>;
     /// Get all currently playing songs.
     fn get_now_playing(&self) -> Vec<NowPlayingEntry>;
 
+    // Scrobble forwarding methods
+    /// Get a user's linked scrobbling accounts, with the number of plays
+    /// waiting to be submitted to each.
+    fn get_scrobble_accounts(&self, user_id: i32) -> Vec<(ScrobbleAccount, i64)>;
+    /// Check credentials with a scrobbling service and link the user's account.
+    fn link_scrobble_account<'a>(
+        &'a self,
+        user_id: i32,
+        service: ScrobbleService,
+        token: &'a str,
+    ) -> Pin<Box<dyn Future<Output = Result<ScrobbleAccount, String>> + Send + 'a>>;
+    /// Unlink a user's scrobbling account. Returns false if there was none.
+    fn unlink_scrobble_account(
+        &self,
+        user_id: i32,
+        service: ScrobbleService,
+    ) -> Result<bool, String>;
+
     // Random/genre song methods
     /// Get random songs with optional filters.
     fn get_random_songs(
@@ -201,16 +277,33 @@ pub trait AuthState: Send + Sync + 'static {
         music_folder_id: Option<i32>,
     ) -> Vec<Song>;
 
-    /// Get similar songs by artist (random songs from the same artist, excluding a specific song).
-    fn get_similar_songs_by_artist(
+    /// Get songs like the seed songs, scored for the user and diversified.
+    fn get_similar_songs(&self, user_id: i32, seeds: &[Song], count: usize) -> Vec<Song>;
+
+    /// Get top songs by artist name (ordered by the user's play count).
+    fn get_top_songs_by_artist_name(
         &self,
-        artist_id: i32,
-        exclude_song_id: i32,
+        user_id: i32,
+        artist_name: &str,
         limit: i64,
     ) -> Vec<Song>;
 
-    /// Get top songs by artist name (ordered by play count).
-    fn get_top_songs_by_artist_name(&self, artist_name: &str, limit: i64) -> Vec<Song>;
+    // Statistics methods
+    /// Get the number of plays and listening time of each user.
+    fn get_listening_by_user(&self, filter: &StatsFilter) -> Vec<UserListening>;
+    /// Get the most played artists with their play counts.
+    fn get_top_artists(&self, filter: &StatsFilter, limit: i64) -> Vec<(Artist, i64)>;
+    /// Get the most played albums with their play counts.
+    fn get_top_albums(&self, filter: &StatsFilter, limit: i64) -> Vec<(Album, i64)>;
+    /// Get the most played songs with their play counts, optionally only by one artist.
+    fn get_top_songs(
+        &self,
+        filter: &StatsFilter,
+        artist_name: Option<&str>,
+        limit: i64,
+    ) -> Vec<(Song, i64)>;
+    /// Get the most played genres with their play counts.
+    fn get_top_genres(&self, filter: &StatsFilter, limit: i64) -> Vec<(String, i64)>;
 
     // Rating methods
     /// Set rating for a song (0 to remove, 1-5 to rate).
@@ -258,6 +351,9 @@ pub trait AuthState: Send + Sync + 'static {
     fn delete_playlist(&self, playlist_id: i32) -> Result<bool, String>;
     /// Check if user owns a playlist.
     fn is_playlist_owner(&self, user_id: i32, playlist_id: i32) -> bool;
+    /// Get how long the songs of smart playlists are kept before their
+    /// rules are evaluated again.
+    fn get_smart_playlist_ttl(&self) -> TimeDelta;
 
     // Play queue methods
     /// Get the play queue for a user.
@@ -272,6 +368,105 @@ pub trait AuthState: Send + Sync + 'static {
         changed_by: Option<&str>,
     ) -> Result<(), String>;
 
+    // Bookmark methods
+    /// Get all bookmarks of a user.
+    fn get_bookmarks(&self, user_id: i32) -> Vec<Bookmark>;
+    /// Create or update a user's bookmark in a song.
+    fn create_bookmark(
+        &self,
+        user_id: i32,
+        song_id: i32,
+        position: i64,
+        comment: Option<&str>,
+    ) -> Result<(), String>;
+    /// Delete a user's bookmark in a song. Returns false if there was none.
+    fn delete_bookmark(&self, user_id: i32, song_id: i32) -> Result<bool, String>;
+
+    // Share methods
+    /// Get all shares created by a user.
+    fn get_shares(&self, user_id: i32) -> Vec<Share>;
+    /// Get a share by ID.
+    fn get_share(&self, share_id: i32) -> Option<Share>;
+    /// Get a share by the token in its public URL.
+    fn get_share_by_token(&self, token: &str) -> Option<Share>;
+    /// Create a share of the given songs.
+    fn create_share(
+        &self,
+        user_id: i32,
+        song_ids: &[i32],
+        description: Option<&str>,
+        expires_at: Option<NaiveDateTime>,
+    ) -> Result<Share, String>;
+    /// Update a share's description and expiry (`None` leaves a field unchanged).
+    /// Returns false if the share does not exist.
+    fn update_share(
+        &self,
+        share_id: i32,
+        description: Option<Option<&str>>,
+        expires_at: Option<Option<NaiveDateTime>>,
+    ) -> Result<bool, String>;
+    /// Delete a share. Returns false if it did not exist.
+    fn delete_share(&self, share_id: i32) -> Result<bool, String>;
+    /// Count a visit to a share's public page.
+    fn record_share_visit(&self, share_id: i32);
+
+    // Internet radio methods
+    /// Get all internet radio stations.
+    fn get_internet_radio_stations(&self) -> Vec<InternetRadioStation>;
+    /// Add an internet radio station.
+    fn create_internet_radio_station(
+        &self,
+        name: &str,
+        stream_url: &str,
+        homepage_url: Option<&str>,
+    ) -> Result<InternetRadioStation, String>;
+    /// Update an internet radio station. Returns false if it does not exist.
+    fn update_internet_radio_station(
+        &self,
+        station_id: i32,
+        name: &str,
+        stream_url: &str,
+        homepage_url: Option<&str>,
+    ) -> Result<bool, String>;
+    /// Delete an internet radio station. Returns false if it did not exist.
+    fn delete_internet_radio_station(&self, station_id: i32) -> Result<bool, String>;
+    /// Resolve a link to a `.pls` or `.m3u` playlist to the stream it lists.
+    /// Other URLs, and links that cannot be resolved, are returned unchanged.
+    fn resolve_stream_url<'a>(
+        &'a self,
+        url: &'a str,
+    ) -> Pin<Box<dyn Future<Output = String> + Send + 'a>>;
+
+    // Podcast methods
+    /// Get all podcast channels.
+    fn get_podcast_channels(&self) -> Vec<PodcastChannel>;
+    /// Get a podcast channel by ID.
+    fn get_podcast_channel(&self, channel_id: i32) -> Option<PodcastChannel>;
+    /// Get a channel's episodes, newest first.
+    fn get_podcast_episodes(&self, channel_id: i32) -> Vec<PodcastEpisode>;
+    /// Get the most recently published episodes of all channels.
+    fn get_newest_podcast_episodes(&self, count: i64) -> Vec<PodcastEpisode>;
+    /// Get a podcast episode by ID.
+    fn get_podcast_episode(&self, episode_id: i32) -> Option<PodcastEpisode>;
+    /// Subscribe to a podcast feed, which is fetched in the background.
+    fn create_podcast_channel(&self, url: &str) -> Result<PodcastChannel, String>;
+    /// Delete a podcast channel and its downloads. Returns false if it did not exist.
+    fn delete_podcast_channel(&self, channel_id: i32) -> Result<bool, String>;
+    /// Refresh all podcast feeds in the background.
+    fn refresh_podcasts(&self);
+    /// Queue a podcast episode for download. Returns false if it does not exist.
+    fn download_podcast_episode(&self, episode_id: i32) -> Result<bool, String>;
+    /// Delete a podcast episode's download. Returns false if it does not exist.
+    fn delete_podcast_episode(&self, episode_id: i32) -> Result<bool, String>;
+    /// Get the folder podcast episodes are downloaded to.
+    fn podcast_folder(&self) -> PathBuf;
+
+    // Chat methods
+    /// Get the chat messages posted after the given time, newest first.
+    fn get_chat_messages(&self, since: Option<NaiveDateTime>) -> Vec<ChatMessage>;
+    /// Post a chat message.
+    fn add_chat_message(&self, user_id: i32, message: &str) -> Result<(), String>;
+
     // User management methods
     /// Get a user by username.
     fn get_user(&self, username: &str) -> Option<User>;
@@ -324,14 +519,30 @@ pub trait AuthState: Send + Sync + 'static {
     ) -> Result<(), String>;
 
     // Lyrics methods
-    /// Get lyrics for a song by reading embedded lyrics from the audio file.
+    /// Get the lyrics indexed for a song during scanning.
     fn get_song_lyrics(&self, song_id: i32) -> Vec<ExtractedLyrics>;
+    /// Find a song and its lyrics by artist name and/or song title.
+    fn find_lyrics_by_artist_title(
+        &self,
+        artist: Option<&str>,
+        title: Option<&str>,
+    ) -> Option<(Song, Vec<ExtractedLyrics>)>;
+    /// Search songs whose lyrics contain the query text.
+    fn search_songs_by_lyrics(&self, query: &str, offset: i64, limit: i64) -> Vec<Song>;
 
     // Scanning methods
     /// Get the database pool for scanning operations.
     fn get_db_pool(&self) -> DbPool;
     /// Get the scan state for checking/updating scan progress.
     fn get_scan_state(&self) -> Arc<ScanState>;
+    /// Get the settings for importing playlist files during scans.
+    fn get_playlist_import_config(&self) -> PlaylistImportConfig;
+    /// Get the most recent scan runs, newest first.
+    fn get_scan_history(&self, limit: i64) -> Vec<ScanRun>;
+    /// Get a scan run by ID.
+    fn get_scan_run(&self, run_id: i32) -> Option<ScanRun>;
+    /// Get the files that failed during a scan run.
+    fn get_scan_errors(&self, run_id: i32) -> Vec<ScanRunError>;
 }
 
 /// Common query parameters for all Subsonic API requests.
@@ -596,15 +807,31 @@ pub struct DatabaseAuthState {
     user_repo: UserRepository,
     music_folder_repo: MusicFolderRepository,
     artist_repo: ArtistRepository,
+    artist_similarity_repo: ArtistSimilarityRepository,
     album_repo: AlbumRepository,
     song_repo: SongRepository,
+    directory_repo: DirectoryRepository,
     starred_repo: StarredRepository,
     now_playing_repo: NowPlayingRepository,
     scrobble_repo: ScrobbleRepository,
     rating_repo: RatingRepository,
     playlist_repo: PlaylistRepository,
     play_queue_repo: PlayQueueRepository,
+    bookmark_repo: BookmarkRepository,
+    share_repo: ShareRepository,
+    radio_repo: InternetRadioRepository,
+    podcast_repo: PodcastRepository,
+    chat_repo: ChatMessageRepository,
+    lyrics_repo: LyricsRepository,
+    scan_history_repo: ScanHistoryRepository,
+    statistics_repo: StatisticsRepository,
     scan_state: Arc<ScanState>,
+    playlist_import: PlaylistImportConfig,
+    smart_playlist_ttl: TimeDelta,
+    metadata: MetadataService,
+    recommender: Recommender,
+    scrobble_forwarder: ScrobbleForwarder,
+    podcasts: PodcastService,
 }
 
 impl DatabaseAuthState {
@@ -620,18 +847,65 @@ impl DatabaseAuthState {
             user_repo: UserRepository::new(pool.clone()),
             music_folder_repo: MusicFolderRepository::new(pool.clone()),
             artist_repo: ArtistRepository::new(pool.clone()),
+            artist_similarity_repo: ArtistSimilarityRepository::new(pool.clone()),
             album_repo: AlbumRepository::new(pool.clone()),
             song_repo: SongRepository::new(pool.clone()),
+            directory_repo: DirectoryRepository::new(pool.clone()),
             starred_repo: StarredRepository::new(pool.clone()),
             now_playing_repo: NowPlayingRepository::new(pool.clone()),
             scrobble_repo: ScrobbleRepository::new(pool.clone()),
             rating_repo: RatingRepository::new(pool.clone()),
             playlist_repo: PlaylistRepository::new(pool.clone()),
-            play_queue_repo: PlayQueueRepository::new(pool),
+            play_queue_repo: PlayQueueRepository::new(pool.clone()),
+            bookmark_repo: BookmarkRepository::new(pool.clone()),
+            share_repo: ShareRepository::new(pool.clone()),
+            radio_repo: InternetRadioRepository::new(pool.clone()),
+            podcast_repo: PodcastRepository::new(pool.clone()),
+            chat_repo: ChatMessageRepository::new(pool.clone()),
+            lyrics_repo: LyricsRepository::new(pool.clone()),
+            scan_history_repo: ScanHistoryRepository::new(pool.clone()),
+            statistics_repo: StatisticsRepository::new(pool.clone()),
             scan_state,
+            playlist_import: PlaylistImportConfig::default(),
+            smart_playlist_ttl: TimeDelta::seconds(DEFAULT_REFRESH_TTL_SECONDS),
+            metadata: MetadataService::new(pool.clone()),
+            recommender: Recommender::new(pool.clone()),
+            scrobble_forwarder: ScrobbleForwarder::new(pool.clone()),
+            podcasts: PodcastService::new(pool, default_podcast_folder()),
         }
     }
 
+    /// Set how playlist files are imported by scans started through the API.
+    pub fn with_playlist_import(mut self, config: PlaylistImportConfig) -> Self {
+        self.playlist_import = config;
+        self
+    }
+
+    /// Set how long the songs of smart playlists are kept before their rules
+    /// are evaluated again.
+    pub fn with_smart_playlist_ttl(mut self, ttl: TimeDelta) -> Self {
+        self.smart_playlist_ttl = ttl;
+        self
+    }
+
+    /// Set the service that looks up artist and album metadata.
+    pub fn with_metadata(mut self, metadata: MetadataService) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    /// Set the forwarder that mirrors plays to users' scrobbling accounts.
+    pub fn with_scrobble_forwarder(mut self, forwarder: ScrobbleForwarder) -> Self {
+        self.scrobble_forwarder = forwarder;
+        self
+    }
+
+    /// Set the service that refreshes podcast feeds and downloads episodes.
+    pub fn with_podcasts(mut self, podcasts: PodcastService) -> Self {
+        self.podcasts = podcasts;
+        self
+    }
+
     /// Get a reference to the user repository.
     pub fn user_repo(&self) -> &UserRepository {
         &self.user_repo
@@ -664,6 +938,32 @@ impl AuthState for DatabaseAuthState {
         self.artist_repo.get_last_modified().ok().flatten()
     }
 
+    fn get_directory(&self, directory_id: i32) -> Option<Directory> {
+        self.directory_repo.find_by_id(directory_id).ok().flatten()
+    }
+
+    fn get_top_level_directories(&self, music_folder_id: Option<i32>) -> Vec<Directory> {
+        self.directory_repo
+            .find_top_level(music_folder_id)
+            .unwrap_or_default()
+    }
+
+    fn get_child_directories(&self, directory_id: i32) -> Vec<Directory> {
+        self.directory_repo
+            .find_children(directory_id)
+            .unwrap_or_default()
+    }
+
+    fn get_songs_by_directory(&self, music_folder_id: i32, path: &str) -> Vec<Song> {
+        self.song_repo
+            .find_by_directory(music_folder_id, path)
+            .unwrap_or_default()
+    }
+
+    fn get_directories_last_modified(&self) -> Option<NaiveDateTime> {
+        self.directory_repo.get_last_modified().ok().flatten()
+    }
+
     fn get_artist_album_count(&self, artist_id: i32) -> i64 {
         self.artist_repo.count_albums(artist_id).unwrap_or(0)
     }
@@ -680,10 +980,32 @@ impl AuthState for DatabaseAuthState {
         self.artist_repo.find_by_id(artist_id).ok().flatten()
     }
 
+    fn get_artists_by_names(&self, names: &[String]) -> Vec<Artist> {
+        self.artist_repo.find_by_names(names).unwrap_or_default()
+    }
+
+    fn get_similar_artists(&self, artist_id: i32, limit: i64) -> Vec<Artist> {
+        self.artist_similarity_repo
+            .find_similar(artist_id, limit)
+            .unwrap_or_default()
+    }
+
+    fn get_artist_metadata(&self, artist: &Artist) -> Option<ArtistMetadata> {
+        self.metadata.artist_info(artist)
+    }
+
+    fn get_album_metadata(&self, album: &Album) -> Option<AlbumMetadata> {
+        self.metadata.album_info(album)
+    }
+
     fn get_songs_by_album(&self, album_id: i32) -> Vec<Song> {
         self.song_repo.find_by_album(album_id).unwrap_or_default()
     }
 
+    fn get_songs_by_artist(&self, artist_id: i32) -> Vec<Song> {
+        self.song_repo.find_by_artist(artist_id).unwrap_or_default()
+    }
+
     fn get_albums_by_artist(&self, artist_id: i32) -> Vec<Album> {
         self.album_repo
             .find_by_artist(artist_id)
@@ -708,15 +1030,15 @@ impl AuthState for DatabaseAuthState {
             .unwrap_or_default()
     }
 
-    fn get_albums_frequent(&self, offset: i64, limit: i64) -> Vec<Album> {
+    fn get_albums_frequent(&self, user_id: i32, offset: i64, limit: i64) -> Vec<Album> {
         self.album_repo
-            .find_frequent(offset, limit)
+            .find_frequent(user_id, offset, limit)
             .unwrap_or_default()
     }
 
-    fn get_albums_recent(&self, offset: i64, limit: i64) -> Vec<Album> {
+    fn get_albums_recent(&self, user_id: i32, offset: i64, limit: i64) -> Vec<Album> {
         self.album_repo
-            .find_recent(offset, limit)
+            .find_recent(user_id, offset, limit)
             .unwrap_or_default()
     }
 
@@ -904,6 +1226,26 @@ impl AuthState for DatabaseAuthState {
             .unwrap_or_default()
     }
 
+    fn get_song_play_stats_batch(
+        &self,
+        user_id: i32,
+        song_ids: &[i32],
+    ) -> std::collections::HashMap<i32, PlayStats> {
+        self.scrobble_repo
+            .get_song_play_stats_batch(user_id, song_ids)
+            .unwrap_or_default()
+    }
+
+    fn get_album_play_stats_batch(
+        &self,
+        user_id: i32,
+        album_ids: &[i32],
+    ) -> std::collections::HashMap<i32, PlayStats> {
+        self.scrobble_repo
+            .get_album_play_stats_batch(user_id, album_ids)
+            .unwrap_or_default()
+    }
+
     fn get_starred_at_for_artists_batch(
         &self,
         user_id: i32,
@@ -921,9 +1263,17 @@ impl AuthState for DatabaseAuthState {
         time: Option<i64>,
         submission: bool,
     ) -> Result<(), String> {
-        self.scrobble_repo
+        let played_at = self
+            .scrobble_repo
             .scrobble(user_id, song_id, time, submission)
-            .map_err(|e| e.to_string())
+            .map_err(|e| e.to_string())?;
+        if submission {
+            self.scrobble_forwarder
+                .scrobble(user_id, song_id, played_at);
+        } else {
+            self.scrobble_forwarder.now_playing(user_id, song_id);
+        }
+        Ok(())
     }
 
     fn set_now_playing(
@@ -931,9 +1281,21 @@ impl AuthState for DatabaseAuthState {
         user_id: i32,
         song_id: i32,
         player_id: Option<&str>,
+        position_ms: Option<i64>,
     ) -> Result<(), String> {
         self.now_playing_repo
-            .set_now_playing(user_id, song_id, player_id)
+            .set_now_playing(user_id, song_id, player_id, position_ms)
+            .map_err(|e| e.to_string())
+    }
+
+    fn clear_now_playing(
+        &self,
+        user_id: i32,
+        song_id: i32,
+        player_id: Option<&str>,
+    ) -> Result<(), String> {
+        self.now_playing_repo
+            .clear_now_playing(user_id, song_id, player_id)
             .map_err(|e| e.to_string())
     }
 
@@ -943,6 +1305,36 @@ impl AuthState for DatabaseAuthState {
             .unwrap_or_default()
     }
 
+    fn get_scrobble_accounts(&self, user_id: i32) -> Vec<(ScrobbleAccount, i64)> {
+        self.scrobble_forwarder
+            .accounts(user_id)
+            .unwrap_or_default()
+    }
+
+    fn link_scrobble_account<'a>(
+        &'a self,
+        user_id: i32,
+        service: ScrobbleService,
+        token: &'a str,
+    ) -> Pin<Box<dyn Future<Output = Result<ScrobbleAccount, String>> + Send + 'a>> {
+        Box::pin(async move {
+            self.scrobble_forwarder
+                .link(user_id, service, token)
+                .await
+                .map_err(|e| e.to_string())
+        })
+    }
+
+    fn unlink_scrobble_account(
+        &self,
+        user_id: i32,
+        service: ScrobbleService,
+    ) -> Result<bool, String> {
+        self.scrobble_forwarder
+            .unlink(user_id, service)
+            .map_err(|e| e.to_string())
+    }
+
     fn get_random_songs(
         &self,
         size: i64,
@@ -1001,12 +1393,24 @@ impl AuthState for DatabaseAuthState {
     }
 
     fn get_playlists(&self, user_id: i32, username: &str) -> Vec<Playlist> {
+        if let Err(e) = self
+            .playlist_repo
+            .refresh_smart_playlists_for_user(user_id, self.smart_playlist_ttl)
+        {
+            tracing::warn!("Failed to refresh smart playlists: {}", e);
+        }
         self.playlist_repo
             .get_playlists(user_id, username)
             .unwrap_or_default()
     }
 
     fn get_playlist(&self, playlist_id: i32) -> Option<Playlist> {
+        if let Err(e) = self
+            .playlist_repo
+            .refresh_smart_playlist(playlist_id, self.smart_playlist_ttl)
+        {
+            tracing::warn!("Failed to refresh smart playlist {}: {}", playlist_id, e);
+        }
         self.playlist_repo.get_playlist(playlist_id).ok().flatten()
     }
 
@@ -1070,6 +1474,10 @@ impl AuthState for DatabaseAuthState {
             .unwrap_or(false)
     }
 
+    fn get_smart_playlist_ttl(&self) -> TimeDelta {
+        self.smart_playlist_ttl
+    }
+
     fn get_play_queue(&self, user_id: i32, username: &str) -> Option<PlayQueue> {
         self.play_queue_repo
             .get_play_queue(user_id, username)
@@ -1090,6 +1498,185 @@ impl AuthState for DatabaseAuthState {
             .map_err(|e| e.to_string())
     }
 
+    fn get_bookmarks(&self, user_id: i32) -> Vec<Bookmark> {
+        self.bookmark_repo.find_by_user(user_id).unwrap_or_default()
+    }
+
+    fn create_bookmark(
+        &self,
+        user_id: i32,
+        song_id: i32,
+        position: i64,
+        comment: Option<&str>,
+    ) -> Result<(), String> {
+        self.bookmark_repo
+            .save(user_id, song_id, position, comment)
+            .map_err(|e| e.to_string())
+    }
+
+    fn delete_bookmark(&self, user_id: i32, song_id: i32) -> Result<bool, String> {
+        self.bookmark_repo
+            .delete(user_id, song_id)
+            .map_err(|e| e.to_string())
+    }
+
+    fn get_shares(&self, user_id: i32) -> Vec<Share> {
+        self.share_repo.find_by_user(user_id).unwrap_or_default()
+    }
+
+    fn get_share(&self, share_id: i32) -> Option<Share> {
+        self.share_repo.find(share_id).ok().flatten()
+    }
+
+    fn get_share_by_token(&self, token: &str) -> Option<Share> {
+        self.share_repo.find_by_token(token).ok().flatten()
+    }
+
+    fn create_share(
+        &self,
+        user_id: i32,
+        song_ids: &[i32],
+        description: Option<&str>,
+        expires_at: Option<NaiveDateTime>,
+    ) -> Result<Share, String> {
+        self.share_repo
+            .create(user_id, song_ids, description, expires_at)
+            .map_err(|e| e.to_string())
+    }
+
+    fn update_share(
+        &self,
+        share_id: i32,
+        description: Option<Option<&str>>,
+        expires_at: Option<Option<NaiveDateTime>>,
+    ) -> Result<bool, String> {
+        self.share_repo
+            .update(share_id, description, expires_at)
+            .map_err(|e| e.to_string())
+    }
+
+    fn delete_share(&self, share_id: i32) -> Result<bool, String> {
+        self.share_repo.delete(share_id).map_err(|e| e.to_string())
+    }
+
+    fn record_share_visit(&self, share_id: i32) {
+        if let Err(e) = self.share_repo.record_visit(share_id) {
+            tracing::warn!("Failed to record visit to share {}: {}", share_id, e);
+        }
+    }
+
+    fn get_internet_radio_stations(&self) -> Vec<InternetRadioStation> {
+        self.radio_repo.find_all().unwrap_or_default()
+    }
+
+    fn create_internet_radio_station(
+        &self,
+        name: &str,
+        stream_url: &str,
+        homepage_url: Option<&str>,
+    ) -> Result<InternetRadioStation, String> {
+        self.radio_repo
+            .create(name, stream_url, homepage_url)
+            .map_err(|e| e.to_string())
+    }
+
+    fn update_internet_radio_station(
+        &self,
+        station_id: i32,
+        name: &str,
+        stream_url: &str,
+        homepage_url: Option<&str>,
+    ) -> Result<bool, String> {
+        self.radio_repo
+            .update(station_id, name, stream_url, homepage_url)
+            .map_err(|e| e.to_string())
+    }
+
+    fn delete_internet_radio_station(&self, station_id: i32) -> Result<bool, String> {
+        self.radio_repo
+            .delete(station_id)
+            .map_err(|e| e.to_string())
+    }
+
+    fn resolve_stream_url<'a>(
+        &'a self,
+        url: &'a str,
+    ) -> Pin<Box<dyn Future<Output = String> + Send + 'a>> {
+        Box::pin(async move {
+            match radio::resolve_stream_url(url).await {
+                Ok(stream_url) => stream_url,
+                Err(e) => {
+                    tracing::warn!("Failed to resolve stream URL {}: {}", url, e);
+                    url.to_string()
+                }
+            }
+        })
+    }
+
+    fn get_podcast_channels(&self) -> Vec<PodcastChannel> {
+        self.podcast_repo.find_channels().unwrap_or_default()
+    }
+
+    fn get_podcast_channel(&self, channel_id: i32) -> Option<PodcastChannel> {
+        self.podcast_repo.find_channel(channel_id).ok().flatten()
+    }
+
+    fn get_podcast_episodes(&self, channel_id: i32) -> Vec<PodcastEpisode> {
+        self.podcast_repo
+            .find_episodes(channel_id)
+            .unwrap_or_default()
+    }
+
+    fn get_newest_podcast_episodes(&self, count: i64) -> Vec<PodcastEpisode> {
+        self.podcast_repo
+            .find_newest_episodes(count)
+            .unwrap_or_default()
+    }
+
+    fn get_podcast_episode(&self, episode_id: i32) -> Option<PodcastEpisode> {
+        self.podcast_repo.find_episode(episode_id).ok().flatten()
+    }
+
+    fn create_podcast_channel(&self, url: &str) -> Result<PodcastChannel, String> {
+        self.podcasts.create_channel(url).map_err(|e| e.to_string())
+    }
+
+    fn delete_podcast_channel(&self, channel_id: i32) -> Result<bool, String> {
+        self.podcasts
+            .delete_channel(channel_id)
+            .map_err(|e| e.to_string())
+    }
+
+    fn refresh_podcasts(&self) {
+        self.podcasts.refresh();
+    }
+
+    fn download_podcast_episode(&self, episode_id: i32) -> Result<bool, String> {
+        self.podcasts
+            .download_episode(episode_id)
+            .map_err(|e| e.to_string())
+    }
+
+    fn delete_podcast_episode(&self, episode_id: i32) -> Result<bool, String> {
+        self.podcasts
+            .delete_episode(episode_id)
+            .map_err(|e| e.to_string())
+    }
+
+    fn podcast_folder(&self) -> PathBuf {
+        self.podcasts.folder().to_path_buf()
+    }
+
+    fn get_chat_messages(&self, since: Option<NaiveDateTime>) -> Vec<ChatMessage> {
+        self.chat_repo.find_since(since).unwrap_or_default()
+    }
+
+    fn add_chat_message(&self, user_id: i32, message: &str) -> Result<(), String> {
+        self.chat_repo
+            .create(user_id, message)
+            .map_err(|e| e.to_string())
+    }
+
     fn get_user(&self, username: &str) -> Option<User> {
         self.user_repo.find_by_username(username).ok().flatten()
     }
@@ -1228,35 +1815,101 @@ impl AuthState for DatabaseAuthState {
         self.scan_state.clone()
     }
 
-    fn get_similar_songs_by_artist(
+    fn get_playlist_import_config(&self) -> PlaylistImportConfig {
+        self.playlist_import.clone()
+    }
+
+    fn get_scan_history(&self, limit: i64) -> Vec<ScanRun> {
+        self.scan_history_repo
+            .find_recent(limit)
+            .unwrap_or_default()
+    }
+
+    fn get_scan_run(&self, run_id: i32) -> Option<ScanRun> {
+        self.scan_history_repo.find_by_id(run_id).ok().flatten()
+    }
+
+    fn get_scan_errors(&self, run_id: i32) -> Vec<ScanRunError> {
+        self.scan_history_repo
+            .find_errors(run_id)
+            .unwrap_or_default()
+    }
+
+    fn get_similar_songs(&self, user_id: i32, seeds: &[Song], count: usize) -> Vec<Song> {
+        self.recommender
+            .similar_songs(user_id, seeds, count)
+            .unwrap_or_default()
+    }
+
+    fn get_top_songs_by_artist_name(
         &self,
-        artist_id: i32,
-        exclude_song_id: i32,
+        user_id: i32,
+        artist_name: &str,
         limit: i64,
     ) -> Vec<Song> {
         self.song_repo
-            .find_random_by_artist(artist_id, exclude_song_id, limit)
+            .find_top_by_artist_name(user_id, artist_name, limit)
             .unwrap_or_default()
     }
 
-    fn get_top_songs_by_artist_name(&self, artist_name: &str, limit: i64) -> Vec<Song> {
-        self.song_repo
-            .find_top_by_artist_name(artist_name, limit)
+    fn get_listening_by_user(&self, filter: &StatsFilter) -> Vec<UserListening> {
+        self.statistics_repo
+            .find_listening_by_user(filter)
+            .unwrap_or_default()
+    }
+
+    fn get_top_artists(&self, filter: &StatsFilter, limit: i64) -> Vec<(Artist, i64)> {
+        self.statistics_repo
+            .find_top_artists(filter, limit)
+            .unwrap_or_default()
+    }
+
+    fn get_top_albums(&self, filter: &StatsFilter, limit: i64) -> Vec<(Album, i64)> {
+        self.statistics_repo
+            .find_top_albums(filter, limit)
+            .unwrap_or_default()
+    }
+
+    fn get_top_songs(
+        &self,
+        filter: &StatsFilter,
+        artist_name: Option<&str>,
+        limit: i64,
+    ) -> Vec<(Song, i64)> {
+        self.statistics_repo
+            .find_top_songs(filter, artist_name, limit)
+            .unwrap_or_default()
+    }
+
+    fn get_top_genres(&self, filter: &StatsFilter, limit: i64) -> Vec<(String, i64)> {
+        self.statistics_repo
+            .find_top_genres(filter, limit)
             .unwrap_or_default()
     }
 
     fn get_song_lyrics(&self, song_id: i32) -> Vec<ExtractedLyrics> {
-        use crate::scanner::lyrics::extract_lyrics;
-        use std::path::Path;
+        self.lyrics_repo
+            .find_by_song_id(song_id)
+            .map(|rows| rows.into_iter().map(ExtractedLyrics::from).collect())
+            .unwrap_or_default()
+    }
 
-        // Get the song to find its file path
-        let song = match self.get_song(song_id) {
-            Some(s) => s,
-            None => return Vec::new(),
-        };
+    fn find_lyrics_by_artist_title(
+        &self,
+        artist: Option<&str>,
+        title: Option<&str>,
+    ) -> Option<(Song, Vec<ExtractedLyrics>)> {
+        self.lyrics_repo
+            .find_by_artist_and_title(artist, title)
+            .ok()
+            .flatten()
+            .map(|(song, rows)| (song, rows.into_iter().map(ExtractedLyrics::from).collect()))
+    }
 
-        // Extract lyrics from the audio file
-        extract_lyrics(Path::new(&song.path))
+    fn search_songs_by_lyrics(&self, query: &str, offset: i64, limit: i64) -> Vec<Song> {
+        self.lyrics_repo
+            .search_songs(query, offset, limit)
+            .unwrap_or_default()
     }
 }
 
@@ -141,18 +141,26 @@ pub async fn get_starred2(auth: SubsonicAuth) -> impl IntoResponse {
 
     // Get starred albums
     let starred_albums = auth.state.get_starred_albums(user_id);
+    let album_ids: Vec<i32> = starred_albums.iter().map(|(a, _)| a.id).collect();
+    let album_play_stats = auth.state.get_album_play_stats_batch(user_id, &album_ids);
     let albums: Vec<StarredAlbumID3Response> = starred_albums
         .iter()
         .map(|(album, starred_at)| {
             StarredAlbumID3Response::from_album_and_starred(album, starred_at)
+                .with_play_stats(album_play_stats.get(&album.id))
         })
         .collect();
 
     // Get starred songs
     let starred_songs = auth.state.get_starred_songs(user_id);
+    let song_ids: Vec<i32> = starred_songs.iter().map(|(s, _)| s.id).collect();
+    let song_play_stats = auth.state.get_song_play_stats_batch(user_id, &song_ids);
     let songs: Vec<StarredChildResponse> = starred_songs
         .iter()
-        .map(|(song, starred_at)| StarredChildResponse::from_song_and_starred(song, starred_at))
+        .map(|(song, starred_at)| {
+            StarredChildResponse::from_song_and_starred(song, starred_at)
+                .with_play_stats(song_play_stats.get(&song.id))
+        })
         .collect();
 
     let response = Starred2Response {
@@ -173,6 +181,10 @@ pub async fn get_starred2(auth: SubsonicAuth) -> impl IntoResponse {
 /// - `id` (required): The ID of the song being played (can be repeated)
 /// - `time` (optional): Time in milliseconds since the media started playing (can be repeated, one per id)
 /// - `submission` (optional): Whether this is a "scrobble" (true) or a "now playing" notification (false). Default true.
+/// - `position` (optional): Playback position in milliseconds, for "now playing" notifications
+///
+/// "Now playing" entries are kept per player (the `c` parameter) until the song
+/// should have ended, and a completed scrobble of the song clears its entry.
 pub async fn scrobble(RawQuery(query): RawQuery, auth: SubsonicAuth) -> impl IntoResponse {
     let query = query.unwrap_or_default();
     let user_id = auth.user.id;
@@ -187,6 +199,12 @@ pub async fn scrobble(RawQuery(query): RawQuery, auth: SubsonicAuth) -> impl Int
         .map(|s| s != "false" && s != "0")
         .unwrap_or(true);
 
+    // Parse position parameter (milliseconds into the song)
+    let position_ms = parse_repeated_param(&query, "position")
+        .first()
+        .and_then(|p| p.parse::<i64>().ok())
+        .filter(|p| *p >= 0);
+
     // Get player_id from the client identifier
     let player_id = if auth.params.c.is_empty() {
         None
@@ -205,8 +223,16 @@ pub async fn scrobble(RawQuery(query): RawQuery, auth: SubsonicAuth) -> impl Int
                 tracing::warn!("Failed to scrobble song {}: {}", song_id, e);
             }
 
-            // If this is a "now playing" notification (submission=false), also update now playing
-            if !submission && let Err(e) = auth.state.set_now_playing(user_id, song_id, player_id) {
+            // A "now playing" notification (submission=false) updates now playing,
+            // and a completed play clears it
+            if submission {
+                if let Err(e) = auth.state.clear_now_playing(user_id, song_id, player_id) {
+                    tracing::warn!("Failed to clear now playing for song {}: {}", song_id, e);
+                }
+            } else if let Err(e) =
+                auth.state
+                    .set_now_playing(user_id, song_id, player_id, position_ms)
+            {
                 tracing::warn!("Failed to set now playing for song {}: {}", song_id, e);
             }
         }
@@ -229,6 +255,7 @@ pub async fn get_now_playing(auth: SubsonicAuth) -> impl IntoResponse {
                 entry.username.clone(),
                 entry.minutes_ago,
                 entry.player_id.clone(),
+                entry.position_ms,
             )
         })
         .collect();
@@ -299,3 +326,36 @@ pub async fn set_rating(
 
     ok_empty(auth.format).into_response()
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeDelta;
+    use diesel::prelude::*;
+
+    use crate::api::handlers::test_library;
+    use crate::db::schema::now_playing;
+
+    #[test]
+    fn test_expired_now_playing_entry_is_hidden() {
+        let (state, pool, user_id, [one, two]) = test_library("now-playing");
+        state
+            .set_now_playing(user_id, one, Some("phone"), Some(190_000))
+            .unwrap();
+        state
+            .set_now_playing(user_id, two, Some("desktop"), None)
+            .unwrap();
+        assert_eq!(state.get_now_playing().len(), 2);
+
+        // The phone stopped reporting after the song should have ended
+        let expired = chrono::Utc::now().naive_utc() - TimeDelta::seconds(1);
+        diesel::update(now_playing::table.filter(now_playing::player_id.eq("phone")))
+            .set(now_playing::expires_at.eq(expired))
+            .execute(&mut pool.get().unwrap())
+            .unwrap();
+
+        let entries = state.get_now_playing();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].song.id, two);
+        assert_eq!(entries[0].player_id.as_deref(), Some("desktop"));
+    }
+}
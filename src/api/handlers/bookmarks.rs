@@ -0,0 +1,164 @@
+//! Bookmark API handlers (getBookmarks, createBookmark, deleteBookmark)
+
+use axum::response::IntoResponse;
+use serde::Deserialize;
+
+use crate::api::auth::SubsonicAuth;
+use crate::api::error::ApiError;
+use crate::api::response::{error_response, ok_bookmarks, ok_empty};
+use crate::models::music::{BookmarkResponse, BookmarksResponse, ChildResponse};
+
+/// Query parameters for createBookmark and deleteBookmark.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct BookmarkParams {
+    /// The ID of the song to bookmark.
+    pub id: Option<String>,
+    /// Position in milliseconds within the song.
+    pub position: Option<i64>,
+    /// User-defined comment.
+    pub comment: Option<String>,
+}
+
+/// GET/POST /rest/getBookmarks[.view]
+///
+/// Returns all bookmarks for this user.
+/// A bookmark is a position within a certain media file.
+pub async fn get_bookmarks(auth: SubsonicAuth) -> impl IntoResponse {
+    let user_id = auth.user.id;
+    let bookmarks = auth.state.get_bookmarks(user_id);
+
+    // Batch fetch starred status and play stats for all songs
+    let song_ids: Vec<i32> = bookmarks.iter().map(|b| b.song.id).collect();
+    let starred_map = auth
+        .state
+        .get_starred_at_for_songs_batch(user_id, &song_ids);
+    let song_play_stats = auth.state.get_song_play_stats_batch(user_id, &song_ids);
+
+    let bookmarks = bookmarks
+        .iter()
+        .map(|b| BookmarkResponse {
+            position: b.position,
+            username: auth.user.username.clone(),
+            comment: b.comment.clone(),
+            created: b.created_at.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
+            changed: b.changed_at.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
+            entry: ChildResponse::from_song_with_starred(&b.song, starred_map.get(&b.song.id))
+                .with_play_stats(song_play_stats.get(&b.song.id)),
+        })
+        .collect();
+
+    ok_bookmarks(auth.format, BookmarksResponse { bookmarks })
+}
+
+/// GET/POST /rest/createBookmark[.view]
+///
+/// Creates or updates a bookmark (a position within a media file).
+///
+/// Parameters:
+/// - `id` (required): ID of the song to bookmark
+/// - `position` (required): Position in milliseconds within the song
+/// - `comment`: User-defined comment
+pub async fn create_bookmark(
+    axum::extract::Query(params): axum::extract::Query<BookmarkParams>,
+    auth: SubsonicAuth,
+) -> impl IntoResponse {
+    let Some(id) = params.id.as_ref().and_then(|id| id.parse::<i32>().ok()) else {
+        return error_response(auth.format, &ApiError::MissingParameter("id".into()));
+    };
+    let position = match params.position {
+        Some(p) if p >= 0 => p,
+        Some(_) => {
+            return error_response(
+                auth.format,
+                &ApiError::Generic("Position must not be negative".into()),
+            );
+        }
+        None => {
+            return error_response(auth.format, &ApiError::MissingParameter("position".into()));
+        }
+    };
+
+    if auth.state.get_song(id).is_none() {
+        return error_response(auth.format, &ApiError::NotFound("Song not found".into()));
+    }
+
+    let comment = params.comment.as_deref().filter(|c| !c.is_empty());
+    match auth
+        .state
+        .create_bookmark(auth.user.id, id, position, comment)
+    {
+        Ok(()) => ok_empty(auth.format),
+        Err(e) => error_response(auth.format, &ApiError::Generic(e)),
+    }
+}
+
+/// GET/POST /rest/deleteBookmark[.view]
+///
+/// Deletes the bookmark for a given file.
+///
+/// Parameters:
+/// - `id` (required): ID of the bookmarked song
+pub async fn delete_bookmark(
+    axum::extract::Query(params): axum::extract::Query<BookmarkParams>,
+    auth: SubsonicAuth,
+) -> impl IntoResponse {
+    let Some(id) = params.id.as_ref().and_then(|id| id.parse::<i32>().ok()) else {
+        return error_response(auth.format, &ApiError::MissingParameter("id".into()));
+    };
+
+    match auth.state.delete_bookmark(auth.user.id, id) {
+        Ok(true) => ok_empty(auth.format),
+        Ok(false) => error_response(auth.format, &ApiError::NotFound("Bookmark".into())),
+        Err(e) => error_response(auth.format, &ApiError::Generic(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use diesel::prelude::*;
+
+    use crate::api::handlers::test_library;
+    use crate::db::schema::bookmarks;
+    use crate::db::{NewUser, UserRepository};
+
+    #[test]
+    fn test_bookmark_lifecycle_and_cascade() {
+        let (state, pool, user_id, [one, two]) = test_library("bookmarks");
+
+        state
+            .create_bookmark(user_id, one, 1000, Some("intro"))
+            .unwrap();
+        state.create_bookmark(user_id, two, 5000, None).unwrap();
+        // Saving again moves the existing bookmark
+        state
+            .create_bookmark(user_id, one, 2000, Some("verse"))
+            .unwrap();
+        let bookmarks = state.get_bookmarks(user_id);
+        assert_eq!(bookmarks.len(), 2);
+        let first = bookmarks.iter().find(|b| b.song.id == one).unwrap();
+        assert_eq!(first.position, 2000);
+        assert_eq!(first.comment.as_deref(), Some("verse"));
+
+        assert!(state.delete_bookmark(user_id, one).unwrap());
+        assert!(!state.delete_bookmark(user_id, one).unwrap());
+        assert_eq!(state.get_bookmarks(user_id).len(), 1);
+
+        // Bookmarks go with their song and with their user
+        diesel::sql_query(format!("DELETE FROM songs WHERE id = {}", two))
+            .execute(&mut pool.get().unwrap())
+            .unwrap();
+        assert!(state.get_bookmarks(user_id).is_empty());
+
+        let other = UserRepository::new(pool.clone())
+            .create(&NewUser::regular("other", "hash", "secret"))
+            .unwrap();
+        state.create_bookmark(other.id, one, 100, None).unwrap();
+        assert!(state.delete_user("other").unwrap());
+        let left: i64 = bookmarks::table
+            .count()
+            .get_result(&mut pool.get().unwrap())
+            .unwrap();
+        assert_eq!(left, 0);
+    }
+}
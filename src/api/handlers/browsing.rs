@@ -14,16 +14,18 @@ use crate::api::response::{
     ok_search_result3, ok_similar_songs, ok_similar_songs2, ok_song, ok_songs_by_genre, ok_starred,
     ok_top_songs,
 };
+use crate::models::metadata::ArtistMetadata;
 use crate::models::music::{
     AlbumID3Response, AlbumInfoResponse, AlbumList2Response, AlbumListResponse,
-    AlbumWithSongsID3Response, ArtistID3Response, ArtistInfo2Response, ArtistInfoResponse,
+    AlbumWithSongsID3Response, Artist, ArtistID3Response, ArtistInfo2Response, ArtistInfoResponse,
     ArtistResponse, ArtistWithAlbumsID3Response, ArtistsID3Response, ChildResponse,
     DirectoryResponse, GenreResponse, GenresResponse, IndexID3Response, IndexResponse,
     IndexesResponse, LyricLine, LyricsListResponse, LyricsResponse, MusicFolderResponse,
     RandomSongsResponse, SearchMatch, SearchResult2Response, SearchResult3Response,
-    SearchResultResponse, SimilarSongs2Response, SimilarSongsResponse, SongsByGenreResponse,
-    StarredResponse, StructuredLyrics, TopSongsResponse,
+    SearchResultResponse, SimilarSongs2Response, SimilarSongsResponse, Song, SongsByGenreResponse,
+    StarredResponse, StructuredLyrics, TopSongsResponse, directory_response_id, parse_directory_id,
 };
+use crate::models::stats::{StatsFilter, StatsPeriod};
 
 /// Query parameters for endpoints that require an ID.
 #[derive(Debug, Clone, Default, Deserialize)]
@@ -43,21 +45,46 @@ pub async fn get_music_folders(auth: SubsonicAuth) -> impl IntoResponse {
     ok_music_folders(auth.format, responses)
 }
 
+/// Articles skipped when indexing directory names.
+const IGNORED_ARTICLES: &str = "The El La Los Las Le Les";
+
+/// Strip a leading ignored article from a name, for indexing and sorting.
+fn strip_article(name: &str) -> &str {
+    for article in IGNORED_ARTICLES.split(' ') {
+        if let Some(rest) = name.strip_prefix(article)
+            && let Some(rest) = rest.strip_prefix(' ')
+        {
+            return rest.trim_start();
+        }
+    }
+    name
+}
+
+/// Query parameters for getIndexes.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct IndexesParams {
+    /// Only return directories in this music folder.
+    #[serde(rename = "musicFolderId")]
+    pub music_folder_id: Option<i32>,
+}
+
 /// GET/POST /rest/getIndexes[.view]
 ///
-/// Returns an indexed structure of all artists.
-/// This is used by older clients that use the folder-based browsing model.
-pub async fn get_indexes(auth: SubsonicAuth) -> impl IntoResponse {
-    let artists = auth.state.get_artists();
+/// Returns an indexed structure of the top-level directories of the music
+/// folders, plus any files at their root.
+pub async fn get_indexes(
+    axum::extract::Query(params): axum::extract::Query<IndexesParams>,
+    auth: SubsonicAuth,
+) -> impl IntoResponse {
+    let mut directories = auth.state.get_top_level_directories(params.music_folder_id);
+    directories.sort_by_cached_key(|dir| strip_article(&dir.name).to_lowercase());
 
-    // Group artists by first letter
+    // Group directories by first letter
     let mut index_map: BTreeMap<String, Vec<ArtistResponse>> = BTreeMap::new();
 
-    for artist in &artists {
-        let first_char = artist
-            .sort_name
-            .as_ref()
-            .unwrap_or(&artist.name)
+    for directory in &directories {
+        let first_char = strip_article(&directory.name)
             .chars()
             .next()
             .unwrap_or('#')
@@ -74,7 +101,7 @@ pub async fn get_indexes(auth: SubsonicAuth) -> impl IntoResponse {
         index_map
             .entry(key)
             .or_default()
-            .push(ArtistResponse::from(artist));
+            .push(ArtistResponse::from(directory));
     }
 
     // Convert to response format
@@ -83,17 +110,44 @@ pub async fn get_indexes(auth: SubsonicAuth) -> impl IntoResponse {
         .map(|(name, artists)| IndexResponse { name, artists })
         .collect();
 
-    // Get last modified time (using current timestamp for now)
+    // Files at the root of the music folders
+    let root_songs: Vec<(i32, Song)> = auth
+        .state
+        .get_music_folders()
+        .iter()
+        .filter(|folder| params.music_folder_id.is_none_or(|id| id == folder.id))
+        .flat_map(|folder| {
+            auth.state
+                .get_songs_by_directory(folder.id, "")
+                .into_iter()
+                .map(|song| (folder.id, song))
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    let song_ids: Vec<i32> = root_songs.iter().map(|(_, song)| song.id).collect();
+    let play_stats = auth
+        .state
+        .get_song_play_stats_batch(auth.user.id, &song_ids);
+    let children: Vec<ChildResponse> = root_songs
+        .iter()
+        .map(|(folder_id, song)| {
+            let mut child = ChildResponse::from(song).with_play_stats(play_stats.get(&song.id));
+            child.parent = Some(folder_id.to_string());
+            child
+        })
+        .collect();
+
     let last_modified = auth
         .state
-        .get_artists_last_modified()
+        .get_directories_last_modified()
         .map(|dt| dt.and_utc().timestamp_millis())
         .unwrap_or(0);
 
     let response = IndexesResponse {
-        ignored_articles: "The El La Los Las Le Les".to_string(),
+        ignored_articles: IGNORED_ARTICLES.to_string(),
         last_modified,
         indexes,
+        children,
     };
 
     ok_indexes(auth.format, response)
@@ -182,8 +236,11 @@ pub async fn get_album(
         }
     };
 
-    // Get the album's starred status
+    // Get the album's starred status and play stats
     let album_starred_at = auth.state.get_starred_at_for_album(auth.user.id, album_id);
+    let album_play_stats = auth
+        .state
+        .get_album_play_stats_batch(auth.user.id, &[album_id]);
 
     // Get songs for the album
     let songs = auth.state.get_songs_by_album(album_id);
@@ -193,12 +250,16 @@ pub async fn get_album(
     let starred_songs = auth
         .state
         .get_starred_at_for_songs_batch(auth.user.id, &song_ids);
+    let song_play_stats = auth
+        .state
+        .get_song_play_stats_batch(auth.user.id, &song_ids);
 
     let song_responses: Vec<ChildResponse> = songs
         .iter()
         .map(|song| {
             let starred_at = starred_songs.get(&song.id);
             ChildResponse::from_song_with_starred(song, starred_at)
+                .with_play_stats(song_play_stats.get(&song.id))
         })
         .collect();
 
@@ -206,7 +267,8 @@ pub async fn get_album(
         &album,
         song_responses,
         album_starred_at.as_ref(),
-    );
+    )
+    .with_play_stats(album_play_stats.get(&album_id));
     ok_album(auth.format, response).into_response()
 }
 
@@ -246,12 +308,16 @@ pub async fn get_artist(
     let starred_map = auth
         .state
         .get_starred_at_for_albums_batch(auth.user.id, &album_ids);
+    let album_play_stats = auth
+        .state
+        .get_album_play_stats_batch(auth.user.id, &album_ids);
 
     let album_responses: Vec<AlbumID3Response> = albums
         .iter()
         .map(|album| {
             let starred_at = starred_map.get(&album.id);
             AlbumID3Response::from_album_with_starred(album, starred_at)
+                .with_play_stats(album_play_stats.get(&album.id))
         })
         .collect();
 
@@ -289,7 +355,11 @@ pub async fn get_song(
 
     // Get the song's starred status
     let starred_at = auth.state.get_starred_at_for_song(auth.user.id, song_id);
-    let response = ChildResponse::from_song_with_starred(&song, starred_at.as_ref());
+    let play_stats = auth
+        .state
+        .get_song_play_stats_batch(auth.user.id, &[song_id]);
+    let response = ChildResponse::from_song_with_starred(&song, starred_at.as_ref())
+        .with_play_stats(play_stats.get(&song_id));
     ok_song(auth.format, response).into_response()
 }
 
@@ -343,8 +413,8 @@ pub async fn get_album_list2(
     let albums = match list_type {
         "random" => auth.state.get_albums_random(size),
         "newest" => auth.state.get_albums_newest(offset, size),
-        "frequent" => auth.state.get_albums_frequent(offset, size),
-        "recent" => auth.state.get_albums_recent(offset, size),
+        "frequent" => auth.state.get_albums_frequent(auth.user.id, offset, size),
+        "recent" => auth.state.get_albums_recent(auth.user.id, offset, size),
         "alphabeticalByName" => auth.state.get_albums_alphabetical_by_name(offset, size),
         "alphabeticalByArtist" => auth.state.get_albums_alphabetical_by_artist(offset, size),
         "byYear" => {
@@ -377,8 +447,14 @@ pub async fn get_album_list2(
         }
     };
 
-    let album_responses: Vec<AlbumID3Response> =
-        albums.iter().map(AlbumID3Response::from).collect();
+    let album_ids: Vec<i32> = albums.iter().map(|a| a.id).collect();
+    let play_stats = auth
+        .state
+        .get_album_play_stats_batch(auth.user.id, &album_ids);
+    let album_responses: Vec<AlbumID3Response> = albums
+        .iter()
+        .map(|a| AlbumID3Response::from(a).with_play_stats(play_stats.get(&a.id)))
+        .collect();
     let response = AlbumList2Response {
         albums: album_responses,
     };
@@ -434,6 +510,9 @@ pub struct Search3Params {
     /// Only return results from this music folder.
     #[serde(rename = "musicFolderId")]
     pub music_folder_id: Option<i32>,
+    /// Match songs by their lyrics instead of their title. Default false.
+    #[serde(rename = "searchLyrics")]
+    pub search_lyrics: Option<bool>,
 }
 
 /// GET/POST /rest/search3[.view]
@@ -441,6 +520,7 @@ pub struct Search3Params {
 /// Returns albums, artists and songs matching the given search criteria.
 /// Supports paging through the result.
 /// An empty query returns all results (up to the count limits).
+/// With `searchLyrics=true`, songs are matched against their indexed lyrics.
 pub async fn search3(
     axum::extract::Query(params): axum::extract::Query<Search3Params>,
     auth: SubsonicAuth,
@@ -462,7 +542,12 @@ pub async fn search3(
         .state
         .search_artists(query, artist_offset, artist_count);
     let albums = auth.state.search_albums(query, album_offset, album_count);
-    let songs = auth.state.search_songs(query, song_offset, song_count);
+    let songs = if params.search_lyrics.unwrap_or(false) && !query.is_empty() {
+        auth.state
+            .search_songs_by_lyrics(query, song_offset, song_count)
+    } else {
+        auth.state.search_songs(query, song_offset, song_count)
+    };
 
     // Collect IDs for batch queries
     let user_id = auth.user.id;
@@ -478,9 +563,11 @@ pub async fn search3(
     let starred_albums = auth
         .state
         .get_starred_at_for_albums_batch(user_id, &album_ids);
+    let album_play_stats = auth.state.get_album_play_stats_batch(user_id, &album_ids);
     let starred_songs = auth
         .state
         .get_starred_at_for_songs_batch(user_id, &song_ids);
+    let song_play_stats = auth.state.get_song_play_stats_batch(user_id, &song_ids);
 
     // Convert to response types with starred status from batch results
     let artist_responses: Vec<ArtistID3Response> = artists
@@ -497,6 +584,7 @@ pub async fn search3(
         .map(|a| {
             let starred_at = starred_albums.get(&a.id);
             AlbumID3Response::from_album_with_starred(a, starred_at)
+                .with_play_stats(album_play_stats.get(&a.id))
         })
         .collect();
 
@@ -505,6 +593,7 @@ pub async fn search3(
         .map(|s| {
             let starred_at = starred_songs.get(&s.id);
             ChildResponse::from_song_with_starred(s, starred_at)
+                .with_play_stats(song_play_stats.get(&s.id))
         })
         .collect();
 
@@ -563,12 +652,14 @@ pub async fn get_random_songs(
     let starred_songs = auth
         .state
         .get_starred_at_for_songs_batch(user_id, &song_ids);
+    let song_play_stats = auth.state.get_song_play_stats_batch(user_id, &song_ids);
 
     let song_responses: Vec<ChildResponse> = songs
         .iter()
         .map(|s| {
             let starred_at = starred_songs.get(&s.id);
             ChildResponse::from_song_with_starred(s, starred_at)
+                .with_play_stats(song_play_stats.get(&s.id))
         })
         .collect();
 
@@ -622,12 +713,14 @@ pub async fn get_songs_by_genre(
     let starred_songs = auth
         .state
         .get_starred_at_for_songs_batch(user_id, &song_ids);
+    let song_play_stats = auth.state.get_song_play_stats_batch(user_id, &song_ids);
 
     let song_responses: Vec<ChildResponse> = songs
         .iter()
         .map(|s| {
             let starred_at = starred_songs.get(&s.id);
             ChildResponse::from_song_with_starred(s, starred_at)
+                .with_play_stats(song_play_stats.get(&s.id))
         })
         .collect();
 
@@ -658,7 +751,8 @@ pub struct ArtistInfo2Params {
 /// GET/POST /rest/getArtistInfo2[.view]
 ///
 /// Returns artist info with biography, image URLs, similar artists, etc.
-/// This is a stub implementation that returns minimal data from the database.
+/// The biography and image come from the artist's `artist.nfo` or `biography.txt`,
+/// with the metadata agent's cached details filling in the rest.
 pub async fn get_artist_info2(
     axum::extract::Query(params): axum::extract::Query<ArtistInfo2Params>,
     auth: SubsonicAuth,
@@ -681,11 +775,95 @@ pub async fn get_artist_info2(
         }
     };
 
-    // Create response with available data from the artist
-    let response = ArtistInfo2Response::from_artist(&artist);
+    // Local info files take precedence over agent metadata
+    let metadata = auth.state.get_artist_metadata(&artist);
+    let mut response = ArtistInfo2Response::from_artist(&artist);
+    if let Some(metadata) = &metadata {
+        response = response.with_metadata(metadata);
+    }
+
+    let similar = similar_artists(&auth, &artist, metadata.as_ref(), &params);
+    let ids: Vec<i32> = similar
+        .iter()
+        .filter_map(|s| match s {
+            SimilarArtist::Present(a) => Some(a.id),
+            SimilarArtist::NotPresent(_) => None,
+        })
+        .collect();
+    let album_counts = auth.state.get_artist_album_counts_batch(&ids);
+    response.similar_artists = similar
+        .iter()
+        .map(|s| match s {
+            SimilarArtist::Present(a) => {
+                let album_count = album_counts.get(&a.id).copied().unwrap_or(0);
+                ArtistID3Response::from_artist(a, Some(album_count as i32))
+            }
+            SimilarArtist::NotPresent(name) => ArtistID3Response::not_present(name),
+        })
+        .collect();
     ok_artist_info2(auth.format, response).into_response()
 }
 
+/// Default number of similar artists returned by getArtistInfo/getArtistInfo2.
+const DEFAULT_SIMILAR_ARTIST_COUNT: i32 = 20;
+
+/// An artist similar to another, which may be missing from the library.
+enum SimilarArtist {
+    Present(Artist),
+    NotPresent(String),
+}
+
+/// Get an artist's similar artists, most similar first.
+///
+/// Artists played alongside it on this server come first, followed by the
+/// metadata agent's suggestions. Suggestions missing from the library are
+/// only included when `includeNotPresent` is set.
+fn similar_artists(
+    auth: &SubsonicAuth,
+    artist: &Artist,
+    metadata: Option<&ArtistMetadata>,
+    params: &ArtistInfo2Params,
+) -> Vec<SimilarArtist> {
+    let count = params.count.unwrap_or(DEFAULT_SIMILAR_ARTIST_COUNT).max(0) as usize;
+    if count == 0 {
+        return Vec::new();
+    }
+
+    let mut similar: Vec<SimilarArtist> = auth
+        .state
+        .get_similar_artists(artist.id, count as i64)
+        .into_iter()
+        .map(SimilarArtist::Present)
+        .collect();
+
+    if let Some(metadata) = metadata
+        && !metadata.similar_artists.is_empty()
+    {
+        let include_not_present = params.include_not_present.unwrap_or(false);
+        let present = auth.state.get_artists_by_names(&metadata.similar_artists);
+        for name in &metadata.similar_artists {
+            match present.iter().find(|a| a.name == *name) {
+                Some(a) => {
+                    let listed = a.id == artist.id
+                        || similar
+                            .iter()
+                            .any(|s| matches!(s, SimilarArtist::Present(p) if p.id == a.id));
+                    if !listed {
+                        similar.push(SimilarArtist::Present(a.clone()));
+                    }
+                }
+                None if include_not_present => {
+                    similar.push(SimilarArtist::NotPresent(name.clone()));
+                }
+                None => {}
+            }
+        }
+    }
+
+    similar.truncate(count);
+    similar
+}
+
 /// Query parameters for getAlbumInfo2.
 #[derive(Debug, Clone, Default, Deserialize)]
 #[serde(default)]
@@ -697,7 +875,8 @@ pub struct AlbumInfo2Params {
 /// GET/POST /rest/getAlbumInfo2[.view]
 ///
 /// Returns album info with notes, MusicBrainz ID, image URLs, etc.
-/// This is a stub implementation that returns minimal data from the database.
+/// The notes and image come from the album's `album.nfo`, with the metadata
+/// agent's cached details filling in the rest.
 pub async fn get_album_info2(
     axum::extract::Query(params): axum::extract::Query<AlbumInfo2Params>,
     auth: SubsonicAuth,
@@ -720,8 +899,11 @@ pub async fn get_album_info2(
         }
     };
 
-    // Create response with available data from the album
-    let response = AlbumInfoResponse::from_album(&album);
+    // Local info files take precedence over agent metadata
+    let mut response = AlbumInfoResponse::from_album(&album);
+    if let Some(metadata) = auth.state.get_album_metadata(&album) {
+        response = response.with_metadata(&metadata);
+    }
     ok_album_info(auth.format, response).into_response()
 }
 
@@ -737,8 +919,8 @@ pub struct SimilarSongs2Params {
 
 /// GET/POST /rest/getSimilarSongs2[.view]
 ///
-/// Returns songs similar to the given song, album, or artist.
-/// Since we don't have external metadata, we return random songs from the same artist or genre.
+/// Returns songs similar to the given song, album, or artist, scored on genre,
+/// year, artists, shared playlists, listening sessions and the user's ratings.
 pub async fn get_similar_songs2(
     axum::extract::Query(params): axum::extract::Query<SimilarSongs2Params>,
     auth: SubsonicAuth,
@@ -755,29 +937,11 @@ pub async fn get_similar_songs2(
     let count = params.count.unwrap_or(50).clamp(1, 500);
     let user_id = auth.user.id;
 
-    // Try to get similar songs - first check if it's a song
-    let songs = if let Some(song) = auth.state.get_song(id) {
-        // Get songs from the same artist (excluding this song)
-        if let Some(artist_id) = song.artist_id {
-            auth.state.get_similar_songs_by_artist(artist_id, id, count)
-        } else if let Some(ref genre) = song.genre {
-            // Fall back to same genre
-            auth.state.get_songs_by_genre(genre, count, 0, None)
-        } else {
-            Vec::new()
-        }
-    } else if let Some(album) = auth.state.get_album(id) {
-        // Get songs from the same artist
-        if let Some(artist_id) = album.artist_id {
-            auth.state.get_similar_songs_by_artist(artist_id, -1, count)
-        } else {
-            Vec::new()
+    let songs = match find_similar_songs(&auth, id, count) {
+        Some(songs) => songs,
+        None => {
+            return error_response(auth.format, &ApiError::NotFound("Item".into())).into_response();
         }
-    } else if auth.state.get_artist(id).is_some() {
-        // Get random songs from this artist
-        auth.state.get_similar_songs_by_artist(id, -1, count)
-    } else {
-        return error_response(auth.format, &ApiError::NotFound("Item".into())).into_response();
     };
 
     // Batch fetch starred status for all songs
@@ -785,12 +949,14 @@ pub async fn get_similar_songs2(
     let starred_songs = auth
         .state
         .get_starred_at_for_songs_batch(user_id, &song_ids);
+    let song_play_stats = auth.state.get_song_play_stats_batch(user_id, &song_ids);
 
     let song_responses: Vec<ChildResponse> = songs
         .iter()
         .map(|s| {
             let starred_at = starred_songs.get(&s.id);
             ChildResponse::from_song_with_starred(s, starred_at)
+                .with_play_stats(song_play_stats.get(&s.id))
         })
         .collect();
 
@@ -801,6 +967,24 @@ pub async fn get_similar_songs2(
     ok_similar_songs2(auth.format, response).into_response()
 }
 
+/// Get songs similar to a song, album or artist, or `None` if the ID matches none.
+fn find_similar_songs(auth: &SubsonicAuth, id: i32, count: i64) -> Option<Vec<Song>> {
+    let seeds = if let Some(song) = auth.state.get_song(id) {
+        vec![song]
+    } else if auth.state.get_album(id).is_some() {
+        auth.state.get_songs_by_album(id)
+    } else if auth.state.get_artist(id).is_some() {
+        auth.state.get_songs_by_artist(id)
+    } else {
+        return None;
+    };
+
+    Some(
+        auth.state
+            .get_similar_songs(auth.user.id, &seeds, count as usize),
+    )
+}
+
 /// Query parameters for getTopSongs.
 #[derive(Debug, Clone, Default, Deserialize)]
 #[serde(default)]
@@ -809,11 +993,15 @@ pub struct TopSongsParams {
     pub artist: Option<String>,
     /// Max number of songs to return. Default 50.
     pub count: Option<i64>,
+    /// Only count plays in the last "week", "month" or "year", or "all".
+    pub period: Option<String>,
 }
 
 /// GET/POST /rest/getTopSongs[.view]
 ///
-/// Returns the top songs for a given artist, ordered by play count.
+/// Returns the top songs for a given artist, ordered by the user's play count.
+/// With a `period`, only songs the user played within it are returned,
+/// ordered by their plays in that period.
 pub async fn get_top_songs(
     axum::extract::Query(params): axum::extract::Query<TopSongsParams>,
     auth: SubsonicAuth,
@@ -831,19 +1019,44 @@ pub async fn get_top_songs(
     let user_id = auth.user.id;
 
     // Get top songs by artist name (ordered by play count)
-    let songs = auth.state.get_top_songs_by_artist_name(artist_name, count);
+    let songs = match params.period.as_deref() {
+        Some(name) => {
+            let Some(period) = StatsPeriod::parse(name) else {
+                return error_response(
+                    auth.format,
+                    &ApiError::Generic(format!("Unknown period: {}", name)),
+                )
+                .into_response();
+            };
+            let filter = StatsFilter {
+                user_id: Some(user_id),
+                from: period.start(chrono::Utc::now().naive_utc()),
+                ..Default::default()
+            };
+            auth.state
+                .get_top_songs(&filter, Some(artist_name), count)
+                .into_iter()
+                .map(|(song, _)| song)
+                .collect()
+        }
+        None => auth
+            .state
+            .get_top_songs_by_artist_name(user_id, artist_name, count),
+    };
 
     // Batch fetch starred status for all songs
     let song_ids: Vec<i32> = songs.iter().map(|s| s.id).collect();
     let starred_songs = auth
         .state
         .get_starred_at_for_songs_batch(user_id, &song_ids);
+    let song_play_stats = auth.state.get_song_play_stats_batch(user_id, &song_ids);
 
     let song_responses: Vec<ChildResponse> = songs
         .iter()
         .map(|s| {
             let starred_at = starred_songs.get(&s.id);
             ChildResponse::from_song_with_starred(s, starred_at)
+                .with_play_stats(song_play_stats.get(&s.id))
         })
         .collect();
 
@@ -868,10 +1081,45 @@ pub async fn get_music_directory(
     auth: SubsonicAuth,
 ) -> impl IntoResponse {
     // Get the required 'id' parameter
-    let id = match params.id.as_ref().and_then(|id| id.parse::<i32>().ok()) {
-        Some(id) => id,
-        None => {
-            return error_response(auth.format, &ApiError::MissingParameter("id".into()))
+    let Some(id) = params.id.as_deref() else {
+        return error_response(auth.format, &ApiError::MissingParameter("id".into()))
+            .into_response();
+    };
+
+    // Directories on disk, as listed by getIndexes
+    if let Some(directory_id) = parse_directory_id(id) {
+        let Some(directory) = auth.state.get_directory(directory_id) else {
+            return error_response(auth.format, &ApiError::NotFound("Directory".into()))
+                .into_response();
+        };
+
+        let mut children: Vec<ChildResponse> = auth
+            .state
+            .get_child_directories(directory.id)
+            .iter()
+            .map(ChildResponse::from_directory)
+            .collect();
+        let songs = auth
+            .state
+            .get_songs_by_directory(directory.music_folder_id, &directory.path);
+        let song_ids: Vec<i32> = songs.iter().map(|s| s.id).collect();
+        let play_stats = auth
+            .state
+            .get_song_play_stats_batch(auth.user.id, &song_ids);
+        children.extend(songs.iter().map(|song| {
+            let mut child = ChildResponse::from(song).with_play_stats(play_stats.get(&song.id));
+            child.parent = Some(directory_response_id(directory.id));
+            child
+        }));
+
+        let response = DirectoryResponse::from_directory(&directory, children);
+        return ok_directory(auth.format, response).into_response();
+    }
+
+    let id = match id.parse::<i32>() {
+        Ok(id) => id,
+        Err(_) => {
+            return error_response(auth.format, &ApiError::NotFound("Directory".into()))
                 .into_response();
         }
     };
@@ -880,17 +1128,30 @@ pub async fn get_music_directory(
     // First, check if it's an album (most common case when browsing)
     if let Some(album) = auth.state.get_album(id) {
         let songs = auth.state.get_songs_by_album(id);
-        let children: Vec<ChildResponse> = songs.iter().map(ChildResponse::from).collect();
-        let response = DirectoryResponse::from_album(&album, children);
+        let song_ids: Vec<i32> = songs.iter().map(|s| s.id).collect();
+        let play_stats = auth
+            .state
+            .get_song_play_stats_batch(auth.user.id, &song_ids);
+        let album_play_stats = auth.state.get_album_play_stats_batch(auth.user.id, &[id]);
+        let children: Vec<ChildResponse> = songs
+            .iter()
+            .map(|s| ChildResponse::from(s).with_play_stats(play_stats.get(&s.id)))
+            .collect();
+        let response = DirectoryResponse::from_album(&album, children)
+            .with_play_stats(album_play_stats.get(&id));
         return ok_directory(auth.format, response).into_response();
     }
 
     // Check if it's an artist
     if let Some(artist) = auth.state.get_artist(id) {
         let albums = auth.state.get_albums_by_artist(id);
+        let album_ids: Vec<i32> = albums.iter().map(|a| a.id).collect();
+        let play_stats = auth
+            .state
+            .get_album_play_stats_batch(auth.user.id, &album_ids);
         let children: Vec<ChildResponse> = albums
             .iter()
-            .map(ChildResponse::from_album_as_dir)
+            .map(|a| ChildResponse::from_album_as_dir(a).with_play_stats(play_stats.get(&a.id)))
             .collect();
         let response = DirectoryResponse::from_artist(&artist, children);
         return ok_directory(auth.format, response).into_response();
@@ -899,12 +1160,23 @@ pub async fn get_music_directory(
     // Check if it's a music folder
     let folders = auth.state.get_music_folders();
     if let Some(folder) = folders.iter().find(|f| f.id == id) {
-        // For music folders, return all artists as children
-        let artists = auth.state.get_artists();
-        let children: Vec<ChildResponse> = artists
+        // For music folders, return the top-level directories and root files
+        let mut children: Vec<ChildResponse> = auth
+            .state
+            .get_top_level_directories(Some(folder.id))
             .iter()
-            .map(ChildResponse::from_artist_as_dir)
+            .map(ChildResponse::from_directory)
             .collect();
+        let songs = auth.state.get_songs_by_directory(folder.id, "");
+        let song_ids: Vec<i32> = songs.iter().map(|s| s.id).collect();
+        let play_stats = auth
+            .state
+            .get_song_play_stats_batch(auth.user.id, &song_ids);
+        children.extend(songs.iter().map(|song| {
+            let mut child = ChildResponse::from(song).with_play_stats(play_stats.get(&song.id));
+            child.parent = Some(folder.id.to_string());
+            child
+        }));
         let response = DirectoryResponse::from_music_folder(folder, children);
         return ok_directory(auth.format, response).into_response();
     }
@@ -934,8 +1206,8 @@ pub async fn get_album_list(
     let albums = match list_type {
         "random" => auth.state.get_albums_random(size),
         "newest" => auth.state.get_albums_newest(offset, size),
-        "frequent" => auth.state.get_albums_frequent(offset, size),
-        "recent" => auth.state.get_albums_recent(offset, size),
+        "frequent" => auth.state.get_albums_frequent(auth.user.id, offset, size),
+        "recent" => auth.state.get_albums_recent(auth.user.id, offset, size),
         "alphabeticalByName" => auth.state.get_albums_alphabetical_by_name(offset, size),
         "alphabeticalByArtist" => auth.state.get_albums_alphabetical_by_artist(offset, size),
         "byYear" => {
@@ -969,9 +1241,13 @@ pub async fn get_album_list(
     };
 
     // Convert to Child elements (non-ID3)
+    let album_ids: Vec<i32> = albums.iter().map(|a| a.id).collect();
+    let play_stats = auth
+        .state
+        .get_album_play_stats_batch(auth.user.id, &album_ids);
     let album_responses: Vec<ChildResponse> = albums
         .iter()
-        .map(ChildResponse::from_album_as_dir)
+        .map(|a| ChildResponse::from_album_as_dir(a).with_play_stats(play_stats.get(&a.id)))
         .collect();
 
     let response = AlbumListResponse {
@@ -992,6 +1268,11 @@ pub async fn get_starred(auth: SubsonicAuth) -> impl IntoResponse {
     let starred_albums = auth.state.get_starred_albums(user_id);
     let starred_songs = auth.state.get_starred_songs(user_id);
 
+    let album_ids: Vec<i32> = starred_albums.iter().map(|(a, _)| a.id).collect();
+    let album_play_stats = auth.state.get_album_play_stats_batch(user_id, &album_ids);
+    let song_ids: Vec<i32> = starred_songs.iter().map(|(s, _)| s.id).collect();
+    let song_play_stats = auth.state.get_song_play_stats_batch(user_id, &song_ids);
+
     // Convert to response types
     let artist_responses: Vec<ArtistResponse> = starred_artists
         .iter()
@@ -1003,7 +1284,8 @@ pub async fn get_starred(auth: SubsonicAuth) -> impl IntoResponse {
     let album_responses: Vec<ChildResponse> = starred_albums
         .iter()
         .map(|(album, starred_at)| {
-            let mut response = ChildResponse::from_album_as_dir(album);
+            let mut response = ChildResponse::from_album_as_dir(album)
+                .with_play_stats(album_play_stats.get(&album.id));
             response.starred = Some(starred_at.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string());
             response
         })
@@ -1011,7 +1293,10 @@ pub async fn get_starred(auth: SubsonicAuth) -> impl IntoResponse {
 
     let song_responses: Vec<ChildResponse> = starred_songs
         .iter()
-        .map(|(song, starred_at)| ChildResponse::from_song_with_starred(song, Some(starred_at)))
+        .map(|(song, starred_at)| {
+            ChildResponse::from_song_with_starred(song, Some(starred_at))
+                .with_play_stats(song_play_stats.get(&song.id))
+        })
         .collect();
 
     let response = StarredResponse {
@@ -1089,9 +1374,11 @@ pub async fn search2(
     let starred_albums = auth
         .state
         .get_starred_at_for_albums_batch(user_id, &album_ids);
+    let album_play_stats = auth.state.get_album_play_stats_batch(user_id, &album_ids);
     let starred_songs = auth
         .state
         .get_starred_at_for_songs_batch(user_id, &song_ids);
+    let song_play_stats = auth.state.get_song_play_stats_batch(user_id, &song_ids);
 
     // Convert to non-ID3 response types
     let artist_responses: Vec<ArtistResponse> = artists
@@ -1106,7 +1393,8 @@ pub async fn search2(
         .iter()
         .map(|a| {
             let starred_at = starred_albums.get(&a.id);
-            let mut response = ChildResponse::from_album_as_dir(a);
+            let mut response =
+                ChildResponse::from_album_as_dir(a).with_play_stats(album_play_stats.get(&a.id));
             response.starred = starred_at.map(|dt| dt.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string());
             response
         })
@@ -1117,6 +1405,7 @@ pub async fn search2(
         .map(|s| {
             let starred_at = starred_songs.get(&s.id);
             ChildResponse::from_song_with_starred(s, starred_at)
+                .with_play_stats(song_play_stats.get(&s.id))
         })
         .collect();
 
@@ -1210,7 +1499,19 @@ pub async fn get_artist_info(
         }
     };
 
-    let response = ArtistInfoResponse::from_artist(&artist);
+    // Local info files take precedence over agent metadata
+    let metadata = auth.state.get_artist_metadata(&artist);
+    let mut response = ArtistInfoResponse::from_artist(&artist);
+    if let Some(metadata) = &metadata {
+        response = response.with_metadata(metadata);
+    }
+    response.similar_artists = similar_artists(&auth, &artist, metadata.as_ref(), &params)
+        .iter()
+        .map(|s| match s {
+            SimilarArtist::Present(a) => ArtistResponse::from(a),
+            SimilarArtist::NotPresent(name) => ArtistResponse::not_present(name),
+        })
+        .collect();
     ok_artist_info(auth.format, response).into_response()
 }
 
@@ -1240,7 +1541,10 @@ pub async fn get_album_info(
     };
 
     // Use AlbumInfoResponse which is the same for ID3 and non-ID3
-    let response = AlbumInfoResponse::from_album(&album);
+    let mut response = AlbumInfoResponse::from_album(&album);
+    if let Some(metadata) = auth.state.get_album_metadata(&album) {
+        response = response.with_metadata(&metadata);
+    }
     ok_album_info(auth.format, response).into_response()
 }
 
@@ -1263,25 +1567,11 @@ pub async fn get_similar_songs(
     let count = params.count.unwrap_or(50).clamp(1, 500);
     let user_id = auth.user.id;
 
-    // Try to get similar songs
-    let songs = if let Some(song) = auth.state.get_song(id) {
-        if let Some(artist_id) = song.artist_id {
-            auth.state.get_similar_songs_by_artist(artist_id, id, count)
-        } else if let Some(ref genre) = song.genre {
-            auth.state.get_songs_by_genre(genre, count, 0, None)
-        } else {
-            Vec::new()
-        }
-    } else if let Some(album) = auth.state.get_album(id) {
-        if let Some(artist_id) = album.artist_id {
-            auth.state.get_similar_songs_by_artist(artist_id, -1, count)
-        } else {
-            Vec::new()
+    let songs = match find_similar_songs(&auth, id, count) {
+        Some(songs) => songs,
+        None => {
+            return error_response(auth.format, &ApiError::NotFound("Item".into())).into_response();
         }
-    } else if auth.state.get_artist(id).is_some() {
-        auth.state.get_similar_songs_by_artist(id, -1, count)
-    } else {
-        return error_response(auth.format, &ApiError::NotFound("Item".into())).into_response();
     };
 
     // Batch fetch starred status for all songs
@@ -1289,12 +1579,14 @@ pub async fn get_similar_songs(
     let starred_songs = auth
         .state
         .get_starred_at_for_songs_batch(user_id, &song_ids);
+    let song_play_stats = auth.state.get_song_play_stats_batch(user_id, &song_ids);
 
     let song_responses: Vec<ChildResponse> = songs
         .iter()
         .map(|s| {
             let starred_at = starred_songs.get(&s.id);
             ChildResponse::from_song_with_starred(s, starred_at)
+                .with_play_stats(song_play_stats.get(&s.id))
         })
         .collect();
 
@@ -1322,18 +1614,46 @@ pub struct LyricsParams {
 /// GET/POST /rest/getLyrics[.view]
 ///
 /// Searches for and returns lyrics for a given song.
-/// Note: This is a stub implementation that returns empty lyrics since we don't
-/// have a lyrics database or external service integration.
+/// Looks up lyrics indexed during scanning by artist and title.
+/// Returns empty lyrics if no matching song has lyrics.
 pub async fn get_lyrics(
     axum::extract::Query(params): axum::extract::Query<LyricsParams>,
     auth: SubsonicAuth,
 ) -> impl IntoResponse {
-    // Return empty lyrics with the requested artist/title
-    let response = LyricsResponse::new(
-        params.artist.clone(),
-        params.title.clone(),
-        None, // No lyrics content
-    );
+    use crate::scanner::lyrics::parse_lrc;
+
+    let artist = params
+        .artist
+        .as_deref()
+        .map(str::trim)
+        .filter(|s| !s.is_empty());
+    let title = params
+        .title
+        .as_deref()
+        .map(str::trim)
+        .filter(|s| !s.is_empty());
+
+    let response = match auth.state.find_lyrics_by_artist_title(artist, title) {
+        Some((song, lyrics)) => {
+            // Prefer plain lyrics; synced lyrics are flattened to their text lines
+            let text = lyrics
+                .iter()
+                .find(|l| !l.synced)
+                .map(|l| l.text.clone())
+                .or_else(|| {
+                    lyrics.first().map(|l| {
+                        parse_lrc(&l.text)
+                            .into_iter()
+                            .map(|line| line.text)
+                            .collect::<Vec<_>>()
+                            .join("\n")
+                    })
+                });
+            LyricsResponse::new(song.artist_name, Some(song.title), text)
+        }
+        // Return empty lyrics with the requested artist/title
+        None => LyricsResponse::new(params.artist.clone(), params.title.clone(), None),
+    };
 
     ok_lyrics(auth.format, response)
 }
@@ -1341,7 +1661,7 @@ pub async fn get_lyrics(
 /// GET/POST /rest/getLyricsBySongId[.view]
 ///
 /// Returns structured lyrics for a given song (OpenSubsonic extension).
-/// Extracts embedded lyrics from the audio file.
+/// Uses the embedded lyrics indexed during scanning.
 /// Returns an empty lyricsList if no lyrics are available.
 pub async fn get_lyrics_by_song_id(
     axum::extract::Query(params): axum::extract::Query<IdParams>,
@@ -1367,7 +1687,7 @@ pub async fn get_lyrics_by_song_id(
         }
     };
 
-    // Extract lyrics from the audio file
+    // Get the lyrics indexed for this song
     let extracted = auth.state.get_song_lyrics(song_id);
 
     // Convert extracted lyrics to OpenSubsonic StructuredLyrics format
@@ -1413,3 +1733,37 @@ pub async fn get_lyrics_by_song_id(
 
     ok_lyrics_list(auth.format, response).into_response()
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::api::handlers::test_library;
+    use crate::db::{LyricsRepository, NewLyrics};
+
+    #[test]
+    fn test_search_songs_by_lyrics() {
+        let (state, pool, _, [with_lyrics, _]) = test_library("lyrics-search");
+        LyricsRepository::replace_for_song(
+            &mut pool.get().unwrap(),
+            with_lyrics,
+            &[NewLyrics {
+                song_id: with_lyrics,
+                lang: Some("eng"),
+                description: None,
+                synced: false,
+                text: "Walking in the rain\nUnder 100% grey skies",
+            }],
+        )
+        .unwrap();
+
+        let found = state.search_songs_by_lyrics("RAIN", 0, 20);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, with_lyrics);
+        assert_eq!(state.search_songs_by_lyrics("100%", 0, 20).len(), 1);
+
+        // Titles aren't searched, and LIKE wildcards are matched literally
+        assert!(state.search_songs_by_lyrics("Two", 0, 20).is_empty());
+        assert!(state.search_songs_by_lyrics("sun", 0, 20).is_empty());
+        assert!(state.search_songs_by_lyrics("r_in", 0, 20).is_empty());
+        assert!(state.search_songs_by_lyrics("rain", 1, 20).is_empty());
+    }
+}
@@ -0,0 +1,108 @@
+//! Chat API handlers (getChatMessages, addChatMessage)
+
+use axum::response::IntoResponse;
+use serde::Deserialize;
+
+use crate::api::auth::SubsonicAuth;
+use crate::api::error::ApiError;
+use crate::api::response::{error_response, ok_chat_messages, ok_empty};
+use crate::models::chat::{ChatMessageResponse, ChatMessagesResponse, MAX_CHAT_MESSAGE_LENGTH};
+
+/// Query parameters for getChatMessages.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct GetChatMessagesParams {
+    /// Only return messages newer than this time (milliseconds since the epoch).
+    pub since: Option<i64>,
+}
+
+/// Query parameters for addChatMessage.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct AddChatMessageParams {
+    /// The chat message.
+    pub message: Option<String>,
+}
+
+/// GET/POST /rest/getChatMessages[.view]
+///
+/// Returns the chat messages, newest first. Only the most recent messages
+/// are kept.
+///
+/// Parameters:
+/// - `since`: Only return messages newer than this time (milliseconds since the epoch)
+pub async fn get_chat_messages(
+    axum::extract::Query(params): axum::extract::Query<GetChatMessagesParams>,
+    auth: SubsonicAuth,
+) -> impl IntoResponse {
+    let since = params
+        .since
+        .and_then(chrono::DateTime::from_timestamp_millis)
+        .map(|since| since.naive_utc());
+
+    let messages = auth
+        .state
+        .get_chat_messages(since)
+        .iter()
+        .map(ChatMessageResponse::from)
+        .collect();
+
+    ok_chat_messages(auth.format, ChatMessagesResponse { messages })
+}
+
+/// GET/POST /rest/addChatMessage[.view]
+///
+/// Posts a message to the chat.
+///
+/// Parameters:
+/// - `message` (required): The chat message
+pub async fn add_chat_message(
+    axum::extract::Query(params): axum::extract::Query<AddChatMessageParams>,
+    auth: SubsonicAuth,
+) -> impl IntoResponse {
+    let Some(message) = params
+        .message
+        .as_deref()
+        .map(str::trim)
+        .filter(|m| !m.is_empty())
+    else {
+        return error_response(auth.format, &ApiError::MissingParameter("message".into()));
+    };
+    if message.chars().count() > MAX_CHAT_MESSAGE_LENGTH {
+        return error_response(
+            auth.format,
+            &ApiError::Generic(format!(
+                "Message is longer than {} characters",
+                MAX_CHAT_MESSAGE_LENGTH
+            )),
+        );
+    }
+
+    match auth.state.add_chat_message(auth.user.id, message) {
+        Ok(()) => ok_empty(auth.format),
+        Err(e) => error_response(auth.format, &ApiError::Generic(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::handlers::test_library;
+    use crate::models::chat::MAX_CHAT_MESSAGES;
+
+    #[test]
+    fn test_only_newest_messages_are_kept() {
+        let (state, _, user_id, _) = test_library("chat");
+        let posted = MAX_CHAT_MESSAGES + 2;
+        for i in 0..posted {
+            state
+                .add_chat_message(user_id, &format!("message {}", i))
+                .unwrap();
+        }
+
+        let messages = state.get_chat_messages(None);
+        assert_eq!(messages.len() as i64, MAX_CHAT_MESSAGES);
+        assert_eq!(messages[0].message, format!("message {}", posted - 1));
+        assert_eq!(messages.last().unwrap().message, "message 2");
+        assert_eq!(messages[0].username, "listener");
+    }
+}
@@ -3,17 +3,19 @@
 use axum::{
     body::Body,
     http::{HeaderMap, StatusCode, header},
-    response::IntoResponse,
+    response::{IntoResponse, Response},
 };
 use std::path::{Path, PathBuf};
 use tokio::fs::File;
 use tokio::io::{AsyncReadExt, AsyncSeekExt};
 use tokio_util::io::ReaderStream;
 
-use crate::api::auth::SubsonicAuth;
+use crate::api::auth::{AuthState, SubsonicAuth};
 use crate::api::error::ApiError;
 use crate::api::response::error_response;
 use crate::models::music::Song;
+use crate::models::podcast::{EPISODE_STREAM_ID_PREFIX, PodcastEpisode};
+use crate::scanner::cue::{AudioSlice, slice_track};
 
 /// Default cover art cache directory (same as in scanner).
 const COVER_ART_CACHE_DIR: &str = ".cache/subsonic/covers";
@@ -28,8 +30,8 @@ fn get_cover_art_dir() -> std::path::PathBuf {
 /// Validate that a song's path is within one of the configured music folders.
 /// This prevents path traversal attacks where a malicious path in the database
 /// could be used to read arbitrary files.
-fn validate_song_path(song: &Song, auth: &SubsonicAuth) -> Result<PathBuf, &'static str> {
-    let song_path = Path::new(&song.path);
+fn validate_song_path(song: &Song, state: &dyn AuthState) -> Result<PathBuf, &'static str> {
+    let song_path = Path::new(song.file_path());
 
     // Canonicalize the song path to resolve any symlinks and ../ components
     let canonical_path = match song_path.canonicalize() {
@@ -38,7 +40,7 @@ fn validate_song_path(song: &Song, auth: &SubsonicAuth) -> Result<PathBuf, &'sta
     };
 
     // Get all music folders and verify the song is within one of them
-    let music_folders = auth.state.get_music_folders();
+    let music_folders = state.get_music_folders();
     for folder in &music_folders {
         if let Ok(folder_canonical) = Path::new(&folder.path).canonicalize()
             && canonical_path.starts_with(&folder_canonical)
@@ -56,6 +58,68 @@ fn validate_song_path(song: &Song, auth: &SubsonicAuth) -> Result<PathBuf, &'sta
     Err("Audio file not found in music library")
 }
 
+/// Validate that a downloaded podcast episode is within the podcast folder.
+fn validate_episode_path(
+    episode: &PodcastEpisode,
+    state: &dyn AuthState,
+) -> Result<PathBuf, &'static str> {
+    let Some(file_path) = episode.file_path.as_deref() else {
+        return Err("Podcast episode has not been downloaded");
+    };
+    let canonical_path = Path::new(file_path)
+        .canonicalize()
+        .map_err(|_| "Audio file not found on disk")?;
+
+    if let Ok(folder_canonical) = state.podcast_folder().canonicalize()
+        && canonical_path.starts_with(&folder_canonical)
+    {
+        return Ok(canonical_path);
+    }
+
+    tracing::warn!(
+        "Path traversal attempt blocked: podcast episode {} has path outside the podcast folder: {}",
+        episode.id,
+        file_path
+    );
+    Err("Audio file not found in podcast folder")
+}
+
+/// Compute the part of a song's file to serve: the whole file, or the
+/// range holding a track split from a CUE sheet.
+async fn song_slice(song: &Song, path: &Path, file_size: u64) -> std::io::Result<AudioSlice> {
+    let Some(start_ms) = song.cue_start_ms else {
+        return Ok(AudioSlice::whole_file(file_size));
+    };
+
+    let path = path.to_path_buf();
+    let suffix = song.suffix.to_lowercase();
+    let end_ms = song.cue_end_ms;
+    tokio::task::spawn_blocking(move || slice_track(&path, &suffix, start_ms, end_ms))
+        .await
+        .map_err(std::io::Error::other)?
+}
+
+/// Build a body serving bytes `start..=end` of a slice.
+async fn slice_body(
+    mut file: File,
+    slice: &AudioSlice,
+    start: u64,
+    end: u64,
+) -> std::io::Result<Body> {
+    let header_len = slice.header.len() as u64;
+    let header_start = start.min(header_len);
+    let header_end = (end + 1).min(header_len).max(header_start);
+    let header = slice.header[header_start as usize..header_end as usize].to_vec();
+
+    let file_start = start.saturating_sub(header_len);
+    let file_len = (end + 1).saturating_sub(start.max(header_len));
+    file.seek(std::io::SeekFrom::Start(slice.offset + file_start))
+        .await?;
+
+    let reader = std::io::Cursor::new(header).chain(file.take(file_len));
+    Ok(Body::from_stream(ReaderStream::new(reader)))
+}
+
 /// Query parameters for the stream endpoint.
 #[derive(Debug, Clone, Default, serde::Deserialize)]
 #[serde(default)]
@@ -79,13 +143,13 @@ pub struct StreamParams {
     pub converted: Option<bool>,
 }
 
-/// Stream a song file.
+/// Stream a song file or a downloaded podcast episode.
 ///
 /// Returns the audio file as a binary stream. Supports HTTP range requests
 /// for seeking within the file.
 ///
 /// Parameters:
-/// - `id` (required): The ID of the song to stream.
+/// - `id` (required): The ID of the song, or the stream ID of the episode.
 /// - `maxBitRate` (optional): Maximum bit rate for transcoding (not yet implemented).
 /// - `format` (optional): Preferred format for transcoding (not yet implemented).
 pub async fn stream(
@@ -93,6 +157,33 @@ pub async fn stream(
     axum::extract::Query(params): axum::extract::Query<StreamParams>,
     auth: SubsonicAuth,
 ) -> impl IntoResponse {
+    // Downloaded podcast episodes are streamed by their stream ID
+    if let Some(episode_id) = params
+        .id
+        .as_deref()
+        .and_then(|id| id.strip_prefix(EPISODE_STREAM_ID_PREFIX))
+    {
+        if !auth.user.roles.stream_role {
+            return error_response(auth.format, &ApiError::NotAuthorized).into_response();
+        }
+        let Some(episode) = episode_id
+            .parse::<i32>()
+            .ok()
+            .and_then(|id| auth.state.get_podcast_episode(id))
+            .filter(|episode| episode.stream_id().is_some())
+        else {
+            return error_response(
+                auth.format,
+                &ApiError::NotFound("Podcast episode not found".into()),
+            )
+            .into_response();
+        };
+        return match serve_podcast_episode(auth.state.as_ref(), &episode, &headers).await {
+            Ok(response) => response,
+            Err(e) => error_response(auth.format, &e).into_response(),
+        };
+    }
+
     // Get song ID
     let song_id = match params.id.as_ref().and_then(|id| id.parse::<i32>().ok()) {
         Some(id) => id,
@@ -116,40 +207,78 @@ pub async fn stream(
         return error_response(auth.format, &ApiError::NotAuthorized).into_response();
     }
 
-    // Validate the song path is within a music folder (prevents path traversal)
-    let path = match validate_song_path(&song, &auth) {
-        Ok(p) => p,
-        Err(msg) => {
-            return error_response(auth.format, &ApiError::NotFound(msg.into())).into_response();
-        }
-    };
+    match serve_song(auth.state.as_ref(), &song, &headers).await {
+        Ok(response) => response,
+        Err(e) => error_response(auth.format, &e).into_response(),
+    }
+}
 
-    // Open the file
-    let file = match File::open(&path).await {
-        Ok(f) => f,
-        Err(_) => {
-            return error_response(
-                auth.format,
-                &ApiError::Generic("Failed to open audio file".into()),
-            )
-            .into_response();
-        }
-    };
+/// Serve a song's audio, supporting HTTP range requests for seeking.
+///
+/// Shared by `stream` and public share links; callers check that the
+/// song may be played first.
+pub(crate) async fn serve_song(
+    state: &dyn AuthState,
+    song: &Song,
+    headers: &HeaderMap,
+) -> Result<Response, ApiError> {
+    // Validate the song path is within a music folder (prevents path traversal)
+    let path = validate_song_path(song, state).map_err(|msg| ApiError::NotFound(msg.into()))?;
+
+    // Open the file and get its metadata
+    let file = File::open(&path)
+        .await
+        .map_err(|_| ApiError::Generic("Failed to open audio file".into()))?;
+    let metadata = file
+        .metadata()
+        .await
+        .map_err(|_| ApiError::Generic("Failed to read file metadata".into()))?;
+
+    let slice = song_slice(song, &path, metadata.len())
+        .await
+        .map_err(|_| ApiError::Generic("Failed to read audio file".into()))?;
+
+    serve_slice(file, slice, song.content_type.clone(), headers).await
+}
 
-    // Get file metadata
-    let metadata = match file.metadata().await {
-        Ok(m) => m,
-        Err(_) => {
-            return error_response(
-                auth.format,
-                &ApiError::Generic("Failed to read file metadata".into()),
-            )
-            .into_response();
-        }
-    };
+/// Serve a downloaded podcast episode, supporting HTTP range requests.
+async fn serve_podcast_episode(
+    state: &dyn AuthState,
+    episode: &PodcastEpisode,
+    headers: &HeaderMap,
+) -> Result<Response, ApiError> {
+    let path =
+        validate_episode_path(episode, state).map_err(|msg| ApiError::NotFound(msg.into()))?;
+
+    let file = File::open(&path)
+        .await
+        .map_err(|_| ApiError::Generic("Failed to open audio file".into()))?;
+    let metadata = file
+        .metadata()
+        .await
+        .map_err(|_| ApiError::Generic("Failed to read file metadata".into()))?;
+
+    let content_type = episode
+        .content_type
+        .clone()
+        .unwrap_or_else(|| "audio/mpeg".to_string());
+    serve_slice(
+        file,
+        AudioSlice::whole_file(metadata.len()),
+        content_type,
+        headers,
+    )
+    .await
+}
 
-    let file_size = metadata.len();
-    let content_type = song.content_type.clone();
+/// Serve a slice of an open audio file, honouring the Range header.
+async fn serve_slice(
+    file: File,
+    slice: AudioSlice,
+    content_type: String,
+    headers: &HeaderMap,
+) -> Result<Response, ApiError> {
+    let file_size = slice.len();
 
     // Check for Range header to support seeking
     let range_header = headers.get(header::RANGE).and_then(|v| v.to_str().ok());
@@ -159,40 +288,32 @@ pub async fn stream(
         if let Some(range_spec) = range.strip_prefix("bytes=") {
             let parts: Vec<&str> = range_spec.split('-').collect();
             if parts.len() == 2 {
+                let last = file_size.saturating_sub(1);
                 let start: u64 = parts[0].parse().unwrap_or(0);
                 let end: u64 = if parts[1].is_empty() {
-                    file_size - 1
+                    last
                 } else {
-                    parts[1].parse().unwrap_or(file_size - 1)
+                    parts[1].parse().unwrap_or(last)
                 };
 
                 // Validate range
-                if start >= file_size {
-                    return (
+                if start >= file_size || end < start {
+                    return Ok((
                         StatusCode::RANGE_NOT_SATISFIABLE,
                         [(header::CONTENT_RANGE, format!("bytes */{}", file_size))],
                     )
-                        .into_response();
+                        .into_response());
                 }
 
-                let end = end.min(file_size - 1);
+                let end = end.min(last);
                 let content_length = end - start + 1;
 
-                // Seek to start position
-                let mut file = file;
-                if file.seek(std::io::SeekFrom::Start(start)).await.is_err() {
-                    return error_response(
-                        auth.format,
-                        &ApiError::Generic("Failed to seek in file".into()),
-                    )
-                    .into_response();
-                }
-
                 // Create a limited reader for the range
-                let stream = ReaderStream::new(file.take(content_length));
-                let body = Body::from_stream(stream);
+                let body = slice_body(file, &slice, start, end)
+                    .await
+                    .map_err(|_| ApiError::Generic("Failed to seek in file".into()))?;
 
-                return (
+                return Ok((
                     StatusCode::PARTIAL_CONTENT,
                     [
                         (header::CONTENT_TYPE, content_type),
@@ -205,16 +326,17 @@ pub async fn stream(
                     ],
                     body,
                 )
-                    .into_response();
+                    .into_response());
             }
         }
     }
 
     // No range requested, stream entire file
-    let stream = ReaderStream::new(file);
-    let body = Body::from_stream(stream);
+    let body = slice_body(file, &slice, 0, file_size.saturating_sub(1))
+        .await
+        .map_err(|_| ApiError::Generic("Failed to seek in file".into()))?;
 
-    (
+    Ok((
         StatusCode::OK,
         [
             (header::CONTENT_TYPE, content_type),
@@ -223,7 +345,7 @@ pub async fn stream(
         ],
         body,
     )
-        .into_response()
+        .into_response())
 }
 
 /// Download a song file.
@@ -257,7 +379,7 @@ pub async fn download(
     }
 
     // Validate the song path is within a music folder (prevents path traversal)
-    let path = match validate_song_path(&song, &auth) {
+    let path = match validate_song_path(&song, auth.state.as_ref()) {
         Ok(p) => p,
         Err(msg) => {
             return error_response(auth.format, &ApiError::NotFound(msg.into())).into_response();
@@ -265,11 +387,20 @@ pub async fn download(
     };
 
     // Get filename for Content-Disposition and sanitize it to prevent header injection
-    let filename = path
-        .file_name()
-        .and_then(|n| n.to_str())
-        .unwrap_or("download")
-        .replace(['"', '\r', '\n'], "");
+    let filename = if song.is_cue_track() {
+        format!(
+            "{:02} - {}.{}",
+            song.track_number.unwrap_or(0),
+            song.title,
+            song.suffix
+        )
+    } else {
+        path.file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("download")
+            .to_string()
+    }
+    .replace(['"', '\r', '\n', '/'], "");
 
     // Open the file
     let file = match File::open(&path).await {
@@ -295,12 +426,31 @@ pub async fn download(
         }
     };
 
-    let file_size = metadata.len();
+    let slice = match song_slice(&song, &path, metadata.len()).await {
+        Ok(slice) => slice,
+        Err(_) => {
+            return error_response(
+                auth.format,
+                &ApiError::Generic("Failed to read audio file".into()),
+            )
+            .into_response();
+        }
+    };
+
+    let file_size = slice.len();
     let content_type = song.content_type.clone();
 
     // Stream the file
-    let stream = ReaderStream::new(file);
-    let body = Body::from_stream(stream);
+    let body = match slice_body(file, &slice, 0, file_size.saturating_sub(1)).await {
+        Ok(body) => body,
+        Err(_) => {
+            return error_response(
+                auth.format,
+                &ApiError::Generic("Failed to read audio file".into()),
+            )
+            .into_response();
+        }
+    };
 
     (
         StatusCode::OK,
@@ -431,3 +581,58 @@ pub async fn get_cover_art(
     )
         .into_response()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Serve a slice of a temporary file with a Range header, returning
+    /// the status and body.
+    async fn serve_range(name: &str, slice: AudioSlice, range: &str) -> (StatusCode, Vec<u8>) {
+        let path = std::env::temp_dir().join(format!(
+            "subsonic-media-test-{}-{}",
+            name,
+            std::process::id()
+        ));
+        std::fs::write(&path, b"0123456789").unwrap();
+        let file = File::open(&path).await.unwrap();
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::RANGE, range.parse().unwrap());
+        let response = serve_slice(file, slice, "audio/flac".into(), &headers)
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let _ = std::fs::remove_file(&path);
+        (status, body.to_vec())
+    }
+
+    #[tokio::test]
+    async fn test_range_spans_header_and_file() {
+        let slice = AudioSlice {
+            header: b"HDR".to_vec(),
+            offset: 4,
+            length: 4,
+        };
+        let (status, body) = serve_range("spans", slice, "bytes=1-4").await;
+        assert_eq!(status, StatusCode::PARTIAL_CONTENT);
+        assert_eq!(body, b"DR45");
+    }
+
+    #[tokio::test]
+    async fn test_inverted_range_is_not_satisfiable() {
+        let slice = AudioSlice {
+            header: b"HDR".to_vec(),
+            offset: 0,
+            length: 10,
+        };
+        let (status, _) = serve_range("inverted", slice, "bytes=8-2").await;
+        assert_eq!(status, StatusCode::RANGE_NOT_SATISFIABLE);
+
+        let (status, _) = serve_range("empty", AudioSlice::whole_file(0), "bytes=0-").await;
+        assert_eq!(status, StatusCode::RANGE_NOT_SATISFIABLE);
+    }
+}
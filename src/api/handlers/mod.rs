@@ -1,19 +1,92 @@
 //! Subsonic API handlers.
 
 pub mod annotation;
+pub mod bookmarks;
 pub mod browsing;
+pub mod chat;
 pub mod media;
 pub mod playlists;
 pub mod playqueue;
+pub mod podcasts;
+pub mod radio;
 pub mod scanning;
+pub mod scrobbling;
+pub mod shares;
+pub mod stats;
 pub mod system;
 pub mod users;
 
 pub use annotation::*;
+pub use bookmarks::*;
 pub use browsing::*;
+pub use chat::*;
 pub use media::*;
 pub use playlists::*;
 pub use playqueue::*;
+pub use podcasts::*;
+pub use radio::*;
 pub use scanning::*;
+pub use scrobbling::*;
+pub use shares::*;
+pub use stats::*;
 pub use system::*;
 pub use users::*;
+
+/// Database with a user and two songs in a music folder, for handler tests.
+/// Returns the state, its pool, the user ID and the song IDs.
+#[cfg(test)]
+pub(crate) fn test_library(
+    name: &str,
+) -> (
+    std::sync::Arc<dyn crate::api::auth::AuthState>,
+    crate::db::DbPool,
+    i32,
+    [i32; 2],
+) {
+    use diesel::RunQueryDsl;
+
+    use crate::api::auth::DatabaseAuthState;
+    use crate::db::{DbConfig, MusicFolderRepository, NewUser, UserRepository, run_migrations};
+    use crate::models::music::NewMusicFolder;
+
+    let dir = std::env::temp_dir().join(format!(
+        "subsonic-handler-test-{}-{}",
+        name,
+        std::process::id()
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let pool = DbConfig::new(dir.join("test.db").to_string_lossy())
+        .build_pool()
+        .unwrap();
+    run_migrations(&mut pool.get().unwrap()).unwrap();
+
+    let user = UserRepository::new(pool.clone())
+        .create(&NewUser::admin("listener", "hash", "secret"))
+        .unwrap();
+    let folder = MusicFolderRepository::new(pool.clone())
+        .create(&NewMusicFolder::new("Music", dir.to_string_lossy()))
+        .unwrap();
+
+    let mut conn = pool.get().unwrap();
+    for (id, title) in [(1, "One"), (2, "Two")] {
+        diesel::sql_query(format!(
+            "INSERT INTO songs (id, title, artist_name, music_folder_id, path, parent_path, \
+             duration, content_type, suffix) \
+             VALUES ({id}, '{title}', 'Artist', {}, '{}/{title}.mp3', '', 200, \
+             'audio/mpeg', 'mp3')",
+            folder.id,
+            dir.to_string_lossy()
+        ))
+        .execute(&mut conn)
+        .unwrap();
+    }
+
+    (
+        std::sync::Arc::new(DatabaseAuthState::new(pool.clone())),
+        pool,
+        user.id,
+        [1, 2],
+    )
+}
@@ -2,11 +2,13 @@
 
 use axum::extract::RawQuery;
 use axum::response::IntoResponse;
+use chrono::TimeDelta;
 use serde::Deserialize;
 
 use crate::api::auth::SubsonicAuth;
 use crate::api::error::ApiError;
 use crate::api::response::{error_response, ok_empty, ok_playlist, ok_playlists};
+use crate::db::Playlist;
 use crate::models::music::{
     ChildResponse, PlaylistResponse, PlaylistWithSongsResponse, PlaylistsResponse,
 };
@@ -30,6 +32,33 @@ fn parse_repeated_param(query: &str, param_name: &str) -> Vec<String> {
     values
 }
 
+/// The OpenSubsonic `readonly` and `validUntil` attributes of a playlist,
+/// set for smart playlists whose songs are evaluated from rules.
+fn smart_playlist_attributes(
+    playlist: &Playlist,
+    ttl: TimeDelta,
+) -> (Option<bool>, Option<String>) {
+    if !playlist.smart {
+        return (None, None);
+    }
+    let valid_until = playlist.evaluated_at.map(|evaluated| {
+        (evaluated + ttl)
+            .format("%Y-%m-%dT%H:%M:%S%.3fZ")
+            .to_string()
+    });
+    (Some(true), valid_until)
+}
+
+/// Reject edits to smart playlists, whose songs come from their rules.
+fn check_editable(auth: &SubsonicAuth, playlist_id: i32) -> Result<(), ApiError> {
+    match auth.state.get_playlist(playlist_id) {
+        Some(playlist) if playlist.smart => {
+            Err(ApiError::Generic("Smart playlists are read-only".into()))
+        }
+        _ => Ok(()),
+    }
+}
+
 /// Query parameters for getPlaylists.
 #[derive(Debug, Clone, Default, Deserialize)]
 #[serde(default)]
@@ -41,7 +70,8 @@ pub struct GetPlaylistsParams {
 
 /// GET/POST /rest/getPlaylists[.view]
 ///
-/// Returns all playlists a user is allowed to play.
+/// Returns all playlists a user is allowed to play. Smart playlists whose
+/// songs are older than the cache TTL are evaluated again first.
 pub async fn get_playlists(
     axum::extract::Query(_params): axum::extract::Query<GetPlaylistsParams>,
     auth: SubsonicAuth,
@@ -55,11 +85,13 @@ pub async fn get_playlists(
     // Batch fetch cover art for all playlists
     let playlist_ids: Vec<i32> = playlists.iter().map(|p| p.id).collect();
     let cover_arts = auth.state.get_playlist_cover_arts_batch(&playlist_ids);
+    let ttl = auth.state.get_smart_playlist_ttl();
 
     let playlist_responses: Vec<PlaylistResponse> = playlists
         .iter()
         .map(|p| {
             let cover_art = cover_arts.get(&p.id).cloned();
+            let (readonly, valid_until) = smart_playlist_attributes(p, ttl);
             PlaylistResponse {
                 id: p.id.to_string(),
                 name: p.name.clone(),
@@ -71,6 +103,8 @@ pub async fn get_playlists(
                 created: p.created_at.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
                 changed: p.updated_at.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
                 cover_art,
+                readonly,
+                valid_until,
             }
         })
         .collect();
@@ -128,17 +162,21 @@ pub async fn get_playlist(
     let starred_map = auth
         .state
         .get_starred_at_for_songs_batch(user_id, &song_ids);
+    let song_play_stats = auth.state.get_song_play_stats_batch(user_id, &song_ids);
 
     let song_responses: Vec<ChildResponse> = songs
         .iter()
         .map(|s| {
             let starred_at = starred_map.get(&s.id);
             ChildResponse::from_song_with_starred(s, starred_at)
+                .with_play_stats(song_play_stats.get(&s.id))
         })
         .collect();
 
     // Derive cover art from first song
     let cover_art = songs.first().and_then(|s| s.cover_art.clone());
+    let (readonly, valid_until) =
+        smart_playlist_attributes(&playlist, auth.state.get_smart_playlist_ttl());
 
     let response = PlaylistWithSongsResponse {
         id: playlist.id.to_string(),
@@ -157,6 +195,8 @@ pub async fn get_playlist(
             .format("%Y-%m-%dT%H:%M:%S%.3fZ")
             .to_string(),
         cover_art,
+        readonly,
+        valid_until,
         entries: song_responses,
     };
 
@@ -214,6 +254,9 @@ pub async fn create_playlist(
         if !auth.state.is_playlist_owner(user_id, playlist_id) {
             return error_response(auth.format, &ApiError::NotAuthorized).into_response();
         }
+        if let Err(e) = check_editable(&auth, playlist_id) {
+            return error_response(auth.format, &e).into_response();
+        }
 
         // Update: add songs to existing playlist
         if let Err(e) = auth.state.update_playlist(
@@ -237,12 +280,14 @@ pub async fn create_playlist(
             let starred_map = auth
                 .state
                 .get_starred_at_for_songs_batch(user_id, &song_ids);
+            let song_play_stats = auth.state.get_song_play_stats_batch(user_id, &song_ids);
 
             let song_responses: Vec<ChildResponse> = songs
                 .iter()
                 .map(|s| {
                     let starred_at = starred_map.get(&s.id);
                     ChildResponse::from_song_with_starred(s, starred_at)
+                        .with_play_stats(song_play_stats.get(&s.id))
                 })
                 .collect();
 
@@ -266,6 +311,8 @@ pub async fn create_playlist(
                     .format("%Y-%m-%dT%H:%M:%S%.3fZ")
                     .to_string(),
                 cover_art,
+                readonly: None,
+                valid_until: None,
                 entries: song_responses,
             };
 
@@ -291,12 +338,14 @@ pub async fn create_playlist(
             let starred_map = auth
                 .state
                 .get_starred_at_for_songs_batch(user_id, &song_ids);
+            let song_play_stats = auth.state.get_song_play_stats_batch(user_id, &song_ids);
 
             let song_responses: Vec<ChildResponse> = songs
                 .iter()
                 .map(|s| {
                     let starred_at = starred_map.get(&s.id);
                     ChildResponse::from_song_with_starred(s, starred_at)
+                        .with_play_stats(song_play_stats.get(&s.id))
                 })
                 .collect();
 
@@ -320,6 +369,8 @@ pub async fn create_playlist(
                     .format("%Y-%m-%dT%H:%M:%S%.3fZ")
                     .to_string(),
                 cover_art,
+                readonly: None,
+                valid_until: None,
                 entries: song_responses,
             };
 
@@ -349,7 +400,8 @@ pub struct UpdatePlaylistParams {
 
 /// GET/POST /rest/updatePlaylist[.view]
 ///
-/// Updates a playlist. Only the owner can update a playlist.
+/// Updates a playlist. Only the owner can update a playlist, and smart
+/// playlists can't be updated.
 ///
 /// Parameters:
 /// - `playlistId`: The playlist ID (required)
@@ -385,6 +437,9 @@ pub async fn update_playlist(
     if !auth.state.is_playlist_owner(user_id, playlist_id) {
         return error_response(auth.format, &ApiError::NotAuthorized).into_response();
     }
+    if let Err(e) = check_editable(&auth, playlist_id) {
+        return error_response(auth.format, &e).into_response();
+    }
 
     // Parse song IDs to add and indices to remove
     let songs_to_add: Vec<i32> = parse_repeated_param(&query, "songIdToAdd")
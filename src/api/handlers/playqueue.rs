@@ -40,6 +40,7 @@ pub async fn get_play_queue(auth: SubsonicAuth) -> impl IntoResponse {
             let starred_map = auth
                 .state
                 .get_starred_at_for_songs_batch(user_id, &song_ids);
+            let song_play_stats = auth.state.get_song_play_stats_batch(user_id, &song_ids);
 
             let song_responses: Vec<ChildResponse> = play_queue
                 .songs
@@ -47,6 +48,7 @@ pub async fn get_play_queue(auth: SubsonicAuth) -> impl IntoResponse {
                 .map(|s| {
                     let starred_at = starred_map.get(&s.id);
                     ChildResponse::from_song_with_starred(s, starred_at)
+                        .with_play_stats(song_play_stats.get(&s.id))
                 })
                 .collect();
 
@@ -143,6 +145,7 @@ pub async fn get_play_queue_by_index(auth: SubsonicAuth) -> impl IntoResponse {
             let starred_map = auth
                 .state
                 .get_starred_at_for_songs_batch(user_id, &song_ids);
+            let song_play_stats = auth.state.get_song_play_stats_batch(user_id, &song_ids);
 
             let song_responses: Vec<ChildResponse> = play_queue
                 .songs
@@ -150,6 +153,7 @@ pub async fn get_play_queue_by_index(auth: SubsonicAuth) -> impl IntoResponse {
                 .map(|s| {
                     let starred_at = starred_map.get(&s.id);
                     ChildResponse::from_song_with_starred(s, starred_at)
+                        .with_play_stats(song_play_stats.get(&s.id))
                 })
                 .collect();
 
@@ -0,0 +1,274 @@
+//! Podcast API handlers (getPodcasts, getNewestPodcasts, refreshPodcasts,
+//! createPodcastChannel, deletePodcastChannel, downloadPodcastEpisode,
+//! deletePodcastEpisode)
+
+use std::collections::HashMap;
+
+use axum::response::IntoResponse;
+use serde::Deserialize;
+
+use crate::agents::radio::is_http_url;
+use crate::api::auth::SubsonicAuth;
+use crate::api::error::ApiError;
+use crate::api::response::{error_response, ok_empty, ok_newest_podcasts, ok_podcasts};
+use crate::models::podcast::{
+    NewestPodcastsResponse, PodcastChannelResponse, PodcastEpisodeResponse, PodcastsResponse,
+};
+
+/// Query parameters for getPodcasts.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct GetPodcastsParams {
+    /// Whether to include the channels' episodes (default true).
+    #[serde(rename = "includeEpisodes")]
+    pub include_episodes: Option<bool>,
+    /// Only return the channel with this ID.
+    pub id: Option<String>,
+}
+
+/// Query parameters for getNewestPodcasts.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct GetNewestPodcastsParams {
+    /// Maximum number of episodes to return (default 20).
+    pub count: Option<i64>,
+}
+
+/// Query parameters for createPodcastChannel.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct CreatePodcastChannelParams {
+    /// The URL of the podcast feed.
+    pub url: Option<String>,
+}
+
+/// Query parameters for endpoints taking a channel or episode ID.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct PodcastIdParams {
+    /// The ID of the channel or episode.
+    pub id: Option<String>,
+}
+
+/// Parse the required `id` parameter.
+fn required_id(params: &PodcastIdParams) -> Result<i32, ApiError> {
+    params
+        .id
+        .as_ref()
+        .and_then(|id| id.parse::<i32>().ok())
+        .ok_or_else(|| ApiError::MissingParameter("id".into()))
+}
+
+/// GET/POST /rest/getPodcasts[.view]
+///
+/// Returns the podcast channels, optionally with their episodes.
+///
+/// Parameters:
+/// - `includeEpisodes`: Whether to include episodes (default true)
+/// - `id`: Only return the channel with this ID
+pub async fn get_podcasts(
+    axum::extract::Query(params): axum::extract::Query<GetPodcastsParams>,
+    auth: SubsonicAuth,
+) -> impl IntoResponse {
+    let channels = match params.id.as_deref() {
+        Some(id) => match id
+            .parse::<i32>()
+            .ok()
+            .and_then(|id| auth.state.get_podcast_channel(id))
+        {
+            Some(channel) => vec![channel],
+            None => {
+                return error_response(auth.format, &ApiError::NotFound("Podcast channel".into()));
+            }
+        },
+        None => auth.state.get_podcast_channels(),
+    };
+    let include_episodes = params.include_episodes.unwrap_or(true);
+
+    let channels = channels
+        .iter()
+        .map(|channel| {
+            let episodes = if include_episodes {
+                auth.state.get_podcast_episodes(channel.id)
+            } else {
+                Vec::new()
+            };
+            PodcastChannelResponse::new(channel, &episodes)
+        })
+        .collect();
+
+    ok_podcasts(auth.format, PodcastsResponse { channels })
+}
+
+/// GET/POST /rest/getNewestPodcasts[.view]
+///
+/// Returns the most recently published episodes of all channels.
+///
+/// Parameters:
+/// - `count`: Maximum number of episodes to return (default 20)
+pub async fn get_newest_podcasts(
+    axum::extract::Query(params): axum::extract::Query<GetNewestPodcastsParams>,
+    auth: SubsonicAuth,
+) -> impl IntoResponse {
+    let count = params.count.unwrap_or(20).clamp(0, 500);
+
+    let titles: HashMap<i32, Option<String>> = auth
+        .state
+        .get_podcast_channels()
+        .into_iter()
+        .map(|channel| (channel.id, channel.title))
+        .collect();
+    let episodes = auth
+        .state
+        .get_newest_podcast_episodes(count)
+        .iter()
+        .map(|episode| {
+            let title = titles.get(&episode.channel_id).and_then(Option::as_deref);
+            PodcastEpisodeResponse::new(episode, title)
+        })
+        .collect();
+
+    ok_newest_podcasts(auth.format, NewestPodcastsResponse { episodes })
+}
+
+/// GET/POST /rest/refreshPodcasts[.view]
+///
+/// Checks all podcast feeds for new episodes in the background. Requires
+/// the podcast role.
+pub async fn refresh_podcasts(auth: SubsonicAuth) -> impl IntoResponse {
+    if !auth.user.roles.podcast_role {
+        return error_response(auth.format, &ApiError::NotAuthorized);
+    }
+
+    auth.state.refresh_podcasts();
+    ok_empty(auth.format)
+}
+
+/// GET/POST /rest/createPodcastChannel[.view]
+///
+/// Subscribes to a podcast. The feed is fetched in the background; until
+/// then the channel has the status "new". Requires the podcast role.
+///
+/// Parameters:
+/// - `url` (required): The URL of the podcast feed
+pub async fn create_podcast_channel(
+    axum::extract::Query(params): axum::extract::Query<CreatePodcastChannelParams>,
+    auth: SubsonicAuth,
+) -> impl IntoResponse {
+    if !auth.user.roles.podcast_role {
+        return error_response(auth.format, &ApiError::NotAuthorized);
+    }
+
+    let Some(url) = params
+        .url
+        .as_deref()
+        .map(str::trim)
+        .filter(|u| !u.is_empty())
+    else {
+        return error_response(auth.format, &ApiError::MissingParameter("url".into()));
+    };
+    if !is_http_url(url) {
+        return error_response(
+            auth.format,
+            &ApiError::Generic(format!("Invalid podcast URL: {}", url)),
+        );
+    }
+    if auth
+        .state
+        .get_podcast_channels()
+        .iter()
+        .any(|c| c.url == url)
+    {
+        return error_response(
+            auth.format,
+            &ApiError::Generic(format!("Already subscribed to {}", url)),
+        );
+    }
+
+    match auth.state.create_podcast_channel(url) {
+        Ok(_) => ok_empty(auth.format),
+        Err(e) => error_response(auth.format, &ApiError::Generic(e)),
+    }
+}
+
+/// GET/POST /rest/deletePodcastChannel[.view]
+///
+/// Unsubscribes from a podcast, deleting its downloaded episodes. Requires
+/// the podcast role.
+///
+/// Parameters:
+/// - `id` (required): The ID of the channel
+pub async fn delete_podcast_channel(
+    axum::extract::Query(params): axum::extract::Query<PodcastIdParams>,
+    auth: SubsonicAuth,
+) -> impl IntoResponse {
+    if !auth.user.roles.podcast_role {
+        return error_response(auth.format, &ApiError::NotAuthorized);
+    }
+
+    let id = match required_id(&params) {
+        Ok(id) => id,
+        Err(e) => return error_response(auth.format, &e),
+    };
+
+    match auth.state.delete_podcast_channel(id) {
+        Ok(true) => ok_empty(auth.format),
+        Ok(false) => error_response(auth.format, &ApiError::NotFound("Podcast channel".into())),
+        Err(e) => error_response(auth.format, &ApiError::Generic(e)),
+    }
+}
+
+/// GET/POST /rest/downloadPodcastEpisode[.view]
+///
+/// Queues a podcast episode for download to the podcast folder. Once
+/// downloaded, the episode can be played with `stream` using its stream ID.
+/// Requires the podcast role.
+///
+/// Parameters:
+/// - `id` (required): The ID of the episode
+pub async fn download_podcast_episode(
+    axum::extract::Query(params): axum::extract::Query<PodcastIdParams>,
+    auth: SubsonicAuth,
+) -> impl IntoResponse {
+    if !auth.user.roles.podcast_role {
+        return error_response(auth.format, &ApiError::NotAuthorized);
+    }
+
+    let id = match required_id(&params) {
+        Ok(id) => id,
+        Err(e) => return error_response(auth.format, &e),
+    };
+
+    match auth.state.download_podcast_episode(id) {
+        Ok(true) => ok_empty(auth.format),
+        Ok(false) => error_response(auth.format, &ApiError::NotFound("Podcast episode".into())),
+        Err(e) => error_response(auth.format, &ApiError::Generic(e)),
+    }
+}
+
+/// GET/POST /rest/deletePodcastEpisode[.view]
+///
+/// Deletes a podcast episode's download. The episode stays listed with the
+/// status "deleted". Requires the podcast role.
+///
+/// Parameters:
+/// - `id` (required): The ID of the episode
+pub async fn delete_podcast_episode(
+    axum::extract::Query(params): axum::extract::Query<PodcastIdParams>,
+    auth: SubsonicAuth,
+) -> impl IntoResponse {
+    if !auth.user.roles.podcast_role {
+        return error_response(auth.format, &ApiError::NotAuthorized);
+    }
+
+    let id = match required_id(&params) {
+        Ok(id) => id,
+        Err(e) => return error_response(auth.format, &e),
+    };
+
+    match auth.state.delete_podcast_episode(id) {
+        Ok(true) => ok_empty(auth.format),
+        Ok(false) => error_response(auth.format, &ApiError::NotFound("Podcast episode".into())),
+        Err(e) => error_response(auth.format, &ApiError::Generic(e)),
+    }
+}
@@ -0,0 +1,192 @@
+//! Internet radio API handlers (getInternetRadioStations, createInternetRadioStation,
+//! updateInternetRadioStation, deleteInternetRadioStation)
+
+use axum::response::IntoResponse;
+use serde::Deserialize;
+
+use crate::agents::radio::is_http_url;
+use crate::api::auth::SubsonicAuth;
+use crate::api::error::ApiError;
+use crate::api::response::{error_response, ok_empty, ok_internet_radio_stations};
+use crate::models::radio::{InternetRadioStationResponse, InternetRadioStationsResponse};
+
+/// Query parameters for createInternetRadioStation and updateInternetRadioStation.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct InternetRadioStationParams {
+    /// The ID of the station (update and delete only).
+    pub id: Option<String>,
+    /// The stream URL of the station.
+    #[serde(rename = "streamUrl")]
+    pub stream_url: Option<String>,
+    /// The name of the station.
+    pub name: Option<String>,
+    /// The home page URL of the station.
+    #[serde(rename = "homepageUrl")]
+    pub homepage_url: Option<String>,
+}
+
+/// Station details validated from the request parameters.
+struct StationDetails {
+    name: String,
+    stream_url: String,
+    homepage_url: Option<String>,
+}
+
+/// Validate the name and URLs of a station.
+fn station_details(params: &InternetRadioStationParams) -> Result<StationDetails, ApiError> {
+    let stream_url = params
+        .stream_url
+        .as_deref()
+        .map(str::trim)
+        .filter(|u| !u.is_empty())
+        .ok_or_else(|| ApiError::MissingParameter("streamUrl".into()))?;
+    let name = params
+        .name
+        .as_deref()
+        .map(str::trim)
+        .filter(|n| !n.is_empty())
+        .ok_or_else(|| ApiError::MissingParameter("name".into()))?;
+    let homepage_url = params
+        .homepage_url
+        .as_deref()
+        .map(str::trim)
+        .filter(|u| !u.is_empty());
+
+    if !is_http_url(stream_url) {
+        return Err(ApiError::Generic(format!(
+            "Invalid stream URL: {}",
+            stream_url
+        )));
+    }
+    if let Some(homepage_url) = homepage_url
+        && !is_http_url(homepage_url)
+    {
+        return Err(ApiError::Generic(format!(
+            "Invalid home page URL: {}",
+            homepage_url
+        )));
+    }
+
+    Ok(StationDetails {
+        name: name.to_string(),
+        stream_url: stream_url.to_string(),
+        homepage_url: homepage_url.map(str::to_string),
+    })
+}
+
+/// GET/POST /rest/getInternetRadioStations[.view]
+///
+/// Returns all internet radio stations.
+pub async fn get_internet_radio_stations(auth: SubsonicAuth) -> impl IntoResponse {
+    let stations = auth
+        .state
+        .get_internet_radio_stations()
+        .iter()
+        .map(InternetRadioStationResponse::from)
+        .collect();
+
+    ok_internet_radio_stations(auth.format, InternetRadioStationsResponse { stations })
+}
+
+/// GET/POST /rest/createInternetRadioStation[.view]
+///
+/// Adds an internet radio station. Only admins can add stations. Links to
+/// `.pls` and `.m3u` playlists are replaced by the stream they list.
+///
+/// Parameters:
+/// - `streamUrl` (required): The stream URL of the station
+/// - `name` (required): The name of the station
+/// - `homepageUrl`: The home page URL of the station
+pub async fn create_internet_radio_station(
+    axum::extract::Query(params): axum::extract::Query<InternetRadioStationParams>,
+    auth: SubsonicAuth,
+) -> impl IntoResponse {
+    if !auth.user.is_admin() {
+        return error_response(auth.format, &ApiError::NotAuthorized);
+    }
+
+    let station = match station_details(&params) {
+        Ok(station) => station,
+        Err(e) => return error_response(auth.format, &e),
+    };
+    let stream_url = auth.state.resolve_stream_url(&station.stream_url).await;
+
+    match auth.state.create_internet_radio_station(
+        &station.name,
+        &stream_url,
+        station.homepage_url.as_deref(),
+    ) {
+        Ok(_) => ok_empty(auth.format),
+        Err(e) => error_response(auth.format, &ApiError::Generic(e)),
+    }
+}
+
+/// GET/POST /rest/updateInternetRadioStation[.view]
+///
+/// Updates an internet radio station. Only admins can update stations.
+///
+/// Parameters:
+/// - `id` (required): The ID of the station
+/// - `streamUrl` (required): The stream URL of the station
+/// - `name` (required): The name of the station
+/// - `homepageUrl`: The home page URL of the station (removed if omitted)
+pub async fn update_internet_radio_station(
+    axum::extract::Query(params): axum::extract::Query<InternetRadioStationParams>,
+    auth: SubsonicAuth,
+) -> impl IntoResponse {
+    if !auth.user.is_admin() {
+        return error_response(auth.format, &ApiError::NotAuthorized);
+    }
+
+    let Some(id) = params.id.as_ref().and_then(|id| id.parse::<i32>().ok()) else {
+        return error_response(auth.format, &ApiError::MissingParameter("id".into()));
+    };
+    let station = match station_details(&params) {
+        Ok(station) => station,
+        Err(e) => return error_response(auth.format, &e),
+    };
+    let stream_url = auth.state.resolve_stream_url(&station.stream_url).await;
+
+    match auth.state.update_internet_radio_station(
+        id,
+        &station.name,
+        &stream_url,
+        station.homepage_url.as_deref(),
+    ) {
+        Ok(true) => ok_empty(auth.format),
+        Ok(false) => error_response(
+            auth.format,
+            &ApiError::NotFound("Internet radio station".into()),
+        ),
+        Err(e) => error_response(auth.format, &ApiError::Generic(e)),
+    }
+}
+
+/// GET/POST /rest/deleteInternetRadioStation[.view]
+///
+/// Deletes an internet radio station. Only admins can delete stations.
+///
+/// Parameters:
+/// - `id` (required): The ID of the station
+pub async fn delete_internet_radio_station(
+    axum::extract::Query(params): axum::extract::Query<InternetRadioStationParams>,
+    auth: SubsonicAuth,
+) -> impl IntoResponse {
+    if !auth.user.is_admin() {
+        return error_response(auth.format, &ApiError::NotAuthorized);
+    }
+
+    let Some(id) = params.id.as_ref().and_then(|id| id.parse::<i32>().ok()) else {
+        return error_response(auth.format, &ApiError::MissingParameter("id".into()));
+    };
+
+    match auth.state.delete_internet_radio_station(id) {
+        Ok(true) => ok_empty(auth.format),
+        Ok(false) => error_response(
+            auth.format,
+            &ApiError::NotFound("Internet radio station".into()),
+        ),
+        Err(e) => error_response(auth.format, &ApiError::Generic(e)),
+    }
+}
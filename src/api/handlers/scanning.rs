@@ -1,10 +1,15 @@
-//! Library scanning API handlers (startScan, getScanStatus)
+//! Library scanning API handlers (startScan, getScanStatus, cancelScan, getScanHistory, getScanErrors)
 
 use axum::response::IntoResponse;
+use serde::Deserialize;
 
 use crate::api::auth::SubsonicAuth;
-use crate::api::response::{ScanStatusData, ok_scan_status};
-use crate::scanner::Scanner;
+use crate::api::error::ApiError;
+use crate::api::response::{
+    ScanStatusData, error_response, ok_scan_errors, ok_scan_history, ok_scan_status,
+};
+use crate::models::scan::{ScanErrorsResponse, ScanHistoryResponse};
+use crate::scanner::{ScanMode, ScanTrigger, Scanner};
 
 /// Build a ScanStatusData from the current scan state.
 fn build_scan_status_data(auth: &SubsonicAuth) -> ScanStatusData {
@@ -15,33 +20,85 @@ fn build_scan_status_data(auth: &SubsonicAuth) -> ScanStatusData {
         total: scan_state.get_total(),
         phase: scan_state.get_phase().as_str().to_string(),
         folder: scan_state.get_current_folder(),
+        mode: scan_state.get_mode().map(|m| m.as_str().to_string()),
     }
 }
 
+/// Query parameters for startScan.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct StartScanParams {
+    /// Re-scan all files regardless of modification time. Default true;
+    /// `false` only reads files changed since the last scan.
+    #[serde(rename = "fullScan")]
+    pub full_scan: Option<bool>,
+    /// Only scan this music folder. Default all folders.
+    #[serde(rename = "musicFolderId")]
+    pub music_folder_id: Option<i32>,
+}
+
 /// GET/POST /rest/startScan[.view]
 ///
 /// Initiates a media library scan. If a scan is already in progress,
 /// returns the current status without starting a new scan.
+/// Only users with admin role are allowed to call this method.
+///
+/// Parameters:
+/// - `fullScan`: re-scan every file (default true); `false` only scans changed files
+/// - `musicFolderId`: only scan the given music folder
 ///
 /// Returns: scanStatus with scanning=true/false and count of items scanned.
-pub async fn start_scan(auth: SubsonicAuth) -> impl IntoResponse {
+pub async fn start_scan(
+    axum::extract::Query(params): axum::extract::Query<StartScanParams>,
+    auth: SubsonicAuth,
+) -> impl IntoResponse {
+    if !auth.user.is_admin() {
+        return error_response(auth.format, &ApiError::NotAuthorized);
+    }
+
+    if let Some(folder_id) = params.music_folder_id
+        && !auth
+            .state
+            .get_music_folders()
+            .iter()
+            .any(|f| f.id == folder_id)
+    {
+        return error_response(
+            auth.format,
+            &ApiError::NotFound("Music folder not found".into()),
+        );
+    }
+
+    let mode = if params.full_scan.unwrap_or(true) {
+        ScanMode::Full
+    } else {
+        ScanMode::Incremental
+    };
     let scan_state = auth.state.get_scan_state();
 
     // Try to start a new scan - returns false if one is already running
     if scan_state.try_start() {
-        // Reset the counter for this new scan
-        scan_state.reset_count();
+        // Reset progress for this new scan
+        scan_state.reset();
 
         let pool = auth.state.get_db_pool();
+        let playlist_import = auth.state.get_playlist_import_config();
         let scan_state_for_scanner = scan_state.clone();
         let scan_state_for_finish = scan_state.clone();
+        let folder_id = params.music_folder_id;
 
         // Spawn background task to run the scan
         tokio::spawn(async move {
             // Run the scan in a blocking task since it's CPU-intensive
             let result = tokio::task::spawn_blocking(move || {
-                let scanner = Scanner::new(pool);
-                scanner.scan_all_with_state(Some(scan_state_for_scanner))
+                let scanner = Scanner::new(pool)
+                    .with_playlist_import(playlist_import)
+                    .with_trigger(ScanTrigger::Api);
+                let state = Some(scan_state_for_scanner);
+                match folder_id {
+                    Some(id) => scanner.scan_folder_by_id_with_options(id, state, mode),
+                    None => scanner.scan_all_with_options(state, mode),
+                }
             })
             .await;
 
@@ -49,6 +106,14 @@ pub async fn start_scan(auth: SubsonicAuth) -> impl IntoResponse {
             scan_state_for_finish.finish();
 
             match result {
+                Ok(Ok(stats)) if stats.cancelled => {
+                    tracing::info!(
+                        "Scan cancelled: {} tracks found, {} added, {} failed",
+                        stats.tracks_found,
+                        stats.tracks_added,
+                        stats.tracks_failed
+                    );
+                }
                 Ok(Ok(stats)) => {
                     tracing::info!(
                         "Scan complete: {} tracks found, {} added, {} failed",
@@ -81,3 +146,90 @@ pub async fn get_scan_status(auth: SubsonicAuth) -> impl IntoResponse {
     let data = build_scan_status_data(&auth);
     ok_scan_status(auth.format, data)
 }
+
+/// GET/POST /rest/cancelScan[.view]
+///
+/// Asks the running scan to stop after its current batch. Songs already
+/// imported are kept, and removal of deleted files is left to the next scan.
+/// Only users with admin role are allowed to call this method.
+///
+/// Returns: scanStatus, with phase=cancelled if a scan was running.
+pub async fn cancel_scan(auth: SubsonicAuth) -> impl IntoResponse {
+    if !auth.user.is_admin() {
+        return error_response(auth.format, &ApiError::NotAuthorized);
+    }
+
+    if auth.state.get_scan_state().cancel() {
+        tracing::info!("Scan cancellation requested by {}", auth.user.username);
+    }
+
+    let data = build_scan_status_data(&auth);
+    ok_scan_status(auth.format, data)
+}
+
+/// Query parameters for getScanHistory.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct GetScanHistoryParams {
+    /// Maximum number of scans to return (default 20, max 500).
+    pub count: Option<i64>,
+}
+
+/// GET/POST /rest/getScanHistory[.view]
+///
+/// Returns the most recent library scans, newest first.
+/// Only users with admin role are allowed to call this method.
+pub async fn get_scan_history(
+    axum::extract::Query(params): axum::extract::Query<GetScanHistoryParams>,
+    auth: SubsonicAuth,
+) -> impl IntoResponse {
+    if !auth.user.is_admin() {
+        return error_response(auth.format, &ApiError::NotAuthorized);
+    }
+
+    let count = params.count.unwrap_or(20).clamp(1, 500);
+    let runs = auth.state.get_scan_history(count);
+
+    let response = ScanHistoryResponse {
+        runs: runs.iter().map(Into::into).collect(),
+    };
+
+    ok_scan_history(auth.format, response)
+}
+
+/// Query parameters for getScanErrors.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct GetScanErrorsParams {
+    /// The scan run ID. Defaults to the most recent scan.
+    pub id: Option<i32>,
+}
+
+/// GET/POST /rest/getScanErrors[.view]
+///
+/// Returns the files that failed during a scan.
+/// Only users with admin role are allowed to call this method.
+pub async fn get_scan_errors(
+    axum::extract::Query(params): axum::extract::Query<GetScanErrorsParams>,
+    auth: SubsonicAuth,
+) -> impl IntoResponse {
+    if !auth.user.is_admin() {
+        return error_response(auth.format, &ApiError::NotAuthorized);
+    }
+
+    let run = match params.id {
+        Some(id) => auth.state.get_scan_run(id),
+        None => auth.state.get_scan_history(1).into_iter().next(),
+    };
+    let Some(run) = run else {
+        return error_response(auth.format, &ApiError::NotFound("Scan not found".into()));
+    };
+
+    let errors = auth.state.get_scan_errors(run.id);
+    let response = ScanErrorsResponse {
+        scan_run_id: run.id,
+        errors: errors.iter().map(Into::into).collect(),
+    };
+
+    ok_scan_errors(auth.format, response)
+}
@@ -0,0 +1,111 @@
+//! Scrobble forwarding API handlers (getScrobbleAccounts, linkScrobbleAccount, unlinkScrobbleAccount)
+
+use axum::response::IntoResponse;
+use serde::Deserialize;
+
+use crate::api::auth::SubsonicAuth;
+use crate::api::error::ApiError;
+use crate::api::response::{error_response, ok_empty, ok_scrobble_accounts};
+use crate::models::scrobbling::{
+    ScrobbleAccountResponse, ScrobbleAccountsResponse, ScrobbleService,
+};
+
+/// Query parameters for linkScrobbleAccount and unlinkScrobbleAccount.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ScrobbleAccountParams {
+    /// The service: "listenbrainz" or "lastfm".
+    pub service: Option<String>,
+    /// ListenBrainz user token, or Last.fm authentication token.
+    pub token: Option<String>,
+}
+
+/// Get the service named in the request parameters.
+fn parse_service(params: &ScrobbleAccountParams) -> Result<ScrobbleService, ApiError> {
+    let name = params
+        .service
+        .as_deref()
+        .ok_or_else(|| ApiError::MissingParameter("service".into()))?;
+    ScrobbleService::parse(name)
+        .ok_or_else(|| ApiError::Generic(format!("Unknown scrobbling service: {}", name)))
+}
+
+/// GET/POST /rest/getScrobbleAccounts[.view]
+///
+/// Returns the scrobbling accounts the current user has linked, with the
+/// number of plays still waiting to be submitted to each.
+pub async fn get_scrobble_accounts(auth: SubsonicAuth) -> impl IntoResponse {
+    let accounts = auth
+        .state
+        .get_scrobble_accounts(auth.user.id)
+        .iter()
+        .map(|(account, pending)| ScrobbleAccountResponse::from_account(account, *pending))
+        .collect();
+
+    ok_scrobble_accounts(auth.format, ScrobbleAccountsResponse { accounts })
+}
+
+/// GET/POST /rest/linkScrobbleAccount[.view]
+///
+/// Links the current user's account on a scrobbling service, so their plays
+/// are forwarded to it. The credentials are checked with the service first.
+///
+/// Parameters:
+/// - `service` (required): `listenbrainz` or `lastfm`
+/// - `token` (required): the ListenBrainz user token, or for Last.fm a token
+///   from the Last.fm web authentication flow, exchanged for a session key
+pub async fn link_scrobble_account(
+    axum::extract::Query(params): axum::extract::Query<ScrobbleAccountParams>,
+    auth: SubsonicAuth,
+) -> impl IntoResponse {
+    let service = match parse_service(&params) {
+        Ok(service) => service,
+        Err(e) => return error_response(auth.format, &e),
+    };
+    let Some(token) = params.token.as_deref().filter(|t| !t.is_empty()) else {
+        return error_response(auth.format, &ApiError::MissingParameter("token".into()));
+    };
+
+    match auth
+        .state
+        .link_scrobble_account(auth.user.id, service, token)
+        .await
+    {
+        Ok(_) => ok_empty(auth.format),
+        Err(e) => {
+            tracing::warn!(
+                "Failed to link {} account of {}: {}",
+                service,
+                auth.user.username,
+                e
+            );
+            error_response(auth.format, &ApiError::Generic(e))
+        }
+    }
+}
+
+/// GET/POST /rest/unlinkScrobbleAccount[.view]
+///
+/// Unlinks the current user's account on a scrobbling service. Plays still
+/// waiting to be submitted to it are dropped.
+///
+/// Parameters:
+/// - `service` (required): `listenbrainz` or `lastfm`
+pub async fn unlink_scrobble_account(
+    axum::extract::Query(params): axum::extract::Query<ScrobbleAccountParams>,
+    auth: SubsonicAuth,
+) -> impl IntoResponse {
+    let service = match parse_service(&params) {
+        Ok(service) => service,
+        Err(e) => return error_response(auth.format, &e),
+    };
+
+    match auth.state.unlink_scrobble_account(auth.user.id, service) {
+        Ok(true) => ok_empty(auth.format),
+        Ok(false) => error_response(
+            auth.format,
+            &ApiError::NotFound(format!("{} account", service)),
+        ),
+        Err(e) => error_response(auth.format, &ApiError::Generic(e)),
+    }
+}
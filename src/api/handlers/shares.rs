@@ -0,0 +1,516 @@
+//! Sharing API handlers (getShares, createShare, updateShare, deleteShare)
+//! and the public pages share links point to.
+
+use std::sync::Arc;
+
+use axum::extract::{Path, RawQuery, State};
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::response::{Html, IntoResponse, Response};
+use chrono::NaiveDateTime;
+
+use super::media::serve_song;
+use crate::api::auth::{AuthState, SubsonicAuth};
+use crate::api::error::ApiError;
+use crate::api::response::{error_response, ok_empty, ok_shares};
+use crate::db::Share;
+use crate::models::music::{ChildResponse, ShareResponse, SharesResponse};
+
+/// Parse repeated query parameters from a query string.
+/// Handles both single values and repeated parameters like `?id=1&id=2`.
+fn parse_repeated_param(query: &str, param_name: &str) -> Vec<String> {
+    let mut values = Vec::new();
+    for part in query.split('&') {
+        if let Some((key, value)) = part.split_once('=')
+            && key == param_name
+        {
+            // URL decode the value
+            values.push(
+                urlencoding::decode(value)
+                    .map(|d| d.into_owned())
+                    .unwrap_or_else(|_| value.to_string()),
+            );
+        }
+    }
+    values
+}
+
+fn format_timestamp(dt: &NaiveDateTime) -> String {
+    dt.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string()
+}
+
+/// Build the public URL of a share from the host the request was sent to.
+fn share_url(headers: &HeaderMap, token: &str) -> String {
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+    let host = header("x-forwarded-host")
+        .or_else(|| header("host"))
+        .unwrap_or("localhost");
+    let scheme = header("x-forwarded-proto").unwrap_or("http");
+    format!("{}://{}/share/{}", scheme, host, token)
+}
+
+/// Parse an expiry time given in milliseconds since the epoch.
+/// Zero or a negative value means the share never expires.
+fn parse_expires(value: &str) -> Result<Option<NaiveDateTime>, ApiError> {
+    let millis: i64 = value
+        .parse()
+        .map_err(|_| ApiError::Generic(format!("Invalid expiry time: {}", value)))?;
+    if millis <= 0 {
+        return Ok(None);
+    }
+    chrono::DateTime::from_timestamp_millis(millis)
+        .map(|dt| Some(dt.naive_utc()))
+        .ok_or_else(|| ApiError::Generic(format!("Invalid expiry time: {}", value)))
+}
+
+/// Build the API responses for shares, with the owner's starred status and
+/// play stats on their songs.
+fn shares_response(auth: &SubsonicAuth, headers: &HeaderMap, shares: &[Share]) -> SharesResponse {
+    let song_ids: Vec<i32> = shares
+        .iter()
+        .flat_map(|share| share.songs.iter().map(|s| s.id))
+        .collect();
+    let starred_map = auth
+        .state
+        .get_starred_at_for_songs_batch(auth.user.id, &song_ids);
+    let song_play_stats = auth
+        .state
+        .get_song_play_stats_batch(auth.user.id, &song_ids);
+
+    let shares = shares
+        .iter()
+        .map(|share| ShareResponse {
+            id: share.id.to_string(),
+            url: share_url(headers, &share.token),
+            description: share.description.clone(),
+            username: share.username.clone(),
+            created: format_timestamp(&share.created_at),
+            expires: share.expires_at.as_ref().map(format_timestamp),
+            last_visited: share.last_visited_at.as_ref().map(format_timestamp),
+            visit_count: share.visit_count,
+            entries: share
+                .songs
+                .iter()
+                .map(|s| {
+                    ChildResponse::from_song_with_starred(s, starred_map.get(&s.id))
+                        .with_play_stats(song_play_stats.get(&s.id))
+                })
+                .collect(),
+        })
+        .collect();
+
+    SharesResponse { shares }
+}
+
+/// Get a share the current user may manage: their own, or any share for admins.
+fn managed_share(auth: &SubsonicAuth, query: &str) -> Result<Share, ApiError> {
+    let share_id = parse_repeated_param(query, "id")
+        .first()
+        .and_then(|id| id.parse::<i32>().ok())
+        .ok_or_else(|| ApiError::MissingParameter("id".into()))?;
+    let share = auth
+        .state
+        .get_share(share_id)
+        .ok_or_else(|| ApiError::NotFound("Share".into()))?;
+    if share.user_id != auth.user.id && !auth.user.is_admin() {
+        return Err(ApiError::NotAuthorized);
+    }
+    Ok(share)
+}
+
+/// GET/POST /rest/getShares[.view]
+///
+/// Returns the shares created by the current user.
+pub async fn get_shares(headers: HeaderMap, auth: SubsonicAuth) -> impl IntoResponse {
+    let shares = auth.state.get_shares(auth.user.id);
+    ok_shares(auth.format, shares_response(&auth, &headers, &shares))
+}
+
+/// GET/POST /rest/createShare[.view]
+///
+/// Creates a public link to songs, albums and playlists, which anyone can
+/// open without logging in. Requires the share role.
+///
+/// Parameters:
+/// - `id`: ID of a song or album to share (can be repeated); song IDs are
+///   tried first
+/// - `albumId`: ID of an album to share (can be repeated)
+/// - `playlistId`: ID of a playlist to share (can be repeated)
+/// - `description`: User-defined description
+/// - `expires`: Expiry time in milliseconds since the epoch
+pub async fn create_share(
+    headers: HeaderMap,
+    RawQuery(query): RawQuery,
+    auth: SubsonicAuth,
+) -> impl IntoResponse {
+    let query = query.unwrap_or_default();
+
+    if !auth.user.roles.share_role {
+        return error_response(auth.format, &ApiError::NotAuthorized);
+    }
+
+    // Collect the songs of every shared item, in order
+    let mut song_ids: Vec<i32> = Vec::new();
+    for id in parse_repeated_param(&query, "id") {
+        let id = id.parse::<i32>().ok();
+        if let Some(song) = id.and_then(|id| auth.state.get_song(id)) {
+            song_ids.push(song.id);
+        } else if let Some(album) = id.and_then(|id| auth.state.get_album(id)) {
+            song_ids.extend(auth.state.get_songs_by_album(album.id).iter().map(|s| s.id));
+        } else {
+            return error_response(
+                auth.format,
+                &ApiError::NotFound("Song or album not found".into()),
+            );
+        }
+    }
+    for id in parse_repeated_param(&query, "albumId") {
+        match id
+            .parse::<i32>()
+            .ok()
+            .and_then(|id| auth.state.get_album(id))
+        {
+            Some(album) => {
+                song_ids.extend(auth.state.get_songs_by_album(album.id).iter().map(|s| s.id));
+            }
+            None => {
+                return error_response(auth.format, &ApiError::NotFound("Album not found".into()));
+            }
+        }
+    }
+    for id in parse_repeated_param(&query, "playlistId") {
+        let playlist = id
+            .parse::<i32>()
+            .ok()
+            .and_then(|id| auth.state.get_playlist(id))
+            .filter(|p| p.owner == auth.user.username || p.public);
+        match playlist {
+            Some(playlist) => {
+                song_ids.extend(
+                    auth.state
+                        .get_playlist_songs(playlist.id)
+                        .iter()
+                        .map(|s| s.id),
+                );
+            }
+            None => {
+                return error_response(
+                    auth.format,
+                    &ApiError::NotFound("Playlist not found".into()),
+                );
+            }
+        }
+    }
+
+    // Each song is shared once, at its first position
+    let mut seen = std::collections::HashSet::new();
+    song_ids.retain(|id| seen.insert(*id));
+    if song_ids.is_empty() {
+        return error_response(auth.format, &ApiError::MissingParameter("id".into()));
+    }
+
+    let expires_at = match parse_repeated_param(&query, "expires").first() {
+        Some(expires) => match parse_expires(expires) {
+            Ok(expires_at) => expires_at,
+            Err(e) => return error_response(auth.format, &e),
+        },
+        None => None,
+    };
+    let description = parse_repeated_param(&query, "description")
+        .into_iter()
+        .next()
+        .filter(|d| !d.is_empty());
+
+    match auth
+        .state
+        .create_share(auth.user.id, &song_ids, description.as_deref(), expires_at)
+    {
+        Ok(share) => ok_shares(
+            auth.format,
+            shares_response(&auth, &headers, std::slice::from_ref(&share)),
+        ),
+        Err(e) => error_response(auth.format, &ApiError::Generic(e)),
+    }
+}
+
+/// GET/POST /rest/updateShare[.view]
+///
+/// Updates the description and expiry of a share. Only the creator of a
+/// share or an admin can update it.
+///
+/// Parameters:
+/// - `id` (required): ID of the share
+/// - `description`: New description (empty to remove it)
+/// - `expires`: New expiry time in milliseconds since the epoch (0 to never expire)
+pub async fn update_share(RawQuery(query): RawQuery, auth: SubsonicAuth) -> impl IntoResponse {
+    let query = query.unwrap_or_default();
+
+    let share = match managed_share(&auth, &query) {
+        Ok(share) => share,
+        Err(e) => return error_response(auth.format, &e),
+    };
+
+    let description = parse_repeated_param(&query, "description")
+        .into_iter()
+        .next();
+    let expires_at = match parse_repeated_param(&query, "expires").first() {
+        Some(expires) => match parse_expires(expires) {
+            Ok(expires_at) => Some(expires_at),
+            Err(e) => return error_response(auth.format, &e),
+        },
+        None => None,
+    };
+
+    let description = description
+        .as_deref()
+        .map(|d| Some(d).filter(|d| !d.is_empty()));
+    match auth.state.update_share(share.id, description, expires_at) {
+        Ok(true) => ok_empty(auth.format),
+        Ok(false) => error_response(auth.format, &ApiError::NotFound("Share".into())),
+        Err(e) => error_response(auth.format, &ApiError::Generic(e)),
+    }
+}
+
+/// GET/POST /rest/deleteShare[.view]
+///
+/// Deletes a share, disabling its public link. Only the creator of a share
+/// or an admin can delete it.
+///
+/// Parameters:
+/// - `id` (required): ID of the share
+pub async fn delete_share(RawQuery(query): RawQuery, auth: SubsonicAuth) -> impl IntoResponse {
+    let query = query.unwrap_or_default();
+
+    let share = match managed_share(&auth, &query) {
+        Ok(share) => share,
+        Err(e) => return error_response(auth.format, &e),
+    };
+
+    match auth.state.delete_share(share.id) {
+        Ok(true) => ok_empty(auth.format),
+        Ok(false) => error_response(auth.format, &ApiError::NotFound("Share".into())),
+        Err(e) => error_response(auth.format, &ApiError::Generic(e)),
+    }
+}
+
+// ============================================================================
+// Public share pages
+// ============================================================================
+
+/// Get a share by token, unless it has expired.
+fn active_share(state: &dyn AuthState, token: &str) -> Option<Share> {
+    let now = chrono::Utc::now().naive_utc();
+    state
+        .get_share_by_token(token)
+        .filter(|share| !share.is_expired(now))
+}
+
+/// Escape text for use in HTML content and attribute values.
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Render the player page of a share.
+fn render_share_page(share: &Share) -> String {
+    let title = escape_html(share.description.as_deref().unwrap_or("Shared music"));
+
+    let mut tracks = String::new();
+    for song in &share.songs {
+        let details: Vec<&str> = [song.artist_name.as_deref(), song.album_name.as_deref()]
+            .into_iter()
+            .flatten()
+            .filter(|s| !s.is_empty())
+            .collect();
+        tracks.push_str(&format!(
+            "<li><div>{}</div><div class=\"meta\">{}</div>\
+             <audio controls preload=\"none\" src=\"{}/{}\"></audio></li>\n",
+            escape_html(&song.title),
+            escape_html(&details.join(" \u{2013} ")),
+            escape_html(&share.token),
+            song.id,
+        ));
+    }
+
+    format!(
+        "<!DOCTYPE html>\n\
+         <html lang=\"en\">\n\
+         <head>\n\
+         <meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>{title}</title>\n\
+         <style>body{{font-family:sans-serif;max-width:40em;margin:2em auto;padding:0 1em}}\
+         li{{margin:1em 0}}audio{{width:100%}}.meta{{color:#666}}</style>\n\
+         </head>\n\
+         <body>\n\
+         <h1>{title}</h1>\n\
+         <p class=\"meta\">Shared by {username}</p>\n\
+         <ol>\n{tracks}</ol>\n\
+         </body>\n\
+         </html>\n",
+        title = title,
+        username = escape_html(&share.username),
+        tracks = tracks,
+    )
+}
+
+/// GET /share/{token}
+///
+/// Public player page of a share. Needs no login; expired and unknown
+/// shares are not found.
+pub async fn share_page(
+    State(state): State<Arc<dyn AuthState>>,
+    Path(token): Path<String>,
+) -> Response {
+    let Some(share) = active_share(state.as_ref(), &token) else {
+        return (
+            StatusCode::NOT_FOUND,
+            Html("<!DOCTYPE html>\n<title>Not found</title>\n<p>This share does not exist or has expired.</p>\n"),
+        )
+            .into_response();
+    };
+
+    state.record_share_visit(share.id);
+    (
+        [(header::CACHE_CONTROL, "no-store")],
+        Html(render_share_page(&share)),
+    )
+        .into_response()
+}
+
+/// GET /share/{token}/{id}
+///
+/// Streams a song of a share. Only songs in the share can be played, and
+/// only until it expires.
+pub async fn share_stream(
+    State(state): State<Arc<dyn AuthState>>,
+    Path((token, song_id)): Path<(String, i32)>,
+    headers: HeaderMap,
+) -> Response {
+    let song = active_share(state.as_ref(), &token)
+        .and_then(|share| share.songs.into_iter().find(|s| s.id == song_id));
+    let Some(song) = song else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    match serve_song(state.as_ref(), &song, &headers).await {
+        Ok(response) => response,
+        Err(e) => {
+            tracing::warn!("Failed to stream shared song {}: {}", song.id, e);
+            StatusCode::NOT_FOUND.into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::auth::DatabaseAuthState;
+    use crate::db::{DbConfig, MusicFolderRepository, NewUser, UserRepository, run_migrations};
+    use crate::models::music::NewMusicFolder;
+    use diesel::RunQueryDsl;
+
+    /// Database with a user and two songs in a music folder, both songs
+    /// backed by files. Returns the state, user ID and song IDs.
+    fn setup(name: &str) -> (Arc<dyn AuthState>, i32, [i32; 2]) {
+        let dir = std::env::temp_dir().join(format!(
+            "subsonic-share-test-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let pool = DbConfig::new(dir.join("test.db").to_string_lossy())
+            .build_pool()
+            .unwrap();
+        run_migrations(&mut pool.get().unwrap()).unwrap();
+
+        let user = UserRepository::new(pool.clone())
+            .create(&NewUser::admin("owner", "hash", "secret"))
+            .unwrap();
+        let folder = MusicFolderRepository::new(pool.clone())
+            .create(&NewMusicFolder::new("Music", dir.to_string_lossy()))
+            .unwrap();
+
+        let mut conn = pool.get().unwrap();
+        for (id, file) in [(1, "one.mp3"), (2, "two.mp3")] {
+            let path = dir.join(file);
+            std::fs::write(&path, b"audio").unwrap();
+            diesel::sql_query(format!(
+                "INSERT INTO songs (id, title, music_folder_id, path, parent_path, \
+                 file_size, content_type, suffix) \
+                 VALUES ({}, '{}', {}, '{}', '', 5, 'audio/mpeg', 'mp3')",
+                id,
+                file,
+                folder.id,
+                path.to_string_lossy()
+            ))
+            .execute(&mut conn)
+            .unwrap();
+        }
+
+        (Arc::new(DatabaseAuthState::new(pool)), user.id, [1, 2])
+    }
+
+    async fn page(state: &Arc<dyn AuthState>, token: &str) -> StatusCode {
+        share_page(State(state.clone()), Path(token.to_string()))
+            .await
+            .status()
+    }
+
+    async fn stream(state: &Arc<dyn AuthState>, token: &str, song_id: i32) -> StatusCode {
+        share_stream(
+            State(state.clone()),
+            Path((token.to_string(), song_id)),
+            HeaderMap::new(),
+        )
+        .await
+        .status()
+    }
+
+    #[tokio::test]
+    async fn test_share_serves_only_its_songs() {
+        let (state, user_id, [shared, other]) = setup("songs");
+        let share = state.create_share(user_id, &[shared], None, None).unwrap();
+
+        assert_eq!(page(&state, &share.token).await, StatusCode::OK);
+        assert_eq!(stream(&state, &share.token, shared).await, StatusCode::OK);
+        assert_eq!(
+            stream(&state, &share.token, other).await,
+            StatusCode::NOT_FOUND
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unknown_share_is_not_found() {
+        let (state, _, [song, _]) = setup("unknown");
+
+        assert_eq!(page(&state, "missing").await, StatusCode::NOT_FOUND);
+        assert_eq!(stream(&state, "missing", song).await, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_expired_share_is_not_found() {
+        let (state, user_id, [song, _]) = setup("expired");
+        let expired = chrono::Utc::now().naive_utc() - chrono::TimeDelta::minutes(1);
+        let share = state
+            .create_share(user_id, &[song], None, Some(expired))
+            .unwrap();
+
+        assert_eq!(page(&state, &share.token).await, StatusCode::NOT_FOUND);
+        assert_eq!(
+            stream(&state, &share.token, song).await,
+            StatusCode::NOT_FOUND
+        );
+    }
+}
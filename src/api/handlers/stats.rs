@@ -0,0 +1,150 @@
+//! Listening statistics API handlers (getListeningStats)
+
+use axum::response::IntoResponse;
+use chrono::NaiveDateTime;
+use serde::Deserialize;
+
+use crate::api::auth::SubsonicAuth;
+use crate::api::error::ApiError;
+use crate::api::response::{error_response, ok_listening_stats};
+use crate::models::music::{AlbumID3Response, ChildResponse};
+use crate::models::stats::{
+    ListeningStatsResponse, StatsArtistResponse, StatsFilter, StatsGenreResponse, StatsPeriod,
+    StatsUserResponse,
+};
+
+/// Query parameters for getListeningStats.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ListeningStatsParams {
+    /// Only plays in the last "week", "month" or "year", or "all" (default).
+    pub period: Option<String>,
+    /// Only plays at or after this time, in milliseconds since the epoch.
+    /// Overrides the start of the period.
+    pub from: Option<i64>,
+    /// Only plays before this time, in milliseconds since the epoch.
+    pub to: Option<i64>,
+    /// The user whose plays to count. Defaults to the current user.
+    pub username: Option<String>,
+    /// Count the plays of all users (admin only). Default false.
+    #[serde(rename = "allUsers")]
+    pub all_users: Option<bool>,
+    /// Maximum number of artists, albums, songs and genres to return (default 10, max 500).
+    pub count: Option<i64>,
+}
+
+/// Convert a timestamp in milliseconds since the epoch.
+fn parse_timestamp(timestamp_ms: i64, name: &str) -> Result<NaiveDateTime, ApiError> {
+    chrono::DateTime::from_timestamp_millis(timestamp_ms)
+        .map(|dt| dt.naive_utc())
+        .ok_or_else(|| ApiError::Generic(format!("Invalid {}: {}", name, timestamp_ms)))
+}
+
+/// Work out which plays to count from the request parameters.
+fn stats_filter(
+    auth: &SubsonicAuth,
+    params: &ListeningStatsParams,
+) -> Result<StatsFilter, ApiError> {
+    let user_id = if params.all_users.unwrap_or(false) {
+        if !auth.user.is_admin() {
+            return Err(ApiError::NotAuthorized);
+        }
+        None
+    } else {
+        match params.username.as_deref() {
+            Some(username) if username != auth.user.username => {
+                // Non-admins can only see their own statistics
+                if !auth.user.is_admin() {
+                    return Err(ApiError::NotAuthorized);
+                }
+                let user = auth
+                    .state
+                    .get_user(username)
+                    .ok_or_else(|| ApiError::NotFound("User".into()))?;
+                Some(user.id)
+            }
+            _ => Some(auth.user.id),
+        }
+    };
+
+    let period = match params.period.as_deref() {
+        Some(name) => StatsPeriod::parse(name)
+            .ok_or_else(|| ApiError::Generic(format!("Unknown period: {}", name)))?,
+        None => StatsPeriod::All,
+    };
+    let from = match params.from {
+        Some(from) => Some(parse_timestamp(from, "from")?),
+        None => period.start(chrono::Utc::now().naive_utc()),
+    };
+    let to = params.to.map(|to| parse_timestamp(to, "to")).transpose()?;
+
+    Ok(StatsFilter { user_id, from, to })
+}
+
+/// GET/POST /rest/getListeningStats[.view]
+///
+/// Returns listening statistics computed from scrobbles: the number of plays
+/// and listening time (in seconds) of each user, and the most played artists,
+/// albums, songs and genres, for the current user by default.
+///
+/// Parameters:
+/// - `period`: only count plays in the last `week`, `month` or `year` (default `all`)
+/// - `from`, `to`: only count plays in this range, in milliseconds since the epoch
+/// - `username`: count another user's plays (admin only)
+/// - `allUsers`: count the plays of all users (admin only)
+/// - `count`: maximum number of artists, albums, songs and genres (default 10, max 500)
+pub async fn get_listening_stats(
+    axum::extract::Query(params): axum::extract::Query<ListeningStatsParams>,
+    auth: SubsonicAuth,
+) -> impl IntoResponse {
+    let filter = match stats_filter(&auth, &params) {
+        Ok(filter) => filter,
+        Err(e) => return error_response(auth.format, &e),
+    };
+    let count = params.count.unwrap_or(10).clamp(1, 500);
+
+    let mut response = ListeningStatsResponse::new(filter.from.as_ref(), filter.to.as_ref());
+
+    let listening = auth.state.get_listening_by_user(&filter);
+    response.play_count = listening.iter().map(|l| l.play_count).sum();
+    response.listening_time = listening.iter().map(|l| l.listening_time).sum();
+    response.users = listening.iter().map(StatsUserResponse::from).collect();
+
+    response.artists = auth
+        .state
+        .get_top_artists(&filter, count)
+        .iter()
+        .map(|(artist, plays)| StatsArtistResponse::from_artist(artist, *plays))
+        .collect();
+
+    response.albums = auth
+        .state
+        .get_top_albums(&filter, count)
+        .iter()
+        .map(|(album, plays)| {
+            let mut album_response = AlbumID3Response::from(album);
+            album_response.play_count = Some(*plays as i32);
+            album_response
+        })
+        .collect();
+
+    response.songs = auth
+        .state
+        .get_top_songs(&filter, None, count)
+        .iter()
+        .map(|(song, plays)| {
+            let mut child = ChildResponse::from(song);
+            child.play_count = Some(*plays as i32);
+            child
+        })
+        .collect();
+
+    response.genres = auth
+        .state
+        .get_top_genres(&filter, count)
+        .into_iter()
+        .map(|(name, play_count)| StatsGenreResponse { name, play_count })
+        .collect();
+
+    ok_listening_stats(auth.format, response)
+}
@@ -3,9 +3,7 @@
 use axum::response::IntoResponse;
 
 use crate::api::auth::SubsonicAuth;
-use crate::api::response::{
-    ok_bookmarks, ok_empty, ok_license, ok_open_subsonic_extensions, ok_token_info,
-};
+use crate::api::response::{ok_empty, ok_license, ok_open_subsonic_extensions, ok_token_info};
 use crate::models::music::TokenInfoResponse;
 
 /// GET/POST /rest/ping[.view]
@@ -32,15 +30,6 @@ pub async fn get_open_subsonic_extensions(auth: SubsonicAuth) -> impl IntoRespon
     ok_open_subsonic_extensions(auth.format)
 }
 
-/// GET/POST /rest/getBookmarks[.view]
-///
-/// Returns all bookmarks for this user.
-/// A bookmark is a position within a certain media file.
-/// Currently returns an empty list (bookmarks not yet implemented).
-pub async fn get_bookmarks(auth: SubsonicAuth) -> impl IntoResponse {
-    ok_bookmarks(auth.format)
-}
-
 /// GET/POST /rest/tokenInfo[.view]
 ///
 /// Returns information about the API key used for authentication.
@@ -10,16 +10,22 @@ use axum::{
 use serde::Serialize;
 
 use super::error::ApiError;
+use crate::models::chat::ChatMessagesResponse;
 use crate::models::music::{
     AlbumInfoResponse, AlbumList2Response, AlbumListResponse, AlbumWithSongsID3Response,
     ArtistInfo2Response, ArtistInfoResponse, ArtistWithAlbumsID3Response, ArtistsID3Response,
-    ChildResponse, DirectoryResponse, GenresResponse, IndexesResponse, LyricsListResponse,
-    LyricsResponse, MusicFolderResponse, NowPlayingResponse, PlayQueueByIndexResponse,
-    PlayQueueResponse, PlaylistWithSongsResponse, PlaylistsResponse, RandomSongsResponse,
-    SearchResult2Response, SearchResult3Response, SearchResultResponse, SimilarSongs2Response,
-    SimilarSongsResponse, SongsByGenreResponse, Starred2Response, StarredResponse,
-    TokenInfoResponse, TopSongsResponse,
+    BookmarksResponse, ChildResponse, DirectoryResponse, GenresResponse, IndexesResponse,
+    LyricsListResponse, LyricsResponse, MusicFolderResponse, NowPlayingResponse,
+    PlayQueueByIndexResponse, PlayQueueResponse, PlaylistWithSongsResponse, PlaylistsResponse,
+    RandomSongsResponse, SearchResult2Response, SearchResult3Response, SearchResultResponse,
+    SharesResponse, SimilarSongs2Response, SimilarSongsResponse, SongsByGenreResponse,
+    Starred2Response, StarredResponse, TokenInfoResponse, TopSongsResponse,
 };
+use crate::models::podcast::{NewestPodcastsResponse, PodcastsResponse};
+use crate::models::radio::InternetRadioStationsResponse;
+use crate::models::scan::{ScanErrorsResponse, ScanHistoryResponse};
+use crate::models::scrobbling::ScrobbleAccountsResponse;
+use crate::models::stats::ListeningStatsResponse;
 use crate::models::user::{UserResponse, UsersResponse};
 
 /// The current Subsonic API version we're compatible with.
@@ -69,10 +75,12 @@ pub struct ScanStatusData {
     pub count: u64,
     /// Total number of items to scan (0 if unknown).
     pub total: u64,
-    /// Current scan phase (idle, discovering, processing, cleaning).
+    /// Current scan phase (idle, discovering, processing, cleaning, cancelled).
     pub phase: String,
     /// Current folder being scanned (if any).
     pub folder: Option<String>,
+    /// Mode of the running scan (full, incremental).
+    pub mode: Option<String>,
 }
 
 // ============================================================================
@@ -943,6 +951,8 @@ mod xml {
         pub phase: Option<String>,
         #[serde(rename = "@folder", skip_serializing_if = "Option::is_none")]
         pub folder: Option<String>,
+        #[serde(rename = "@mode", skip_serializing_if = "Option::is_none")]
+        pub mode: Option<String>,
     }
 
     impl ScanStatus {
@@ -955,12 +965,14 @@ mod xml {
                 } else {
                     None
                 },
-                phase: if data.scanning {
+                // A cancelled scan keeps reporting its phase after it stops
+                phase: if data.scanning || data.phase == "cancelled" {
                     Some(data.phase.clone())
                 } else {
                     None
                 },
                 folder: data.folder.clone(),
+                mode: data.mode.clone(),
             }
         }
     }
@@ -998,12 +1010,6 @@ mod xml {
         }
     }
 
-    /// Empty bookmarks response for XML format.
-    #[derive(Debug, Serialize)]
-    pub struct Bookmarks {
-        // Empty - no bookmarks implemented yet
-    }
-
     #[derive(Debug, Serialize)]
     #[serde(rename = "subsonic-response")]
     pub struct BookmarksResponse {
@@ -1020,11 +1026,11 @@ mod xml {
         #[serde(rename = "@openSubsonic")]
         pub open_subsonic: bool,
         #[serde(rename = "bookmarks")]
-        pub bookmarks: Bookmarks,
+        pub bookmarks: super::BookmarksResponse,
     }
 
     impl BookmarksResponse {
-        pub fn new() -> Self {
+        pub fn new(bookmarks: super::BookmarksResponse) -> Self {
             Self {
                 xmlns: "http://subsonic.org/restapi",
                 status: ResponseStatus::Ok,
@@ -1032,7 +1038,7 @@ mod xml {
                 server_type: SERVER_NAME,
                 server_version: SERVER_VERSION,
                 open_subsonic: true,
-                bookmarks: Bookmarks {},
+                bookmarks,
             }
         }
     }
@@ -1465,6 +1471,303 @@ mod xml {
             }
         }
     }
+
+    #[derive(Debug, Serialize)]
+    #[serde(rename = "subsonic-response")]
+    pub struct ScanHistoryResponse {
+        #[serde(rename = "@xmlns")]
+        pub xmlns: &'static str,
+        #[serde(rename = "@status")]
+        pub status: ResponseStatus,
+        #[serde(rename = "@version")]
+        pub version: &'static str,
+        #[serde(rename = "@type")]
+        pub server_type: &'static str,
+        #[serde(rename = "@serverVersion")]
+        pub server_version: &'static str,
+        #[serde(rename = "@openSubsonic")]
+        pub open_subsonic: bool,
+        #[serde(rename = "scanHistory")]
+        pub scan_history: super::ScanHistoryResponse,
+    }
+
+    impl ScanHistoryResponse {
+        pub fn new(scan_history: super::ScanHistoryResponse) -> Self {
+            Self {
+                xmlns: "http://subsonic.org/restapi",
+                status: ResponseStatus::Ok,
+                version: API_VERSION,
+                server_type: SERVER_NAME,
+                server_version: SERVER_VERSION,
+                open_subsonic: true,
+                scan_history,
+            }
+        }
+    }
+
+    #[derive(Debug, Serialize)]
+    #[serde(rename = "subsonic-response")]
+    pub struct ScanErrorsResponse {
+        #[serde(rename = "@xmlns")]
+        pub xmlns: &'static str,
+        #[serde(rename = "@status")]
+        pub status: ResponseStatus,
+        #[serde(rename = "@version")]
+        pub version: &'static str,
+        #[serde(rename = "@type")]
+        pub server_type: &'static str,
+        #[serde(rename = "@serverVersion")]
+        pub server_version: &'static str,
+        #[serde(rename = "@openSubsonic")]
+        pub open_subsonic: bool,
+        #[serde(rename = "scanErrors")]
+        pub scan_errors: super::ScanErrorsResponse,
+    }
+
+    impl ScanErrorsResponse {
+        pub fn new(scan_errors: super::ScanErrorsResponse) -> Self {
+            Self {
+                xmlns: "http://subsonic.org/restapi",
+                status: ResponseStatus::Ok,
+                version: API_VERSION,
+                server_type: SERVER_NAME,
+                server_version: SERVER_VERSION,
+                open_subsonic: true,
+                scan_errors,
+            }
+        }
+    }
+
+    #[derive(Debug, Serialize)]
+    #[serde(rename = "subsonic-response")]
+    pub struct ListeningStatsResponse {
+        #[serde(rename = "@xmlns")]
+        pub xmlns: &'static str,
+        #[serde(rename = "@status")]
+        pub status: ResponseStatus,
+        #[serde(rename = "@version")]
+        pub version: &'static str,
+        #[serde(rename = "@type")]
+        pub server_type: &'static str,
+        #[serde(rename = "@serverVersion")]
+        pub server_version: &'static str,
+        #[serde(rename = "@openSubsonic")]
+        pub open_subsonic: bool,
+        #[serde(rename = "listeningStats")]
+        pub listening_stats: super::ListeningStatsResponse,
+    }
+
+    impl ListeningStatsResponse {
+        pub fn new(listening_stats: super::ListeningStatsResponse) -> Self {
+            Self {
+                xmlns: "http://subsonic.org/restapi",
+                status: ResponseStatus::Ok,
+                version: API_VERSION,
+                server_type: SERVER_NAME,
+                server_version: SERVER_VERSION,
+                open_subsonic: true,
+                listening_stats,
+            }
+        }
+    }
+
+    #[derive(Debug, Serialize)]
+    #[serde(rename = "subsonic-response")]
+    pub struct ScrobbleAccountsResponse {
+        #[serde(rename = "@xmlns")]
+        pub xmlns: &'static str,
+        #[serde(rename = "@status")]
+        pub status: ResponseStatus,
+        #[serde(rename = "@version")]
+        pub version: &'static str,
+        #[serde(rename = "@type")]
+        pub server_type: &'static str,
+        #[serde(rename = "@serverVersion")]
+        pub server_version: &'static str,
+        #[serde(rename = "@openSubsonic")]
+        pub open_subsonic: bool,
+        #[serde(rename = "scrobbleAccounts")]
+        pub scrobble_accounts: super::ScrobbleAccountsResponse,
+    }
+
+    impl ScrobbleAccountsResponse {
+        pub fn new(scrobble_accounts: super::ScrobbleAccountsResponse) -> Self {
+            Self {
+                xmlns: "http://subsonic.org/restapi",
+                status: ResponseStatus::Ok,
+                version: API_VERSION,
+                server_type: SERVER_NAME,
+                server_version: SERVER_VERSION,
+                open_subsonic: true,
+                scrobble_accounts,
+            }
+        }
+    }
+
+    #[derive(Debug, Serialize)]
+    #[serde(rename = "subsonic-response")]
+    pub struct SharesResponse {
+        #[serde(rename = "@xmlns")]
+        pub xmlns: &'static str,
+        #[serde(rename = "@status")]
+        pub status: ResponseStatus,
+        #[serde(rename = "@version")]
+        pub version: &'static str,
+        #[serde(rename = "@type")]
+        pub server_type: &'static str,
+        #[serde(rename = "@serverVersion")]
+        pub server_version: &'static str,
+        #[serde(rename = "@openSubsonic")]
+        pub open_subsonic: bool,
+        #[serde(rename = "shares")]
+        pub shares: super::SharesResponse,
+    }
+
+    impl SharesResponse {
+        pub fn new(shares: super::SharesResponse) -> Self {
+            Self {
+                xmlns: "http://subsonic.org/restapi",
+                status: ResponseStatus::Ok,
+                version: API_VERSION,
+                server_type: SERVER_NAME,
+                server_version: SERVER_VERSION,
+                open_subsonic: true,
+                shares,
+            }
+        }
+    }
+
+    #[derive(Debug, Serialize)]
+    #[serde(rename = "subsonic-response")]
+    pub struct InternetRadioStationsResponse {
+        #[serde(rename = "@xmlns")]
+        pub xmlns: &'static str,
+        #[serde(rename = "@status")]
+        pub status: ResponseStatus,
+        #[serde(rename = "@version")]
+        pub version: &'static str,
+        #[serde(rename = "@type")]
+        pub server_type: &'static str,
+        #[serde(rename = "@serverVersion")]
+        pub server_version: &'static str,
+        #[serde(rename = "@openSubsonic")]
+        pub open_subsonic: bool,
+        #[serde(rename = "internetRadioStations")]
+        pub internet_radio_stations: super::InternetRadioStationsResponse,
+    }
+
+    impl InternetRadioStationsResponse {
+        pub fn new(internet_radio_stations: super::InternetRadioStationsResponse) -> Self {
+            Self {
+                xmlns: "http://subsonic.org/restapi",
+                status: ResponseStatus::Ok,
+                version: API_VERSION,
+                server_type: SERVER_NAME,
+                server_version: SERVER_VERSION,
+                open_subsonic: true,
+                internet_radio_stations,
+            }
+        }
+    }
+
+    #[derive(Debug, Serialize)]
+    #[serde(rename = "subsonic-response")]
+    pub struct PodcastsResponse {
+        #[serde(rename = "@xmlns")]
+        pub xmlns: &'static str,
+        #[serde(rename = "@status")]
+        pub status: ResponseStatus,
+        #[serde(rename = "@version")]
+        pub version: &'static str,
+        #[serde(rename = "@type")]
+        pub server_type: &'static str,
+        #[serde(rename = "@serverVersion")]
+        pub server_version: &'static str,
+        #[serde(rename = "@openSubsonic")]
+        pub open_subsonic: bool,
+        #[serde(rename = "podcasts")]
+        pub podcasts: super::PodcastsResponse,
+    }
+
+    impl PodcastsResponse {
+        pub fn new(podcasts: super::PodcastsResponse) -> Self {
+            Self {
+                xmlns: "http://subsonic.org/restapi",
+                status: ResponseStatus::Ok,
+                version: API_VERSION,
+                server_type: SERVER_NAME,
+                server_version: SERVER_VERSION,
+                open_subsonic: true,
+                podcasts,
+            }
+        }
+    }
+
+    #[derive(Debug, Serialize)]
+    #[serde(rename = "subsonic-response")]
+    pub struct NewestPodcastsResponse {
+        #[serde(rename = "@xmlns")]
+        pub xmlns: &'static str,
+        #[serde(rename = "@status")]
+        pub status: ResponseStatus,
+        #[serde(rename = "@version")]
+        pub version: &'static str,
+        #[serde(rename = "@type")]
+        pub server_type: &'static str,
+        #[serde(rename = "@serverVersion")]
+        pub server_version: &'static str,
+        #[serde(rename = "@openSubsonic")]
+        pub open_subsonic: bool,
+        #[serde(rename = "newestPodcasts")]
+        pub newest_podcasts: super::NewestPodcastsResponse,
+    }
+
+    impl NewestPodcastsResponse {
+        pub fn new(newest_podcasts: super::NewestPodcastsResponse) -> Self {
+            Self {
+                xmlns: "http://subsonic.org/restapi",
+                status: ResponseStatus::Ok,
+                version: API_VERSION,
+                server_type: SERVER_NAME,
+                server_version: SERVER_VERSION,
+                open_subsonic: true,
+                newest_podcasts,
+            }
+        }
+    }
+
+    #[derive(Debug, Serialize)]
+    #[serde(rename = "subsonic-response")]
+    pub struct ChatMessagesResponse {
+        #[serde(rename = "@xmlns")]
+        pub xmlns: &'static str,
+        #[serde(rename = "@status")]
+        pub status: ResponseStatus,
+        #[serde(rename = "@version")]
+        pub version: &'static str,
+        #[serde(rename = "@type")]
+        pub server_type: &'static str,
+        #[serde(rename = "@serverVersion")]
+        pub server_version: &'static str,
+        #[serde(rename = "@openSubsonic")]
+        pub open_subsonic: bool,
+        #[serde(rename = "chatMessages")]
+        pub chat_messages: super::ChatMessagesResponse,
+    }
+
+    impl ChatMessagesResponse {
+        pub fn new(chat_messages: super::ChatMessagesResponse) -> Self {
+            Self {
+                xmlns: "http://subsonic.org/restapi",
+                status: ResponseStatus::Ok,
+                version: API_VERSION,
+                server_type: SERVER_NAME,
+                server_version: SERVER_VERSION,
+                open_subsonic: true,
+                chat_messages,
+            }
+        }
+    }
 }
 
 // ============================================================================
@@ -1535,7 +1838,7 @@ mod json {
         #[serde(skip_serializing_if = "Option::is_none", rename = "scanStatus")]
         pub scan_status: Option<ScanStatusJson>,
         #[serde(skip_serializing_if = "Option::is_none")]
-        pub bookmarks: Option<BookmarksJson>,
+        pub bookmarks: Option<super::BookmarksResponse>,
         #[serde(skip_serializing_if = "Option::is_none", rename = "artistInfo2")]
         pub artist_info2: Option<super::ArtistInfo2Response>,
         #[serde(skip_serializing_if = "Option::is_none", rename = "albumInfo")]
@@ -1562,6 +1865,27 @@ mod json {
         pub artist_info: Option<super::ArtistInfoResponse>,
         #[serde(skip_serializing_if = "Option::is_none", rename = "similarSongs")]
         pub similar_songs: Option<super::SimilarSongsResponse>,
+        #[serde(skip_serializing_if = "Option::is_none", rename = "scanHistory")]
+        pub scan_history: Option<super::ScanHistoryResponse>,
+        #[serde(skip_serializing_if = "Option::is_none", rename = "scanErrors")]
+        pub scan_errors: Option<super::ScanErrorsResponse>,
+        #[serde(skip_serializing_if = "Option::is_none", rename = "listeningStats")]
+        pub listening_stats: Option<super::ListeningStatsResponse>,
+        #[serde(skip_serializing_if = "Option::is_none", rename = "scrobbleAccounts")]
+        pub scrobble_accounts: Option<super::ScrobbleAccountsResponse>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub shares: Option<super::SharesResponse>,
+        #[serde(
+            skip_serializing_if = "Option::is_none",
+            rename = "internetRadioStations"
+        )]
+        pub internet_radio_stations: Option<super::InternetRadioStationsResponse>,
+        #[serde(skip_serializing_if = "Option::is_none", rename = "podcasts")]
+        pub podcasts: Option<super::PodcastsResponse>,
+        #[serde(skip_serializing_if = "Option::is_none", rename = "newestPodcasts")]
+        pub newest_podcasts: Option<super::NewestPodcastsResponse>,
+        #[serde(skip_serializing_if = "Option::is_none", rename = "chatMessages")]
+        pub chat_messages: Option<super::ChatMessagesResponse>,
     }
 
     #[derive(Debug, Serialize)]
@@ -1574,6 +1898,8 @@ mod json {
         pub phase: Option<String>,
         #[serde(skip_serializing_if = "Option::is_none")]
         pub folder: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub mode: Option<String>,
     }
 
     impl ScanStatusJson {
@@ -1586,22 +1912,18 @@ mod json {
                 } else {
                     None
                 },
-                phase: if data.scanning {
+                // A cancelled scan keeps reporting its phase after it stops
+                phase: if data.scanning || data.phase == "cancelled" {
                     Some(data.phase.clone())
                 } else {
                     None
                 },
                 folder: data.folder.clone(),
+                mode: data.mode.clone(),
             }
         }
     }
 
-    /// Empty bookmarks response for JSON format.
-    #[derive(Debug, Serialize)]
-    pub struct BookmarksJson {
-        // Empty - no bookmarks implemented yet
-    }
-
     #[derive(Debug, Serialize)]
     pub struct MusicFoldersJson {
         #[serde(rename = "musicFolder")]
@@ -1671,6 +1993,15 @@ mod json {
                 search_result: None,
                 artist_info: None,
                 similar_songs: None,
+                scan_history: None,
+                scan_errors: None,
+                listening_stats: None,
+                scrobble_accounts: None,
+                shares: None,
+                internet_radio_stations: None,
+                podcasts: None,
+                newest_podcasts: None,
+                chat_messages: None,
             }
         }
 
@@ -1719,6 +2050,15 @@ mod json {
                 search_result: None,
                 artist_info: None,
                 similar_songs: None,
+                scan_history: None,
+                scan_errors: None,
+                listening_stats: None,
+                scrobble_accounts: None,
+                shares: None,
+                internet_radio_stations: None,
+                podcasts: None,
+                newest_podcasts: None,
+                chat_messages: None,
             }
         }
 
@@ -1840,8 +2180,8 @@ mod json {
             self
         }
 
-        pub fn with_bookmarks(mut self) -> Self {
-            self.bookmarks = Some(BookmarksJson {});
+        pub fn with_bookmarks(mut self, bookmarks: super::BookmarksResponse) -> Self {
+            self.bookmarks = Some(bookmarks);
             self
         }
 
@@ -1910,6 +2250,63 @@ mod json {
             self
         }
 
+        pub fn with_scan_history(mut self, scan_history: super::ScanHistoryResponse) -> Self {
+            self.scan_history = Some(scan_history);
+            self
+        }
+
+        pub fn with_scan_errors(mut self, scan_errors: super::ScanErrorsResponse) -> Self {
+            self.scan_errors = Some(scan_errors);
+            self
+        }
+
+        pub fn with_listening_stats(
+            mut self,
+            listening_stats: super::ListeningStatsResponse,
+        ) -> Self {
+            self.listening_stats = Some(listening_stats);
+            self
+        }
+
+        pub fn with_scrobble_accounts(
+            mut self,
+            scrobble_accounts: super::ScrobbleAccountsResponse,
+        ) -> Self {
+            self.scrobble_accounts = Some(scrobble_accounts);
+            self
+        }
+
+        pub fn with_shares(mut self, shares: super::SharesResponse) -> Self {
+            self.shares = Some(shares);
+            self
+        }
+
+        pub fn with_internet_radio_stations(
+            mut self,
+            internet_radio_stations: super::InternetRadioStationsResponse,
+        ) -> Self {
+            self.internet_radio_stations = Some(internet_radio_stations);
+            self
+        }
+
+        pub fn with_podcasts(mut self, podcasts: super::PodcastsResponse) -> Self {
+            self.podcasts = Some(podcasts);
+            self
+        }
+
+        pub fn with_newest_podcasts(
+            mut self,
+            newest_podcasts: super::NewestPodcastsResponse,
+        ) -> Self {
+            self.newest_podcasts = Some(newest_podcasts);
+            self
+        }
+
+        pub fn with_chat_messages(mut self, chat_messages: super::ChatMessagesResponse) -> Self {
+            self.chat_messages = Some(chat_messages);
+            self
+        }
+
         pub fn wrap(self) -> JsonWrapper {
             JsonWrapper {
                 subsonic_response: self,
@@ -1955,7 +2352,7 @@ enum ResponseKind {
     User(UserResponse),
     Users(UsersResponse),
     ScanStatus(ScanStatusData),
-    Bookmarks,
+    Bookmarks(BookmarksResponse),
     ArtistInfo2(ArtistInfo2Response),
     AlbumInfo(AlbumInfoResponse),
     SimilarSongs2(SimilarSongs2Response),
@@ -1970,6 +2367,15 @@ enum ResponseKind {
     SearchResult(SearchResultResponse),
     ArtistInfo(ArtistInfoResponse),
     SimilarSongs(SimilarSongsResponse),
+    ScanHistory(ScanHistoryResponse),
+    ScanErrors(ScanErrorsResponse),
+    ListeningStats(ListeningStatsResponse),
+    ScrobbleAccounts(ScrobbleAccountsResponse),
+    Shares(SharesResponse),
+    InternetRadioStations(InternetRadioStationsResponse),
+    Podcasts(PodcastsResponse),
+    NewestPodcasts(NewestPodcastsResponse),
+    ChatMessages(ChatMessagesResponse),
 }
 
 impl SubsonicResponse {
@@ -2157,10 +2563,10 @@ impl SubsonicResponse {
         }
     }
 
-    pub fn bookmarks(format: Format) -> Self {
+    pub fn bookmarks(format: Format, bookmarks: BookmarksResponse) -> Self {
         Self {
             format,
-            kind: ResponseKind::Bookmarks,
+            kind: ResponseKind::Bookmarks(bookmarks),
         }
     }
 
@@ -2254,6 +2660,72 @@ impl SubsonicResponse {
             kind: ResponseKind::SimilarSongs(similar_songs),
         }
     }
+
+    pub fn scan_history(format: Format, scan_history: ScanHistoryResponse) -> Self {
+        Self {
+            format,
+            kind: ResponseKind::ScanHistory(scan_history),
+        }
+    }
+
+    pub fn scan_errors(format: Format, scan_errors: ScanErrorsResponse) -> Self {
+        Self {
+            format,
+            kind: ResponseKind::ScanErrors(scan_errors),
+        }
+    }
+
+    pub fn listening_stats(format: Format, listening_stats: ListeningStatsResponse) -> Self {
+        Self {
+            format,
+            kind: ResponseKind::ListeningStats(listening_stats),
+        }
+    }
+
+    pub fn scrobble_accounts(format: Format, scrobble_accounts: ScrobbleAccountsResponse) -> Self {
+        Self {
+            format,
+            kind: ResponseKind::ScrobbleAccounts(scrobble_accounts),
+        }
+    }
+
+    pub fn shares(format: Format, shares: SharesResponse) -> Self {
+        Self {
+            format,
+            kind: ResponseKind::Shares(shares),
+        }
+    }
+
+    pub fn internet_radio_stations(
+        format: Format,
+        internet_radio_stations: InternetRadioStationsResponse,
+    ) -> Self {
+        Self {
+            format,
+            kind: ResponseKind::InternetRadioStations(internet_radio_stations),
+        }
+    }
+
+    pub fn podcasts(format: Format, podcasts: PodcastsResponse) -> Self {
+        Self {
+            format,
+            kind: ResponseKind::Podcasts(podcasts),
+        }
+    }
+
+    pub fn newest_podcasts(format: Format, newest_podcasts: NewestPodcastsResponse) -> Self {
+        Self {
+            format,
+            kind: ResponseKind::NewestPodcasts(newest_podcasts),
+        }
+    }
+
+    pub fn chat_messages(format: Format, chat_messages: ChatMessagesResponse) -> Self {
+        Self {
+            format,
+            kind: ResponseKind::ChatMessages(chat_messages),
+        }
+    }
 }
 
 impl IntoResponse for SubsonicResponse {
@@ -2339,7 +2811,9 @@ impl SubsonicResponse {
             ResponseKind::ScanStatus(data) => {
                 quick_xml::se::to_string(&xml::ScanStatusResponse::from_data(&data))
             }
-            ResponseKind::Bookmarks => quick_xml::se::to_string(&xml::BookmarksResponse::new()),
+            ResponseKind::Bookmarks(bookmarks) => {
+                quick_xml::se::to_string(&xml::BookmarksResponse::new(bookmarks))
+            }
             ResponseKind::ArtistInfo2(artist_info2) => {
                 quick_xml::se::to_string(&xml::ArtistInfo2Response::new(artist_info2))
             }
@@ -2379,6 +2853,35 @@ impl SubsonicResponse {
             ResponseKind::SimilarSongs(similar_songs) => {
                 quick_xml::se::to_string(&xml::SimilarSongsResponse::new(similar_songs))
             }
+            ResponseKind::ScanHistory(scan_history) => {
+                quick_xml::se::to_string(&xml::ScanHistoryResponse::new(scan_history))
+            }
+            ResponseKind::ScanErrors(scan_errors) => {
+                quick_xml::se::to_string(&xml::ScanErrorsResponse::new(scan_errors))
+            }
+            ResponseKind::ListeningStats(listening_stats) => {
+                quick_xml::se::to_string(&xml::ListeningStatsResponse::new(listening_stats))
+            }
+            ResponseKind::ScrobbleAccounts(scrobble_accounts) => {
+                quick_xml::se::to_string(&xml::ScrobbleAccountsResponse::new(scrobble_accounts))
+            }
+            ResponseKind::Shares(shares) => {
+                quick_xml::se::to_string(&xml::SharesResponse::new(shares))
+            }
+            ResponseKind::InternetRadioStations(internet_radio_stations) => {
+                quick_xml::se::to_string(&xml::InternetRadioStationsResponse::new(
+                    internet_radio_stations,
+                ))
+            }
+            ResponseKind::Podcasts(podcasts) => {
+                quick_xml::se::to_string(&xml::PodcastsResponse::new(podcasts))
+            }
+            ResponseKind::NewestPodcasts(newest_podcasts) => {
+                quick_xml::se::to_string(&xml::NewestPodcastsResponse::new(newest_podcasts))
+            }
+            ResponseKind::ChatMessages(chat_messages) => {
+                quick_xml::se::to_string(&xml::ChatMessagesResponse::new(chat_messages))
+            }
         };
 
         match xml_result {
@@ -2461,7 +2964,9 @@ impl SubsonicResponse {
             ResponseKind::ScanStatus(data) => {
                 json::SubsonicResponse::ok().with_scan_status(&data).wrap()
             }
-            ResponseKind::Bookmarks => json::SubsonicResponse::ok().with_bookmarks().wrap(),
+            ResponseKind::Bookmarks(bookmarks) => json::SubsonicResponse::ok()
+                .with_bookmarks(bookmarks)
+                .wrap(),
             ResponseKind::ArtistInfo2(artist_info2) => json::SubsonicResponse::ok()
                 .with_artist_info2(artist_info2)
                 .wrap(),
@@ -2499,6 +3004,33 @@ impl SubsonicResponse {
             ResponseKind::SimilarSongs(similar_songs) => json::SubsonicResponse::ok()
                 .with_similar_songs(similar_songs)
                 .wrap(),
+            ResponseKind::ScanHistory(scan_history) => json::SubsonicResponse::ok()
+                .with_scan_history(scan_history)
+                .wrap(),
+            ResponseKind::ScanErrors(scan_errors) => json::SubsonicResponse::ok()
+                .with_scan_errors(scan_errors)
+                .wrap(),
+            ResponseKind::ListeningStats(listening_stats) => json::SubsonicResponse::ok()
+                .with_listening_stats(listening_stats)
+                .wrap(),
+            ResponseKind::ScrobbleAccounts(scrobble_accounts) => json::SubsonicResponse::ok()
+                .with_scrobble_accounts(scrobble_accounts)
+                .wrap(),
+            ResponseKind::Shares(shares) => json::SubsonicResponse::ok().with_shares(shares).wrap(),
+            ResponseKind::InternetRadioStations(internet_radio_stations) => {
+                json::SubsonicResponse::ok()
+                    .with_internet_radio_stations(internet_radio_stations)
+                    .wrap()
+            }
+            ResponseKind::Podcasts(podcasts) => {
+                json::SubsonicResponse::ok().with_podcasts(podcasts).wrap()
+            }
+            ResponseKind::NewestPodcasts(newest_podcasts) => json::SubsonicResponse::ok()
+                .with_newest_podcasts(newest_podcasts)
+                .wrap(),
+            ResponseKind::ChatMessages(chat_messages) => json::SubsonicResponse::ok()
+                .with_chat_messages(chat_messages)
+                .wrap(),
         };
 
         match serde_json::to_string(&response) {
@@ -2692,9 +3224,9 @@ pub fn ok_scan_status(format: Format, data: ScanStatusData) -> SubsonicResponse
     SubsonicResponse::scan_status(format, data)
 }
 
-/// Helper function to create an empty bookmarks response.
-pub fn ok_bookmarks(format: Format) -> SubsonicResponse {
-    SubsonicResponse::bookmarks(format)
+/// Helper function to create a bookmarks response.
+pub fn ok_bookmarks(format: Format, bookmarks: BookmarksResponse) -> SubsonicResponse {
+    SubsonicResponse::bookmarks(format, bookmarks)
 }
 
 /// Helper function to create an artist info2 response.
@@ -2767,3 +3299,60 @@ pub fn ok_artist_info(format: Format, artist_info: ArtistInfoResponse) -> Subson
 pub fn ok_similar_songs(format: Format, similar_songs: SimilarSongsResponse) -> SubsonicResponse {
     SubsonicResponse::similar_songs(format, similar_songs)
 }
+
+/// Helper function to create a scan history response (getScanHistory).
+pub fn ok_scan_history(format: Format, scan_history: ScanHistoryResponse) -> SubsonicResponse {
+    SubsonicResponse::scan_history(format, scan_history)
+}
+
+/// Helper function to create a scan errors response (getScanErrors).
+pub fn ok_scan_errors(format: Format, scan_errors: ScanErrorsResponse) -> SubsonicResponse {
+    SubsonicResponse::scan_errors(format, scan_errors)
+}
+
+/// Helper function to create a listening statistics response (getListeningStats).
+pub fn ok_listening_stats(
+    format: Format,
+    listening_stats: ListeningStatsResponse,
+) -> SubsonicResponse {
+    SubsonicResponse::listening_stats(format, listening_stats)
+}
+
+/// Helper function to create a linked scrobbling accounts response (getScrobbleAccounts).
+pub fn ok_scrobble_accounts(
+    format: Format,
+    scrobble_accounts: ScrobbleAccountsResponse,
+) -> SubsonicResponse {
+    SubsonicResponse::scrobble_accounts(format, scrobble_accounts)
+}
+
+/// Helper function to create a shares response (getShares, createShare).
+pub fn ok_shares(format: Format, shares: SharesResponse) -> SubsonicResponse {
+    SubsonicResponse::shares(format, shares)
+}
+
+/// Helper function to create an internet radio stations response.
+pub fn ok_internet_radio_stations(
+    format: Format,
+    internet_radio_stations: InternetRadioStationsResponse,
+) -> SubsonicResponse {
+    SubsonicResponse::internet_radio_stations(format, internet_radio_stations)
+}
+
+/// Helper function to create a podcasts response.
+pub fn ok_podcasts(format: Format, podcasts: PodcastsResponse) -> SubsonicResponse {
+    SubsonicResponse::podcasts(format, podcasts)
+}
+
+/// Helper function to create a newest podcasts response.
+pub fn ok_newest_podcasts(
+    format: Format,
+    newest_podcasts: NewestPodcastsResponse,
+) -> SubsonicResponse {
+    SubsonicResponse::newest_podcasts(format, newest_podcasts)
+}
+
+/// Helper function to create a chat messages response.
+pub fn ok_chat_messages(format: Format, chat_messages: ChatMessagesResponse) -> SubsonicResponse {
+    SubsonicResponse::chat_messages(format, chat_messages)
+}
@@ -170,6 +170,18 @@ pub fn run_migrations(conn: &mut SqliteConnection) -> Result<(), diesel::result:
     )
     .execute(conn)?;
 
+    // Migration: Add exclude_patterns column for per-folder scan exclusions
+    let has_exclude_patterns: Result<i32, _> = diesel::sql_query(
+        "SELECT COUNT(*) as cnt FROM pragma_table_info('music_folders') WHERE name = 'exclude_patterns'",
+    )
+    .get_result::<CountResult>(conn)
+    .map(|r| r.cnt);
+
+    if has_exclude_patterns.unwrap_or(0) == 0 {
+        let _ = diesel::sql_query("ALTER TABLE music_folders ADD COLUMN exclude_patterns TEXT")
+            .execute(conn);
+    }
+
     // Create artists table
     diesel::sql_query(
         r#"
@@ -268,6 +280,18 @@ pub fn run_migrations(conn: &mut SqliteConnection) -> Result<(), diesel::result:
             diesel::sql_query("ALTER TABLE songs ADD COLUMN file_modified_at BIGINT").execute(conn);
     }
 
+    // Migration: Add CUE offsets for virtual tracks split from a single file
+    let has_cue_start: Result<i32, _> = diesel::sql_query(
+        "SELECT COUNT(*) as cnt FROM pragma_table_info('songs') WHERE name = 'cue_start_ms'",
+    )
+    .get_result::<CountResult>(conn)
+    .map(|r| r.cnt);
+
+    if has_cue_start.unwrap_or(0) == 0 {
+        let _ = diesel::sql_query("ALTER TABLE songs ADD COLUMN cue_start_ms BIGINT").execute(conn);
+        let _ = diesel::sql_query("ALTER TABLE songs ADD COLUMN cue_end_ms BIGINT").execute(conn);
+    }
+
     diesel::sql_query("CREATE INDEX IF NOT EXISTS idx_songs_title ON songs(title)")
         .execute(conn)?;
 
@@ -351,6 +375,19 @@ pub fn run_migrations(conn: &mut SqliteConnection) -> Result<(), diesel::result:
     )
     .execute(conn)?;
 
+    // Migration: now_playing used to hold one entry per user with a stored
+    // minutes_ago. Entries only live for a song's length, so drop the old
+    // table and let it be recreated below.
+    let has_minutes_ago: Result<i32, _> = diesel::sql_query(
+        "SELECT COUNT(*) as cnt FROM pragma_table_info('now_playing') WHERE name = 'minutes_ago'",
+    )
+    .get_result::<CountResult>(conn)
+    .map(|r| r.cnt);
+
+    if has_minutes_ago.unwrap_or(0) > 0 {
+        diesel::sql_query("DROP TABLE now_playing").execute(conn)?;
+    }
+
     // Create now_playing table for currently playing songs
     diesel::sql_query(
         r#"
@@ -358,20 +395,19 @@ pub fn run_migrations(conn: &mut SqliteConnection) -> Result<(), diesel::result:
             id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
             user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
             song_id INTEGER NOT NULL REFERENCES songs(id) ON DELETE CASCADE,
-            player_id TEXT,
+            player_id TEXT NOT NULL DEFAULT '',
             started_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
-            minutes_ago INTEGER NOT NULL DEFAULT 0
+            position_ms BIGINT,
+            updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            expires_at TIMESTAMP NOT NULL
         )
         "#,
     )
     .execute(conn)?;
 
-    diesel::sql_query("CREATE INDEX IF NOT EXISTS idx_now_playing_user_id ON now_playing(user_id)")
-        .execute(conn)?;
-
-    // Only one "now playing" entry per user
+    // One "now playing" entry per user and player
     diesel::sql_query(
-        "CREATE UNIQUE INDEX IF NOT EXISTS idx_now_playing_user ON now_playing(user_id)",
+        "CREATE UNIQUE INDEX IF NOT EXISTS idx_now_playing_user_player ON now_playing(user_id, player_id)",
     )
     .execute(conn)?;
 
@@ -463,6 +499,37 @@ pub fn run_migrations(conn: &mut SqliteConnection) -> Result<(), diesel::result:
     diesel::sql_query("CREATE INDEX IF NOT EXISTS idx_playlists_user_id ON playlists(user_id)")
         .execute(conn)?;
 
+    // Migration: Add source_path column for playlists imported from playlist files
+    let has_source_path: Result<i32, _> = diesel::sql_query(
+        "SELECT COUNT(*) as cnt FROM pragma_table_info('playlists') WHERE name = 'source_path'",
+    )
+    .get_result::<CountResult>(conn)
+    .map(|r| r.cnt);
+
+    if has_source_path.unwrap_or(0) == 0 {
+        let _ =
+            diesel::sql_query("ALTER TABLE playlists ADD COLUMN source_path TEXT").execute(conn);
+    }
+
+    diesel::sql_query(
+        "CREATE UNIQUE INDEX IF NOT EXISTS idx_playlists_source_path ON playlists(source_path) WHERE source_path IS NOT NULL",
+    )
+    .execute(conn)?;
+
+    // Migration: Add rules columns for smart playlists, whose songs are
+    // evaluated from a JSON definition
+    let has_rules: Result<i32, _> = diesel::sql_query(
+        "SELECT COUNT(*) as cnt FROM pragma_table_info('playlists') WHERE name = 'rules'",
+    )
+    .get_result::<CountResult>(conn)
+    .map(|r| r.cnt);
+
+    if has_rules.unwrap_or(0) == 0 {
+        let _ = diesel::sql_query("ALTER TABLE playlists ADD COLUMN rules TEXT").execute(conn);
+        let _ = diesel::sql_query("ALTER TABLE playlists ADD COLUMN rules_evaluated_at TIMESTAMP")
+            .execute(conn);
+    }
+
     // Create playlist_songs table
     diesel::sql_query(
         r#"
@@ -525,6 +592,408 @@ pub fn run_migrations(conn: &mut SqliteConnection) -> Result<(), diesel::result:
     )
     .execute(conn)?;
 
+    // Check whether the lyrics table exists before creating it, so libraries scanned
+    // before lyrics were indexed get picked up by the next incremental scan
+    let has_lyrics_table: Result<i32, _> = diesel::sql_query(
+        "SELECT COUNT(*) as cnt FROM sqlite_master WHERE type = 'table' AND name = 'lyrics'",
+    )
+    .get_result::<CountResult>(conn)
+    .map(|r| r.cnt);
+
+    // Create lyrics table for lyrics extracted at scan time
+    diesel::sql_query(
+        r#"
+        CREATE TABLE IF NOT EXISTS lyrics (
+            id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+            song_id INTEGER NOT NULL REFERENCES songs(id) ON DELETE CASCADE,
+            lang TEXT,
+            description TEXT,
+            synced BOOLEAN NOT NULL DEFAULT FALSE,
+            text TEXT NOT NULL,
+            created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )
+        "#,
+    )
+    .execute(conn)?;
+
+    diesel::sql_query("CREATE INDEX IF NOT EXISTS idx_lyrics_song_id ON lyrics(song_id)")
+        .execute(conn)?;
+
+    if has_lyrics_table.unwrap_or(0) == 0 {
+        // Force existing songs to be re-read so their lyrics get indexed
+        diesel::sql_query("UPDATE songs SET file_modified_at = NULL").execute(conn)?;
+    }
+
+    // Create scan_runs table for scan history
+    diesel::sql_query(
+        r#"
+        CREATE TABLE IF NOT EXISTS scan_runs (
+            id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+            started_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            finished_at TIMESTAMP,
+            mode TEXT NOT NULL,
+            trigger TEXT NOT NULL,
+            status TEXT NOT NULL DEFAULT 'running',
+            error TEXT,
+            tracks_found INTEGER NOT NULL DEFAULT 0,
+            tracks_added INTEGER NOT NULL DEFAULT 0,
+            tracks_updated INTEGER NOT NULL DEFAULT 0,
+            tracks_skipped INTEGER NOT NULL DEFAULT 0,
+            tracks_removed INTEGER NOT NULL DEFAULT 0,
+            tracks_moved INTEGER NOT NULL DEFAULT 0,
+            tracks_failed INTEGER NOT NULL DEFAULT 0,
+            artists_added INTEGER NOT NULL DEFAULT 0,
+            albums_added INTEGER NOT NULL DEFAULT 0,
+            cover_art_saved INTEGER NOT NULL DEFAULT 0,
+            playlists_imported INTEGER NOT NULL DEFAULT 0
+        )
+        "#,
+    )
+    .execute(conn)?;
+
+    // Migration: Record the process running a scan and when it was last seen working
+    let has_scan_pid: Result<i32, _> = diesel::sql_query(
+        "SELECT COUNT(*) as cnt FROM pragma_table_info('scan_runs') WHERE name = 'pid'",
+    )
+    .get_result::<CountResult>(conn)
+    .map(|r| r.cnt);
+
+    if has_scan_pid.unwrap_or(0) == 0 {
+        let _ = diesel::sql_query("ALTER TABLE scan_runs ADD COLUMN pid INTEGER").execute(conn);
+        let _ = diesel::sql_query("ALTER TABLE scan_runs ADD COLUMN heartbeat_at TIMESTAMP")
+            .execute(conn);
+    }
+
+    // Create scan_errors table for files that failed during a scan
+    diesel::sql_query(
+        r#"
+        CREATE TABLE IF NOT EXISTS scan_errors (
+            id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+            scan_run_id INTEGER NOT NULL REFERENCES scan_runs(id) ON DELETE CASCADE,
+            path TEXT NOT NULL,
+            error TEXT NOT NULL,
+            created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )
+        "#,
+    )
+    .execute(conn)?;
+
+    diesel::sql_query(
+        "CREATE INDEX IF NOT EXISTS idx_scan_errors_scan_run_id ON scan_errors(scan_run_id)",
+    )
+    .execute(conn)?;
+
+    // Create scanned_directories table so incremental scans can skip unchanged directories
+    diesel::sql_query(
+        r#"
+        CREATE TABLE IF NOT EXISTS scanned_directories (
+            id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+            music_folder_id INTEGER NOT NULL REFERENCES music_folders(id) ON DELETE CASCADE,
+            path TEXT NOT NULL,
+            modified_at BIGINT NOT NULL,
+            scanned_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            UNIQUE(music_folder_id, path)
+        )
+        "#,
+    )
+    .execute(conn)?;
+
+    // Create directories table for folder-based browsing of the on-disk tree
+    diesel::sql_query(
+        r#"
+        CREATE TABLE IF NOT EXISTS directories (
+            id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+            music_folder_id INTEGER NOT NULL REFERENCES music_folders(id) ON DELETE CASCADE,
+            parent_id INTEGER REFERENCES directories(id) ON DELETE CASCADE,
+            path TEXT NOT NULL,
+            name TEXT NOT NULL,
+            cover_art TEXT,
+            created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            UNIQUE(music_folder_id, path)
+        )
+        "#,
+    )
+    .execute(conn)?;
+
+    diesel::sql_query(
+        "CREATE INDEX IF NOT EXISTS idx_directories_parent_id ON directories(parent_id)",
+    )
+    .execute(conn)?;
+
+    diesel::sql_query(
+        "CREATE INDEX IF NOT EXISTS idx_songs_parent_path ON songs(music_folder_id, parent_path)",
+    )
+    .execute(conn)?;
+
+    // Migration: Add artist biography and album notes read from info files
+    let has_biography: Result<i32, _> = diesel::sql_query(
+        "SELECT COUNT(*) as cnt FROM pragma_table_info('artists') WHERE name = 'biography'",
+    )
+    .get_result::<CountResult>(conn)
+    .map(|r| r.cnt);
+
+    if has_biography.unwrap_or(0) == 0 {
+        let _ = diesel::sql_query("ALTER TABLE artists ADD COLUMN biography TEXT").execute(conn);
+    }
+
+    let has_notes: Result<i32, _> = diesel::sql_query(
+        "SELECT COUNT(*) as cnt FROM pragma_table_info('albums') WHERE name = 'notes'",
+    )
+    .get_result::<CountResult>(conn)
+    .map(|r| r.cnt);
+
+    if has_notes.unwrap_or(0) == 0 {
+        let _ = diesel::sql_query("ALTER TABLE albums ADD COLUMN notes TEXT").execute(conn);
+    }
+
+    let has_album_image_url: Result<i32, _> = diesel::sql_query(
+        "SELECT COUNT(*) as cnt FROM pragma_table_info('albums') WHERE name = 'album_image_url'",
+    )
+    .get_result::<CountResult>(conn)
+    .map(|r| r.cnt);
+
+    if has_album_image_url.unwrap_or(0) == 0 {
+        let _ =
+            diesel::sql_query("ALTER TABLE albums ADD COLUMN album_image_url TEXT").execute(conn);
+    }
+
+    // Create metadata cache tables for details fetched from metadata agents
+    diesel::sql_query(
+        r#"
+        CREATE TABLE IF NOT EXISTS artist_metadata (
+            artist_id INTEGER PRIMARY KEY NOT NULL REFERENCES artists(id) ON DELETE CASCADE,
+            agent TEXT NOT NULL,
+            biography TEXT,
+            musicbrainz_id TEXT,
+            last_fm_url TEXT,
+            small_image_url TEXT,
+            medium_image_url TEXT,
+            large_image_url TEXT,
+            similar_artists TEXT NOT NULL DEFAULT '[]',
+            fetched_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            expires_at TIMESTAMP NOT NULL
+        )
+        "#,
+    )
+    .execute(conn)?;
+
+    diesel::sql_query(
+        r#"
+        CREATE TABLE IF NOT EXISTS album_metadata (
+            album_id INTEGER PRIMARY KEY NOT NULL REFERENCES albums(id) ON DELETE CASCADE,
+            agent TEXT NOT NULL,
+            notes TEXT,
+            musicbrainz_id TEXT,
+            last_fm_url TEXT,
+            small_image_url TEXT,
+            medium_image_url TEXT,
+            large_image_url TEXT,
+            fetched_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            expires_at TIMESTAMP NOT NULL
+        )
+        "#,
+    )
+    .execute(conn)?;
+
+    // Migration: Create artist similarity table computed from co-listening
+    diesel::sql_query(
+        r#"
+        CREATE TABLE IF NOT EXISTS artist_similarity (
+            artist_id INTEGER NOT NULL REFERENCES artists(id) ON DELETE CASCADE,
+            similar_artist_id INTEGER NOT NULL REFERENCES artists(id) ON DELETE CASCADE,
+            score DOUBLE NOT NULL,
+            updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY (artist_id, similar_artist_id)
+        )
+        "#,
+    )
+    .execute(conn)?;
+
+    diesel::sql_query(
+        "CREATE INDEX IF NOT EXISTS idx_artist_similarity_similar_artist_id ON artist_similarity(similar_artist_id)",
+    )
+    .execute(conn)?;
+
+    // Migration: Index plays by user and time for listening session lookups
+    diesel::sql_query(
+        "CREATE INDEX IF NOT EXISTS idx_scrobbles_user_played_at ON scrobbles(user_id, played_at)",
+    )
+    .execute(conn)?;
+
+    // Migration: Index plays by user and song for per-user play counts
+    diesel::sql_query(
+        "CREATE INDEX IF NOT EXISTS idx_scrobbles_user_song_id ON scrobbles(user_id, song_id)",
+    )
+    .execute(conn)?;
+
+    // Migration: Create tables for forwarding scrobbles to external services
+    diesel::sql_query(
+        r#"
+        CREATE TABLE IF NOT EXISTS scrobble_accounts (
+            user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+            service TEXT NOT NULL,
+            token TEXT NOT NULL,
+            username TEXT,
+            linked_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY (user_id, service)
+        )
+        "#,
+    )
+    .execute(conn)?;
+
+    diesel::sql_query(
+        r#"
+        CREATE TABLE IF NOT EXISTS scrobble_outbox (
+            id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+            user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+            service TEXT NOT NULL,
+            song_id INTEGER NOT NULL REFERENCES songs(id) ON DELETE CASCADE,
+            played_at TIMESTAMP NOT NULL,
+            attempts INTEGER NOT NULL DEFAULT 0,
+            next_attempt_at TIMESTAMP NOT NULL,
+            last_error TEXT,
+            created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )
+        "#,
+    )
+    .execute(conn)?;
+
+    diesel::sql_query(
+        "CREATE INDEX IF NOT EXISTS idx_scrobble_outbox_next_attempt_at ON scrobble_outbox(next_attempt_at)",
+    )
+    .execute(conn)?;
+
+    // Migration: Create bookmarks table for saved playback positions
+    diesel::sql_query(
+        r#"
+        CREATE TABLE IF NOT EXISTS bookmarks (
+            id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+            user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+            song_id INTEGER NOT NULL REFERENCES songs(id) ON DELETE CASCADE,
+            position BIGINT NOT NULL,
+            comment TEXT,
+            created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            changed_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            UNIQUE(user_id, song_id)
+        )
+        "#,
+    )
+    .execute(conn)?;
+
+    // Migration: Create tables for public share links
+    diesel::sql_query(
+        r#"
+        CREATE TABLE IF NOT EXISTS shares (
+            id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+            token TEXT NOT NULL UNIQUE,
+            user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+            description TEXT,
+            created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            expires_at TIMESTAMP,
+            last_visited_at TIMESTAMP,
+            visit_count INTEGER NOT NULL DEFAULT 0
+        )
+        "#,
+    )
+    .execute(conn)?;
+
+    diesel::sql_query(
+        r#"
+        CREATE TABLE IF NOT EXISTS share_songs (
+            id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+            share_id INTEGER NOT NULL REFERENCES shares(id) ON DELETE CASCADE,
+            song_id INTEGER NOT NULL REFERENCES songs(id) ON DELETE CASCADE,
+            position INTEGER NOT NULL
+        )
+        "#,
+    )
+    .execute(conn)?;
+
+    diesel::sql_query(
+        "CREATE INDEX IF NOT EXISTS idx_share_songs_share_id ON share_songs(share_id)",
+    )
+    .execute(conn)?;
+
+    // Migration: Create internet radio stations table
+    diesel::sql_query(
+        r#"
+        CREATE TABLE IF NOT EXISTS internet_radio_stations (
+            id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+            name TEXT NOT NULL,
+            stream_url TEXT NOT NULL,
+            homepage_url TEXT,
+            created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )
+        "#,
+    )
+    .execute(conn)?;
+
+    // Migration: Create podcast tables
+    diesel::sql_query(
+        r#"
+        CREATE TABLE IF NOT EXISTS podcast_channels (
+            id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+            url TEXT NOT NULL UNIQUE,
+            title TEXT,
+            description TEXT,
+            image_url TEXT,
+            status TEXT NOT NULL DEFAULT 'new',
+            error_message TEXT,
+            created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            last_refreshed_at TIMESTAMP
+        )
+        "#,
+    )
+    .execute(conn)?;
+
+    diesel::sql_query(
+        r#"
+        CREATE TABLE IF NOT EXISTS podcast_episodes (
+            id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+            channel_id INTEGER NOT NULL REFERENCES podcast_channels(id) ON DELETE CASCADE,
+            guid TEXT NOT NULL,
+            title TEXT NOT NULL,
+            description TEXT,
+            publish_date TIMESTAMP,
+            duration INTEGER,
+            enclosure_url TEXT NOT NULL,
+            content_type TEXT,
+            size BIGINT,
+            status TEXT NOT NULL DEFAULT 'skipped',
+            file_path TEXT,
+            error_message TEXT,
+            created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            UNIQUE(channel_id, guid)
+        )
+        "#,
+    )
+    .execute(conn)?;
+
+    diesel::sql_query(
+        "CREATE INDEX IF NOT EXISTS idx_podcast_episodes_publish_date ON podcast_episodes(publish_date)",
+    )
+    .execute(conn)?;
+
+    // Migration: Create chat messages table
+    diesel::sql_query(
+        r#"
+        CREATE TABLE IF NOT EXISTS chat_messages (
+            id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+            user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+            message TEXT NOT NULL,
+            created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )
+        "#,
+    )
+    .execute(conn)?;
+
+    diesel::sql_query(
+        "CREATE INDEX IF NOT EXISTS idx_chat_messages_created_at ON chat_messages(created_at)",
+    )
+    .execute(conn)?;
+
     Ok(())
 }
 
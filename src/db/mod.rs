@@ -6,8 +6,12 @@ pub mod schema;
 
 pub use connection::{DbConfig, DbConn, DbPool, run_migrations};
 pub use repository::{
-    AlbumRepository, ArtistRepository, MusicFolderRepository, MusicRepoError, NewUser,
-    NowPlayingEntry, NowPlayingRepository, PlayQueue, PlayQueueRepository, Playlist,
-    PlaylistRepository, RatingRepository, ScrobbleRepository, SongRepository, StarredRepository,
-    UserRepoError, UserRepository, UserUpdate,
+    AlbumRepository, ArtistRepository, ArtistSimilarityRepository, Bookmark, BookmarkRepository,
+    ChatMessageRepository, DirectoryRepository, InternetRadioRepository, LyricsRepository,
+    LyricsRow, MetadataCacheRepository, MusicFolderRepository, MusicRepoError, NewDirectory,
+    NewLyrics, NewUser, NowPlayingEntry, NowPlayingRepository, PlayQueue, PlayQueueRepository,
+    Playlist, PlaylistRepository, PodcastRepository, RatingRepository, ScanHistoryRepository,
+    ScannedDirectoryRepository, ScrobbleAccountRepository, ScrobbleOutboxRepository,
+    ScrobbleRepository, Share, ShareRepository, SongRepository, StarredRepository,
+    StatisticsRepository, UserRepoError, UserRepository, UserUpdate,
 };
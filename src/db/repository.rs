@@ -10,7 +10,7 @@ use crate::db::schema::{
     starred, user_ratings, users,
 };
 use crate::models::User;
-use crate::models::music::{Album, Artist, MusicFolder, NewMusicFolder, Song};
+use crate::models::music::{Album, Artist, MusicFolder, NewMusicFolder, PlayStats, Song};
 use crate::models::user::UserRoles;
 
 /// Errors that can occur during user repository operations.
@@ -257,6 +257,20 @@ impl UserRepository {
         Ok(updated > 0)
     }
 
+    /// Find the oldest admin user.
+    pub fn find_first_admin(&self) -> Result<Option<User>, UserRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let result = users::table
+            .filter(users::admin_role.eq(true))
+            .select(UserRow::as_select())
+            .order(users::id.asc())
+            .first(&mut conn)
+            .optional()?;
+
+        Ok(result.map(User::from))
+    }
+
     /// Check if any users exist in the database.
     pub fn has_users(&self) -> Result<bool, UserRepoError> {
         let mut conn = self.pool.get()?;
@@ -425,6 +439,7 @@ pub struct MusicFolderRow {
     pub enabled: bool,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
+    pub exclude_patterns: Option<String>,
 }
 
 impl From<MusicFolderRow> for MusicFolder {
@@ -436,6 +451,10 @@ impl From<MusicFolderRow> for MusicFolder {
             enabled: row.enabled,
             created_at: row.created_at,
             updated_at: row.updated_at,
+            exclude_patterns: row
+                .exclude_patterns
+                .map(|p| p.lines().map(str::to_string).collect())
+                .unwrap_or_default(),
         }
     }
 }
@@ -447,6 +466,7 @@ pub struct NewMusicFolderRow<'a> {
     pub name: &'a str,
     pub path: &'a str,
     pub enabled: bool,
+    pub exclude_patterns: Option<String>,
 }
 
 impl<'a> From<&'a NewMusicFolder> for NewMusicFolderRow<'a> {
@@ -455,6 +475,8 @@ impl<'a> From<&'a NewMusicFolder> for NewMusicFolderRow<'a> {
             name: &folder.name,
             path: &folder.path,
             enabled: folder.enabled,
+            exclude_patterns: (!folder.exclude_patterns.is_empty())
+                .then(|| folder.exclude_patterns.join("\n")),
         }
     }
 }
@@ -570,6 +592,35 @@ impl MusicFolderRepository {
 
         Ok(updated > 0)
     }
+
+    /// Replace the exclude patterns of a music folder.
+    ///
+    /// Also forgets the folder's scanned directories, so the next incremental
+    /// scan walks directories that were excluded before. Returns false if the
+    /// folder doesn't exist.
+    pub fn set_exclude_patterns(
+        &self,
+        folder_id: i32,
+        patterns: &[String],
+    ) -> Result<bool, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+        let patterns = (!patterns.is_empty()).then(|| patterns.join("\n"));
+
+        let updated = conn.transaction(|conn| {
+            let updated =
+                diesel::update(music_folders::table.filter(music_folders::id.eq(folder_id)))
+                    .set(music_folders::exclude_patterns.eq(&patterns))
+                    .execute(conn)?;
+            diesel::delete(
+                scanned_directories::table
+                    .filter(scanned_directories::music_folder_id.eq(folder_id)),
+            )
+            .execute(conn)?;
+            QueryResult::Ok(updated)
+        })?;
+
+        Ok(updated > 0)
+    }
 }
 
 // ============================================================================
@@ -589,6 +640,7 @@ pub struct ArtistRow {
     pub artist_image_url: Option<String>,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
+    pub biography: Option<String>,
 }
 
 impl From<ArtistRow> for Artist {
@@ -600,6 +652,7 @@ impl From<ArtistRow> for Artist {
             musicbrainz_id: row.musicbrainz_id,
             cover_art: row.cover_art,
             artist_image_url: row.artist_image_url,
+            biography: row.biography,
             created_at: row.created_at,
             updated_at: row.updated_at,
         }
@@ -656,6 +709,18 @@ impl ArtistRepository {
         Ok(result.map(Artist::from))
     }
 
+    /// Find the artists with any of the given names.
+    pub fn find_by_names(&self, names: &[String]) -> Result<Vec<Artist>, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let results = artists::table
+            .filter(artists::name.eq_any(names))
+            .select(ArtistRow::as_select())
+            .load(&mut conn)?;
+
+        Ok(results.into_iter().map(Artist::from).collect())
+    }
+
     /// Count albums for an artist.
     pub fn count_albums(&self, artist_id: i32) -> Result<i64, MusicRepoError> {
         let mut conn = self.pool.get()?;
@@ -759,9 +824,13 @@ pub struct AlbumRow {
     pub musicbrainz_id: Option<String>,
     pub duration: i32,
     pub song_count: i32,
+    /// Global play count from before plays were counted per user. No longer
+    /// updated; per-user counts come from `scrobbles`.
     pub play_count: i32,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
+    pub notes: Option<String>,
+    pub album_image_url: Option<String>,
 }
 
 impl From<AlbumRow> for Album {
@@ -778,7 +847,8 @@ impl From<AlbumRow> for Album {
             musicbrainz_id: row.musicbrainz_id,
             duration: row.duration,
             song_count: row.song_count,
-            play_count: row.play_count,
+            notes: row.notes,
+            album_image_url: row.album_image_url,
             created_at: row.created_at,
             updated_at: row.updated_at,
         }
@@ -885,13 +955,25 @@ impl AlbumRepository {
         Ok(results.into_iter().map(Album::from).collect())
     }
 
-    /// Find most frequently played albums with pagination.
-    pub fn find_frequent(&self, offset: i64, limit: i64) -> Result<Vec<Album>, MusicRepoError> {
+    /// Find the albums a user has played most often, with pagination.
+    pub fn find_frequent(
+        &self,
+        user_id: i32,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<Album>, MusicRepoError> {
+        use diesel::dsl::{count_star, max};
+
         let mut conn = self.pool.get()?;
 
         let results = albums::table
+            .inner_join(songs::table.on(songs::album_id.eq(albums::id.nullable())))
+            .inner_join(scrobbles::table.on(scrobbles::song_id.eq(songs::id)))
+            .filter(scrobbles::user_id.eq(user_id))
+            .filter(scrobbles::submission.eq(true))
+            .group_by(albums::id)
             .select(AlbumRow::as_select())
-            .order(albums::play_count.desc())
+            .order((count_star().desc(), max(scrobbles::played_at).desc()))
             .offset(offset)
             .limit(limit)
             .load(&mut conn)?;
@@ -899,15 +981,25 @@ impl AlbumRepository {
         Ok(results.into_iter().map(Album::from).collect())
     }
 
-    /// Find recently played albums with pagination.
-    /// Note: Using updated_at as a proxy for last played time.
-    pub fn find_recent(&self, offset: i64, limit: i64) -> Result<Vec<Album>, MusicRepoError> {
+    /// Find the albums a user has played, most recently played first, with pagination.
+    pub fn find_recent(
+        &self,
+        user_id: i32,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<Album>, MusicRepoError> {
+        use diesel::dsl::max;
+
         let mut conn = self.pool.get()?;
 
         let results = albums::table
-            .filter(albums::play_count.gt(0))
+            .inner_join(songs::table.on(songs::album_id.eq(albums::id.nullable())))
+            .inner_join(scrobbles::table.on(scrobbles::song_id.eq(songs::id)))
+            .filter(scrobbles::user_id.eq(user_id))
+            .filter(scrobbles::submission.eq(true))
+            .group_by(albums::id)
             .select(AlbumRow::as_select())
-            .order(albums::updated_at.desc())
+            .order(max(scrobbles::played_at).desc())
             .offset(offset)
             .limit(limit)
             .load(&mut conn)?;
@@ -1050,9 +1142,13 @@ pub struct SongRow {
     pub genre: Option<String>,
     pub cover_art: Option<String>,
     pub musicbrainz_id: Option<String>,
+    /// Global play count from before plays were counted per user. No longer
+    /// updated; per-user counts come from `scrobbles`.
     pub play_count: i32,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
+    pub cue_start_ms: Option<i64>,
+    pub cue_end_ms: Option<i64>,
 }
 
 impl From<SongRow> for Song {
@@ -1082,9 +1178,10 @@ impl From<SongRow> for Song {
             genre: row.genre,
             cover_art: row.cover_art,
             musicbrainz_id: row.musicbrainz_id,
-            play_count: row.play_count,
             created_at: row.created_at,
             updated_at: row.updated_at,
+            cue_start_ms: row.cue_start_ms,
+            cue_end_ms: row.cue_end_ms,
         }
     }
 }
@@ -1153,6 +1250,28 @@ impl SongRepository {
         Ok(results.into_iter().map(Song::from).collect())
     }
 
+    /// Find the songs directly inside a directory of a music folder.
+    pub fn find_by_directory(
+        &self,
+        folder_id: i32,
+        parent_path: &str,
+    ) -> Result<Vec<Song>, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let results = songs::table
+            .filter(songs::music_folder_id.eq(folder_id))
+            .filter(songs::parent_path.eq(parent_path))
+            .select(SongRow::as_select())
+            .order((
+                songs::disc_number.asc(),
+                songs::track_number.asc(),
+                songs::path.asc(),
+            ))
+            .load(&mut conn)?;
+
+        Ok(results.into_iter().map(Song::from).collect())
+    }
+
     /// Search songs by title with pagination.
     /// An empty query returns all songs.
     pub fn search(
@@ -1308,19 +1427,34 @@ impl SongRepository {
         Ok(results.into_iter().map(Song::from).collect())
     }
 
-    /// Find random songs by artist, excluding a specific song.
-    /// Used for getSimilarSongs2 endpoint.
-    pub fn find_random_by_artist(
+    /// Find random songs by any of several artists.
+    pub fn find_random_by_artists(
         &self,
-        artist_id: i32,
-        exclude_song_id: i32,
+        artist_ids: &[i32],
         limit: i64,
     ) -> Result<Vec<Song>, MusicRepoError> {
         let mut conn = self.pool.get()?;
 
         let results = songs::table
-            .filter(songs::artist_id.eq(artist_id))
-            .filter(songs::id.ne(exclude_song_id))
+            .filter(songs::artist_id.eq_any(artist_ids))
+            .select(SongRow::as_select())
+            .order(diesel::dsl::sql::<diesel::sql_types::Integer>("RANDOM()"))
+            .limit(limit)
+            .load(&mut conn)?;
+
+        Ok(results.into_iter().map(Song::from).collect())
+    }
+
+    /// Find random songs in any of several genres.
+    pub fn find_random_by_genres(
+        &self,
+        genres: &[String],
+        limit: i64,
+    ) -> Result<Vec<Song>, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let results = songs::table
+            .filter(songs::genre.eq_any(genres))
             .select(SongRow::as_select())
             .order(diesel::dsl::sql::<diesel::sql_types::Integer>("RANDOM()"))
             .limit(limit)
@@ -1329,22 +1463,39 @@ impl SongRepository {
         Ok(results.into_iter().map(Song::from).collect())
     }
 
-    /// Find top songs by artist name, ordered by play count.
+    /// Find top songs by artist name, ordered by the user's play count.
     /// Used for getTopSongs endpoint.
     pub fn find_top_by_artist_name(
         &self,
+        user_id: i32,
         artist_name: &str,
         limit: i64,
     ) -> Result<Vec<Song>, MusicRepoError> {
+        use diesel::dsl::count_star;
+
         let mut conn = self.pool.get()?;
 
-        let results = songs::table
+        let plays: std::collections::HashMap<i32, i64> = scrobbles::table
+            .inner_join(songs::table.on(scrobbles::song_id.eq(songs::id)))
+            .filter(scrobbles::user_id.eq(user_id))
+            .filter(scrobbles::submission.eq(true))
+            .filter(songs::artist_name.eq(artist_name))
+            .group_by(songs::id)
+            .select((songs::id, count_star()))
+            .load::<(i32, i64)>(&mut conn)?
+            .into_iter()
+            .collect();
+
+        let mut results: Vec<SongRow> = songs::table
             .filter(songs::artist_name.eq(artist_name))
             .select(SongRow::as_select())
-            .order(songs::play_count.desc())
-            .limit(limit)
+            .order(songs::title.asc())
             .load(&mut conn)?;
 
+        // Songs the user never played follow the played ones, by title
+        results.sort_by_key(|row| std::cmp::Reverse(plays.get(&row.id).copied().unwrap_or(0)));
+        results.truncate(limit.max(0) as usize);
+
         Ok(results.into_iter().map(Song::from).collect())
     }
 }
@@ -1766,6 +1917,12 @@ impl StarredRepository {
 
 use crate::db::schema::now_playing;
 
+/// Time a now playing entry is kept after the song should have ended.
+pub const NOW_PLAYING_GRACE: chrono::TimeDelta = chrono::TimeDelta::minutes(5);
+
+/// Time a now playing entry is kept for a song of unknown length.
+const NOW_PLAYING_UNKNOWN_DURATION: chrono::TimeDelta = chrono::TimeDelta::minutes(10);
+
 /// Database row representation for now playing.
 #[derive(Debug, Clone, Queryable, Selectable)]
 #[diesel(table_name = now_playing)]
@@ -1774,18 +1931,11 @@ pub struct NowPlayingRow {
     pub id: i32,
     pub user_id: i32,
     pub song_id: i32,
-    pub player_id: Option<String>,
+    pub player_id: String,
     pub started_at: NaiveDateTime,
-    pub minutes_ago: i32,
-}
-
-/// Data for inserting a now playing entry.
-#[derive(Debug, Clone, Insertable)]
-#[diesel(table_name = now_playing)]
-pub struct NewNowPlaying {
-    pub user_id: i32,
-    pub song_id: i32,
-    pub player_id: Option<String>,
+    pub position_ms: Option<i64>,
+    pub updated_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
 }
 
 /// Now playing entry with song and user info.
@@ -1795,6 +1945,25 @@ pub struct NowPlayingEntry {
     pub username: String,
     pub player_id: Option<String>,
     pub minutes_ago: i32,
+    /// Estimated playback position in milliseconds, if the player reported one.
+    pub position_ms: Option<i64>,
+}
+
+/// When a now playing entry expires: once the rest of the song has played
+/// from the reported position (or the start), plus a grace period.
+pub fn now_playing_expires_at(
+    updated_at: NaiveDateTime,
+    duration_secs: i32,
+    position_ms: Option<i64>,
+) -> NaiveDateTime {
+    let remaining = if duration_secs > 0 {
+        let duration = chrono::TimeDelta::seconds(i64::from(duration_secs));
+        let played = chrono::TimeDelta::milliseconds(position_ms.unwrap_or(0).max(0));
+        (duration - played).max(chrono::TimeDelta::zero())
+    } else {
+        NOW_PLAYING_UNKNOWN_DURATION
+    };
+    updated_at + remaining + NOW_PLAYING_GRACE
 }
 
 /// Repository for now playing database operations.
@@ -1809,56 +1978,93 @@ impl NowPlayingRepository {
         Self { pool }
     }
 
-    /// Set a song as now playing for a user.
-    /// Replaces any existing now playing entry for the user.
+    /// Set a song as now playing on a user's player, with the playback
+    /// position in milliseconds if the player reported one.
+    ///
+    /// Replaces any other song playing on the same player. Reporting the same
+    /// song again only updates the position, keeping when it started.
     pub fn set_now_playing(
         &self,
         user_id: i32,
         song_id: i32,
         player_id: Option<&str>,
+        position_ms: Option<i64>,
     ) -> Result<(), MusicRepoError> {
         use diesel::upsert::excluded;
 
         let mut conn = self.pool.get()?;
 
-        // Use upsert to atomically replace any existing entry for this user.
-        // This avoids race conditions that can occur with DELETE + INSERT.
-        let new_entry = NewNowPlaying {
-            user_id,
-            song_id,
-            player_id: player_id.map(|s| s.to_string()),
-        };
+        let duration: i32 = songs::table
+            .find(song_id)
+            .select(songs::duration)
+            .first(&mut conn)
+            .optional()?
+            .ok_or_else(|| MusicRepoError::NotFound(format!("Song {}", song_id)))?;
+
+        let now = chrono::Utc::now().naive_utc();
+        let player_id = player_id.unwrap_or_default();
+        let expires_at = now_playing_expires_at(now, duration, position_ms);
 
+        // Upsert atomically replaces the player's entry, restarting it only
+        // when the song changed
         diesel::insert_into(now_playing::table)
-            .values(&new_entry)
-            .on_conflict(now_playing::user_id)
+            .values((
+                now_playing::user_id.eq(user_id),
+                now_playing::song_id.eq(song_id),
+                now_playing::player_id.eq(player_id),
+                now_playing::started_at.eq(now),
+                now_playing::position_ms.eq(position_ms),
+                now_playing::updated_at.eq(now),
+                now_playing::expires_at.eq(expires_at),
+            ))
+            .on_conflict((now_playing::user_id, now_playing::player_id))
             .do_update()
             .set((
+                now_playing::started_at.eq(diesel::dsl::sql::<diesel::sql_types::Timestamp>(
+                    "CASE WHEN now_playing.song_id = excluded.song_id \
+                     THEN now_playing.started_at ELSE excluded.started_at END",
+                )),
                 now_playing::song_id.eq(excluded(now_playing::song_id)),
-                now_playing::player_id.eq(excluded(now_playing::player_id)),
-                now_playing::started_at.eq(diesel::dsl::now),
-                now_playing::minutes_ago.eq(0),
+                now_playing::position_ms.eq(excluded(now_playing::position_ms)),
+                now_playing::updated_at.eq(excluded(now_playing::updated_at)),
+                now_playing::expires_at.eq(excluded(now_playing::expires_at)),
             ))
             .execute(&mut conn)?;
 
         Ok(())
     }
 
-    /// Clear the now playing entry for a user.
-    pub fn clear_now_playing(&self, user_id: i32) -> Result<(), MusicRepoError> {
+    /// Clear a song from a user's player once it has been played.
+    ///
+    /// Does nothing if the player has moved on to another song.
+    pub fn clear_now_playing(
+        &self,
+        user_id: i32,
+        song_id: i32,
+        player_id: Option<&str>,
+    ) -> Result<(), MusicRepoError> {
         let mut conn = self.pool.get()?;
 
-        diesel::delete(now_playing::table.filter(now_playing::user_id.eq(user_id)))
-            .execute(&mut conn)?;
+        diesel::delete(
+            now_playing::table
+                .filter(now_playing::user_id.eq(user_id))
+                .filter(now_playing::song_id.eq(song_id))
+                .filter(now_playing::player_id.eq(player_id.unwrap_or_default())),
+        )
+        .execute(&mut conn)?;
 
         Ok(())
     }
 
-    /// Get all currently playing songs.
+    /// Get all currently playing songs, dropping expired entries.
     /// Returns entries with song and user info, ordered by most recent.
     pub fn get_all_now_playing(&self) -> Result<Vec<NowPlayingEntry>, MusicRepoError> {
         let mut conn = self.pool.get()?;
 
+        let now = chrono::Utc::now().naive_utc();
+        diesel::delete(now_playing::table.filter(now_playing::expires_at.le(now)))
+            .execute(&mut conn)?;
+
         let results: Vec<(NowPlayingRow, SongRow, UserRow)> = now_playing::table
             .inner_join(songs::table.on(now_playing::song_id.eq(songs::id)))
             .inner_join(users::table.on(now_playing::user_id.eq(users::id)))
@@ -1870,17 +2076,26 @@ impl NowPlayingRepository {
             .order(now_playing::started_at.desc())
             .load(&mut conn)?;
 
-        let now = chrono::Utc::now().naive_utc();
-
         Ok(results
             .into_iter()
             .map(|(np, song, user)| {
-                let minutes_ago = (now - np.started_at).num_minutes() as i32;
+                let minutes_ago = (now - np.started_at).num_minutes().max(0) as i32;
+                // Assume playback continued since the position was reported
+                let position_ms = np.position_ms.map(|position| {
+                    let elapsed = (now - np.updated_at).num_milliseconds().max(0);
+                    let position = position + elapsed;
+                    if song.duration > 0 {
+                        position.min(i64::from(song.duration) * 1000)
+                    } else {
+                        position
+                    }
+                });
                 NowPlayingEntry {
                     song: Song::from(song),
                     username: user.username,
-                    player_id: np.player_id,
+                    player_id: Some(np.player_id).filter(|p| !p.is_empty()),
                     minutes_ago,
+                    position_ms,
                 }
             })
             .collect())
@@ -1927,14 +2142,14 @@ impl ScrobbleRepository {
         Self { pool }
     }
 
-    /// Record a scrobble (song play).
+    /// Record a scrobble (song play). Returns the time the song was played.
     pub fn scrobble(
         &self,
         user_id: i32,
         song_id: i32,
         time: Option<i64>,
         submission: bool,
-    ) -> Result<(), MusicRepoError> {
+    ) -> Result<NaiveDateTime, MusicRepoError> {
         let mut conn = self.pool.get()?;
 
         // Determine the played_at timestamp
@@ -1954,32 +2169,12 @@ impl ScrobbleRepository {
             submission,
         };
 
+        // Play counts are aggregated per user from the scrobbles themselves
         diesel::insert_into(scrobbles::table)
             .values(&new_scrobble)
             .execute(&mut conn)?;
 
-        // If this is a submission (full play), increment the song's play count
-        if submission {
-            diesel::update(songs::table.filter(songs::id.eq(song_id)))
-                .set(songs::play_count.eq(songs::play_count + 1))
-                .execute(&mut conn)?;
-
-            // Also try to increment the album's play count
-            let album_id: Option<i32> = songs::table
-                .filter(songs::id.eq(song_id))
-                .select(songs::album_id)
-                .first(&mut conn)
-                .optional()?
-                .flatten();
-
-            if let Some(aid) = album_id {
-                diesel::update(albums::table.filter(albums::id.eq(aid)))
-                    .set(albums::play_count.eq(albums::play_count + 1))
-                    .execute(&mut conn)?;
-            }
-        }
-
-        Ok(())
+        Ok(played_at)
     }
 
     /// Get recent scrobbles for a user.
@@ -2004,6 +2199,126 @@ impl ScrobbleRepository {
             .map(|(scrobble, song)| (Song::from(song), scrobble.played_at))
             .collect())
     }
+
+    /// Get a user's play count and last play time for multiple songs.
+    /// Returns a HashMap mapping song_id to the stats; unplayed songs are absent.
+    pub fn get_song_play_stats_batch(
+        &self,
+        user_id: i32,
+        song_ids: &[i32],
+    ) -> Result<std::collections::HashMap<i32, PlayStats>, MusicRepoError> {
+        use diesel::dsl::{count_star, max};
+
+        if song_ids.is_empty() {
+            return Ok(std::collections::HashMap::new());
+        }
+
+        let mut conn = self.pool.get()?;
+
+        let results: Vec<(i32, i64, Option<NaiveDateTime>)> = scrobbles::table
+            .filter(scrobbles::user_id.eq(user_id))
+            .filter(scrobbles::submission.eq(true))
+            .filter(scrobbles::song_id.eq_any(song_ids))
+            .group_by(scrobbles::song_id)
+            .select((scrobbles::song_id, count_star(), max(scrobbles::played_at)))
+            .load(&mut conn)?;
+
+        Ok(results
+            .into_iter()
+            .map(|(song_id, count, last_played)| (song_id, play_stats(count, last_played)))
+            .collect())
+    }
+
+    /// Get a user's play count and last play time for multiple albums.
+    /// Returns a HashMap mapping album_id to the stats; unplayed albums are absent.
+    pub fn get_album_play_stats_batch(
+        &self,
+        user_id: i32,
+        album_ids: &[i32],
+    ) -> Result<std::collections::HashMap<i32, PlayStats>, MusicRepoError> {
+        use diesel::dsl::{count_star, max};
+
+        if album_ids.is_empty() {
+            return Ok(std::collections::HashMap::new());
+        }
+
+        let mut conn = self.pool.get()?;
+
+        let results: Vec<(Option<i32>, i64, Option<NaiveDateTime>)> = scrobbles::table
+            .inner_join(songs::table.on(scrobbles::song_id.eq(songs::id)))
+            .filter(scrobbles::user_id.eq(user_id))
+            .filter(scrobbles::submission.eq(true))
+            .filter(songs::album_id.eq_any(album_ids))
+            .group_by(songs::album_id)
+            .select((songs::album_id, count_star(), max(scrobbles::played_at)))
+            .load(&mut conn)?;
+
+        Ok(results
+            .into_iter()
+            .filter_map(|(album_id, count, last_played)| {
+                Some((album_id?, play_stats(count, last_played)))
+            })
+            .collect())
+    }
+
+    /// Count how often other songs were played within `window_minutes` of
+    /// plays of the given songs by the same user, most often first.
+    pub fn count_session_co_occurrences(
+        &self,
+        song_ids: &[i32],
+        window_minutes: i64,
+        limit: i64,
+    ) -> Result<Vec<(i32, i64)>, MusicRepoError> {
+        if song_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut conn = self.pool.get()?;
+
+        let ids = join_ids(song_ids);
+        let rows: Vec<SongCountRow> = diesel::sql_query(format!(
+            "SELECT other.song_id AS song_id, COUNT(DISTINCT seed.id) AS cnt
+             FROM scrobbles seed
+             JOIN scrobbles other ON other.user_id = seed.user_id
+                 AND other.played_at BETWEEN datetime(seed.played_at, '-{window} minutes')
+                     AND datetime(seed.played_at, '+{window} minutes')
+             WHERE seed.song_id IN ({ids}) AND seed.submission AND other.submission
+                 AND other.song_id NOT IN ({ids})
+             GROUP BY other.song_id
+             ORDER BY cnt DESC
+             LIMIT ?",
+            window = window_minutes,
+        ))
+        .bind::<diesel::sql_types::BigInt, _>(limit)
+        .load(&mut conn)?;
+
+        Ok(rows.into_iter().map(|r| (r.song_id, r.cnt)).collect())
+    }
+}
+
+/// Build play stats from a scrobble count and the latest play time.
+fn play_stats(count: i64, last_played: Option<NaiveDateTime>) -> PlayStats {
+    PlayStats {
+        play_count: i32::try_from(count).unwrap_or(i32::MAX),
+        last_played,
+    }
+}
+
+/// A song with a count, from raw SQL aggregate queries.
+#[derive(QueryableByName)]
+struct SongCountRow {
+    #[diesel(sql_type = diesel::sql_types::Integer)]
+    song_id: i32,
+    #[diesel(sql_type = diesel::sql_types::BigInt)]
+    cnt: i64,
+}
+
+/// Format IDs as a comma-separated list for an SQL `IN` clause.
+fn join_ids(ids: &[i32]) -> String {
+    ids.iter()
+        .map(|id| id.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
 }
 
 // ============================================================================
@@ -2182,6 +2497,30 @@ impl RatingRepository {
         Ok(result)
     }
 
+    /// Get a user's ratings for multiple songs in a single query.
+    pub fn get_song_ratings_batch(
+        &self,
+        user_id: i32,
+        song_ids: &[i32],
+    ) -> Result<std::collections::HashMap<i32, i32>, MusicRepoError> {
+        if song_ids.is_empty() {
+            return Ok(std::collections::HashMap::new());
+        }
+
+        let mut conn = self.pool.get()?;
+
+        let results: Vec<(i32, i32)> = user_ratings::table
+            .filter(user_ratings::user_id.eq(user_id))
+            .filter(user_ratings::song_id.eq_any(song_ids))
+            .select((
+                user_ratings::song_id.assume_not_null(),
+                user_ratings::rating,
+            ))
+            .load(&mut conn)?;
+
+        Ok(results.into_iter().collect())
+    }
+
     /// Get rating for an artist.
     pub fn get_artist_rating(
         &self,
@@ -2245,6 +2584,9 @@ pub struct PlaylistRow {
     pub duration: i32,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
+    pub source_path: Option<String>,
+    pub rules: Option<String>,
+    pub rules_evaluated_at: Option<NaiveDateTime>,
 }
 
 /// Data for inserting a new playlist.
@@ -2255,6 +2597,8 @@ pub struct NewPlaylist<'a> {
     pub name: &'a str,
     pub comment: Option<&'a str>,
     pub public: bool,
+    pub source_path: Option<&'a str>,
+    pub rules: Option<&'a str>,
 }
 
 /// Database row representation for playlist songs.
@@ -2290,6 +2634,28 @@ pub struct Playlist {
     pub duration: i32,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
+    /// Whether this is a smart playlist, whose songs are evaluated from rules.
+    pub smart: bool,
+    /// When a smart playlist's rules were last evaluated.
+    pub evaluated_at: Option<NaiveDateTime>,
+}
+
+impl Playlist {
+    fn from_row(row: PlaylistRow, owner: String) -> Self {
+        Self {
+            id: row.id,
+            name: row.name,
+            comment: row.comment,
+            owner,
+            public: row.public,
+            song_count: row.song_count,
+            duration: row.duration,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+            smart: row.rules.is_some(),
+            evaluated_at: row.rules_evaluated_at,
+        }
+    }
 }
 
 /// Repository for playlist database operations.
@@ -2304,6 +2670,35 @@ impl PlaylistRepository {
         Self { pool }
     }
 
+    /// Count the playlists other songs share with any of the given songs,
+    /// most shared first.
+    pub fn count_co_occurrences(
+        &self,
+        song_ids: &[i32],
+        limit: i64,
+    ) -> Result<Vec<(i32, i64)>, MusicRepoError> {
+        if song_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut conn = self.pool.get()?;
+
+        let ids = join_ids(song_ids);
+        let rows: Vec<SongCountRow> = diesel::sql_query(format!(
+            "SELECT other.song_id AS song_id, COUNT(DISTINCT other.playlist_id) AS cnt
+             FROM playlist_songs seed
+             JOIN playlist_songs other ON other.playlist_id = seed.playlist_id
+             WHERE seed.song_id IN ({ids}) AND other.song_id NOT IN ({ids})
+             GROUP BY other.song_id
+             ORDER BY cnt DESC
+             LIMIT ?"
+        ))
+        .bind::<diesel::sql_types::BigInt, _>(limit)
+        .load(&mut conn)?;
+
+        Ok(rows.into_iter().map(|r| (r.song_id, r.cnt)).collect())
+    }
+
     /// Get all playlists for a user (including public playlists from others).
     pub fn get_playlists(
         &self,
@@ -2326,17 +2721,7 @@ impl PlaylistRepository {
 
         Ok(results
             .into_iter()
-            .map(|(p, u)| Playlist {
-                id: p.id,
-                name: p.name,
-                comment: p.comment,
-                owner: u.username,
-                public: p.public,
-                song_count: p.song_count,
-                duration: p.duration,
-                created_at: p.created_at,
-                updated_at: p.updated_at,
-            })
+            .map(|(p, u)| Playlist::from_row(p, u.username))
             .collect())
     }
 
@@ -2351,17 +2736,7 @@ impl PlaylistRepository {
             .first(&mut conn)
             .optional()?;
 
-        Ok(result.map(|(p, u)| Playlist {
-            id: p.id,
-            name: p.name,
-            comment: p.comment,
-            owner: u.username,
-            public: p.public,
-            song_count: p.song_count,
-            duration: p.duration,
-            created_at: p.created_at,
-            updated_at: p.updated_at,
-        }))
+        Ok(result.map(|(p, u)| Playlist::from_row(p, u.username)))
     }
 
     /// Get songs in a playlist, ordered by position.
@@ -2420,6 +2795,8 @@ impl PlaylistRepository {
             name,
             comment,
             public: false,
+            source_path: None,
+            rules: None,
         };
 
         diesel::insert_into(playlists::table)
@@ -2566,20 +2943,326 @@ impl PlaylistRepository {
         Ok(deleted > 0)
     }
 
-    /// Check if user owns a playlist.
-    pub fn is_owner(&self, user_id: i32, playlist_id: i32) -> Result<bool, MusicRepoError> {
+    /// Create or update a playlist imported from a playlist file.
+    ///
+    /// Playlists are keyed by their source file path, so rescans replace the
+    /// songs of the existing playlist instead of creating duplicates.
+    /// Returns true if a new playlist was created.
+    pub fn sync_imported_playlist(
+        &self,
+        user_id: i32,
+        source_path: &str,
+        name: &str,
+        public: bool,
+        song_ids: &[i32],
+    ) -> Result<bool, MusicRepoError> {
         let mut conn = self.pool.get()?;
 
-        let owner_id: Option<i32> = playlists::table
-            .filter(playlists::id.eq(playlist_id))
-            .select(playlists::user_id)
-            .first(&mut conn)
-            .optional()?;
+        conn.transaction(|conn| {
+            let existing: Option<i32> = playlists::table
+                .filter(playlists::source_path.eq(source_path))
+                .select(playlists::id)
+                .first(conn)
+                .optional()?;
 
-        Ok(owner_id == Some(user_id))
-    }
+            let (playlist_id, created) = match existing {
+                Some(id) => {
+                    diesel::update(playlists::table.filter(playlists::id.eq(id)))
+                        .set((
+                            playlists::user_id.eq(user_id),
+                            playlists::name.eq(name),
+                            playlists::public.eq(public),
+                        ))
+                        .execute(conn)?;
+                    diesel::delete(
+                        playlist_songs::table.filter(playlist_songs::playlist_id.eq(id)),
+                    )
+                    .execute(conn)?;
+                    (id, false)
+                }
+                None => {
+                    let new_playlist = NewPlaylist {
+                        user_id,
+                        name,
+                        comment: None,
+                        public,
+                        source_path: Some(source_path),
+                        rules: None,
+                    };
+                    diesel::insert_into(playlists::table)
+                        .values(&new_playlist)
+                        .execute(conn)?;
+                    let id = playlists::table
+                        .filter(playlists::source_path.eq(source_path))
+                        .select(playlists::id)
+                        .first(conn)?;
+                    (id, true)
+                }
+            };
 
-    /// Helper to renumber positions after removal.
+            for (position, song_id) in (0..).zip(song_ids.iter()) {
+                let new_song = NewPlaylistSong {
+                    playlist_id,
+                    song_id: *song_id,
+                    position,
+                };
+
+                diesel::insert_into(playlist_songs::table)
+                    .values(&new_song)
+                    .execute(conn)?;
+            }
+
+            self.update_playlist_stats(conn, playlist_id)?;
+
+            Ok(created)
+        })
+    }
+
+    /// Get the source file paths of all imported playlists.
+    pub fn find_imported_source_paths(&self) -> Result<Vec<String>, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let paths: Vec<Option<String>> = playlists::table
+            .filter(playlists::source_path.is_not_null())
+            .select(playlists::source_path)
+            .load(&mut conn)?;
+
+        Ok(paths.into_iter().flatten().collect())
+    }
+
+    /// Delete imported playlists by their source file paths.
+    pub fn delete_imported_playlists(
+        &self,
+        source_paths: &[String],
+    ) -> Result<usize, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let deleted =
+            diesel::delete(playlists::table.filter(playlists::source_path.eq_any(source_paths)))
+                .execute(&mut conn)?;
+
+        Ok(deleted)
+    }
+
+    /// Create a smart playlist from a validated JSON definition. Its songs
+    /// are evaluated when it is first accessed.
+    pub fn create_smart_playlist(
+        &self,
+        user_id: i32,
+        name: &str,
+        comment: Option<&str>,
+        public: bool,
+        definition: &str,
+    ) -> Result<Playlist, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let playlist_id = conn.transaction(|conn| {
+            let new_playlist = NewPlaylist {
+                user_id,
+                name,
+                comment,
+                public,
+                source_path: None,
+                rules: Some(definition),
+            };
+            diesel::insert_into(playlists::table)
+                .values(&new_playlist)
+                .execute(conn)?;
+
+            playlists::table
+                .select(playlists::id)
+                .order(playlists::id.desc())
+                .first::<i32>(conn)
+        })?;
+
+        self.get_playlist(playlist_id)?
+            .ok_or_else(|| MusicRepoError::NotFound("Playlist not found".to_string()))
+    }
+
+    /// Create or update a smart playlist imported from a `.nsp` file.
+    ///
+    /// Like [`Self::sync_imported_playlist`], playlists are keyed by their
+    /// source file path. The rules are evaluated again on next access.
+    /// Returns true if a new playlist was created.
+    pub fn sync_smart_playlist(
+        &self,
+        user_id: i32,
+        source_path: &str,
+        name: &str,
+        comment: Option<&str>,
+        public: bool,
+        definition: &str,
+    ) -> Result<bool, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        conn.transaction(|conn| {
+            let existing: Option<i32> = playlists::table
+                .filter(playlists::source_path.eq(source_path))
+                .select(playlists::id)
+                .first(conn)
+                .optional()?;
+
+            match existing {
+                Some(id) => {
+                    diesel::update(playlists::table.filter(playlists::id.eq(id)))
+                        .set((
+                            playlists::user_id.eq(user_id),
+                            playlists::name.eq(name),
+                            playlists::comment.eq(comment),
+                            playlists::public.eq(public),
+                            playlists::rules.eq(definition),
+                            playlists::rules_evaluated_at.eq(None::<NaiveDateTime>),
+                        ))
+                        .execute(conn)?;
+                    Ok(false)
+                }
+                None => {
+                    let new_playlist = NewPlaylist {
+                        user_id,
+                        name,
+                        comment,
+                        public,
+                        source_path: Some(source_path),
+                        rules: Some(definition),
+                    };
+                    diesel::insert_into(playlists::table)
+                        .values(&new_playlist)
+                        .execute(conn)?;
+                    Ok(true)
+                }
+            }
+        })
+    }
+
+    /// Evaluate the rules of a smart playlist again if its songs are older
+    /// than `ttl`. Returns true if the playlist was evaluated.
+    pub fn refresh_smart_playlist(
+        &self,
+        playlist_id: i32,
+        ttl: chrono::TimeDelta,
+    ) -> Result<bool, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let rows: Vec<PlaylistRow> = playlists::table
+            .filter(playlists::id.eq(playlist_id))
+            .select(PlaylistRow::as_select())
+            .load(&mut conn)?;
+
+        self.refresh_stale_smart_playlists(&mut conn, rows, ttl)
+            .map(|refreshed| refreshed > 0)
+    }
+
+    /// Evaluate the rules of the smart playlists a user can see (their own
+    /// and public ones) whose songs are older than `ttl`.
+    /// Returns the number of playlists evaluated.
+    pub fn refresh_smart_playlists_for_user(
+        &self,
+        user_id: i32,
+        ttl: chrono::TimeDelta,
+    ) -> Result<usize, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let visible: Vec<PlaylistRow> = playlists::table
+            .filter(playlists::rules.is_not_null())
+            .filter(
+                playlists::user_id
+                    .eq(user_id)
+                    .or(playlists::public.eq(true)),
+            )
+            .select(PlaylistRow::as_select())
+            .load(&mut conn)?;
+
+        self.refresh_stale_smart_playlists(&mut conn, visible, ttl)
+    }
+
+    /// Evaluate the rules of the given playlists that are smart playlists
+    /// with songs older than `ttl`.
+    fn refresh_stale_smart_playlists(
+        &self,
+        conn: &mut diesel::SqliteConnection,
+        playlists: Vec<PlaylistRow>,
+        ttl: chrono::TimeDelta,
+    ) -> Result<usize, MusicRepoError> {
+        let now = chrono::Utc::now().naive_utc();
+        let mut refreshed = 0;
+
+        for playlist in playlists {
+            let Some(definition) = &playlist.rules else {
+                continue;
+            };
+            if playlist
+                .rules_evaluated_at
+                .is_some_and(|evaluated| evaluated + ttl > now)
+            {
+                continue;
+            }
+
+            // Definitions are validated before they are stored
+            let song_ids = match SmartPlaylistRules::parse(definition) {
+                Ok(rules) => evaluate_smart_playlist(conn, &rules, playlist.user_id, now)?,
+                Err(e) => {
+                    tracing::warn!("Invalid rules in smart playlist {}: {}", playlist.id, e);
+                    Vec::new()
+                }
+            };
+
+            conn.transaction(|conn| {
+                let current: Vec<i32> = playlist_songs::table
+                    .filter(playlist_songs::playlist_id.eq(playlist.id))
+                    .order(playlist_songs::position.asc())
+                    .select(playlist_songs::song_id)
+                    .load(conn)?;
+
+                // Leave unchanged playlists alone so their change time stays put
+                if current != song_ids || playlist.rules_evaluated_at.is_none() {
+                    diesel::delete(
+                        playlist_songs::table.filter(playlist_songs::playlist_id.eq(playlist.id)),
+                    )
+                    .execute(conn)?;
+
+                    let new_songs: Vec<NewPlaylistSong> = (0..)
+                        .zip(song_ids.iter())
+                        .map(|(position, song_id)| NewPlaylistSong {
+                            playlist_id: playlist.id,
+                            song_id: *song_id,
+                            position,
+                        })
+                        .collect();
+                    for chunk in new_songs.chunks(500) {
+                        diesel::insert_into(playlist_songs::table)
+                            .values(chunk)
+                            .execute(conn)?;
+                    }
+
+                    self.update_playlist_stats(conn, playlist.id)?;
+                }
+
+                diesel::update(playlists::table.filter(playlists::id.eq(playlist.id)))
+                    .set(playlists::rules_evaluated_at.eq(now))
+                    .execute(conn)?;
+
+                Ok::<_, MusicRepoError>(())
+            })?;
+            refreshed += 1;
+        }
+
+        Ok(refreshed)
+    }
+
+    /// Check if user owns a playlist.
+    pub fn is_owner(&self, user_id: i32, playlist_id: i32) -> Result<bool, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let owner_id: Option<i32> = playlists::table
+            .filter(playlists::id.eq(playlist_id))
+            .select(playlists::user_id)
+            .first(&mut conn)
+            .optional()?;
+
+        Ok(owner_id == Some(user_id))
+    }
+
+    /// Helper to renumber positions after removal.
     fn renumber_positions(
         &self,
         conn: &mut diesel::SqliteConnection,
@@ -2824,3 +3507,2733 @@ impl PlayQueueRepository {
         Ok(())
     }
 }
+
+// ============================================================================
+// Lyrics Repository
+// ============================================================================
+
+use crate::db::schema::lyrics;
+use crate::scanner::lyrics::ExtractedLyrics;
+
+/// Database row representation for lyrics.
+#[derive(Debug, Clone, Queryable, Selectable)]
+#[diesel(table_name = lyrics)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct LyricsRow {
+    pub id: i32,
+    pub song_id: i32,
+    pub lang: Option<String>,
+    pub description: Option<String>,
+    pub synced: bool,
+    pub text: String,
+    pub created_at: NaiveDateTime,
+}
+
+impl From<LyricsRow> for ExtractedLyrics {
+    fn from(row: LyricsRow) -> Self {
+        Self {
+            text: row.text,
+            synced: row.synced,
+            lang: row.lang,
+            description: row.description,
+        }
+    }
+}
+
+/// Data for inserting lyrics for a song.
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = lyrics)]
+pub struct NewLyrics<'a> {
+    pub song_id: i32,
+    pub lang: Option<&'a str>,
+    pub description: Option<&'a str>,
+    pub synced: bool,
+    pub text: &'a str,
+}
+
+/// Repository for lyrics indexed at scan time.
+#[derive(Clone)]
+pub struct LyricsRepository {
+    pool: DbPool,
+}
+
+impl LyricsRepository {
+    /// Create a new lyrics repository.
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    /// Find all lyrics stored for a song.
+    pub fn find_by_song_id(&self, song_id: i32) -> Result<Vec<LyricsRow>, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let results = lyrics::table
+            .filter(lyrics::song_id.eq(song_id))
+            .select(LyricsRow::as_select())
+            .order(lyrics::id.asc())
+            .load(&mut conn)?;
+
+        Ok(results)
+    }
+
+    /// Find the first song with lyrics matching the given artist and title.
+    /// Matching is case-insensitive; at least one of artist or title must be given.
+    pub fn find_by_artist_and_title(
+        &self,
+        artist: Option<&str>,
+        title: Option<&str>,
+    ) -> Result<Option<(Song, Vec<LyricsRow>)>, MusicRepoError> {
+        if artist.is_none() && title.is_none() {
+            return Ok(None);
+        }
+
+        let mut conn = self.pool.get()?;
+
+        let mut query = songs::table
+            .filter(diesel::dsl::exists(
+                lyrics::table.filter(lyrics::song_id.eq(songs::id)),
+            ))
+            .into_boxed();
+        if let Some(title) = title {
+            query = query.filter(songs::title.like(escape_like(title)).escape('\\'));
+        }
+        if let Some(artist) = artist {
+            query = query.filter(songs::artist_name.like(escape_like(artist)).escape('\\'));
+        }
+
+        let song: Option<SongRow> = query
+            .select(SongRow::as_select())
+            .order(songs::id.asc())
+            .first(&mut conn)
+            .optional()?;
+
+        let Some(song) = song else {
+            return Ok(None);
+        };
+
+        let entries = lyrics::table
+            .filter(lyrics::song_id.eq(song.id))
+            .select(LyricsRow::as_select())
+            .order(lyrics::id.asc())
+            .load(&mut conn)?;
+
+        Ok(Some((Song::from(song), entries)))
+    }
+
+    /// Search songs whose lyrics contain the query text, with pagination.
+    pub fn search_songs(
+        &self,
+        query: &str,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<Song>, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let pattern = format!("%{}%", escape_like(query));
+        let results = songs::table
+            .filter(diesel::dsl::exists(
+                lyrics::table
+                    .filter(lyrics::song_id.eq(songs::id))
+                    .filter(lyrics::text.like(&pattern).escape('\\')),
+            ))
+            .select(SongRow::as_select())
+            .order(songs::title.asc())
+            .offset(offset)
+            .limit(limit)
+            .load(&mut conn)?;
+
+        Ok(results.into_iter().map(Song::from).collect())
+    }
+
+    /// Replace the stored lyrics for a song.
+    /// Takes a connection so the scanner can run it inside its batch transaction.
+    pub fn replace_for_song(
+        conn: &mut diesel::SqliteConnection,
+        song_id: i32,
+        entries: &[NewLyrics<'_>],
+    ) -> QueryResult<()> {
+        diesel::delete(lyrics::table.filter(lyrics::song_id.eq(song_id))).execute(conn)?;
+
+        if !entries.is_empty() {
+            diesel::insert_into(lyrics::table)
+                .values(entries)
+                .execute(conn)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Escape LIKE wildcards so user input is matched literally (with `\` as escape).
+fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+// ============================================================================
+// Scan History Repository
+// ============================================================================
+
+use crate::db::schema::{scan_errors, scan_runs};
+use crate::models::scan::{ScanRun, ScanRunError};
+use crate::scanner::{SCAN_HEARTBEAT_INTERVAL, ScanFileError, ScanResult};
+
+/// Check whether a process is still running.
+///
+/// Without `/proc` every process is assumed to be running, leaving stale runs
+/// to be found by their heartbeat.
+fn process_alive(pid: i32) -> bool {
+    let proc = std::path::Path::new("/proc");
+    !proc.is_dir() || proc.join(pid.to_string()).exists()
+}
+
+/// Database row representation for scan runs.
+#[derive(Debug, Clone, Queryable, Selectable)]
+#[diesel(table_name = scan_runs)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct ScanRunRow {
+    pub id: i32,
+    pub started_at: NaiveDateTime,
+    pub finished_at: Option<NaiveDateTime>,
+    pub mode: String,
+    pub trigger: String,
+    pub status: String,
+    pub error: Option<String>,
+    pub tracks_found: i32,
+    pub tracks_added: i32,
+    pub tracks_updated: i32,
+    pub tracks_skipped: i32,
+    pub tracks_removed: i32,
+    pub tracks_moved: i32,
+    pub tracks_failed: i32,
+    pub artists_added: i32,
+    pub albums_added: i32,
+    pub cover_art_saved: i32,
+    pub playlists_imported: i32,
+}
+
+impl From<ScanRunRow> for ScanRun {
+    fn from(row: ScanRunRow) -> Self {
+        Self {
+            id: row.id,
+            started_at: row.started_at,
+            finished_at: row.finished_at,
+            mode: row.mode,
+            trigger: row.trigger,
+            status: row.status,
+            error: row.error,
+            tracks_found: row.tracks_found,
+            tracks_added: row.tracks_added,
+            tracks_updated: row.tracks_updated,
+            tracks_skipped: row.tracks_skipped,
+            tracks_removed: row.tracks_removed,
+            tracks_moved: row.tracks_moved,
+            tracks_failed: row.tracks_failed,
+            artists_added: row.artists_added,
+            albums_added: row.albums_added,
+            cover_art_saved: row.cover_art_saved,
+            playlists_imported: row.playlists_imported,
+        }
+    }
+}
+
+/// Database row representation for scan errors.
+#[derive(Debug, Clone, Queryable, Selectable)]
+#[diesel(table_name = scan_errors)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct ScanErrorRow {
+    pub id: i32,
+    pub scan_run_id: i32,
+    pub path: String,
+    pub error: String,
+    pub created_at: NaiveDateTime,
+}
+
+impl From<ScanErrorRow> for ScanRunError {
+    fn from(row: ScanErrorRow) -> Self {
+        Self {
+            id: row.id,
+            scan_run_id: row.scan_run_id,
+            path: row.path,
+            error: row.error,
+            created_at: row.created_at,
+        }
+    }
+}
+
+diesel::define_sql_function! {
+    /// SQLite's `last_insert_rowid()`, the rowid of the last insert on this connection.
+    fn last_insert_rowid() -> diesel::sql_types::BigInt;
+}
+
+/// Repository for scan history operations.
+#[derive(Clone)]
+pub struct ScanHistoryRepository {
+    pool: DbPool,
+}
+
+impl ScanHistoryRepository {
+    /// Create a new scan history repository.
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    /// Record the start of a scan by this process. Returns the new scan run ID.
+    pub fn start_run(&self, mode: &str, trigger: &str) -> Result<i32, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let id = conn.transaction(|conn| {
+            diesel::insert_into(scan_runs::table)
+                .values((
+                    scan_runs::mode.eq(mode),
+                    scan_runs::trigger.eq(trigger),
+                    scan_runs::pid.eq(std::process::id() as i32),
+                    scan_runs::heartbeat_at.eq(diesel::dsl::now),
+                ))
+                .execute(conn)?;
+
+            diesel::select(last_insert_rowid()).get_result::<i64>(conn)
+        })?;
+
+        Ok(id as i32)
+    }
+
+    /// Record that a scan run is still working.
+    pub fn heartbeat(&self, run_id: i32) -> Result<(), MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        diesel::update(scan_runs::table.filter(scan_runs::id.eq(run_id)))
+            .set(scan_runs::heartbeat_at.eq(diesel::dsl::now))
+            .execute(&mut conn)?;
+
+        Ok(())
+    }
+
+    /// Mark runs still recorded as running as aborted when their process has
+    /// exited or they have missed several heartbeats.
+    ///
+    /// Used at startup to close runs left behind by a process that exited
+    /// mid-scan, without touching scans running in other processes.
+    /// Returns the number of runs marked.
+    pub fn abort_stale_runs(&self) -> Result<usize, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let stale_before = chrono::Utc::now().naive_utc()
+            - chrono::TimeDelta::from_std(SCAN_HEARTBEAT_INTERVAL * 4)
+                .unwrap_or(chrono::TimeDelta::MAX);
+        let running: Vec<(i32, Option<i32>, NaiveDateTime, Option<NaiveDateTime>)> =
+            scan_runs::table
+                .filter(scan_runs::status.eq("running"))
+                .select((
+                    scan_runs::id,
+                    scan_runs::pid,
+                    scan_runs::started_at,
+                    scan_runs::heartbeat_at,
+                ))
+                .load(&mut conn)?;
+        let stale: Vec<i32> = running
+            .into_iter()
+            .filter(|(_, pid, started_at, heartbeat_at)| {
+                !pid.is_some_and(process_alive)
+                    || heartbeat_at.unwrap_or(*started_at) < stale_before
+            })
+            .map(|(id, ..)| id)
+            .collect();
+        if stale.is_empty() {
+            return Ok(0);
+        }
+
+        let count = diesel::update(scan_runs::table.filter(scan_runs::id.eq_any(&stale)))
+            .set((
+                scan_runs::finished_at.eq(diesel::dsl::now),
+                scan_runs::status.eq("aborted"),
+                scan_runs::error.eq("Scan was interrupted before it finished"),
+            ))
+            .execute(&mut conn)?;
+
+        Ok(count)
+    }
+
+    /// Record the outcome of a scan and the files that failed.
+    ///
+    /// A scan with an `error` is marked as failed, otherwise as completed.
+    pub fn finish_run(
+        &self,
+        run_id: i32,
+        result: &ScanResult,
+        error: Option<&str>,
+    ) -> Result<(), MusicRepoError> {
+        let mut conn = self.pool.get()?;
+        let status = if error.is_some() {
+            "failed"
+        } else if result.cancelled {
+            "cancelled"
+        } else {
+            "completed"
+        };
+
+        conn.transaction(|conn| {
+            diesel::update(scan_runs::table.filter(scan_runs::id.eq(run_id)))
+                .set((
+                    scan_runs::finished_at.eq(diesel::dsl::now),
+                    scan_runs::status.eq(status),
+                    scan_runs::error.eq(error),
+                    scan_runs::tracks_found.eq(result.tracks_found as i32),
+                    scan_runs::tracks_added.eq(result.tracks_added as i32),
+                    scan_runs::tracks_updated.eq(result.tracks_updated as i32),
+                    scan_runs::tracks_skipped.eq(result.tracks_skipped as i32),
+                    scan_runs::tracks_removed.eq(result.tracks_removed as i32),
+                    scan_runs::tracks_moved.eq(result.tracks_moved as i32),
+                    scan_runs::tracks_failed.eq(result.tracks_failed as i32),
+                    scan_runs::artists_added.eq(result.artists_added as i32),
+                    scan_runs::albums_added.eq(result.albums_added as i32),
+                    scan_runs::cover_art_saved.eq(result.cover_art_saved as i32),
+                    scan_runs::playlists_imported.eq(result.playlists_imported as i32),
+                ))
+                .execute(conn)?;
+
+            for chunk in result.errors.chunks(100) {
+                let rows: Vec<_> = chunk
+                    .iter()
+                    .map(|ScanFileError { path, error }| {
+                        (
+                            scan_errors::scan_run_id.eq(run_id),
+                            scan_errors::path.eq(path),
+                            scan_errors::error.eq(error),
+                        )
+                    })
+                    .collect();
+                diesel::insert_into(scan_errors::table)
+                    .values(&rows)
+                    .execute(conn)?;
+            }
+
+            Ok(())
+        })
+    }
+
+    /// Get the most recent scan runs, newest first.
+    pub fn find_recent(&self, limit: i64) -> Result<Vec<ScanRun>, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let results = scan_runs::table
+            .order(scan_runs::id.desc())
+            .limit(limit)
+            .select(ScanRunRow::as_select())
+            .load(&mut conn)?;
+
+        Ok(results.into_iter().map(ScanRun::from).collect())
+    }
+
+    /// Find a scan run by ID.
+    pub fn find_by_id(&self, run_id: i32) -> Result<Option<ScanRun>, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let result = scan_runs::table
+            .filter(scan_runs::id.eq(run_id))
+            .select(ScanRunRow::as_select())
+            .first(&mut conn)
+            .optional()?;
+
+        Ok(result.map(ScanRun::from))
+    }
+
+    /// Get the files that failed during a scan run.
+    pub fn find_errors(&self, run_id: i32) -> Result<Vec<ScanRunError>, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let results = scan_errors::table
+            .filter(scan_errors::scan_run_id.eq(run_id))
+            .order(scan_errors::id.asc())
+            .select(ScanErrorRow::as_select())
+            .load(&mut conn)?;
+
+        Ok(results.into_iter().map(ScanRunError::from).collect())
+    }
+}
+
+// ============================================================================
+// Scanned Directory Repository
+// ============================================================================
+
+use crate::db::schema::scanned_directories;
+
+/// Repository for the directory modification times recorded by scans.
+#[derive(Clone)]
+pub struct ScannedDirectoryRepository {
+    pool: DbPool,
+}
+
+impl ScannedDirectoryRepository {
+    /// Create a new scanned directory repository.
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    /// Get the recorded directories of a music folder as path -> modification time.
+    pub fn find_by_folder(
+        &self,
+        folder_id: i32,
+    ) -> Result<std::collections::HashMap<String, i64>, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let results: Vec<(String, i64)> = scanned_directories::table
+            .filter(scanned_directories::music_folder_id.eq(folder_id))
+            .select((scanned_directories::path, scanned_directories::modified_at))
+            .load(&mut conn)?;
+
+        Ok(results.into_iter().collect())
+    }
+
+    /// Replace the recorded directories of a music folder.
+    pub fn replace_for_folder(
+        &self,
+        folder_id: i32,
+        directories: &[(String, i64)],
+    ) -> Result<(), MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        conn.transaction(|conn| {
+            diesel::delete(
+                scanned_directories::table
+                    .filter(scanned_directories::music_folder_id.eq(folder_id)),
+            )
+            .execute(conn)?;
+
+            for chunk in directories.chunks(100) {
+                let rows: Vec<_> = chunk
+                    .iter()
+                    .map(|(path, modified_at)| {
+                        (
+                            scanned_directories::music_folder_id.eq(folder_id),
+                            scanned_directories::path.eq(path),
+                            scanned_directories::modified_at.eq(modified_at),
+                        )
+                    })
+                    .collect();
+                diesel::insert_into(scanned_directories::table)
+                    .values(&rows)
+                    .execute(conn)?;
+            }
+
+            Ok(())
+        })
+    }
+}
+
+// ============================================================================
+// Directory Repository
+// ============================================================================
+
+use crate::db::schema::directories;
+use crate::models::music::Directory;
+
+/// Database row for a directory.
+#[derive(Debug, Clone, Queryable, Selectable)]
+#[diesel(table_name = directories)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct DirectoryRow {
+    pub id: i32,
+    pub music_folder_id: i32,
+    pub parent_id: Option<i32>,
+    pub path: String,
+    pub name: String,
+    pub cover_art: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+impl From<DirectoryRow> for Directory {
+    fn from(row: DirectoryRow) -> Self {
+        Self {
+            id: row.id,
+            music_folder_id: row.music_folder_id,
+            parent_id: row.parent_id,
+            path: row.path,
+            name: row.name,
+            cover_art: row.cover_art,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        }
+    }
+}
+
+/// A directory found by a scan, to be synced into the directory tree.
+#[derive(Debug, Clone)]
+pub struct NewDirectory {
+    /// Path relative to the music folder.
+    pub path: String,
+    /// Path of the parent directory, or `None` for top-level directories.
+    pub parent_path: Option<String>,
+    pub name: String,
+    pub cover_art: Option<String>,
+}
+
+/// Repository for the on-disk directory tree of each music folder.
+#[derive(Clone)]
+pub struct DirectoryRepository {
+    pool: DbPool,
+}
+
+impl DirectoryRepository {
+    /// Create a new directory repository.
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    /// Find a directory by ID.
+    pub fn find_by_id(&self, id: i32) -> Result<Option<Directory>, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let result = directories::table
+            .find(id)
+            .select(DirectoryRow::as_select())
+            .first(&mut conn)
+            .optional()?;
+
+        Ok(result.map(Directory::from))
+    }
+
+    /// Find the top-level directories, optionally limited to one music folder.
+    pub fn find_top_level(&self, folder_id: Option<i32>) -> Result<Vec<Directory>, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let mut query = directories::table
+            .filter(directories::parent_id.is_null())
+            .select(DirectoryRow::as_select())
+            .order(directories::name.asc())
+            .into_boxed();
+
+        if let Some(folder_id) = folder_id {
+            query = query.filter(directories::music_folder_id.eq(folder_id));
+        }
+
+        let results = query.load(&mut conn)?;
+        Ok(results.into_iter().map(Directory::from).collect())
+    }
+
+    /// Find the subdirectories of a directory.
+    pub fn find_children(&self, parent_id: i32) -> Result<Vec<Directory>, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let results = directories::table
+            .filter(directories::parent_id.eq(parent_id))
+            .select(DirectoryRow::as_select())
+            .order(directories::name.asc())
+            .load(&mut conn)?;
+
+        Ok(results.into_iter().map(Directory::from).collect())
+    }
+
+    /// Get the most recent time a directory was added or changed.
+    pub fn get_last_modified(&self) -> Result<Option<NaiveDateTime>, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let result = directories::table
+            .select(diesel::dsl::max(directories::updated_at))
+            .first(&mut conn)?;
+
+        Ok(result)
+    }
+
+    /// Sync the directory tree of a music folder with the given directories.
+    ///
+    /// Directories keep their IDs while their path exists, and directories
+    /// not in the list are removed. Parents must come before their children.
+    pub fn sync_folder(
+        &self,
+        folder_id: i32,
+        new_directories: &[NewDirectory],
+    ) -> Result<(), MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        conn.transaction(|conn| {
+            let existing: Vec<(i32, String, Option<i32>, Option<String>)> = directories::table
+                .filter(directories::music_folder_id.eq(folder_id))
+                .select((
+                    directories::id,
+                    directories::path,
+                    directories::parent_id,
+                    directories::cover_art,
+                ))
+                .load(conn)?;
+            let existing: std::collections::HashMap<String, (i32, Option<i32>, Option<String>)> =
+                existing
+                    .into_iter()
+                    .map(|(id, path, parent_id, cover_art)| (path, (id, parent_id, cover_art)))
+                    .collect();
+
+            let now = chrono::Utc::now().naive_utc();
+            let mut ids: std::collections::HashMap<&str, i32> = std::collections::HashMap::new();
+
+            for dir in new_directories {
+                let parent_id = dir
+                    .parent_path
+                    .as_deref()
+                    .and_then(|parent| ids.get(parent).copied());
+
+                let id = match existing.get(&dir.path) {
+                    Some((id, old_parent_id, old_cover_art)) => {
+                        if *old_parent_id != parent_id || *old_cover_art != dir.cover_art {
+                            diesel::update(directories::table.find(*id))
+                                .set((
+                                    directories::parent_id.eq(parent_id),
+                                    directories::cover_art.eq(&dir.cover_art),
+                                    directories::updated_at.eq(now),
+                                ))
+                                .execute(conn)?;
+                        }
+                        *id
+                    }
+                    None => {
+                        diesel::insert_into(directories::table)
+                            .values((
+                                directories::music_folder_id.eq(folder_id),
+                                directories::parent_id.eq(parent_id),
+                                directories::path.eq(&dir.path),
+                                directories::name.eq(&dir.name),
+                                directories::cover_art.eq(&dir.cover_art),
+                                directories::created_at.eq(now),
+                                directories::updated_at.eq(now),
+                            ))
+                            .execute(conn)?;
+
+                        directories::table
+                            .filter(directories::music_folder_id.eq(folder_id))
+                            .filter(directories::path.eq(&dir.path))
+                            .select(directories::id)
+                            .first(conn)?
+                    }
+                };
+                ids.insert(&dir.path, id);
+            }
+
+            let stale: Vec<i32> = existing
+                .iter()
+                .filter(|(path, _)| !ids.contains_key(path.as_str()))
+                .map(|(_, (id, _, _))| *id)
+                .collect();
+            for chunk in stale.chunks(500) {
+                diesel::delete(directories::table.filter(directories::id.eq_any(chunk)))
+                    .execute(conn)?;
+            }
+
+            Ok(())
+        })
+    }
+}
+
+// ============================================================================
+// Metadata Cache Repository
+// ============================================================================
+
+use crate::db::schema::{album_metadata, artist_metadata};
+use crate::models::metadata::{AlbumMetadata, ArtistMetadata, CachedMetadata};
+
+/// Database row for cached artist metadata.
+#[derive(Debug, Clone, Queryable, Selectable)]
+#[diesel(table_name = artist_metadata)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct ArtistMetadataRow {
+    pub artist_id: i32,
+    pub agent: String,
+    pub biography: Option<String>,
+    pub musicbrainz_id: Option<String>,
+    pub last_fm_url: Option<String>,
+    pub small_image_url: Option<String>,
+    pub medium_image_url: Option<String>,
+    pub large_image_url: Option<String>,
+    pub similar_artists: String,
+    pub fetched_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+}
+
+impl From<ArtistMetadataRow> for CachedMetadata<ArtistMetadata> {
+    fn from(row: ArtistMetadataRow) -> Self {
+        Self {
+            value: ArtistMetadata {
+                biography: row.biography,
+                musicbrainz_id: row.musicbrainz_id,
+                last_fm_url: row.last_fm_url,
+                small_image_url: row.small_image_url,
+                medium_image_url: row.medium_image_url,
+                large_image_url: row.large_image_url,
+                similar_artists: serde_json::from_str(&row.similar_artists).unwrap_or_default(),
+            },
+            fetched_at: row.fetched_at,
+            expires_at: row.expires_at,
+        }
+    }
+}
+
+/// Database row for cached album metadata.
+#[derive(Debug, Clone, Queryable, Selectable)]
+#[diesel(table_name = album_metadata)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct AlbumMetadataRow {
+    pub album_id: i32,
+    pub agent: String,
+    pub notes: Option<String>,
+    pub musicbrainz_id: Option<String>,
+    pub last_fm_url: Option<String>,
+    pub small_image_url: Option<String>,
+    pub medium_image_url: Option<String>,
+    pub large_image_url: Option<String>,
+    pub fetched_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+}
+
+impl From<AlbumMetadataRow> for CachedMetadata<AlbumMetadata> {
+    fn from(row: AlbumMetadataRow) -> Self {
+        Self {
+            value: AlbumMetadata {
+                notes: row.notes,
+                musicbrainz_id: row.musicbrainz_id,
+                last_fm_url: row.last_fm_url,
+                small_image_url: row.small_image_url,
+                medium_image_url: row.medium_image_url,
+                large_image_url: row.large_image_url,
+            },
+            fetched_at: row.fetched_at,
+            expires_at: row.expires_at,
+        }
+    }
+}
+
+/// Repository for metadata fetched from metadata agents.
+#[derive(Clone)]
+pub struct MetadataCacheRepository {
+    pool: DbPool,
+}
+
+impl MetadataCacheRepository {
+    /// Create a new metadata cache repository.
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    /// Get the cached metadata of an artist.
+    pub fn find_artist(
+        &self,
+        artist_id: i32,
+    ) -> Result<Option<CachedMetadata<ArtistMetadata>>, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let result = artist_metadata::table
+            .find(artist_id)
+            .select(ArtistMetadataRow::as_select())
+            .first(&mut conn)
+            .optional()?;
+
+        Ok(result.map(CachedMetadata::from))
+    }
+
+    /// Store the metadata of an artist, replacing any cached metadata.
+    pub fn save_artist(
+        &self,
+        artist_id: i32,
+        agent: &str,
+        metadata: &ArtistMetadata,
+        expires_at: NaiveDateTime,
+    ) -> Result<(), MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let similar_artists =
+            serde_json::to_string(&metadata.similar_artists).unwrap_or_else(|_| "[]".into());
+        diesel::replace_into(artist_metadata::table)
+            .values((
+                artist_metadata::artist_id.eq(artist_id),
+                artist_metadata::agent.eq(agent),
+                artist_metadata::biography.eq(&metadata.biography),
+                artist_metadata::musicbrainz_id.eq(&metadata.musicbrainz_id),
+                artist_metadata::last_fm_url.eq(&metadata.last_fm_url),
+                artist_metadata::small_image_url.eq(&metadata.small_image_url),
+                artist_metadata::medium_image_url.eq(&metadata.medium_image_url),
+                artist_metadata::large_image_url.eq(&metadata.large_image_url),
+                artist_metadata::similar_artists.eq(similar_artists),
+                artist_metadata::fetched_at.eq(chrono::Utc::now().naive_utc()),
+                artist_metadata::expires_at.eq(expires_at),
+            ))
+            .execute(&mut conn)?;
+
+        Ok(())
+    }
+
+    /// Put off the next refresh of an artist's metadata, keeping what is cached.
+    pub fn postpone_artist(
+        &self,
+        artist_id: i32,
+        agent: &str,
+        expires_at: NaiveDateTime,
+    ) -> Result<(), MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        diesel::insert_into(artist_metadata::table)
+            .values((
+                artist_metadata::artist_id.eq(artist_id),
+                artist_metadata::agent.eq(agent),
+                artist_metadata::expires_at.eq(expires_at),
+            ))
+            .on_conflict(artist_metadata::artist_id)
+            .do_update()
+            .set(artist_metadata::expires_at.eq(expires_at))
+            .execute(&mut conn)?;
+
+        Ok(())
+    }
+
+    /// Get the cached metadata of an album.
+    pub fn find_album(
+        &self,
+        album_id: i32,
+    ) -> Result<Option<CachedMetadata<AlbumMetadata>>, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let result = album_metadata::table
+            .find(album_id)
+            .select(AlbumMetadataRow::as_select())
+            .first(&mut conn)
+            .optional()?;
+
+        Ok(result.map(CachedMetadata::from))
+    }
+
+    /// Store the metadata of an album, replacing any cached metadata.
+    pub fn save_album(
+        &self,
+        album_id: i32,
+        agent: &str,
+        metadata: &AlbumMetadata,
+        expires_at: NaiveDateTime,
+    ) -> Result<(), MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        diesel::replace_into(album_metadata::table)
+            .values((
+                album_metadata::album_id.eq(album_id),
+                album_metadata::agent.eq(agent),
+                album_metadata::notes.eq(&metadata.notes),
+                album_metadata::musicbrainz_id.eq(&metadata.musicbrainz_id),
+                album_metadata::last_fm_url.eq(&metadata.last_fm_url),
+                album_metadata::small_image_url.eq(&metadata.small_image_url),
+                album_metadata::medium_image_url.eq(&metadata.medium_image_url),
+                album_metadata::large_image_url.eq(&metadata.large_image_url),
+                album_metadata::fetched_at.eq(chrono::Utc::now().naive_utc()),
+                album_metadata::expires_at.eq(expires_at),
+            ))
+            .execute(&mut conn)?;
+
+        Ok(())
+    }
+
+    /// Put off the next refresh of an album's metadata, keeping what is cached.
+    pub fn postpone_album(
+        &self,
+        album_id: i32,
+        agent: &str,
+        expires_at: NaiveDateTime,
+    ) -> Result<(), MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        diesel::insert_into(album_metadata::table)
+            .values((
+                album_metadata::album_id.eq(album_id),
+                album_metadata::agent.eq(agent),
+                album_metadata::expires_at.eq(expires_at),
+            ))
+            .on_conflict(album_metadata::album_id)
+            .do_update()
+            .set(album_metadata::expires_at.eq(expires_at))
+            .execute(&mut conn)?;
+
+        Ok(())
+    }
+}
+
+// ============================================================================
+// Artist Similarity Repository
+// ============================================================================
+
+use crate::db::schema::artist_similarity;
+use crate::models::metadata::{ArtistPlay, ArtistSimilarity};
+
+/// Repository for the artist similarity computed from listening history.
+#[derive(Clone)]
+pub struct ArtistSimilarityRepository {
+    pool: DbPool,
+}
+
+impl ArtistSimilarityRepository {
+    /// Create a new artist similarity repository.
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    /// Get every completed play of a song with an artist, ordered by user and time.
+    pub fn find_artist_plays(&self) -> Result<Vec<ArtistPlay>, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let rows: Vec<(i32, Option<i32>, NaiveDateTime)> = scrobbles::table
+            .inner_join(songs::table.on(scrobbles::song_id.eq(songs::id)))
+            .filter(scrobbles::submission.eq(true))
+            .filter(songs::artist_id.is_not_null())
+            .order((scrobbles::user_id.asc(), scrobbles::played_at.asc()))
+            .select((scrobbles::user_id, songs::artist_id, scrobbles::played_at))
+            .load(&mut conn)?;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|(user_id, artist_id, played_at)| {
+                Some(ArtistPlay {
+                    user_id,
+                    artist_id: artist_id?,
+                    played_at,
+                })
+            })
+            .collect())
+    }
+
+    /// Replace the whole similarity table.
+    pub fn replace_all(&self, similarities: &[ArtistSimilarity]) -> Result<(), MusicRepoError> {
+        let mut conn = self.pool.get()?;
+        let now = chrono::Utc::now().naive_utc();
+
+        conn.transaction(|conn| {
+            diesel::delete(artist_similarity::table).execute(conn)?;
+
+            for chunk in similarities.chunks(500) {
+                let rows: Vec<_> = chunk
+                    .iter()
+                    .map(|s| {
+                        (
+                            artist_similarity::artist_id.eq(s.artist_id),
+                            artist_similarity::similar_artist_id.eq(s.similar_artist_id),
+                            artist_similarity::score.eq(s.score),
+                            artist_similarity::updated_at.eq(now),
+                        )
+                    })
+                    .collect();
+                diesel::insert_into(artist_similarity::table)
+                    .values(&rows)
+                    .execute(conn)?;
+            }
+
+            Ok(())
+        })
+    }
+
+    /// Get the similarity of the given artists to every artist like them.
+    pub fn find_scores(&self, artist_ids: &[i32]) -> Result<Vec<ArtistSimilarity>, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let results: Vec<(i32, i32, f64)> = artist_similarity::table
+            .filter(artist_similarity::artist_id.eq_any(artist_ids))
+            .select((
+                artist_similarity::artist_id,
+                artist_similarity::similar_artist_id,
+                artist_similarity::score,
+            ))
+            .load(&mut conn)?;
+
+        Ok(results
+            .into_iter()
+            .map(|(artist_id, similar_artist_id, score)| ArtistSimilarity {
+                artist_id,
+                similar_artist_id,
+                score,
+            })
+            .collect())
+    }
+
+    /// Get the artists most similar to an artist, most similar first.
+    pub fn find_similar(&self, artist_id: i32, limit: i64) -> Result<Vec<Artist>, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let results = artist_similarity::table
+            .inner_join(artists::table.on(artist_similarity::similar_artist_id.eq(artists::id)))
+            .filter(artist_similarity::artist_id.eq(artist_id))
+            .order((artist_similarity::score.desc(), artists::name.asc()))
+            .limit(limit)
+            .select(ArtistRow::as_select())
+            .load(&mut conn)?;
+
+        Ok(results.into_iter().map(Artist::from).collect())
+    }
+}
+
+// ============================================================================
+// Statistics Repository
+// ============================================================================
+
+use crate::models::stats::{StatsFilter, UserListening};
+
+/// Condition selecting the submitted scrobbles (full plays) matched by a filter.
+fn stats_condition<QS>(
+    filter: &StatsFilter,
+) -> Box<dyn BoxableExpression<QS, diesel::sqlite::Sqlite, SqlType = diesel::sql_types::Bool>>
+where
+    QS: 'static,
+    scrobbles::submission: SelectableExpression<QS>,
+    scrobbles::user_id: SelectableExpression<QS>,
+    scrobbles::played_at: SelectableExpression<QS>,
+{
+    let mut condition: Box<
+        dyn BoxableExpression<QS, diesel::sqlite::Sqlite, SqlType = diesel::sql_types::Bool>,
+    > = Box::new(scrobbles::submission.eq(true));
+    if let Some(user_id) = filter.user_id {
+        condition = Box::new(condition.and(scrobbles::user_id.eq(user_id)));
+    }
+    if let Some(from) = filter.from {
+        condition = Box::new(condition.and(scrobbles::played_at.ge(from)));
+    }
+    if let Some(to) = filter.to {
+        condition = Box::new(condition.and(scrobbles::played_at.lt(to)));
+    }
+    condition
+}
+
+/// Repository for listening statistics over the scrobble history.
+///
+/// Only submitted scrobbles (full plays) are counted.
+#[derive(Clone)]
+pub struct StatisticsRepository {
+    pool: DbPool,
+}
+
+impl StatisticsRepository {
+    /// Create a new statistics repository.
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    /// Get the number of plays and listening time of each user, most
+    /// listened first.
+    pub fn find_listening_by_user(
+        &self,
+        filter: &StatsFilter,
+    ) -> Result<Vec<UserListening>, MusicRepoError> {
+        use diesel::dsl::{count_star, sum};
+
+        let mut conn = self.pool.get()?;
+
+        let results: Vec<(i32, String, i64, Option<i64>)> = scrobbles::table
+            .inner_join(songs::table.on(scrobbles::song_id.eq(songs::id)))
+            .inner_join(users::table.on(scrobbles::user_id.eq(users::id)))
+            .filter(stats_condition(filter))
+            .group_by(users::id)
+            .select((
+                users::id,
+                users::username,
+                count_star(),
+                sum(songs::duration),
+            ))
+            .order((sum(songs::duration).desc(), users::username.asc()))
+            .load(&mut conn)?;
+
+        Ok(results
+            .into_iter()
+            .map(
+                |(user_id, username, play_count, listening_time)| UserListening {
+                    user_id,
+                    username,
+                    play_count,
+                    listening_time: listening_time.unwrap_or(0),
+                },
+            )
+            .collect())
+    }
+
+    /// Get the most played artists with their play counts.
+    pub fn find_top_artists(
+        &self,
+        filter: &StatsFilter,
+        limit: i64,
+    ) -> Result<Vec<(Artist, i64)>, MusicRepoError> {
+        use diesel::dsl::count_star;
+
+        let mut conn = self.pool.get()?;
+
+        let results: Vec<(ArtistRow, i64)> = scrobbles::table
+            .inner_join(songs::table.on(scrobbles::song_id.eq(songs::id)))
+            .inner_join(artists::table.on(songs::artist_id.eq(artists::id.nullable())))
+            .filter(stats_condition(filter))
+            .group_by(artists::id)
+            .select((ArtistRow::as_select(), count_star()))
+            .order((count_star().desc(), artists::name.asc()))
+            .limit(limit)
+            .load(&mut conn)?;
+
+        Ok(results
+            .into_iter()
+            .map(|(row, count)| (Artist::from(row), count))
+            .collect())
+    }
+
+    /// Get the most played albums with their play counts.
+    pub fn find_top_albums(
+        &self,
+        filter: &StatsFilter,
+        limit: i64,
+    ) -> Result<Vec<(Album, i64)>, MusicRepoError> {
+        use diesel::dsl::count_star;
+
+        let mut conn = self.pool.get()?;
+
+        let results: Vec<(AlbumRow, i64)> = scrobbles::table
+            .inner_join(songs::table.on(scrobbles::song_id.eq(songs::id)))
+            .inner_join(albums::table.on(songs::album_id.eq(albums::id.nullable())))
+            .filter(stats_condition(filter))
+            .group_by(albums::id)
+            .select((AlbumRow::as_select(), count_star()))
+            .order((count_star().desc(), albums::name.asc()))
+            .limit(limit)
+            .load(&mut conn)?;
+
+        Ok(results
+            .into_iter()
+            .map(|(row, count)| (Album::from(row), count))
+            .collect())
+    }
+
+    /// Get the most played songs with their play counts, optionally only
+    /// songs by the named artist.
+    pub fn find_top_songs(
+        &self,
+        filter: &StatsFilter,
+        artist_name: Option<&str>,
+        limit: i64,
+    ) -> Result<Vec<(Song, i64)>, MusicRepoError> {
+        use diesel::dsl::count_star;
+
+        let mut conn = self.pool.get()?;
+
+        let mut query = scrobbles::table
+            .inner_join(songs::table.on(scrobbles::song_id.eq(songs::id)))
+            .filter(stats_condition(filter))
+            .group_by(songs::id)
+            .select((SongRow::as_select(), count_star()))
+            .order((count_star().desc(), songs::title.asc()))
+            .limit(limit)
+            .into_boxed();
+        if let Some(artist_name) = artist_name {
+            query = query.filter(songs::artist_name.eq(artist_name));
+        }
+
+        let results: Vec<(SongRow, i64)> = query.load(&mut conn)?;
+
+        Ok(results
+            .into_iter()
+            .map(|(row, count)| (Song::from(row), count))
+            .collect())
+    }
+
+    /// Get the most played genres with their play counts.
+    pub fn find_top_genres(
+        &self,
+        filter: &StatsFilter,
+        limit: i64,
+    ) -> Result<Vec<(String, i64)>, MusicRepoError> {
+        use diesel::dsl::count_star;
+
+        let mut conn = self.pool.get()?;
+
+        let results: Vec<(Option<String>, i64)> = scrobbles::table
+            .inner_join(songs::table.on(scrobbles::song_id.eq(songs::id)))
+            .filter(stats_condition(filter))
+            .filter(songs::genre.is_not_null())
+            .group_by(songs::genre)
+            .select((songs::genre, count_star()))
+            .order((count_star().desc(), songs::genre.asc()))
+            .limit(limit)
+            .load(&mut conn)?;
+
+        Ok(results
+            .into_iter()
+            .filter_map(|(genre, count)| Some((genre?, count)))
+            .collect())
+    }
+}
+
+// ============================================================================
+// Scrobble Forwarding Repositories
+// ============================================================================
+
+use crate::db::schema::{scrobble_accounts, scrobble_outbox};
+use crate::models::scrobbling::{PendingScrobble, ScrobbleAccount, ScrobbleService};
+
+/// Database row for linked scrobbling accounts.
+#[derive(Debug, Clone, Queryable, Selectable)]
+#[diesel(table_name = scrobble_accounts)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct ScrobbleAccountRow {
+    pub user_id: i32,
+    pub service: String,
+    pub token: String,
+    pub username: Option<String>,
+    pub linked_at: NaiveDateTime,
+}
+
+impl ScrobbleAccountRow {
+    /// Convert to a linked account, or `None` for a service this server does not know.
+    fn into_account(self) -> Option<ScrobbleAccount> {
+        Some(ScrobbleAccount {
+            user_id: self.user_id,
+            service: ScrobbleService::parse(&self.service)?,
+            token: self.token,
+            username: self.username,
+            linked_at: self.linked_at,
+        })
+    }
+}
+
+/// Repository for users' linked scrobbling accounts.
+#[derive(Clone)]
+pub struct ScrobbleAccountRepository {
+    pool: DbPool,
+}
+
+impl ScrobbleAccountRepository {
+    /// Create a new scrobble account repository.
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    /// Get all accounts a user has linked.
+    pub fn find_by_user(&self, user_id: i32) -> Result<Vec<ScrobbleAccount>, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let rows: Vec<ScrobbleAccountRow> = scrobble_accounts::table
+            .filter(scrobble_accounts::user_id.eq(user_id))
+            .select(ScrobbleAccountRow::as_select())
+            .order(scrobble_accounts::service.asc())
+            .load(&mut conn)?;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(ScrobbleAccountRow::into_account)
+            .collect())
+    }
+
+    /// Get a user's account on a service.
+    pub fn find(
+        &self,
+        user_id: i32,
+        service: ScrobbleService,
+    ) -> Result<Option<ScrobbleAccount>, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let row: Option<ScrobbleAccountRow> = scrobble_accounts::table
+            .find((user_id, service.as_str()))
+            .select(ScrobbleAccountRow::as_select())
+            .first(&mut conn)
+            .optional()?;
+
+        Ok(row.and_then(ScrobbleAccountRow::into_account))
+    }
+
+    /// Link an account, replacing any account the user linked on the same service.
+    pub fn save(
+        &self,
+        user_id: i32,
+        service: ScrobbleService,
+        token: &str,
+        username: Option<&str>,
+    ) -> Result<ScrobbleAccount, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let linked_at = chrono::Utc::now().naive_utc();
+        diesel::replace_into(scrobble_accounts::table)
+            .values((
+                scrobble_accounts::user_id.eq(user_id),
+                scrobble_accounts::service.eq(service.as_str()),
+                scrobble_accounts::token.eq(token),
+                scrobble_accounts::username.eq(username),
+                scrobble_accounts::linked_at.eq(linked_at),
+            ))
+            .execute(&mut conn)?;
+
+        Ok(ScrobbleAccount {
+            user_id,
+            service,
+            token: token.to_string(),
+            username: username.map(str::to_string),
+            linked_at,
+        })
+    }
+
+    /// Unlink an account, dropping the plays still waiting to be submitted to it.
+    /// Returns false if the user had no account on the service.
+    pub fn delete(&self, user_id: i32, service: ScrobbleService) -> Result<bool, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        conn.transaction(|conn| {
+            diesel::delete(
+                scrobble_outbox::table
+                    .filter(scrobble_outbox::user_id.eq(user_id))
+                    .filter(scrobble_outbox::service.eq(service.as_str())),
+            )
+            .execute(conn)?;
+
+            let deleted =
+                diesel::delete(scrobble_accounts::table.find((user_id, service.as_str())))
+                    .execute(conn)?;
+            Ok(deleted > 0)
+        })
+    }
+}
+
+/// Database row for plays waiting to be submitted.
+#[derive(Debug, Clone, Queryable, Selectable)]
+#[diesel(table_name = scrobble_outbox)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct ScrobbleOutboxRow {
+    pub id: i32,
+    pub user_id: i32,
+    pub service: String,
+    pub song_id: i32,
+    pub played_at: NaiveDateTime,
+    pub attempts: i32,
+}
+
+/// Repository for the outbox of plays waiting to be submitted to scrobbling services.
+#[derive(Clone)]
+pub struct ScrobbleOutboxRepository {
+    pool: DbPool,
+}
+
+impl ScrobbleOutboxRepository {
+    /// Create a new scrobble outbox repository.
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    /// Queue a play for submission to a service as soon as possible.
+    pub fn enqueue(
+        &self,
+        user_id: i32,
+        service: ScrobbleService,
+        song_id: i32,
+        played_at: NaiveDateTime,
+    ) -> Result<(), MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        diesel::insert_into(scrobble_outbox::table)
+            .values((
+                scrobble_outbox::user_id.eq(user_id),
+                scrobble_outbox::service.eq(service.as_str()),
+                scrobble_outbox::song_id.eq(song_id),
+                scrobble_outbox::played_at.eq(played_at),
+                scrobble_outbox::next_attempt_at.eq(chrono::Utc::now().naive_utc()),
+            ))
+            .execute(&mut conn)?;
+
+        Ok(())
+    }
+
+    /// Get the plays for the given services due for submission at `now`, oldest first.
+    pub fn find_due(
+        &self,
+        services: &[ScrobbleService],
+        now: NaiveDateTime,
+        limit: i64,
+    ) -> Result<Vec<PendingScrobble>, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let names: Vec<&str> = services.iter().map(|s| s.as_str()).collect();
+        let rows: Vec<ScrobbleOutboxRow> = scrobble_outbox::table
+            .filter(scrobble_outbox::service.eq_any(names))
+            .filter(scrobble_outbox::next_attempt_at.le(now))
+            .select(ScrobbleOutboxRow::as_select())
+            .order((scrobble_outbox::played_at.asc(), scrobble_outbox::id.asc()))
+            .limit(limit)
+            .load(&mut conn)?;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|row| {
+                Some(PendingScrobble {
+                    id: row.id,
+                    user_id: row.user_id,
+                    service: ScrobbleService::parse(&row.service)?,
+                    song_id: row.song_id,
+                    played_at: row.played_at,
+                    attempts: row.attempts,
+                })
+            })
+            .collect())
+    }
+
+    /// Get the time the next play queued for the given services is due, if any are queued.
+    pub fn next_attempt_at(
+        &self,
+        services: &[ScrobbleService],
+    ) -> Result<Option<NaiveDateTime>, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let names: Vec<&str> = services.iter().map(|s| s.as_str()).collect();
+        let next = scrobble_outbox::table
+            .filter(scrobble_outbox::service.eq_any(names))
+            .select(diesel::dsl::min(scrobble_outbox::next_attempt_at))
+            .first(&mut conn)?;
+
+        Ok(next)
+    }
+
+    /// Remove a play from the outbox once it is submitted or given up on.
+    pub fn remove(&self, id: i32) -> Result<(), MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        diesel::delete(scrobble_outbox::table.find(id)).execute(&mut conn)?;
+
+        Ok(())
+    }
+
+    /// Record a failed submission and when to try again.
+    pub fn postpone(
+        &self,
+        id: i32,
+        attempts: i32,
+        next_attempt_at: NaiveDateTime,
+        error: &str,
+    ) -> Result<(), MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        diesel::update(scrobble_outbox::table.find(id))
+            .set((
+                scrobble_outbox::attempts.eq(attempts),
+                scrobble_outbox::next_attempt_at.eq(next_attempt_at),
+                scrobble_outbox::last_error.eq(error),
+            ))
+            .execute(&mut conn)?;
+
+        Ok(())
+    }
+
+    /// Postpone every play of a user's account on a service that is due at
+    /// `now`, after a submission for the account failed.
+    pub fn postpone_account(
+        &self,
+        user_id: i32,
+        service: ScrobbleService,
+        now: NaiveDateTime,
+        next_attempt_at: NaiveDateTime,
+    ) -> Result<(), MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        diesel::update(
+            scrobble_outbox::table
+                .filter(scrobble_outbox::user_id.eq(user_id))
+                .filter(scrobble_outbox::service.eq(service.as_str()))
+                .filter(scrobble_outbox::next_attempt_at.le(now)),
+        )
+        .set(scrobble_outbox::next_attempt_at.eq(next_attempt_at))
+        .execute(&mut conn)?;
+
+        Ok(())
+    }
+
+    /// Count the plays waiting to be submitted for a user, by service.
+    pub fn count_by_user(
+        &self,
+        user_id: i32,
+    ) -> Result<std::collections::HashMap<ScrobbleService, i64>, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let counts: Vec<(String, i64)> = scrobble_outbox::table
+            .filter(scrobble_outbox::user_id.eq(user_id))
+            .group_by(scrobble_outbox::service)
+            .select((scrobble_outbox::service, diesel::dsl::count_star()))
+            .load(&mut conn)?;
+
+        Ok(counts
+            .into_iter()
+            .filter_map(|(service, count)| Some((ScrobbleService::parse(&service)?, count)))
+            .collect())
+    }
+}
+
+// ============================================================================
+// Bookmark Repository
+// ============================================================================
+
+use crate::db::schema::bookmarks;
+
+/// Database row representation for bookmarks.
+#[derive(Debug, Clone, Queryable, Selectable)]
+#[diesel(table_name = bookmarks)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct BookmarkRow {
+    pub id: i32,
+    pub user_id: i32,
+    pub song_id: i32,
+    pub position: i64,
+    pub comment: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub changed_at: NaiveDateTime,
+}
+
+/// A saved playback position in a song.
+#[derive(Debug, Clone)]
+pub struct Bookmark {
+    pub song: Song,
+    /// Position in milliseconds.
+    pub position: i64,
+    pub comment: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub changed_at: NaiveDateTime,
+}
+
+/// Repository for bookmark database operations.
+#[derive(Clone)]
+pub struct BookmarkRepository {
+    pool: DbPool,
+}
+
+impl BookmarkRepository {
+    /// Create a new bookmark repository.
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    /// Get all bookmarks of a user, most recently changed first.
+    pub fn find_by_user(&self, user_id: i32) -> Result<Vec<Bookmark>, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let rows: Vec<(BookmarkRow, SongRow)> = bookmarks::table
+            .inner_join(songs::table.on(bookmarks::song_id.eq(songs::id)))
+            .filter(bookmarks::user_id.eq(user_id))
+            .order(bookmarks::changed_at.desc())
+            .select((BookmarkRow::as_select(), SongRow::as_select()))
+            .load(&mut conn)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(bookmark, song)| Bookmark {
+                song: Song::from(song),
+                position: bookmark.position,
+                comment: bookmark.comment,
+                created_at: bookmark.created_at,
+                changed_at: bookmark.changed_at,
+            })
+            .collect())
+    }
+
+    /// Create or update a user's bookmark in a song.
+    ///
+    /// An existing bookmark keeps its creation time.
+    pub fn save(
+        &self,
+        user_id: i32,
+        song_id: i32,
+        position: i64,
+        comment: Option<&str>,
+    ) -> Result<(), MusicRepoError> {
+        use diesel::upsert::excluded;
+
+        let mut conn = self.pool.get()?;
+
+        let now = chrono::Utc::now().naive_utc();
+        diesel::insert_into(bookmarks::table)
+            .values((
+                bookmarks::user_id.eq(user_id),
+                bookmarks::song_id.eq(song_id),
+                bookmarks::position.eq(position),
+                bookmarks::comment.eq(comment),
+                bookmarks::created_at.eq(now),
+                bookmarks::changed_at.eq(now),
+            ))
+            .on_conflict((bookmarks::user_id, bookmarks::song_id))
+            .do_update()
+            .set((
+                bookmarks::position.eq(excluded(bookmarks::position)),
+                bookmarks::comment.eq(excluded(bookmarks::comment)),
+                bookmarks::changed_at.eq(excluded(bookmarks::changed_at)),
+            ))
+            .execute(&mut conn)?;
+
+        Ok(())
+    }
+
+    /// Delete a user's bookmark in a song.
+    /// Returns false if there was no bookmark.
+    pub fn delete(&self, user_id: i32, song_id: i32) -> Result<bool, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let deleted = diesel::delete(
+            bookmarks::table
+                .filter(bookmarks::user_id.eq(user_id))
+                .filter(bookmarks::song_id.eq(song_id)),
+        )
+        .execute(&mut conn)?;
+
+        Ok(deleted > 0)
+    }
+}
+
+// ============================================================================
+// Share Repository
+// ============================================================================
+
+use crate::db::schema::{share_songs, shares};
+
+/// Database row representation for shares.
+#[derive(Debug, Clone, Queryable, Selectable)]
+#[diesel(table_name = shares)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct ShareRow {
+    pub id: i32,
+    pub token: String,
+    pub user_id: i32,
+    pub description: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub expires_at: Option<NaiveDateTime>,
+    pub last_visited_at: Option<NaiveDateTime>,
+    pub visit_count: i32,
+}
+
+/// A public link to a list of songs.
+#[derive(Debug, Clone)]
+pub struct Share {
+    pub id: i32,
+    /// Random token identifying the share in its public URL.
+    pub token: String,
+    pub user_id: i32,
+    pub username: String,
+    pub description: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub expires_at: Option<NaiveDateTime>,
+    pub last_visited_at: Option<NaiveDateTime>,
+    pub visit_count: i32,
+    pub songs: Vec<Song>,
+}
+
+impl Share {
+    /// Whether the share has expired at the given time.
+    pub fn is_expired(&self, now: NaiveDateTime) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+}
+
+/// Repository for share database operations.
+#[derive(Clone)]
+pub struct ShareRepository {
+    pool: DbPool,
+}
+
+impl ShareRepository {
+    /// Create a new share repository.
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    /// Load the songs and owner names of share rows.
+    fn load_shares(
+        conn: &mut diesel::SqliteConnection,
+        rows: Vec<(ShareRow, String)>,
+    ) -> Result<Vec<Share>, MusicRepoError> {
+        let share_ids: Vec<i32> = rows.iter().map(|(row, _)| row.id).collect();
+        let song_rows: Vec<(i32, SongRow)> = share_songs::table
+            .inner_join(songs::table.on(share_songs::song_id.eq(songs::id)))
+            .filter(share_songs::share_id.eq_any(&share_ids))
+            .order((share_songs::share_id.asc(), share_songs::position.asc()))
+            .select((share_songs::share_id, SongRow::as_select()))
+            .load(conn)?;
+
+        let mut songs_by_share: std::collections::HashMap<i32, Vec<Song>> =
+            std::collections::HashMap::new();
+        for (share_id, song) in song_rows {
+            songs_by_share
+                .entry(share_id)
+                .or_default()
+                .push(Song::from(song));
+        }
+
+        Ok(rows
+            .into_iter()
+            .map(|(row, username)| Share {
+                songs: songs_by_share.remove(&row.id).unwrap_or_default(),
+                id: row.id,
+                token: row.token,
+                user_id: row.user_id,
+                username,
+                description: row.description,
+                created_at: row.created_at,
+                expires_at: row.expires_at,
+                last_visited_at: row.last_visited_at,
+                visit_count: row.visit_count,
+            })
+            .collect())
+    }
+
+    /// Get all shares created by a user, newest first.
+    pub fn find_by_user(&self, user_id: i32) -> Result<Vec<Share>, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let rows: Vec<(ShareRow, String)> = shares::table
+            .inner_join(users::table.on(shares::user_id.eq(users::id)))
+            .filter(shares::user_id.eq(user_id))
+            .order(shares::created_at.desc())
+            .select((ShareRow::as_select(), users::username))
+            .load(&mut conn)?;
+
+        Self::load_shares(&mut conn, rows)
+    }
+
+    /// Get a share by ID.
+    pub fn find(&self, share_id: i32) -> Result<Option<Share>, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let rows: Vec<(ShareRow, String)> = shares::table
+            .inner_join(users::table.on(shares::user_id.eq(users::id)))
+            .filter(shares::id.eq(share_id))
+            .select((ShareRow::as_select(), users::username))
+            .load(&mut conn)?;
+
+        Ok(Self::load_shares(&mut conn, rows)?.pop())
+    }
+
+    /// Get a share by the token in its public URL.
+    pub fn find_by_token(&self, token: &str) -> Result<Option<Share>, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let rows: Vec<(ShareRow, String)> = shares::table
+            .inner_join(users::table.on(shares::user_id.eq(users::id)))
+            .filter(shares::token.eq(token))
+            .select((ShareRow::as_select(), users::username))
+            .load(&mut conn)?;
+
+        Ok(Self::load_shares(&mut conn, rows)?.pop())
+    }
+
+    /// Create a share of the given songs with a new random token.
+    pub fn create(
+        &self,
+        user_id: i32,
+        song_ids: &[i32],
+        description: Option<&str>,
+        expires_at: Option<NaiveDateTime>,
+    ) -> Result<Share, MusicRepoError> {
+        use rand_core::{OsRng, RngCore};
+
+        // Generate a random 16-byte token and encode as hex (32 characters)
+        let mut token_bytes = [0u8; 16];
+        OsRng.fill_bytes(&mut token_bytes);
+        let token = hex::encode(token_bytes);
+
+        let mut conn = self.pool.get()?;
+
+        let share_id = conn.transaction(|conn| {
+            diesel::insert_into(shares::table)
+                .values((
+                    shares::token.eq(&token),
+                    shares::user_id.eq(user_id),
+                    shares::description.eq(description),
+                    shares::created_at.eq(chrono::Utc::now().naive_utc()),
+                    shares::expires_at.eq(expires_at),
+                ))
+                .execute(conn)?;
+
+            let share_id: i32 = shares::table
+                .filter(shares::token.eq(&token))
+                .select(shares::id)
+                .first(conn)?;
+
+            for (position, song_id) in song_ids.iter().enumerate() {
+                diesel::insert_into(share_songs::table)
+                    .values((
+                        share_songs::share_id.eq(share_id),
+                        share_songs::song_id.eq(song_id),
+                        share_songs::position.eq(position as i32),
+                    ))
+                    .execute(conn)?;
+            }
+
+            Ok::<_, MusicRepoError>(share_id)
+        })?;
+
+        self.find(share_id)?
+            .ok_or_else(|| MusicRepoError::NotFound(format!("Share {}", share_id)))
+    }
+
+    /// Update a share's description and expiry. `None` leaves a field
+    /// unchanged. Returns false if the share does not exist.
+    pub fn update(
+        &self,
+        share_id: i32,
+        description: Option<Option<&str>>,
+        expires_at: Option<Option<NaiveDateTime>>,
+    ) -> Result<bool, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        conn.transaction(|conn| {
+            let exists = shares::table
+                .find(share_id)
+                .select(shares::id)
+                .first::<i32>(conn)
+                .optional()?
+                .is_some();
+
+            if let Some(description) = description {
+                diesel::update(shares::table.find(share_id))
+                    .set(shares::description.eq(description))
+                    .execute(conn)?;
+            }
+            if let Some(expires_at) = expires_at {
+                diesel::update(shares::table.find(share_id))
+                    .set(shares::expires_at.eq(expires_at))
+                    .execute(conn)?;
+            }
+
+            Ok(exists)
+        })
+    }
+
+    /// Delete a share. Returns false if it did not exist.
+    pub fn delete(&self, share_id: i32) -> Result<bool, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let deleted = diesel::delete(shares::table.find(share_id)).execute(&mut conn)?;
+        Ok(deleted > 0)
+    }
+
+    /// Count a visit to a share's public page.
+    pub fn record_visit(&self, share_id: i32) -> Result<(), MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        diesel::update(shares::table.find(share_id))
+            .set((
+                shares::visit_count.eq(shares::visit_count + 1),
+                shares::last_visited_at.eq(chrono::Utc::now().naive_utc()),
+            ))
+            .execute(&mut conn)?;
+
+        Ok(())
+    }
+}
+
+// ============================================================================
+// Internet Radio Repository
+// ============================================================================
+
+use crate::db::schema::internet_radio_stations;
+use crate::models::radio::InternetRadioStation;
+
+/// Database row representation for internet radio stations.
+#[derive(Debug, Clone, Queryable, Selectable)]
+#[diesel(table_name = internet_radio_stations)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct InternetRadioStationRow {
+    pub id: i32,
+    pub name: String,
+    pub stream_url: String,
+    pub homepage_url: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+impl From<InternetRadioStationRow> for InternetRadioStation {
+    fn from(row: InternetRadioStationRow) -> Self {
+        Self {
+            id: row.id,
+            name: row.name,
+            stream_url: row.stream_url,
+            homepage_url: row.homepage_url,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        }
+    }
+}
+
+/// Repository for internet radio station database operations.
+#[derive(Clone)]
+pub struct InternetRadioRepository {
+    pool: DbPool,
+}
+
+impl InternetRadioRepository {
+    /// Create a new internet radio repository.
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    /// Get all stations, ordered by name.
+    pub fn find_all(&self) -> Result<Vec<InternetRadioStation>, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let rows: Vec<InternetRadioStationRow> = internet_radio_stations::table
+            .select(InternetRadioStationRow::as_select())
+            .order((
+                internet_radio_stations::name.asc(),
+                internet_radio_stations::id.asc(),
+            ))
+            .load(&mut conn)?;
+
+        Ok(rows.into_iter().map(InternetRadioStation::from).collect())
+    }
+
+    /// Get a station by ID.
+    pub fn find(&self, station_id: i32) -> Result<Option<InternetRadioStation>, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let row: Option<InternetRadioStationRow> = internet_radio_stations::table
+            .find(station_id)
+            .select(InternetRadioStationRow::as_select())
+            .first(&mut conn)
+            .optional()?;
+
+        Ok(row.map(InternetRadioStation::from))
+    }
+
+    /// Check whether a station with the given stream URL exists.
+    pub fn exists_with_stream_url(&self, stream_url: &str) -> Result<bool, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let count: i64 = internet_radio_stations::table
+            .filter(internet_radio_stations::stream_url.eq(stream_url))
+            .count()
+            .get_result(&mut conn)?;
+
+        Ok(count > 0)
+    }
+
+    /// Add a station.
+    pub fn create(
+        &self,
+        name: &str,
+        stream_url: &str,
+        homepage_url: Option<&str>,
+    ) -> Result<InternetRadioStation, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let now = chrono::Utc::now().naive_utc();
+        conn.transaction(|conn| {
+            diesel::insert_into(internet_radio_stations::table)
+                .values((
+                    internet_radio_stations::name.eq(name),
+                    internet_radio_stations::stream_url.eq(stream_url),
+                    internet_radio_stations::homepage_url.eq(homepage_url),
+                    internet_radio_stations::created_at.eq(now),
+                    internet_radio_stations::updated_at.eq(now),
+                ))
+                .execute(conn)?;
+
+            let row: InternetRadioStationRow = internet_radio_stations::table
+                .order(internet_radio_stations::id.desc())
+                .select(InternetRadioStationRow::as_select())
+                .first(conn)?;
+
+            Ok(InternetRadioStation::from(row))
+        })
+    }
+
+    /// Update a station. Returns false if it does not exist.
+    pub fn update(
+        &self,
+        station_id: i32,
+        name: &str,
+        stream_url: &str,
+        homepage_url: Option<&str>,
+    ) -> Result<bool, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let updated = diesel::update(internet_radio_stations::table.find(station_id))
+            .set((
+                internet_radio_stations::name.eq(name),
+                internet_radio_stations::stream_url.eq(stream_url),
+                internet_radio_stations::homepage_url.eq(homepage_url),
+                internet_radio_stations::updated_at.eq(chrono::Utc::now().naive_utc()),
+            ))
+            .execute(&mut conn)?;
+
+        Ok(updated > 0)
+    }
+
+    /// Delete a station. Returns false if it did not exist.
+    pub fn delete(&self, station_id: i32) -> Result<bool, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let deleted =
+            diesel::delete(internet_radio_stations::table.find(station_id)).execute(&mut conn)?;
+        Ok(deleted > 0)
+    }
+}
+
+// ============================================================================
+// Podcast Repository
+// ============================================================================
+
+use crate::db::schema::{podcast_channels, podcast_episodes};
+use crate::models::podcast::{PodcastChannel, PodcastEpisode, PodcastFeed, PodcastStatus};
+
+/// Database row representation for podcast channels.
+#[derive(Debug, Clone, Queryable, Selectable)]
+#[diesel(table_name = podcast_channels)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct PodcastChannelRow {
+    pub id: i32,
+    pub url: String,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub image_url: Option<String>,
+    pub status: String,
+    pub error_message: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub last_refreshed_at: Option<NaiveDateTime>,
+}
+
+impl From<PodcastChannelRow> for PodcastChannel {
+    fn from(row: PodcastChannelRow) -> Self {
+        Self {
+            id: row.id,
+            url: row.url,
+            title: row.title,
+            description: row.description,
+            image_url: row.image_url,
+            status: PodcastStatus::parse(&row.status),
+            error_message: row.error_message,
+            created_at: row.created_at,
+            last_refreshed_at: row.last_refreshed_at,
+        }
+    }
+}
+
+/// Database row representation for podcast episodes.
+#[derive(Debug, Clone, Queryable, Selectable)]
+#[diesel(table_name = podcast_episodes)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct PodcastEpisodeRow {
+    pub id: i32,
+    pub channel_id: i32,
+    pub guid: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub publish_date: Option<NaiveDateTime>,
+    pub duration: Option<i32>,
+    pub enclosure_url: String,
+    pub content_type: Option<String>,
+    pub size: Option<i64>,
+    pub status: String,
+    pub file_path: Option<String>,
+    pub error_message: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+impl From<PodcastEpisodeRow> for PodcastEpisode {
+    fn from(row: PodcastEpisodeRow) -> Self {
+        Self {
+            id: row.id,
+            channel_id: row.channel_id,
+            guid: row.guid,
+            title: row.title,
+            description: row.description,
+            publish_date: row.publish_date,
+            duration: row.duration,
+            enclosure_url: row.enclosure_url,
+            content_type: row.content_type,
+            size: row.size,
+            status: PodcastStatus::parse(&row.status),
+            file_path: row.file_path,
+            error_message: row.error_message,
+            created_at: row.created_at,
+        }
+    }
+}
+
+/// Repository for podcast channel and episode database operations.
+#[derive(Clone)]
+pub struct PodcastRepository {
+    pool: DbPool,
+}
+
+impl PodcastRepository {
+    /// Create a new podcast repository.
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    /// Get all channels, in the order they were added.
+    pub fn find_channels(&self) -> Result<Vec<PodcastChannel>, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let rows: Vec<PodcastChannelRow> = podcast_channels::table
+            .select(PodcastChannelRow::as_select())
+            .order(podcast_channels::id.asc())
+            .load(&mut conn)?;
+
+        Ok(rows.into_iter().map(PodcastChannel::from).collect())
+    }
+
+    /// Get a channel by ID.
+    pub fn find_channel(&self, channel_id: i32) -> Result<Option<PodcastChannel>, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let row: Option<PodcastChannelRow> = podcast_channels::table
+            .find(channel_id)
+            .select(PodcastChannelRow::as_select())
+            .first(&mut conn)
+            .optional()?;
+
+        Ok(row.map(PodcastChannel::from))
+    }
+
+    /// Subscribe to a feed. The channel is new until it is first refreshed.
+    pub fn create_channel(&self, url: &str) -> Result<PodcastChannel, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        diesel::insert_into(podcast_channels::table)
+            .values((
+                podcast_channels::url.eq(url),
+                podcast_channels::status.eq(PodcastStatus::New.as_str()),
+                podcast_channels::created_at.eq(chrono::Utc::now().naive_utc()),
+            ))
+            .execute(&mut conn)?;
+
+        let row: PodcastChannelRow = podcast_channels::table
+            .filter(podcast_channels::url.eq(url))
+            .select(PodcastChannelRow::as_select())
+            .first(&mut conn)?;
+
+        Ok(PodcastChannel::from(row))
+    }
+
+    /// Delete a channel and its episodes. Returns false if it did not exist.
+    pub fn delete_channel(&self, channel_id: i32) -> Result<bool, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let deleted =
+            diesel::delete(podcast_channels::table.find(channel_id)).execute(&mut conn)?;
+        Ok(deleted > 0)
+    }
+
+    /// Store a refreshed feed: update the channel's details and add the
+    /// episodes it does not have yet. Episodes already known keep their
+    /// status and download. Returns the number of episodes added.
+    pub fn save_feed(&self, channel_id: i32, feed: &PodcastFeed) -> Result<usize, MusicRepoError> {
+        use diesel::upsert::excluded;
+
+        let mut conn = self.pool.get()?;
+
+        let now = chrono::Utc::now().naive_utc();
+        conn.transaction(|conn| {
+            diesel::update(podcast_channels::table.find(channel_id))
+                .set((
+                    podcast_channels::title.eq(&feed.title),
+                    podcast_channels::description.eq(&feed.description),
+                    podcast_channels::image_url.eq(&feed.image_url),
+                    podcast_channels::status.eq(PodcastStatus::Completed.as_str()),
+                    podcast_channels::error_message.eq(None::<String>),
+                    podcast_channels::last_refreshed_at.eq(now),
+                ))
+                .execute(conn)?;
+
+            let count_episodes = |conn: &mut diesel::SqliteConnection| {
+                podcast_episodes::table
+                    .filter(podcast_episodes::channel_id.eq(channel_id))
+                    .count()
+                    .get_result::<i64>(conn)
+            };
+            let before = count_episodes(conn)?;
+
+            for episode in &feed.episodes {
+                diesel::insert_into(podcast_episodes::table)
+                    .values((
+                        podcast_episodes::channel_id.eq(channel_id),
+                        podcast_episodes::guid.eq(&episode.guid),
+                        podcast_episodes::title.eq(&episode.title),
+                        podcast_episodes::description.eq(&episode.description),
+                        podcast_episodes::publish_date.eq(episode.publish_date),
+                        podcast_episodes::duration.eq(episode.duration),
+                        podcast_episodes::enclosure_url.eq(&episode.enclosure_url),
+                        podcast_episodes::content_type.eq(&episode.content_type),
+                        podcast_episodes::size.eq(episode.size),
+                        podcast_episodes::status.eq(PodcastStatus::Skipped.as_str()),
+                        podcast_episodes::created_at.eq(now),
+                    ))
+                    .on_conflict((podcast_episodes::channel_id, podcast_episodes::guid))
+                    .do_update()
+                    .set((
+                        podcast_episodes::title.eq(excluded(podcast_episodes::title)),
+                        podcast_episodes::description.eq(excluded(podcast_episodes::description)),
+                        podcast_episodes::publish_date.eq(excluded(podcast_episodes::publish_date)),
+                        podcast_episodes::duration.eq(excluded(podcast_episodes::duration)),
+                        podcast_episodes::enclosure_url
+                            .eq(excluded(podcast_episodes::enclosure_url)),
+                    ))
+                    .execute(conn)?;
+            }
+
+            Ok((count_episodes(conn)? - before) as usize)
+        })
+    }
+
+    /// Record that refreshing a channel failed.
+    pub fn set_channel_error(&self, channel_id: i32, message: &str) -> Result<(), MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        diesel::update(podcast_channels::table.find(channel_id))
+            .set((
+                podcast_channels::status.eq(PodcastStatus::Error.as_str()),
+                podcast_channels::error_message.eq(message),
+                podcast_channels::last_refreshed_at.eq(chrono::Utc::now().naive_utc()),
+            ))
+            .execute(&mut conn)?;
+
+        Ok(())
+    }
+
+    /// Get a channel's episodes, newest first.
+    pub fn find_episodes(&self, channel_id: i32) -> Result<Vec<PodcastEpisode>, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let rows: Vec<PodcastEpisodeRow> = podcast_episodes::table
+            .filter(podcast_episodes::channel_id.eq(channel_id))
+            .select(PodcastEpisodeRow::as_select())
+            .order((
+                podcast_episodes::publish_date.desc(),
+                podcast_episodes::id.desc(),
+            ))
+            .load(&mut conn)?;
+
+        Ok(rows.into_iter().map(PodcastEpisode::from).collect())
+    }
+
+    /// Get the most recently published episodes of all channels.
+    pub fn find_newest_episodes(&self, count: i64) -> Result<Vec<PodcastEpisode>, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let rows: Vec<PodcastEpisodeRow> = podcast_episodes::table
+            .select(PodcastEpisodeRow::as_select())
+            .order((
+                podcast_episodes::publish_date.desc(),
+                podcast_episodes::id.desc(),
+            ))
+            .limit(count)
+            .load(&mut conn)?;
+
+        Ok(rows.into_iter().map(PodcastEpisode::from).collect())
+    }
+
+    /// Get an episode by ID.
+    pub fn find_episode(&self, episode_id: i32) -> Result<Option<PodcastEpisode>, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let row: Option<PodcastEpisodeRow> = podcast_episodes::table
+            .find(episode_id)
+            .select(PodcastEpisodeRow::as_select())
+            .first(&mut conn)
+            .optional()?;
+
+        Ok(row.map(PodcastEpisode::from))
+    }
+
+    /// Get the episode queued for download the longest.
+    pub fn next_queued_episode(&self) -> Result<Option<PodcastEpisode>, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let row: Option<PodcastEpisodeRow> = podcast_episodes::table
+            .filter(podcast_episodes::status.eq(PodcastStatus::Downloading.as_str()))
+            .select(PodcastEpisodeRow::as_select())
+            .order(podcast_episodes::id.asc())
+            .first(&mut conn)
+            .optional()?;
+
+        Ok(row.map(PodcastEpisode::from))
+    }
+
+    /// Set an episode's status along with its downloaded file and error
+    /// message. Returns false if the episode does not exist.
+    pub fn set_episode_status(
+        &self,
+        episode_id: i32,
+        status: PodcastStatus,
+        file_path: Option<&str>,
+        error_message: Option<&str>,
+    ) -> Result<bool, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let updated = diesel::update(podcast_episodes::table.find(episode_id))
+            .set((
+                podcast_episodes::status.eq(status.as_str()),
+                podcast_episodes::file_path.eq(file_path),
+                podcast_episodes::error_message.eq(error_message),
+            ))
+            .execute(&mut conn)?;
+
+        Ok(updated > 0)
+    }
+
+    /// Record a finished download. Returns false if the episode was deleted
+    /// or dequeued while it was being downloaded.
+    pub fn complete_episode(
+        &self,
+        episode_id: i32,
+        file_path: &str,
+        size: i64,
+    ) -> Result<bool, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let updated = diesel::update(
+            podcast_episodes::table
+                .find(episode_id)
+                .filter(podcast_episodes::status.eq(PodcastStatus::Downloading.as_str())),
+        )
+        .set((
+            podcast_episodes::status.eq(PodcastStatus::Completed.as_str()),
+            podcast_episodes::file_path.eq(file_path),
+            podcast_episodes::size.eq(size),
+            podcast_episodes::error_message.eq(None::<String>),
+        ))
+        .execute(&mut conn)?;
+
+        Ok(updated > 0)
+    }
+}
+
+// ============================================================================
+// Chat Message Repository
+// ============================================================================
+
+use crate::db::schema::chat_messages;
+use crate::models::chat::{ChatMessage, MAX_CHAT_MESSAGES};
+
+/// Database row representation for chat messages.
+#[derive(Debug, Clone, Queryable, Selectable)]
+#[diesel(table_name = chat_messages)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct ChatMessageRow {
+    pub id: i32,
+    pub user_id: i32,
+    pub message: String,
+    pub created_at: NaiveDateTime,
+}
+
+/// Repository for chat message database operations.
+#[derive(Clone)]
+pub struct ChatMessageRepository {
+    pool: DbPool,
+}
+
+impl ChatMessageRepository {
+    /// Create a new chat message repository.
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    /// Get the messages posted after the given time, newest first.
+    pub fn find_since(
+        &self,
+        since: Option<NaiveDateTime>,
+    ) -> Result<Vec<ChatMessage>, MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        let mut query = chat_messages::table
+            .inner_join(users::table.on(chat_messages::user_id.eq(users::id)))
+            .select((ChatMessageRow::as_select(), users::username))
+            .order((chat_messages::created_at.desc(), chat_messages::id.desc()))
+            .into_boxed();
+        if let Some(since) = since {
+            query = query.filter(chat_messages::created_at.gt(since));
+        }
+        let rows: Vec<(ChatMessageRow, String)> = query.load(&mut conn)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(row, username)| ChatMessage {
+                id: row.id,
+                username,
+                message: row.message,
+                created_at: row.created_at,
+            })
+            .collect())
+    }
+
+    /// Post a message, deleting the oldest messages beyond the retention limit.
+    pub fn create(&self, user_id: i32, message: &str) -> Result<(), MusicRepoError> {
+        let mut conn = self.pool.get()?;
+
+        conn.transaction(|conn| {
+            diesel::insert_into(chat_messages::table)
+                .values((
+                    chat_messages::user_id.eq(user_id),
+                    chat_messages::message.eq(message),
+                    chat_messages::created_at.eq(chrono::Utc::now().naive_utc()),
+                ))
+                .execute(conn)?;
+
+            // Messages are numbered in the order they were posted
+            let newest_dropped: Option<i32> = chat_messages::table
+                .select(chat_messages::id)
+                .order(chat_messages::id.desc())
+                .offset(MAX_CHAT_MESSAGES)
+                .first(conn)
+                .optional()?;
+            if let Some(newest_dropped) = newest_dropped {
+                diesel::delete(chat_messages::table.filter(chat_messages::id.le(newest_dropped)))
+                    .execute(conn)?;
+            }
+
+            Ok(())
+        })
+    }
+}
+
+// ============================================================================
+// Smart Playlist Queries
+// ============================================================================
+
+use crate::scanner::smart_playlists::{
+    Condition, Criteria, DateCondition, Field, NumberCondition, Rule, SmartPlaylistRules, SortBy,
+    TextCondition,
+};
+use diesel::dsl::{not, sql};
+use diesel::expression::SqlLiteral;
+use diesel::sql_types::{BigInt, Bool, Integer, Nullable, Timestamp};
+
+/// A condition on songs compiled from smart playlist rules.
+type SongPredicate =
+    Box<dyn BoxableExpression<songs::table, diesel::sqlite::Sqlite, SqlType = Nullable<Bool>>>;
+
+// The owner's ratings, plays and stars of each song. Diesel can't express
+// correlated subqueries, so these are SQL literals; only the user ID is
+// formatted into them and rule values are still bound.
+
+fn user_rating(user_id: i32) -> SqlLiteral<Integer> {
+    sql(&format!(
+        "COALESCE((SELECT rating FROM user_ratings \
+         WHERE user_ratings.song_id = songs.id AND user_ratings.user_id = {user_id}), 0)"
+    ))
+}
+
+fn user_play_count(user_id: i32) -> SqlLiteral<BigInt> {
+    sql(&format!(
+        "(SELECT COUNT(*) FROM scrobbles WHERE scrobbles.song_id = songs.id \
+         AND scrobbles.user_id = {user_id} AND scrobbles.submission = 1)"
+    ))
+}
+
+fn user_last_played(user_id: i32) -> SqlLiteral<Nullable<Timestamp>> {
+    sql(&format!(
+        "(SELECT MAX(played_at) FROM scrobbles WHERE scrobbles.song_id = songs.id \
+         AND scrobbles.user_id = {user_id} AND scrobbles.submission = 1)"
+    ))
+}
+
+fn user_loved(user_id: i32) -> SqlLiteral<Bool> {
+    sql(&format!(
+        "EXISTS (SELECT 1 FROM starred \
+         WHERE starred.song_id = songs.id AND starred.user_id = {user_id})"
+    ))
+}
+
+fn user_date_loved(user_id: i32) -> SqlLiteral<Nullable<Timestamp>> {
+    sql(&format!(
+        "(SELECT starred_at FROM starred \
+         WHERE starred.song_id = songs.id AND starred.user_id = {user_id})"
+    ))
+}
+
+/// The LIKE pattern of a text condition, and whether matches are excluded.
+fn like_pattern(condition: &TextCondition) -> (String, bool) {
+    match condition {
+        TextCondition::Is(v) => (escape_like(v), false),
+        TextCondition::IsNot(v) => (escape_like(v), true),
+        TextCondition::Contains(v) => (format!("%{}%", escape_like(v)), false),
+        TextCondition::NotContains(v) => (format!("%{}%", escape_like(v)), true),
+        TextCondition::StartsWith(v) => (format!("{}%", escape_like(v)), false),
+        TextCondition::EndsWith(v) => (format!("%{}", escape_like(v)), false),
+    }
+}
+
+/// The inclusive range of a number condition, and whether it is excluded.
+fn number_range(condition: &NumberCondition) -> (i64, i64, bool) {
+    match *condition {
+        NumberCondition::Is(v) => (v, v, false),
+        NumberCondition::IsNot(v) => (v, v, true),
+        NumberCondition::Gt(v) => (v.saturating_add(1), i64::MAX, false),
+        NumberCondition::Lt(v) => (i64::MIN, v.saturating_sub(1), false),
+        NumberCondition::InTheRange(from, to) => (from, to, false),
+    }
+}
+
+/// Compile a text condition on a column. LIKE ignores case in SQLite, and
+/// excluding conditions keep songs without a value.
+macro_rules! text_predicate {
+    ($column:expr, $condition:expr) => {{
+        let (pattern, excluded) = like_pattern($condition);
+        let matches = $column.nullable().like(pattern).escape('\\');
+        let predicate: SongPredicate = if excluded {
+            Box::new(not(matches).or($column.is_null().nullable()))
+        } else {
+            Box::new(matches)
+        };
+        predicate
+    }};
+}
+
+/// Compile a number condition on an expression of Rust type `$ty`.
+macro_rules! number_predicate {
+    ($expr:expr, $ty:ty, $condition:expr) => {{
+        let clamp =
+            |v: i64| <$ty>::try_from(v).unwrap_or(if v < 0 { <$ty>::MIN } else { <$ty>::MAX });
+        let (from, to, excluded) = number_range($condition);
+        let matches = $expr.nullable().between(clamp(from), clamp(to));
+        let predicate: SongPredicate = if excluded {
+            Box::new(not(matches).or($expr.is_null().nullable()))
+        } else {
+            Box::new(matches)
+        };
+        predicate
+    }};
+}
+
+/// Compile a date condition on a timestamp expression. Ranges include all
+/// of their last day, and "not in the last" includes songs without a date.
+macro_rules! date_predicate {
+    ($expr:expr, $condition:expr, $now:expr) => {{
+        let predicate: SongPredicate = match *$condition {
+            DateCondition::Before(date) => Box::new($expr.nullable().lt(date)),
+            DateCondition::After(date) => Box::new($expr.nullable().gt(date)),
+            DateCondition::InTheLast(days) => {
+                Box::new($expr.nullable().ge(DateCondition::since(days, $now)))
+            }
+            DateCondition::NotInTheLast(days) => Box::new(
+                $expr
+                    .nullable()
+                    .lt(DateCondition::since(days, $now))
+                    .or($expr.is_null().nullable()),
+            ),
+            DateCondition::InTheRange(from, to) => Box::new(
+                $expr
+                    .nullable()
+                    .ge(from)
+                    .and($expr.nullable().lt(to + chrono::TimeDelta::days(1))),
+            ),
+        };
+        predicate
+    }};
+}
+
+/// Add an ascending or descending sort key to a boxed query.
+macro_rules! order_by {
+    ($query:expr, $expr:expr, $descending:expr) => {
+        if $descending {
+            $query.then_order_by($expr.desc())
+        } else {
+            $query.then_order_by($expr.asc())
+        }
+    };
+}
+
+/// Compile criteria into a condition on songs, with per-user fields
+/// evaluated for `user_id`. Empty groups match every song.
+fn criteria_predicate(criteria: &Criteria, user_id: i32, now: NaiveDateTime) -> SongPredicate {
+    let (items, all) = match criteria {
+        Criteria::All(items) => (items, true),
+        Criteria::Any(items) => (items, false),
+        Criteria::Rule(rule) => return rule_predicate(rule, user_id, now),
+    };
+
+    items
+        .iter()
+        .map(|item| criteria_predicate(item, user_id, now))
+        .reduce(|a, b| -> SongPredicate {
+            if all {
+                Box::new(a.and(b))
+            } else {
+                Box::new(a.or(b))
+            }
+        })
+        .unwrap_or_else(|| Box::new(true.into_sql::<Bool>().nullable()))
+}
+
+/// Compile a single rule into a condition on songs.
+fn rule_predicate(rule: &Rule, user_id: i32, now: NaiveDateTime) -> SongPredicate {
+    match (rule.field, &rule.condition) {
+        (Field::Title, Condition::Text(c)) => text_predicate!(songs::title, c),
+        (Field::Album, Condition::Text(c)) => text_predicate!(songs::album_name, c),
+        (Field::Artist, Condition::Text(c)) => text_predicate!(songs::artist_name, c),
+        (Field::Genre, Condition::Text(c)) => text_predicate!(songs::genre, c),
+        (Field::FileType, Condition::Text(c)) => text_predicate!(songs::suffix, c),
+        (Field::FilePath, Condition::Text(c)) => text_predicate!(songs::path, c),
+        (Field::Year, Condition::Number(c)) => number_predicate!(songs::year, i32, c),
+        (Field::TrackNumber, Condition::Number(c)) => {
+            number_predicate!(songs::track_number, i32, c)
+        }
+        (Field::DiscNumber, Condition::Number(c)) => number_predicate!(songs::disc_number, i32, c),
+        (Field::Duration, Condition::Number(c)) => number_predicate!(songs::duration, i32, c),
+        (Field::BitRate, Condition::Number(c)) => number_predicate!(songs::bit_rate, i32, c),
+        (Field::Size, Condition::Number(c)) => number_predicate!(songs::file_size, i64, c),
+        (Field::Rating, Condition::Number(c)) => number_predicate!(user_rating(user_id), i32, c),
+        (Field::PlayCount, Condition::Number(c)) => {
+            number_predicate!(user_play_count(user_id), i64, c)
+        }
+        (Field::DateAdded, Condition::Date(c)) => date_predicate!(songs::created_at, c, now),
+        (Field::DateModified, Condition::Date(c)) => date_predicate!(songs::updated_at, c, now),
+        (Field::LastPlayed, Condition::Date(c)) => {
+            date_predicate!(user_last_played(user_id), c, now)
+        }
+        (Field::DateLoved, Condition::Date(c)) => date_predicate!(user_date_loved(user_id), c, now),
+        (Field::Loved, Condition::Bool(loved)) => {
+            Box::new(user_loved(user_id).nullable().eq(*loved))
+        }
+        // The parser only pairs fields with conditions of their kind
+        _ => Box::new(false.into_sql::<Bool>().nullable()),
+    }
+}
+
+/// Find the IDs of the songs matching a smart playlist's rules for its
+/// owner, in playlist order.
+fn evaluate_smart_playlist(
+    conn: &mut diesel::SqliteConnection,
+    rules: &SmartPlaylistRules,
+    user_id: i32,
+    now: NaiveDateTime,
+) -> Result<Vec<i32>, MusicRepoError> {
+    let mut query = songs::table
+        .select(songs::id)
+        .filter(criteria_predicate(&rules.criteria, user_id, now))
+        .into_boxed();
+
+    if rules.sort.is_empty() {
+        query = query.then_order_by(songs::title.asc());
+    }
+    for key in &rules.sort {
+        let descending = key.descending;
+        query = match key.by {
+            SortBy::Random => query.then_order_by(sql::<Integer>("RANDOM()")),
+            SortBy::Field(field) => match field {
+                Field::Title => order_by!(query, songs::title, descending),
+                Field::Album => order_by!(query, songs::album_name, descending),
+                Field::Artist => order_by!(query, songs::artist_name, descending),
+                Field::Genre => order_by!(query, songs::genre, descending),
+                Field::FileType => order_by!(query, songs::suffix, descending),
+                Field::FilePath => order_by!(query, songs::path, descending),
+                Field::Year => order_by!(query, songs::year, descending),
+                Field::TrackNumber => order_by!(query, songs::track_number, descending),
+                Field::DiscNumber => order_by!(query, songs::disc_number, descending),
+                Field::Duration => order_by!(query, songs::duration, descending),
+                Field::BitRate => order_by!(query, songs::bit_rate, descending),
+                Field::Size => order_by!(query, songs::file_size, descending),
+                Field::Rating => order_by!(query, user_rating(user_id), descending),
+                Field::PlayCount => order_by!(query, user_play_count(user_id), descending),
+                Field::DateAdded => order_by!(query, songs::created_at, descending),
+                Field::DateModified => order_by!(query, songs::updated_at, descending),
+                Field::LastPlayed => order_by!(query, user_last_played(user_id), descending),
+                Field::DateLoved => order_by!(query, user_date_loved(user_id), descending),
+                Field::Loved => order_by!(query, user_loved(user_id), descending),
+            },
+        };
+    }
+    query = query.then_order_by(songs::id.asc());
+
+    if let Some(limit) = rules.limit {
+        query = query.limit(limit);
+    }
+    if let Some(offset) = rules.offset {
+        query = query.offset(offset);
+    }
+
+    Ok(query.load(conn)?)
+}
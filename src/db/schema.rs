@@ -34,6 +34,7 @@ diesel::table! {
         enabled -> Bool,
         created_at -> Timestamp,
         updated_at -> Timestamp,
+        exclude_patterns -> Nullable<Text>,
     }
 }
 
@@ -47,6 +48,7 @@ diesel::table! {
         artist_image_url -> Nullable<Text>,
         created_at -> Timestamp,
         updated_at -> Timestamp,
+        biography -> Nullable<Text>,
     }
 }
 
@@ -66,6 +68,8 @@ diesel::table! {
         play_count -> Integer,
         created_at -> Timestamp,
         updated_at -> Timestamp,
+        notes -> Nullable<Text>,
+        album_image_url -> Nullable<Text>,
     }
 }
 
@@ -99,6 +103,8 @@ diesel::table! {
         file_modified_at -> Nullable<BigInt>,
         created_at -> Timestamp,
         updated_at -> Timestamp,
+        cue_start_ms -> Nullable<BigInt>,
+        cue_end_ms -> Nullable<BigInt>,
     }
 }
 
@@ -118,9 +124,13 @@ diesel::table! {
         id -> Integer,
         user_id -> Integer,
         song_id -> Integer,
-        player_id -> Nullable<Text>,
+        /// Client reporting the song, or empty if unknown.
+        player_id -> Text,
         started_at -> Timestamp,
-        minutes_ago -> Integer,
+        /// Playback position in milliseconds when last reported.
+        position_ms -> Nullable<BigInt>,
+        updated_at -> Timestamp,
+        expires_at -> Timestamp,
     }
 }
 
@@ -158,6 +168,9 @@ diesel::table! {
         duration -> Integer,
         created_at -> Timestamp,
         updated_at -> Timestamp,
+        source_path -> Nullable<Text>,
+        rules -> Nullable<Text>,
+        rules_evaluated_at -> Nullable<Timestamp>,
     }
 }
 
@@ -191,6 +204,110 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    lyrics (id) {
+        id -> Integer,
+        song_id -> Integer,
+        lang -> Nullable<Text>,
+        description -> Nullable<Text>,
+        synced -> Bool,
+        text -> Text,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    scan_runs (id) {
+        id -> Integer,
+        started_at -> Timestamp,
+        finished_at -> Nullable<Timestamp>,
+        mode -> Text,
+        trigger -> Text,
+        status -> Text,
+        error -> Nullable<Text>,
+        tracks_found -> Integer,
+        tracks_added -> Integer,
+        tracks_updated -> Integer,
+        tracks_skipped -> Integer,
+        tracks_removed -> Integer,
+        tracks_moved -> Integer,
+        tracks_failed -> Integer,
+        artists_added -> Integer,
+        albums_added -> Integer,
+        cover_art_saved -> Integer,
+        playlists_imported -> Integer,
+        pid -> Nullable<Integer>,
+        heartbeat_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    scan_errors (id) {
+        id -> Integer,
+        scan_run_id -> Integer,
+        path -> Text,
+        error -> Text,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    scanned_directories (id) {
+        id -> Integer,
+        music_folder_id -> Integer,
+        path -> Text,
+        /// Directory modification time in nanoseconds since the Unix epoch.
+        modified_at -> BigInt,
+        scanned_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    directories (id) {
+        id -> Integer,
+        music_folder_id -> Integer,
+        parent_id -> Nullable<Integer>,
+        /// Directory path relative to its music folder.
+        path -> Text,
+        name -> Text,
+        cover_art -> Nullable<Text>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    artist_metadata (artist_id) {
+        artist_id -> Integer,
+        agent -> Text,
+        biography -> Nullable<Text>,
+        musicbrainz_id -> Nullable<Text>,
+        last_fm_url -> Nullable<Text>,
+        small_image_url -> Nullable<Text>,
+        medium_image_url -> Nullable<Text>,
+        large_image_url -> Nullable<Text>,
+        /// JSON array of similar artist names.
+        similar_artists -> Text,
+        fetched_at -> Timestamp,
+        expires_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    album_metadata (album_id) {
+        album_id -> Integer,
+        agent -> Text,
+        notes -> Nullable<Text>,
+        musicbrainz_id -> Nullable<Text>,
+        last_fm_url -> Nullable<Text>,
+        small_image_url -> Nullable<Text>,
+        medium_image_url -> Nullable<Text>,
+        large_image_url -> Nullable<Text>,
+        fetched_at -> Timestamp,
+        expires_at -> Timestamp,
+    }
+}
+
 // Define foreign key relationships
 diesel::joinable!(albums -> artists (artist_id));
 diesel::joinable!(songs -> albums (album_id));
@@ -208,6 +325,142 @@ diesel::joinable!(playlist_songs -> songs (song_id));
 diesel::joinable!(play_queue -> users (user_id));
 diesel::joinable!(play_queue_songs -> play_queue (play_queue_id));
 diesel::joinable!(play_queue_songs -> songs (song_id));
+diesel::joinable!(lyrics -> songs (song_id));
+diesel::joinable!(scan_errors -> scan_runs (scan_run_id));
+diesel::table! {
+    artist_similarity (artist_id, similar_artist_id) {
+        artist_id -> Integer,
+        similar_artist_id -> Integer,
+        score -> Double,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    scrobble_accounts (user_id, service) {
+        user_id -> Integer,
+        service -> Text,
+        token -> Text,
+        username -> Nullable<Text>,
+        linked_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    scrobble_outbox (id) {
+        id -> Integer,
+        user_id -> Integer,
+        service -> Text,
+        song_id -> Integer,
+        played_at -> Timestamp,
+        attempts -> Integer,
+        next_attempt_at -> Timestamp,
+        last_error -> Nullable<Text>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    bookmarks (id) {
+        id -> Integer,
+        user_id -> Integer,
+        song_id -> Integer,
+        position -> BigInt,
+        comment -> Nullable<Text>,
+        created_at -> Timestamp,
+        changed_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    shares (id) {
+        id -> Integer,
+        token -> Text,
+        user_id -> Integer,
+        description -> Nullable<Text>,
+        created_at -> Timestamp,
+        expires_at -> Nullable<Timestamp>,
+        last_visited_at -> Nullable<Timestamp>,
+        visit_count -> Integer,
+    }
+}
+
+diesel::table! {
+    share_songs (id) {
+        id -> Integer,
+        share_id -> Integer,
+        song_id -> Integer,
+        position -> Integer,
+    }
+}
+
+diesel::table! {
+    internet_radio_stations (id) {
+        id -> Integer,
+        name -> Text,
+        stream_url -> Text,
+        homepage_url -> Nullable<Text>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    podcast_channels (id) {
+        id -> Integer,
+        url -> Text,
+        title -> Nullable<Text>,
+        description -> Nullable<Text>,
+        image_url -> Nullable<Text>,
+        status -> Text,
+        error_message -> Nullable<Text>,
+        created_at -> Timestamp,
+        last_refreshed_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    podcast_episodes (id) {
+        id -> Integer,
+        channel_id -> Integer,
+        guid -> Text,
+        title -> Text,
+        description -> Nullable<Text>,
+        publish_date -> Nullable<Timestamp>,
+        duration -> Nullable<Integer>,
+        enclosure_url -> Text,
+        content_type -> Nullable<Text>,
+        size -> Nullable<BigInt>,
+        status -> Text,
+        file_path -> Nullable<Text>,
+        error_message -> Nullable<Text>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    chat_messages (id) {
+        id -> Integer,
+        user_id -> Integer,
+        message -> Text,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::joinable!(scanned_directories -> music_folders (music_folder_id));
+diesel::joinable!(directories -> music_folders (music_folder_id));
+diesel::joinable!(artist_metadata -> artists (artist_id));
+diesel::joinable!(album_metadata -> albums (album_id));
+diesel::joinable!(scrobble_accounts -> users (user_id));
+diesel::joinable!(scrobble_outbox -> users (user_id));
+diesel::joinable!(scrobble_outbox -> songs (song_id));
+diesel::joinable!(bookmarks -> users (user_id));
+diesel::joinable!(bookmarks -> songs (song_id));
+diesel::joinable!(shares -> users (user_id));
+diesel::joinable!(share_songs -> shares (share_id));
+diesel::joinable!(share_songs -> songs (song_id));
+diesel::joinable!(podcast_episodes -> podcast_channels (channel_id));
+diesel::joinable!(chat_messages -> users (user_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
     users,
@@ -223,4 +476,21 @@ diesel::allow_tables_to_appear_in_same_query!(
     playlist_songs,
     play_queue,
     play_queue_songs,
+    lyrics,
+    scan_runs,
+    scan_errors,
+    scanned_directories,
+    directories,
+    artist_metadata,
+    album_metadata,
+    artist_similarity,
+    scrobble_accounts,
+    scrobble_outbox,
+    bookmarks,
+    shares,
+    share_songs,
+    internet_radio_stations,
+    podcast_channels,
+    podcast_episodes,
+    chat_messages,
 );
@@ -1,5 +1,6 @@
 //! Subsonic API compatible server library.
 
+pub mod agents;
 pub mod api;
 pub mod crypto;
 pub mod db;
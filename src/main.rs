@@ -2,18 +2,26 @@
 
 use std::sync::Arc;
 
-use axum::{Router, extract::FromRef};
+use axum::{Router, extract::FromRef, routing::get};
 use clap::{Parser, Subcommand};
 use tower_http::cors::{Any, CorsLayer};
 use tower_http::trace::TraceLayer;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
+use subsonic::agents::{
+    DEFAULT_CACHE_TTL_HOURS, LastFmAgent, LastFmScrobbler, ListenBrainzScrobbler, MetadataService,
+    PodcastService, ScrobbleForwarder, SimilarityRefresher, lastfm, listenbrainz, podcast, radio,
+    similarity,
+};
 use subsonic::api::{AuthState, DatabaseAuthState, SubsonicRouterExt, handlers};
 use subsonic::crypto::hash_password;
 use subsonic::db::{
-    DbConfig, DbPool, MusicFolderRepository, NewUser, UserRepository, run_migrations,
+    DbConfig, DbPool, InternetRadioRepository, MusicFolderRepository, NewUser, PlaylistRepository,
+    ScanHistoryRepository, UserRepository, run_migrations,
 };
 use subsonic::models::music::NewMusicFolder;
+use subsonic::scanner::playlists::PlaylistImportConfig;
+use subsonic::scanner::smart_playlists;
 use subsonic::scanner::{AutoScanner, ScanMode, ScanState, Scanner};
 
 /// Subsonic-compatible music streaming server.
@@ -29,6 +37,47 @@ struct Cli {
     #[arg(short, long, default_value = "4040")]
     port: u16,
 
+    /// Owner of playlists imported from playlist files (defaults to the first admin)
+    #[arg(long, value_name = "USERNAME")]
+    playlist_owner: Option<String>,
+
+    /// Make playlists imported from playlist files public
+    #[arg(long)]
+    public_playlists: bool,
+
+    /// Seconds the songs of smart playlists are kept before their rules are evaluated again
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value_t = smart_playlists::DEFAULT_REFRESH_TTL_SECONDS,
+        value_parser = clap::value_parser!(i64).range(0..)
+    )]
+    smart_playlist_ttl: i64,
+
+    /// Last.fm API key, enabling artist and album info lookups
+    #[arg(long, value_name = "KEY")]
+    lastfm_api_key: Option<String>,
+
+    /// Last.fm API secret, enabling scrobbling to Last.fm (with the API key)
+    #[arg(long, value_name = "SECRET")]
+    lastfm_secret: Option<String>,
+
+    /// Base URL of the Last.fm API (or a compatible server)
+    #[arg(long, value_name = "URL", default_value = lastfm::DEFAULT_BASE_URL)]
+    lastfm_url: String,
+
+    /// Base URL of the ListenBrainz API (or a compatible server)
+    #[arg(long, value_name = "URL", default_value = listenbrainz::DEFAULT_BASE_URL)]
+    listenbrainz_url: String,
+
+    /// Hours artist and album info is cached before it is looked up again
+    #[arg(long, value_name = "HOURS", default_value_t = DEFAULT_CACHE_TTL_HOURS)]
+    metadata_ttl: i64,
+
+    /// Folder podcast episodes are downloaded to (defaults to the user's data directory)
+    #[arg(long, value_name = "DIR")]
+    podcast_folder: Option<std::path::PathBuf>,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -80,11 +129,27 @@ enum Commands {
         /// Path to the music folder
         #[arg(short, long)]
         path: String,
+
+        /// Glob pattern (gitignore syntax) for paths to skip when scanning (repeatable)
+        #[arg(short, long = "exclude", value_name = "GLOB")]
+        exclude: Vec<String>,
     },
 
     /// List all music folders
     ListFolders,
 
+    /// Replace the exclude patterns of a music folder
+    SetFolderExcludes {
+        /// ID of the folder to update
+        #[arg(short, long)]
+        id: i32,
+
+        /// Glob pattern (gitignore syntax) for paths to skip when scanning
+        /// (repeatable; none clears the patterns)
+        #[arg(short, long = "exclude", value_name = "GLOB")]
+        exclude: Vec<String>,
+    },
+
     /// Remove a music folder
     RemoveFolder {
         /// ID of the folder to remove
@@ -101,6 +166,22 @@ enum Commands {
         /// Run full scan (re-scan all files regardless of modification time)
         #[arg(long)]
         full: bool,
+
+        /// Skip directories whose modification time hasn't changed, without
+        /// checking their files (misses files retagged in place)
+        #[arg(long)]
+        skip_unchanged_dirs: bool,
+    },
+
+    /// Show recent scans, or the files that failed during a scan
+    ScanHistory {
+        /// Maximum number of scans to show
+        #[arg(short, long, default_value = "20")]
+        limit: i64,
+
+        /// Show the failed files of the given scan ID
+        #[arg(short, long, value_name = "SCAN_ID")]
+        errors: Option<i32>,
     },
 
     /// Start the server (default)
@@ -112,6 +193,54 @@ enum Commands {
         /// Auto-scan interval in seconds (default: 300 = 5 minutes)
         #[arg(long, default_value = "300")]
         auto_scan_interval: u64,
+
+        /// Check every file in auto-scans, instead of skipping directories whose
+        /// modification time hasn't changed (catches files retagged in place)
+        #[arg(long)]
+        check_unchanged_dirs: bool,
+
+        /// Hours between refreshes of artist similarity from listening history
+        #[arg(
+            long,
+            value_name = "HOURS",
+            default_value_t = similarity::DEFAULT_REFRESH_INTERVAL_HOURS,
+            value_parser = clap::value_parser!(u64).range(1..)
+        )]
+        similarity_interval: u64,
+
+        /// Hours between checks of podcast feeds for new episodes
+        #[arg(
+            long,
+            value_name = "HOURS",
+            default_value_t = podcast::DEFAULT_REFRESH_INTERVAL_HOURS,
+            value_parser = clap::value_parser!(u64).range(1..)
+        )]
+        podcast_interval: u64,
+    },
+
+    /// Recompute artist similarity from listening history
+    RefreshSimilarity,
+
+    /// Import internet radio stations from an M3U or PLS station list
+    ImportRadio {
+        /// Station list file (.m3u, .m3u8 or .pls)
+        #[arg(short, long)]
+        file: std::path::PathBuf,
+    },
+
+    /// Create a smart playlist from a JSON rules file (Navidrome .nsp format)
+    ImportSmartPlaylist {
+        /// Rules file (.json or .nsp)
+        #[arg(short, long)]
+        file: std::path::PathBuf,
+
+        /// Owner of the playlist (defaults to the first admin)
+        #[arg(short, long)]
+        owner: Option<String>,
+
+        /// Make the playlist public
+        #[arg(long)]
+        public: bool,
     },
 }
 
@@ -123,10 +252,24 @@ pub struct AppState {
 }
 
 impl AppState {
-    pub fn new(pool: DbPool) -> Self {
+    pub fn new(
+        pool: DbPool,
+        playlist_import: PlaylistImportConfig,
+        smart_playlist_ttl: chrono::TimeDelta,
+        metadata: MetadataService,
+        scrobble_forwarder: ScrobbleForwarder,
+        podcasts: PodcastService,
+    ) -> Self {
         let scan_state = Arc::new(ScanState::new());
         Self {
-            auth: Arc::new(DatabaseAuthState::with_scan_state(pool, scan_state.clone())),
+            auth: Arc::new(
+                DatabaseAuthState::with_scan_state(pool, scan_state.clone())
+                    .with_playlist_import(playlist_import)
+                    .with_smart_playlist_ttl(smart_playlist_ttl)
+                    .with_metadata(metadata)
+                    .with_scrobble_forwarder(scrobble_forwarder)
+                    .with_podcasts(podcasts),
+            ),
             scan_state,
         }
     }
@@ -160,6 +303,8 @@ fn create_router(state: AppState) -> Router {
         .subsonic_route("/tokenInfo", handlers::token_info)
         // Bookmarks endpoints
         .subsonic_route("/getBookmarks", handlers::get_bookmarks)
+        .subsonic_route("/createBookmark", handlers::create_bookmark)
+        .subsonic_route("/deleteBookmark", handlers::delete_bookmark)
         // Browsing endpoints
         .subsonic_route("/getMusicFolders", handlers::get_music_folders)
         .subsonic_route("/getIndexes", handlers::get_indexes)
@@ -221,10 +366,61 @@ fn create_router(state: AppState) -> Router {
         .subsonic_route("/updateUser", handlers::update_user)
         // Scanning endpoints
         .subsonic_route("/startScan", handlers::start_scan)
-        .subsonic_route("/getScanStatus", handlers::get_scan_status);
+        .subsonic_route("/getScanStatus", handlers::get_scan_status)
+        .subsonic_route("/cancelScan", handlers::cancel_scan)
+        .subsonic_route("/getScanHistory", handlers::get_scan_history)
+        .subsonic_route("/getScanErrors", handlers::get_scan_errors)
+        // Statistics endpoints
+        .subsonic_route("/getListeningStats", handlers::get_listening_stats)
+        // Scrobble forwarding endpoints
+        .subsonic_route("/getScrobbleAccounts", handlers::get_scrobble_accounts)
+        .subsonic_route("/linkScrobbleAccount", handlers::link_scrobble_account)
+        .subsonic_route("/unlinkScrobbleAccount", handlers::unlink_scrobble_account)
+        // Sharing endpoints
+        .subsonic_route("/getShares", handlers::get_shares)
+        .subsonic_route("/createShare", handlers::create_share)
+        .subsonic_route("/updateShare", handlers::update_share)
+        .subsonic_route("/deleteShare", handlers::delete_share)
+        // Internet radio endpoints
+        .subsonic_route(
+            "/getInternetRadioStations",
+            handlers::get_internet_radio_stations,
+        )
+        .subsonic_route(
+            "/createInternetRadioStation",
+            handlers::create_internet_radio_station,
+        )
+        .subsonic_route(
+            "/updateInternetRadioStation",
+            handlers::update_internet_radio_station,
+        )
+        .subsonic_route(
+            "/deleteInternetRadioStation",
+            handlers::delete_internet_radio_station,
+        )
+        // Podcast endpoints
+        .subsonic_route("/getPodcasts", handlers::get_podcasts)
+        .subsonic_route("/getNewestPodcasts", handlers::get_newest_podcasts)
+        .subsonic_route("/refreshPodcasts", handlers::refresh_podcasts)
+        .subsonic_route("/createPodcastChannel", handlers::create_podcast_channel)
+        .subsonic_route("/deletePodcastChannel", handlers::delete_podcast_channel)
+        .subsonic_route(
+            "/downloadPodcastEpisode",
+            handlers::download_podcast_episode,
+        )
+        .subsonic_route("/deletePodcastEpisode", handlers::delete_podcast_episode)
+        // Chat endpoints
+        .subsonic_route("/getChatMessages", handlers::get_chat_messages)
+        .subsonic_route("/addChatMessage", handlers::add_chat_message);
+
+    // Public share links, served without authentication
+    let share_routes = Router::new()
+        .route("/{token}", get(handlers::share_page))
+        .route("/{token}/{id}", get(handlers::share_stream));
 
     Router::new()
         .nest("/rest", rest_routes)
+        .nest("/share", share_routes)
         .layer(
             CorsLayer::new()
                 .allow_origin(Any)
@@ -292,6 +488,33 @@ async fn main() {
     // Setup database
     let pool = setup_database(&cli.database);
 
+    let playlist_import = PlaylistImportConfig {
+        owner: cli.playlist_owner.clone(),
+        public: cli.public_playlists,
+    };
+    let smart_playlist_ttl = chrono::TimeDelta::seconds(cli.smart_playlist_ttl);
+
+    let mut metadata =
+        MetadataService::new(pool.clone()).with_ttl(chrono::Duration::hours(cli.metadata_ttl));
+    if let Some(api_key) = &cli.lastfm_api_key {
+        let agent = LastFmAgent::new(api_key).with_base_url(&cli.lastfm_url);
+        metadata = metadata.with_agent(Arc::new(agent));
+    }
+
+    let listenbrainz = ListenBrainzScrobbler::new().with_base_url(&cli.listenbrainz_url);
+    let mut scrobble_forwarder =
+        ScrobbleForwarder::new(pool.clone()).with_scrobbler(Arc::new(listenbrainz));
+    if let (Some(api_key), Some(secret)) = (&cli.lastfm_api_key, &cli.lastfm_secret) {
+        let scrobbler = LastFmScrobbler::new(api_key, secret).with_base_url(&cli.lastfm_url);
+        scrobble_forwarder = scrobble_forwarder.with_scrobbler(Arc::new(scrobbler));
+    }
+
+    let podcast_folder = cli
+        .podcast_folder
+        .clone()
+        .unwrap_or_else(podcast::default_podcast_folder);
+    let podcasts = PodcastService::new(pool.clone(), podcast_folder);
+
     match cli.command {
         Some(Commands::CreateUser {
             username,
@@ -374,13 +597,20 @@ async fn main() {
                 }
             }
         }
-        Some(Commands::AddFolder { name, path }) => {
+        Some(Commands::AddFolder {
+            name,
+            path,
+            exclude,
+        }) => {
             let repo = MusicFolderRepository::new(pool.clone());
-            let new_folder = NewMusicFolder::new(&name, &path);
+            let new_folder = NewMusicFolder::new(&name, &path).with_exclude_patterns(exclude);
             match repo.create(&new_folder) {
                 Ok(folder) => {
                     println!("Added music folder '{}' (id: {})", folder.name, folder.id);
                     println!("  Path: {}", folder.path);
+                    if !folder.exclude_patterns.is_empty() {
+                        println!("  Exclude: {}", folder.exclude_patterns.join(", "));
+                    }
                 }
                 Err(e) => {
                     eprintln!("Failed to add music folder: {}", e);
@@ -407,6 +637,9 @@ async fn main() {
                                 "  [{}] {} - {} ({})",
                                 folder.id, folder.name, folder.path, status
                             );
+                            if !folder.exclude_patterns.is_empty() {
+                                println!("      Exclude: {}", folder.exclude_patterns.join(", "));
+                            }
                         }
                     }
                 }
@@ -416,6 +649,26 @@ async fn main() {
                 }
             }
         }
+        Some(Commands::SetFolderExcludes { id, exclude }) => {
+            let repo = MusicFolderRepository::new(pool.clone());
+            match repo.set_exclude_patterns(id, &exclude) {
+                Ok(true) if exclude.is_empty() => {
+                    println!("Cleared exclude patterns of music folder {}", id);
+                }
+                Ok(true) => {
+                    println!("Music folder {} now excludes: {}", id, exclude.join(", "));
+                    println!("Run a scan to apply the new patterns");
+                }
+                Ok(false) => {
+                    eprintln!("Music folder with id {} not found", id);
+                    std::process::exit(1);
+                }
+                Err(e) => {
+                    eprintln!("Failed to update music folder: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
         Some(Commands::RemoveFolder { id }) => {
             let repo = MusicFolderRepository::new(pool.clone());
             match repo.delete(id) {
@@ -432,8 +685,19 @@ async fn main() {
                 }
             }
         }
-        Some(Commands::Scan { folder, full }) => {
-            let scanner = Scanner::new(pool.clone());
+        Some(Commands::Scan {
+            folder,
+            full,
+            skip_unchanged_dirs,
+        }) => {
+            // Close scans whose process exited without recording their outcome
+            if let Err(e) = ScanHistoryRepository::new(pool.clone()).abort_stale_runs() {
+                eprintln!("Warning: Failed to close interrupted scan runs: {}", e);
+            }
+
+            let scanner = Scanner::new(pool.clone())
+                .with_playlist_import(playlist_import)
+                .with_skip_unchanged_dirs(skip_unchanged_dirs);
             let mode = if full {
                 ScanMode::Full
             } else {
@@ -454,10 +718,12 @@ async fn main() {
                     println!("  Tracks updated:   {}", stats.tracks_updated);
                     println!("  Tracks skipped:   {}", stats.tracks_skipped);
                     println!("  Tracks removed:   {}", stats.tracks_removed);
+                    println!("  Tracks moved:     {}", stats.tracks_moved);
                     println!("  Tracks failed:    {}", stats.tracks_failed);
                     println!("  Artists added:    {}", stats.artists_added);
                     println!("  Albums added:     {}", stats.albums_added);
                     println!("  Cover art saved:  {}", stats.cover_art_saved);
+                    println!("  Playlists:        {}", stats.playlists_imported);
                 }
                 Err(e) => {
                     eprintln!("Scan failed: {}", e);
@@ -465,20 +731,228 @@ async fn main() {
                 }
             }
         }
+        Some(Commands::ScanHistory { limit, errors }) => {
+            let repo = ScanHistoryRepository::new(pool.clone());
+            if let Some(run_id) = errors {
+                match repo.find_errors(run_id) {
+                    Ok(errors) if errors.is_empty() => {
+                        println!("No failed files recorded for scan {}", run_id);
+                    }
+                    Ok(errors) => {
+                        println!("Failed files for scan {}:", run_id);
+                        for error in errors {
+                            println!("  {}", error.path);
+                            println!("      {}", error.error);
+                        }
+                    }
+                    Err(e) => {
+                        eprintln!("Failed to load scan errors: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+            } else {
+                match repo.find_recent(limit) {
+                    Ok(runs) if runs.is_empty() => {
+                        println!("No scans recorded yet.");
+                    }
+                    Ok(runs) => {
+                        println!("Recent scans:");
+                        for run in runs {
+                            println!(
+                                "  [{}] {} {} ({}, {}) - {}",
+                                run.id,
+                                run.started_at.format("%Y-%m-%d %H:%M:%S"),
+                                run.mode,
+                                run.trigger,
+                                run.status,
+                                run.finished_at
+                                    .map(|t| format!("{}s", (t - run.started_at).num_seconds()))
+                                    .unwrap_or_else(|| "in progress".to_string())
+                            );
+                            println!(
+                                "      found {}, added {}, updated {}, removed {}, moved {}, failed {}",
+                                run.tracks_found,
+                                run.tracks_added,
+                                run.tracks_updated,
+                                run.tracks_removed,
+                                run.tracks_moved,
+                                run.tracks_failed
+                            );
+                            if let Some(error) = &run.error {
+                                println!("      error: {}", error);
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        eprintln!("Failed to load scan history: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+        }
+        Some(Commands::RefreshSimilarity) => match similarity::refresh_similarity(pool) {
+            Ok(count) => println!("Artist similarity refreshed: {} pairs", count),
+            Err(e) => {
+                eprintln!("Failed to refresh artist similarity: {}", e);
+                std::process::exit(1);
+            }
+        },
+        Some(Commands::ImportRadio { file }) => {
+            let stations = match radio::read_station_list(&file) {
+                Ok(stations) => stations,
+                Err(e) => {
+                    eprintln!("Failed to read {}: {}", file.display(), e);
+                    std::process::exit(1);
+                }
+            };
+
+            let repo = InternetRadioRepository::new(pool.clone());
+            let (mut imported, mut existing, mut invalid) = (0, 0, 0);
+            for station in stations {
+                if !radio::is_http_url(&station.stream_url) {
+                    println!("  Skipped invalid stream URL: {}", station.stream_url);
+                    invalid += 1;
+                    continue;
+                }
+                let stream_url = match radio::resolve_stream_url(&station.stream_url).await {
+                    Ok(stream_url) => stream_url,
+                    Err(e) => {
+                        println!("  Could not resolve {}: {}", station.stream_url, e);
+                        station.stream_url.clone()
+                    }
+                };
+                match repo.exists_with_stream_url(&stream_url) {
+                    Ok(true) => existing += 1,
+                    Ok(false) => match repo.create(&station.name_or_host(), &stream_url, None) {
+                        Ok(created) => {
+                            println!("  [{}] {} - {}", created.id, created.name, stream_url);
+                            imported += 1;
+                        }
+                        Err(e) => {
+                            eprintln!("Failed to add station: {}", e);
+                            std::process::exit(1);
+                        }
+                    },
+                    Err(e) => {
+                        eprintln!("Database error: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            println!(
+                "Imported {} stations ({} already present, {} invalid)",
+                imported, existing, invalid
+            );
+        }
+        Some(Commands::ImportSmartPlaylist {
+            file,
+            owner,
+            public,
+        }) => {
+            let smart = match smart_playlists::read_smart_playlist_file(&file) {
+                Ok(smart) => smart,
+                Err(e) => {
+                    eprintln!("Failed to read {}: {}", file.display(), e);
+                    std::process::exit(1);
+                }
+            };
+
+            let user_repo = UserRepository::new(pool.clone());
+            let user = match &owner {
+                Some(username) => user_repo.find_by_username(username),
+                None => user_repo.find_first_admin(),
+            };
+            let user = match user {
+                Ok(Some(user)) => user,
+                Ok(None) => {
+                    match &owner {
+                        Some(username) => eprintln!("User '{}' not found", username),
+                        None => eprintln!("No admin user found; use --owner"),
+                    }
+                    std::process::exit(1);
+                }
+                Err(e) => {
+                    eprintln!("Database error: {}", e);
+                    std::process::exit(1);
+                }
+            };
+
+            let repo = PlaylistRepository::new(pool.clone());
+            match repo.create_smart_playlist(
+                user.id,
+                &smart.name,
+                smart.comment.as_deref(),
+                public,
+                &smart.definition,
+            ) {
+                Ok(playlist) => println!(
+                    "Created smart playlist '{}' (id: {}, owner: {})",
+                    playlist.name, playlist.id, playlist.owner
+                ),
+                Err(e) => {
+                    eprintln!("Failed to create smart playlist: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
         Some(Commands::Serve {
             auto_scan,
             auto_scan_interval,
+            check_unchanged_dirs,
+            similarity_interval,
+            podcast_interval,
         }) => {
-            run_server(pool, cli.port, auto_scan, auto_scan_interval).await;
+            let podcasts =
+                podcasts.with_interval(std::time::Duration::from_secs(podcast_interval * 60 * 60));
+            run_server(
+                pool,
+                cli.port,
+                auto_scan,
+                auto_scan_interval,
+                !check_unchanged_dirs,
+                similarity_interval,
+                playlist_import,
+                smart_playlist_ttl,
+                metadata,
+                scrobble_forwarder,
+                podcasts,
+            )
+            .await;
         }
         None => {
             // Default: start server without auto-scan
-            run_server(pool, cli.port, false, 300).await;
+            run_server(
+                pool,
+                cli.port,
+                false,
+                300,
+                true,
+                similarity::DEFAULT_REFRESH_INTERVAL_HOURS,
+                playlist_import,
+                smart_playlist_ttl,
+                metadata,
+                scrobble_forwarder,
+                podcasts,
+            )
+            .await;
         }
     }
 }
 
-async fn run_server(pool: DbPool, port: u16, auto_scan: bool, auto_scan_interval: u64) {
+#[allow(clippy::too_many_arguments)]
+async fn run_server(
+    pool: DbPool,
+    port: u16,
+    auto_scan: bool,
+    auto_scan_interval: u64,
+    skip_unchanged_dirs: bool,
+    similarity_interval: u64,
+    playlist_import: PlaylistImportConfig,
+    smart_playlist_ttl: chrono::TimeDelta,
+    metadata: MetadataService,
+    scrobble_forwarder: ScrobbleForwarder,
+    podcasts: PodcastService,
+) {
     // Check if there are any users
     let repo = UserRepository::new(pool.clone());
     if !repo.has_users().unwrap_or(false) {
@@ -486,13 +960,36 @@ async fn run_server(pool: DbPool, port: u16, auto_scan: bool, auto_scan_interval
         tracing::warn!("  subsonic create-user --username admin --password <password> --admin");
     }
 
-    let state = AppState::new(pool.clone());
+    // Close scans whose process exited without recording their outcome
+    if let Err(e) = ScanHistoryRepository::new(pool.clone()).abort_stale_runs() {
+        tracing::warn!("Failed to close interrupted scan runs: {}", e);
+    }
+
+    let _scrobble_handle = scrobble_forwarder.start();
+    let _podcast_handle = podcasts.start();
+    let state = AppState::new(
+        pool.clone(),
+        playlist_import.clone(),
+        smart_playlist_ttl,
+        metadata,
+        scrobble_forwarder,
+        podcasts,
+    );
     let app = create_router(state.clone());
 
+    // Periodically recompute artist similarity from listening history
+    let _similarity_handle = SimilarityRefresher::new(pool.clone())
+        .with_interval(std::time::Duration::from_secs(
+            similarity_interval * 60 * 60,
+        ))
+        .start();
+
     // Start auto-scanner if enabled, sharing the same scan state with the API
     let _auto_scan_handle = if auto_scan {
         let scan_state = state.scan_state();
-        let mut auto_scanner = AutoScanner::with_interval(pool, scan_state, auto_scan_interval);
+        let mut auto_scanner = AutoScanner::with_interval(pool, scan_state, auto_scan_interval)
+            .with_playlist_import(playlist_import)
+            .with_skip_unchanged_dirs(skip_unchanged_dirs);
         tracing::info!(
             "Auto-scan enabled with interval {} seconds",
             auto_scan_interval
@@ -0,0 +1,49 @@
+//! Chat models.
+
+use chrono::NaiveDateTime;
+use serde::Serialize;
+
+/// Number of chat messages kept; older messages are deleted as new ones
+/// are added.
+pub const MAX_CHAT_MESSAGES: i64 = 1000;
+
+/// Longest chat message accepted, in characters.
+pub const MAX_CHAT_MESSAGE_LENGTH: usize = 2000;
+
+/// A message posted to the shared chat.
+#[derive(Debug, Clone)]
+pub struct ChatMessage {
+    pub id: i32,
+    pub username: String,
+    pub message: String,
+    pub created_at: NaiveDateTime,
+}
+
+/// Chat message entry for getChatMessages.
+#[derive(Debug, Serialize, Clone)]
+pub struct ChatMessageResponse {
+    #[serde(rename = "@username")]
+    pub username: String,
+    /// Time the message was posted, in milliseconds since the epoch.
+    #[serde(rename = "@time")]
+    pub time: i64,
+    #[serde(rename = "@message")]
+    pub message: String,
+}
+
+impl From<&ChatMessage> for ChatMessageResponse {
+    fn from(message: &ChatMessage) -> Self {
+        Self {
+            username: message.username.clone(),
+            time: message.created_at.and_utc().timestamp_millis(),
+            message: message.message.clone(),
+        }
+    }
+}
+
+/// Chat messages response for getChatMessages.
+#[derive(Debug, Serialize, Clone)]
+pub struct ChatMessagesResponse {
+    #[serde(rename = "chatMessage", skip_serializing_if = "Vec::is_empty")]
+    pub messages: Vec<ChatMessageResponse>,
+}
@@ -0,0 +1,58 @@
+//! External metadata models.
+
+use chrono::NaiveDateTime;
+
+/// Artist details fetched from a metadata agent.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ArtistMetadata {
+    pub biography: Option<String>,
+    pub musicbrainz_id: Option<String>,
+    pub last_fm_url: Option<String>,
+    pub small_image_url: Option<String>,
+    pub medium_image_url: Option<String>,
+    pub large_image_url: Option<String>,
+    /// Names of similar artists, most similar first.
+    pub similar_artists: Vec<String>,
+}
+
+/// Album details fetched from a metadata agent.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AlbumMetadata {
+    pub notes: Option<String>,
+    pub musicbrainz_id: Option<String>,
+    pub last_fm_url: Option<String>,
+    pub small_image_url: Option<String>,
+    pub medium_image_url: Option<String>,
+    pub large_image_url: Option<String>,
+}
+
+/// Metadata read from the cache, with the time it should be refreshed.
+#[derive(Debug, Clone)]
+pub struct CachedMetadata<T> {
+    pub value: T,
+    pub fetched_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+}
+
+impl<T> CachedMetadata<T> {
+    /// Whether the cached value is due to be refreshed.
+    pub fn is_expired(&self, now: NaiveDateTime) -> bool {
+        self.expires_at <= now
+    }
+}
+
+/// A play of an artist's song by a user, as input to the similarity model.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ArtistPlay {
+    pub user_id: i32,
+    pub artist_id: i32,
+    pub played_at: NaiveDateTime,
+}
+
+/// How similar one artist is to another, from 0 to 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ArtistSimilarity {
+    pub artist_id: i32,
+    pub similar_artist_id: i32,
+    pub score: f64,
+}
@@ -1,6 +1,13 @@
 //! Models for the Subsonic API.
 
+pub mod chat;
+pub mod metadata;
 pub mod music;
+pub mod podcast;
+pub mod radio;
+pub mod scan;
+pub mod scrobbling;
+pub mod stats;
 pub mod user;
 
 pub use music::*;
@@ -3,6 +3,8 @@
 use chrono::NaiveDateTime;
 use serde::Serialize;
 
+use super::metadata::{AlbumMetadata, ArtistMetadata};
+
 /// A music folder (library root directory).
 #[derive(Debug, Clone)]
 pub struct MusicFolder {
@@ -12,6 +14,8 @@ pub struct MusicFolder {
     pub enabled: bool,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
+    /// Glob patterns for paths to skip when scanning.
+    pub exclude_patterns: Vec<String>,
 }
 
 /// Subsonic API music folder response format.
@@ -32,6 +36,34 @@ impl From<&MusicFolder> for MusicFolderResponse {
     }
 }
 
+/// A directory on disk inside a music folder, recorded by scans.
+#[derive(Debug, Clone)]
+pub struct Directory {
+    pub id: i32,
+    pub music_folder_id: i32,
+    /// Parent directory, or `None` for top-level directories.
+    pub parent_id: Option<i32>,
+    /// Path relative to the music folder.
+    pub path: String,
+    pub name: String,
+    pub cover_art: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+/// Prefix that keeps directory IDs apart from album, artist and folder IDs.
+const DIRECTORY_ID_PREFIX: &str = "dir-";
+
+/// Format a directory ID for API responses.
+pub fn directory_response_id(id: i32) -> String {
+    format!("{}{}", DIRECTORY_ID_PREFIX, id)
+}
+
+/// Parse a directory ID from an API request.
+pub fn parse_directory_id(id: &str) -> Option<i32> {
+    id.strip_prefix(DIRECTORY_ID_PREFIX)?.parse().ok()
+}
+
 /// An artist in the music library.
 #[derive(Debug, Clone)]
 pub struct Artist {
@@ -41,6 +73,8 @@ pub struct Artist {
     pub musicbrainz_id: Option<String>,
     pub cover_art: Option<String>,
     pub artist_image_url: Option<String>,
+    /// Biography from the artist's info files.
+    pub biography: Option<String>,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
 }
@@ -62,6 +96,19 @@ pub struct ArtistResponse {
     pub average_rating: Option<f64>,
 }
 
+impl From<&Directory> for ArtistResponse {
+    fn from(directory: &Directory) -> Self {
+        Self {
+            id: directory_response_id(directory.id),
+            name: directory.name.clone(),
+            artist_image_url: None,
+            starred: None,
+            user_rating: None,
+            average_rating: None,
+        }
+    }
+}
+
 impl From<&Artist> for ArtistResponse {
     fn from(artist: &Artist) -> Self {
         Self {
@@ -97,6 +144,20 @@ pub struct ArtistID3Response {
 }
 
 impl ArtistID3Response {
+    /// Create a response for a similar artist that is not in the library.
+    pub fn not_present(name: &str) -> Self {
+        Self {
+            id: String::new(),
+            name: name.to_string(),
+            cover_art: None,
+            artist_image_url: None,
+            album_count: None,
+            starred: None,
+            musicbrainz_id: None,
+            sort_name: None,
+        }
+    }
+
     pub fn from_artist(artist: &Artist, album_count: Option<i32>) -> Self {
         Self {
             id: artist.id.to_string(),
@@ -142,11 +203,21 @@ pub struct Album {
     pub musicbrainz_id: Option<String>,
     pub duration: i32,
     pub song_count: i32,
-    pub play_count: i32,
+    /// Notes from the album's info file.
+    pub notes: Option<String>,
+    /// Image URL from the album's info file.
+    pub album_image_url: Option<String>,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
 }
 
+/// How often and when a user last played a song or album.
+#[derive(Debug, Clone, Default)]
+pub struct PlayStats {
+    pub play_count: i32,
+    pub last_played: Option<NaiveDateTime>,
+}
+
 /// Subsonic API album ID3 response format.
 #[derive(Debug, Serialize, Clone)]
 pub struct AlbumID3Response {
@@ -166,6 +237,8 @@ pub struct AlbumID3Response {
     pub duration: i32,
     #[serde(rename = "@playCount", skip_serializing_if = "Option::is_none")]
     pub play_count: Option<i32>,
+    #[serde(rename = "@played", skip_serializing_if = "Option::is_none")]
+    pub played: Option<String>,
     #[serde(rename = "@created")]
     pub created: String,
     #[serde(rename = "@starred", skip_serializing_if = "Option::is_none")]
@@ -186,7 +259,8 @@ impl From<&Album> for AlbumID3Response {
             cover_art: album.cover_art.clone(),
             song_count: album.song_count,
             duration: album.duration,
-            play_count: Some(album.play_count),
+            play_count: None,
+            played: None,
             created: album
                 .created_at
                 .format("%Y-%m-%dT%H:%M:%S%.3fZ")
@@ -208,7 +282,8 @@ impl AlbumID3Response {
             cover_art: album.cover_art.clone(),
             song_count: album.song_count,
             duration: album.duration,
-            play_count: Some(album.play_count),
+            play_count: None,
+            played: None,
             created: album
                 .created_at
                 .format("%Y-%m-%dT%H:%M:%S%.3fZ")
@@ -218,6 +293,15 @@ impl AlbumID3Response {
             genre: album.genre.clone(),
         }
     }
+
+    /// Set the requesting user's play count and last play time.
+    pub fn with_play_stats(mut self, stats: Option<&PlayStats>) -> Self {
+        self.play_count = Some(stats.map_or(0, |s| s.play_count));
+        self.played = stats
+            .and_then(|s| s.last_played)
+            .map(|dt| dt.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string());
+        self
+    }
 }
 
 /// A song/track in the music library.
@@ -247,9 +331,34 @@ pub struct Song {
     pub genre: Option<String>,
     pub cover_art: Option<String>,
     pub musicbrainz_id: Option<String>,
-    pub play_count: i32,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
+    /// Start offset in milliseconds for a virtual track split by a CUE sheet.
+    pub cue_start_ms: Option<i64>,
+    /// End offset in milliseconds for a CUE track (None = end of file).
+    pub cue_end_ms: Option<i64>,
+}
+
+impl Song {
+    /// Whether this song is a virtual track split from a larger file by a CUE sheet.
+    pub fn is_cue_track(&self) -> bool {
+        self.cue_start_ms.is_some()
+    }
+
+    /// Path of the audio file on disk.
+    ///
+    /// CUE tracks are stored as `<file>#<track>` so each one gets a unique path;
+    /// this strips the track suffix to get the underlying file.
+    pub fn file_path(&self) -> &str {
+        if self.is_cue_track() {
+            self.path
+                .rsplit_once('#')
+                .map(|(file, _)| file)
+                .unwrap_or(&self.path)
+        } else {
+            &self.path
+        }
+    }
 }
 
 /// Subsonic API child (song) response format.
@@ -295,6 +404,8 @@ pub struct ChildResponse {
     pub path: Option<String>,
     #[serde(rename = "@playCount", skip_serializing_if = "Option::is_none")]
     pub play_count: Option<i32>,
+    #[serde(rename = "@played", skip_serializing_if = "Option::is_none")]
+    pub played: Option<String>,
     #[serde(rename = "@discNumber", skip_serializing_if = "Option::is_none")]
     pub disc_number: Option<i32>,
     #[serde(rename = "@created", skip_serializing_if = "Option::is_none")]
@@ -331,7 +442,8 @@ impl From<&Song> for ChildResponse {
             sampling_rate: song.sampling_rate,
             channel_count: song.channel_count,
             path: Some(song.path.clone()),
-            play_count: Some(song.play_count),
+            play_count: None,
+            played: None,
             disc_number: song.disc_number,
             created: Some(song.created_at.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string()),
             album_id: song.album_id.map(|id| id.to_string()),
@@ -364,7 +476,8 @@ impl ChildResponse {
             sampling_rate: song.sampling_rate,
             channel_count: song.channel_count,
             path: Some(song.path.clone()),
-            play_count: Some(song.play_count),
+            play_count: None,
+            played: None,
             disc_number: song.disc_number,
             created: Some(song.created_at.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string()),
             album_id: song.album_id.map(|id| id.to_string()),
@@ -373,6 +486,15 @@ impl ChildResponse {
             starred: starred_at.map(|dt| dt.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string()),
         }
     }
+
+    /// Set the requesting user's play count and last play time.
+    pub fn with_play_stats(mut self, stats: Option<&PlayStats>) -> Self {
+        self.play_count = Some(stats.map_or(0, |s| s.play_count));
+        self.played = stats
+            .and_then(|s| s.last_played)
+            .map(|dt| dt.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string());
+        self
+    }
 }
 
 /// Index entry for getIndexes response.
@@ -393,6 +515,9 @@ pub struct IndexesResponse {
     pub last_modified: i64,
     #[serde(rename = "index", skip_serializing_if = "Vec::is_empty")]
     pub indexes: Vec<IndexResponse>,
+    /// Files at the root of the music folder.
+    #[serde(rename = "child", skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<ChildResponse>,
 }
 
 /// Index entry for getArtists response (ID3 version).
@@ -432,6 +557,8 @@ pub struct AlbumWithSongsID3Response {
     pub duration: i32,
     #[serde(rename = "@playCount", skip_serializing_if = "Option::is_none")]
     pub play_count: Option<i32>,
+    #[serde(rename = "@played", skip_serializing_if = "Option::is_none")]
+    pub played: Option<String>,
     #[serde(rename = "@created")]
     pub created: String,
     #[serde(rename = "@starred", skip_serializing_if = "Option::is_none")]
@@ -454,7 +581,8 @@ impl AlbumWithSongsID3Response {
             cover_art: album.cover_art.clone(),
             song_count: album.song_count,
             duration: album.duration,
-            play_count: Some(album.play_count),
+            play_count: None,
+            played: None,
             created: album
                 .created_at
                 .format("%Y-%m-%dT%H:%M:%S%.3fZ")
@@ -479,7 +607,8 @@ impl AlbumWithSongsID3Response {
             cover_art: album.cover_art.clone(),
             song_count: album.song_count,
             duration: album.duration,
-            play_count: Some(album.play_count),
+            play_count: None,
+            played: None,
             created: album
                 .created_at
                 .format("%Y-%m-%dT%H:%M:%S%.3fZ")
@@ -490,6 +619,15 @@ impl AlbumWithSongsID3Response {
             songs,
         }
     }
+
+    /// Set the requesting user's play count and last play time.
+    pub fn with_play_stats(mut self, stats: Option<&PlayStats>) -> Self {
+        self.play_count = Some(stats.map_or(0, |s| s.play_count));
+        self.played = stats
+            .and_then(|s| s.last_played)
+            .map(|dt| dt.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string());
+        self
+    }
 }
 
 /// Artist with albums response for getArtist.
@@ -555,6 +693,7 @@ pub struct NewMusicFolder {
     pub name: String,
     pub path: String,
     pub enabled: bool,
+    pub exclude_patterns: Vec<String>,
 }
 
 impl NewMusicFolder {
@@ -563,8 +702,15 @@ impl NewMusicFolder {
             name: name.into(),
             path: path.into(),
             enabled: true,
+            exclude_patterns: Vec::new(),
         }
     }
+
+    /// Set glob patterns for paths to skip when scanning.
+    pub fn with_exclude_patterns(mut self, patterns: Vec<String>) -> Self {
+        self.exclude_patterns = patterns;
+        self
+    }
 }
 
 /// New artist for insertion.
@@ -725,6 +871,8 @@ pub struct StarredChildResponse {
     pub path: Option<String>,
     #[serde(rename = "@playCount", skip_serializing_if = "Option::is_none")]
     pub play_count: Option<i32>,
+    #[serde(rename = "@played", skip_serializing_if = "Option::is_none")]
+    pub played: Option<String>,
     #[serde(rename = "@discNumber", skip_serializing_if = "Option::is_none")]
     pub disc_number: Option<i32>,
     #[serde(rename = "@created", skip_serializing_if = "Option::is_none")]
@@ -761,7 +909,8 @@ impl StarredChildResponse {
             sampling_rate: song.sampling_rate,
             channel_count: song.channel_count,
             path: Some(song.path.clone()),
-            play_count: Some(song.play_count),
+            play_count: None,
+            played: None,
             disc_number: song.disc_number,
             created: Some(song.created_at.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string()),
             album_id: song.album_id.map(|id| id.to_string()),
@@ -770,6 +919,15 @@ impl StarredChildResponse {
             starred: starred_at.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
         }
     }
+
+    /// Set the requesting user's play count and last play time.
+    pub fn with_play_stats(mut self, stats: Option<&PlayStats>) -> Self {
+        self.play_count = Some(stats.map_or(0, |s| s.play_count));
+        self.played = stats
+            .and_then(|s| s.last_played)
+            .map(|dt| dt.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string());
+        self
+    }
 }
 
 /// ArtistID3Response with starred timestamp for getStarred2.
@@ -831,6 +989,8 @@ pub struct StarredAlbumID3Response {
     pub duration: i32,
     #[serde(rename = "@playCount", skip_serializing_if = "Option::is_none")]
     pub play_count: Option<i32>,
+    #[serde(rename = "@played", skip_serializing_if = "Option::is_none")]
+    pub played: Option<String>,
     #[serde(rename = "@created")]
     pub created: String,
     #[serde(rename = "@starred")]
@@ -851,7 +1011,8 @@ impl StarredAlbumID3Response {
             cover_art: album.cover_art.clone(),
             song_count: album.song_count,
             duration: album.duration,
-            play_count: Some(album.play_count),
+            play_count: None,
+            played: None,
             created: album
                 .created_at
                 .format("%Y-%m-%dT%H:%M:%S%.3fZ")
@@ -861,6 +1022,15 @@ impl StarredAlbumID3Response {
             genre: album.genre.clone(),
         }
     }
+
+    /// Set the requesting user's play count and last play time.
+    pub fn with_play_stats(mut self, stats: Option<&PlayStats>) -> Self {
+        self.play_count = Some(stats.map_or(0, |s| s.play_count));
+        self.played = stats
+            .and_then(|s| s.last_played)
+            .map(|dt| dt.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string());
+        self
+    }
 }
 
 /// Starred2 response for getStarred2.
@@ -926,6 +1096,9 @@ pub struct NowPlayingEntryResponse {
     pub minutes_ago: i32,
     #[serde(rename = "@playerId", skip_serializing_if = "Option::is_none")]
     pub player_id: Option<String>,
+    /// Estimated playback position in milliseconds, if the player reported one.
+    #[serde(rename = "@position", skip_serializing_if = "Option::is_none")]
+    pub position: Option<i64>,
 }
 
 impl NowPlayingEntryResponse {
@@ -934,6 +1107,7 @@ impl NowPlayingEntryResponse {
         username: String,
         minutes_ago: i32,
         player_id: Option<String>,
+        position: Option<i64>,
     ) -> Self {
         Self {
             id: song.id.to_string(),
@@ -958,6 +1132,7 @@ impl NowPlayingEntryResponse {
             username,
             minutes_ago,
             player_id,
+            position,
         }
     }
 }
@@ -1014,6 +1189,10 @@ pub struct PlaylistResponse {
     pub changed: String,
     #[serde(rename = "@coverArt", skip_serializing_if = "Option::is_none")]
     pub cover_art: Option<String>,
+    #[serde(rename = "@readonly", skip_serializing_if = "Option::is_none")]
+    pub readonly: Option<bool>,
+    #[serde(rename = "@validUntil", skip_serializing_if = "Option::is_none")]
+    pub valid_until: Option<String>,
 }
 
 /// Playlists response for getPlaylists.
@@ -1046,6 +1225,10 @@ pub struct PlaylistWithSongsResponse {
     pub changed: String,
     #[serde(rename = "@coverArt", skip_serializing_if = "Option::is_none")]
     pub cover_art: Option<String>,
+    #[serde(rename = "@readonly", skip_serializing_if = "Option::is_none")]
+    pub readonly: Option<bool>,
+    #[serde(rename = "@validUntil", skip_serializing_if = "Option::is_none")]
+    pub valid_until: Option<String>,
     #[serde(rename = "entry", skip_serializing_if = "Vec::is_empty")]
     pub entries: Vec<ChildResponse>,
 }
@@ -1089,6 +1272,69 @@ pub struct PlayQueueByIndexResponse {
     pub entries: Vec<ChildResponse>,
 }
 
+// ============================================================================
+// Response types for bookmarks
+// ============================================================================
+
+/// Bookmark entry for getBookmarks.
+#[derive(Debug, Serialize, Clone)]
+pub struct BookmarkResponse {
+    /// Position in milliseconds.
+    #[serde(rename = "@position")]
+    pub position: i64,
+    #[serde(rename = "@username")]
+    pub username: String,
+    #[serde(rename = "@comment", skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    #[serde(rename = "@created")]
+    pub created: String,
+    #[serde(rename = "@changed")]
+    pub changed: String,
+    pub entry: ChildResponse,
+}
+
+/// Bookmarks response for getBookmarks.
+#[derive(Debug, Serialize, Clone)]
+pub struct BookmarksResponse {
+    #[serde(rename = "bookmark", skip_serializing_if = "Vec::is_empty")]
+    pub bookmarks: Vec<BookmarkResponse>,
+}
+
+// ============================================================================
+// Response types for shares
+// ============================================================================
+
+/// Share entry for getShares and createShare.
+#[derive(Debug, Serialize, Clone)]
+pub struct ShareResponse {
+    #[serde(rename = "@id")]
+    pub id: String,
+    /// Public URL of the share.
+    #[serde(rename = "@url")]
+    pub url: String,
+    #[serde(rename = "@description", skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(rename = "@username")]
+    pub username: String,
+    #[serde(rename = "@created")]
+    pub created: String,
+    #[serde(rename = "@expires", skip_serializing_if = "Option::is_none")]
+    pub expires: Option<String>,
+    #[serde(rename = "@lastVisited", skip_serializing_if = "Option::is_none")]
+    pub last_visited: Option<String>,
+    #[serde(rename = "@visitCount")]
+    pub visit_count: i32,
+    #[serde(rename = "entry", skip_serializing_if = "Vec::is_empty")]
+    pub entries: Vec<ChildResponse>,
+}
+
+/// Shares response for getShares and createShare.
+#[derive(Debug, Serialize, Clone)]
+pub struct SharesResponse {
+    #[serde(rename = "share", skip_serializing_if = "Vec::is_empty")]
+    pub shares: Vec<ShareResponse>,
+}
+
 /// Token info response for tokenInfo (OpenSubsonic).
 /// Returns information about the API key used for authentication.
 #[derive(Debug, Serialize, Clone)]
@@ -1134,10 +1380,10 @@ impl ArtistInfo2Response {
         }
     }
 
-    /// Create an artist info response with musicbrainz_id from the artist.
+    /// Create an artist info response with the biography and IDs of the artist.
     pub fn from_artist(artist: &Artist) -> Self {
         Self {
-            biography: None,
+            biography: artist.biography.clone(),
             musicbrainz_id: artist.musicbrainz_id.clone(),
             last_fm_url: None,
             small_image_url: artist.artist_image_url.clone(),
@@ -1146,6 +1392,25 @@ impl ArtistInfo2Response {
             similar_artists: Vec::new(),
         }
     }
+
+    /// Fill in details the artist's own info files lack from agent metadata.
+    pub fn with_metadata(mut self, metadata: &ArtistMetadata) -> Self {
+        self.biography = self.biography.or_else(|| metadata.biography.clone());
+        self.musicbrainz_id = self
+            .musicbrainz_id
+            .or_else(|| metadata.musicbrainz_id.clone());
+        self.last_fm_url = self.last_fm_url.or_else(|| metadata.last_fm_url.clone());
+        self.small_image_url = self
+            .small_image_url
+            .or_else(|| metadata.small_image_url.clone());
+        self.medium_image_url = self
+            .medium_image_url
+            .or_else(|| metadata.medium_image_url.clone());
+        self.large_image_url = self
+            .large_image_url
+            .or_else(|| metadata.large_image_url.clone());
+        self
+    }
 }
 
 /// Album info response for getAlbumInfo2.
@@ -1181,14 +1446,33 @@ impl AlbumInfoResponse {
     /// Create an album info response with data from the album.
     pub fn from_album(album: &Album) -> Self {
         Self {
-            notes: None,
+            notes: album.notes.clone(),
             musicbrainz_id: album.musicbrainz_id.clone(),
             last_fm_url: None,
-            small_image_url: None,
-            medium_image_url: None,
-            large_image_url: None,
+            small_image_url: album.album_image_url.clone(),
+            medium_image_url: album.album_image_url.clone(),
+            large_image_url: album.album_image_url.clone(),
         }
     }
+
+    /// Fill in details the album's own info file lacks from agent metadata.
+    pub fn with_metadata(mut self, metadata: &AlbumMetadata) -> Self {
+        self.notes = self.notes.or_else(|| metadata.notes.clone());
+        self.musicbrainz_id = self
+            .musicbrainz_id
+            .or_else(|| metadata.musicbrainz_id.clone());
+        self.last_fm_url = self.last_fm_url.or_else(|| metadata.last_fm_url.clone());
+        self.small_image_url = self
+            .small_image_url
+            .or_else(|| metadata.small_image_url.clone());
+        self.medium_image_url = self
+            .medium_image_url
+            .or_else(|| metadata.medium_image_url.clone());
+        self.large_image_url = self
+            .large_image_url
+            .or_else(|| metadata.large_image_url.clone());
+        self
+    }
 }
 
 /// Similar songs response for getSimilarSongs2.
@@ -1378,6 +1662,8 @@ pub struct DirectoryResponse {
     pub starred: Option<String>,
     #[serde(rename = "@playCount", skip_serializing_if = "Option::is_none")]
     pub play_count: Option<i32>,
+    #[serde(rename = "@played", skip_serializing_if = "Option::is_none")]
+    pub played: Option<String>,
     #[serde(rename = "child", skip_serializing_if = "Vec::is_empty")]
     pub children: Vec<ChildResponse>,
 }
@@ -1391,6 +1677,7 @@ impl DirectoryResponse {
             name: folder.name.clone(),
             starred: None,
             play_count: None,
+            played: None,
             children,
         }
     }
@@ -1403,6 +1690,20 @@ impl DirectoryResponse {
             name: artist.name.clone(),
             starred: None,
             play_count: None,
+            played: None,
+            children,
+        }
+    }
+
+    /// Create a directory response from a directory on disk.
+    pub fn from_directory(directory: &Directory, children: Vec<ChildResponse>) -> Self {
+        Self {
+            id: directory_response_id(directory.id),
+            parent: directory.parent_id.map(directory_response_id),
+            name: directory.name.clone(),
+            starred: None,
+            play_count: None,
+            played: None,
             children,
         }
     }
@@ -1414,10 +1715,20 @@ impl DirectoryResponse {
             parent: album.artist_id.map(|id| id.to_string()),
             name: album.name.clone(),
             starred: None,
-            play_count: Some(album.play_count),
+            play_count: None,
+            played: None,
             children,
         }
     }
+
+    /// Set the requesting user's play count and last play time.
+    pub fn with_play_stats(mut self, stats: Option<&PlayStats>) -> Self {
+        self.play_count = Some(stats.map_or(0, |s| s.play_count));
+        self.played = stats
+            .and_then(|s| s.last_played)
+            .map(|dt| dt.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string());
+        self
+    }
 }
 
 impl ChildResponse {
@@ -1444,6 +1755,7 @@ impl ChildResponse {
             channel_count: None,
             path: None,
             play_count: None,
+            played: None,
             disc_number: None,
             created: Some(
                 artist
@@ -1458,6 +1770,44 @@ impl ChildResponse {
         }
     }
 
+    /// Create a child response representing a directory on disk.
+    pub fn from_directory(directory: &Directory) -> Self {
+        Self {
+            id: directory_response_id(directory.id),
+            parent: directory.parent_id.map(directory_response_id),
+            is_dir: true,
+            title: directory.name.clone(),
+            album: None,
+            artist: None,
+            track: None,
+            year: None,
+            genre: None,
+            cover_art: directory.cover_art.clone(),
+            size: None,
+            content_type: None,
+            suffix: None,
+            duration: None,
+            bit_rate: None,
+            bit_depth: None,
+            sampling_rate: None,
+            channel_count: None,
+            path: None,
+            play_count: None,
+            played: None,
+            disc_number: None,
+            created: Some(
+                directory
+                    .created_at
+                    .format("%Y-%m-%dT%H:%M:%S%.3fZ")
+                    .to_string(),
+            ),
+            album_id: None,
+            artist_id: None,
+            media_type: None,
+            starred: None,
+        }
+    }
+
     /// Create a child response representing an album (as directory).
     pub fn from_album_as_dir(album: &Album) -> Self {
         Self {
@@ -1480,7 +1830,8 @@ impl ChildResponse {
             sampling_rate: None,
             channel_count: None,
             path: None,
-            play_count: Some(album.play_count),
+            play_count: None,
+            played: None,
             disc_number: None,
             created: Some(
                 album
@@ -1537,6 +1888,18 @@ impl ArtistResponse {
             average_rating: None,
         }
     }
+
+    /// Create a response for a similar artist that is not in the library.
+    pub fn not_present(name: &str) -> Self {
+        Self {
+            id: String::new(),
+            name: name.to_string(),
+            artist_image_url: None,
+            starred: None,
+            user_rating: None,
+            average_rating: None,
+        }
+    }
 }
 
 // ============================================================================
@@ -1659,7 +2022,7 @@ impl ArtistInfoResponse {
     /// Create an artist info response from an artist.
     pub fn from_artist(artist: &Artist) -> Self {
         Self {
-            biography: None,
+            biography: artist.biography.clone(),
             musicbrainz_id: artist.musicbrainz_id.clone(),
             last_fm_url: None,
             small_image_url: artist.artist_image_url.clone(),
@@ -1668,6 +2031,25 @@ impl ArtistInfoResponse {
             similar_artists: Vec::new(),
         }
     }
+
+    /// Fill in details the artist's own info files lack from agent metadata.
+    pub fn with_metadata(mut self, metadata: &ArtistMetadata) -> Self {
+        self.biography = self.biography.or_else(|| metadata.biography.clone());
+        self.musicbrainz_id = self
+            .musicbrainz_id
+            .or_else(|| metadata.musicbrainz_id.clone());
+        self.last_fm_url = self.last_fm_url.or_else(|| metadata.last_fm_url.clone());
+        self.small_image_url = self
+            .small_image_url
+            .or_else(|| metadata.small_image_url.clone());
+        self.medium_image_url = self
+            .medium_image_url
+            .or_else(|| metadata.medium_image_url.clone());
+        self.large_image_url = self
+            .large_image_url
+            .or_else(|| metadata.large_image_url.clone());
+        self
+    }
 }
 
 // ============================================================================
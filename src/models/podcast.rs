@@ -0,0 +1,258 @@
+//! Podcast models.
+
+use chrono::NaiveDateTime;
+use serde::Serialize;
+
+/// Prefix of the stream IDs of downloaded podcast episodes, which keeps
+/// them apart from song IDs in `stream`.
+pub const EPISODE_STREAM_ID_PREFIX: &str = "pe-";
+
+/// Status of a podcast channel or episode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PodcastStatus {
+    /// A channel that has not been refreshed yet.
+    New,
+    /// An episode queued for or being downloaded.
+    Downloading,
+    /// A refreshed channel or a downloaded episode.
+    Completed,
+    /// A channel or episode whose last refresh or download failed.
+    Error,
+    /// An episode whose download was deleted.
+    Deleted,
+    /// An episode that has not been downloaded.
+    Skipped,
+}
+
+impl PodcastStatus {
+    /// Name used in the API and stored in the database.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::New => "new",
+            Self::Downloading => "downloading",
+            Self::Completed => "completed",
+            Self::Error => "error",
+            Self::Deleted => "deleted",
+            Self::Skipped => "skipped",
+        }
+    }
+
+    /// Parse a status name, treating unknown names as errors.
+    pub fn parse(name: &str) -> Self {
+        match name {
+            "new" => Self::New,
+            "downloading" => Self::Downloading,
+            "completed" => Self::Completed,
+            "deleted" => Self::Deleted,
+            "skipped" => Self::Skipped,
+            _ => Self::Error,
+        }
+    }
+}
+
+/// A podcast channel subscribed to by its feed URL.
+#[derive(Debug, Clone)]
+pub struct PodcastChannel {
+    pub id: i32,
+    pub url: String,
+    /// Title from the feed, unknown until the channel is first refreshed.
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub image_url: Option<String>,
+    pub status: PodcastStatus,
+    /// Why the last refresh failed.
+    pub error_message: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub last_refreshed_at: Option<NaiveDateTime>,
+}
+
+/// An episode of a podcast channel.
+#[derive(Debug, Clone)]
+pub struct PodcastEpisode {
+    pub id: i32,
+    pub channel_id: i32,
+    /// Identifier of the episode in the feed.
+    pub guid: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub publish_date: Option<NaiveDateTime>,
+    /// Duration in seconds.
+    pub duration: Option<i32>,
+    /// URL of the episode's audio.
+    pub enclosure_url: String,
+    pub content_type: Option<String>,
+    /// Size in bytes, as given by the feed until the episode is downloaded.
+    pub size: Option<i64>,
+    pub status: PodcastStatus,
+    /// Path of the downloaded file.
+    pub file_path: Option<String>,
+    /// Why the last download failed.
+    pub error_message: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+impl PodcastEpisode {
+    /// The ID to stream the episode with, once it has been downloaded.
+    pub fn stream_id(&self) -> Option<String> {
+        (self.status == PodcastStatus::Completed && self.file_path.is_some())
+            .then(|| format!("{}{}", EPISODE_STREAM_ID_PREFIX, self.id))
+    }
+
+    /// File extension of the episode's audio, from the downloaded file or
+    /// the enclosure URL.
+    pub fn suffix(&self) -> Option<String> {
+        let name = match &self.file_path {
+            Some(path) => path.as_str(),
+            None => self.enclosure_url.split(['?', '#']).next().unwrap_or(""),
+        };
+        let file_name = name.rsplit('/').next().unwrap_or(name);
+        file_name
+            .rsplit_once('.')
+            .map(|(_, ext)| ext.to_ascii_lowercase())
+            .filter(|ext| !ext.is_empty() && ext.len() <= 5)
+    }
+}
+
+/// A podcast feed as parsed from RSS or Atom.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PodcastFeed {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub image_url: Option<String>,
+    pub episodes: Vec<FeedEpisode>,
+}
+
+/// An episode listed in a podcast feed. Entries without audio are left out.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FeedEpisode {
+    /// The entry's `guid` or `id`, or its enclosure URL if it has neither.
+    pub guid: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub publish_date: Option<NaiveDateTime>,
+    /// Duration in seconds.
+    pub duration: Option<i32>,
+    pub enclosure_url: String,
+    pub content_type: Option<String>,
+    pub size: Option<i64>,
+}
+
+/// Format a timestamp the way the API returns dates.
+fn format_date(date: &NaiveDateTime) -> String {
+    date.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string()
+}
+
+/// Podcast episode entry for getPodcasts and getNewestPodcasts.
+#[derive(Debug, Serialize, Clone)]
+pub struct PodcastEpisodeResponse {
+    #[serde(rename = "@id")]
+    pub id: String,
+    #[serde(rename = "@streamId", skip_serializing_if = "Option::is_none")]
+    pub stream_id: Option<String>,
+    #[serde(rename = "@channelId")]
+    pub channel_id: String,
+    #[serde(rename = "@parent")]
+    pub parent: String,
+    #[serde(rename = "@isDir")]
+    pub is_dir: bool,
+    #[serde(rename = "@title")]
+    pub title: String,
+    #[serde(rename = "@album", skip_serializing_if = "Option::is_none")]
+    pub album: Option<String>,
+    #[serde(rename = "@artist", skip_serializing_if = "Option::is_none")]
+    pub artist: Option<String>,
+    #[serde(rename = "@description", skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(rename = "@publishDate", skip_serializing_if = "Option::is_none")]
+    pub publish_date: Option<String>,
+    #[serde(rename = "@status")]
+    pub status: &'static str,
+    #[serde(rename = "@size", skip_serializing_if = "Option::is_none")]
+    pub size: Option<i64>,
+    #[serde(rename = "@contentType", skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    #[serde(rename = "@suffix", skip_serializing_if = "Option::is_none")]
+    pub suffix: Option<String>,
+    #[serde(rename = "@duration", skip_serializing_if = "Option::is_none")]
+    pub duration: Option<i32>,
+    #[serde(rename = "@type")]
+    pub media_type: &'static str,
+}
+
+impl PodcastEpisodeResponse {
+    /// Build the entry for an episode of a channel with the given title.
+    pub fn new(episode: &PodcastEpisode, channel_title: Option<&str>) -> Self {
+        Self {
+            id: episode.id.to_string(),
+            stream_id: episode.stream_id(),
+            channel_id: episode.channel_id.to_string(),
+            parent: episode.channel_id.to_string(),
+            is_dir: false,
+            title: episode.title.clone(),
+            album: channel_title.map(str::to_string),
+            artist: channel_title.map(str::to_string),
+            description: episode.description.clone(),
+            publish_date: episode.publish_date.as_ref().map(format_date),
+            status: episode.status.as_str(),
+            size: episode.size,
+            content_type: episode.content_type.clone(),
+            suffix: episode.suffix(),
+            duration: episode.duration,
+            media_type: "podcast",
+        }
+    }
+}
+
+/// Podcast channel entry for getPodcasts.
+#[derive(Debug, Serialize, Clone)]
+pub struct PodcastChannelResponse {
+    #[serde(rename = "@id")]
+    pub id: String,
+    #[serde(rename = "@url")]
+    pub url: String,
+    #[serde(rename = "@title", skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(rename = "@description", skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(rename = "@originalImageUrl", skip_serializing_if = "Option::is_none")]
+    pub original_image_url: Option<String>,
+    #[serde(rename = "@status")]
+    pub status: &'static str,
+    #[serde(rename = "@errorMessage", skip_serializing_if = "Option::is_none")]
+    pub error_message: Option<String>,
+    #[serde(rename = "episode", skip_serializing_if = "Vec::is_empty")]
+    pub episodes: Vec<PodcastEpisodeResponse>,
+}
+
+impl PodcastChannelResponse {
+    /// Build the entry for a channel with the given episodes.
+    pub fn new(channel: &PodcastChannel, episodes: &[PodcastEpisode]) -> Self {
+        Self {
+            id: channel.id.to_string(),
+            url: channel.url.clone(),
+            title: channel.title.clone(),
+            description: channel.description.clone(),
+            original_image_url: channel.image_url.clone(),
+            status: channel.status.as_str(),
+            error_message: channel.error_message.clone(),
+            episodes: episodes
+                .iter()
+                .map(|e| PodcastEpisodeResponse::new(e, channel.title.as_deref()))
+                .collect(),
+        }
+    }
+}
+
+/// Podcasts response for getPodcasts.
+#[derive(Debug, Serialize, Clone)]
+pub struct PodcastsResponse {
+    #[serde(rename = "channel", skip_serializing_if = "Vec::is_empty")]
+    pub channels: Vec<PodcastChannelResponse>,
+}
+
+/// Newest podcast episodes response for getNewestPodcasts.
+#[derive(Debug, Serialize, Clone)]
+pub struct NewestPodcastsResponse {
+    #[serde(rename = "episode", skip_serializing_if = "Vec::is_empty")]
+    pub episodes: Vec<PodcastEpisodeResponse>,
+}
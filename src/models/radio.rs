@@ -0,0 +1,46 @@
+//! Internet radio models.
+
+use chrono::NaiveDateTime;
+use serde::Serialize;
+
+/// An internet radio station.
+#[derive(Debug, Clone)]
+pub struct InternetRadioStation {
+    pub id: i32,
+    pub name: String,
+    pub stream_url: String,
+    pub homepage_url: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+/// Internet radio station entry for getInternetRadioStations.
+#[derive(Debug, Serialize, Clone)]
+pub struct InternetRadioStationResponse {
+    #[serde(rename = "@id")]
+    pub id: String,
+    #[serde(rename = "@name")]
+    pub name: String,
+    #[serde(rename = "@streamUrl")]
+    pub stream_url: String,
+    #[serde(rename = "@homePageUrl", skip_serializing_if = "Option::is_none")]
+    pub home_page_url: Option<String>,
+}
+
+impl From<&InternetRadioStation> for InternetRadioStationResponse {
+    fn from(station: &InternetRadioStation) -> Self {
+        Self {
+            id: station.id.to_string(),
+            name: station.name.clone(),
+            stream_url: station.stream_url.clone(),
+            home_page_url: station.homepage_url.clone(),
+        }
+    }
+}
+
+/// Internet radio stations response for getInternetRadioStations.
+#[derive(Debug, Serialize, Clone)]
+pub struct InternetRadioStationsResponse {
+    #[serde(rename = "internetRadioStation", skip_serializing_if = "Vec::is_empty")]
+    pub stations: Vec<InternetRadioStationResponse>,
+}
@@ -0,0 +1,148 @@
+//! Scan history models.
+
+use chrono::NaiveDateTime;
+use serde::Serialize;
+
+/// A recorded library scan.
+#[derive(Debug, Clone)]
+pub struct ScanRun {
+    pub id: i32,
+    pub started_at: NaiveDateTime,
+    pub finished_at: Option<NaiveDateTime>,
+    /// Scan mode ("full" or "incremental").
+    pub mode: String,
+    /// What started the scan ("cli", "api" or "auto").
+    pub trigger: String,
+    /// Scan status ("running", "completed", "cancelled", "failed" or "aborted").
+    pub status: String,
+    /// Error that aborted the scan, if it failed.
+    pub error: Option<String>,
+    pub tracks_found: i32,
+    pub tracks_added: i32,
+    pub tracks_updated: i32,
+    pub tracks_skipped: i32,
+    pub tracks_removed: i32,
+    pub tracks_moved: i32,
+    pub tracks_failed: i32,
+    pub artists_added: i32,
+    pub albums_added: i32,
+    pub cover_art_saved: i32,
+    pub playlists_imported: i32,
+}
+
+/// A file that failed during a recorded scan.
+#[derive(Debug, Clone)]
+pub struct ScanRunError {
+    pub id: i32,
+    pub scan_run_id: i32,
+    pub path: String,
+    pub error: String,
+    pub created_at: NaiveDateTime,
+}
+
+fn format_timestamp(dt: &NaiveDateTime) -> String {
+    dt.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string()
+}
+
+/// Scan run entry for getScanHistory.
+#[derive(Debug, Serialize, Clone)]
+pub struct ScanRunResponse {
+    #[serde(rename = "@id")]
+    pub id: i32,
+    #[serde(rename = "@startedAt")]
+    pub started_at: String,
+    #[serde(rename = "@finishedAt", skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<String>,
+    #[serde(rename = "@mode")]
+    pub mode: String,
+    #[serde(rename = "@trigger")]
+    pub trigger: String,
+    #[serde(rename = "@status")]
+    pub status: String,
+    #[serde(rename = "@error", skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(rename = "@tracksFound")]
+    pub tracks_found: i32,
+    #[serde(rename = "@tracksAdded")]
+    pub tracks_added: i32,
+    #[serde(rename = "@tracksUpdated")]
+    pub tracks_updated: i32,
+    #[serde(rename = "@tracksSkipped")]
+    pub tracks_skipped: i32,
+    #[serde(rename = "@tracksRemoved")]
+    pub tracks_removed: i32,
+    #[serde(rename = "@tracksMoved")]
+    pub tracks_moved: i32,
+    #[serde(rename = "@tracksFailed")]
+    pub tracks_failed: i32,
+    #[serde(rename = "@artistsAdded")]
+    pub artists_added: i32,
+    #[serde(rename = "@albumsAdded")]
+    pub albums_added: i32,
+    #[serde(rename = "@coverArtSaved")]
+    pub cover_art_saved: i32,
+    #[serde(rename = "@playlistsImported")]
+    pub playlists_imported: i32,
+}
+
+impl From<&ScanRun> for ScanRunResponse {
+    fn from(run: &ScanRun) -> Self {
+        Self {
+            id: run.id,
+            started_at: format_timestamp(&run.started_at),
+            finished_at: run.finished_at.as_ref().map(format_timestamp),
+            mode: run.mode.clone(),
+            trigger: run.trigger.clone(),
+            status: run.status.clone(),
+            error: run.error.clone(),
+            tracks_found: run.tracks_found,
+            tracks_added: run.tracks_added,
+            tracks_updated: run.tracks_updated,
+            tracks_skipped: run.tracks_skipped,
+            tracks_removed: run.tracks_removed,
+            tracks_moved: run.tracks_moved,
+            tracks_failed: run.tracks_failed,
+            artists_added: run.artists_added,
+            albums_added: run.albums_added,
+            cover_art_saved: run.cover_art_saved,
+            playlists_imported: run.playlists_imported,
+        }
+    }
+}
+
+/// Scan history response for getScanHistory.
+#[derive(Debug, Serialize, Clone)]
+pub struct ScanHistoryResponse {
+    #[serde(rename = "scanRun", skip_serializing_if = "Vec::is_empty")]
+    pub runs: Vec<ScanRunResponse>,
+}
+
+/// Failed file entry for getScanErrors.
+#[derive(Debug, Serialize, Clone)]
+pub struct ScanErrorResponse {
+    #[serde(rename = "@path")]
+    pub path: String,
+    #[serde(rename = "@error")]
+    pub error: String,
+    #[serde(rename = "@created")]
+    pub created: String,
+}
+
+impl From<&ScanRunError> for ScanErrorResponse {
+    fn from(error: &ScanRunError) -> Self {
+        Self {
+            path: error.path.clone(),
+            error: error.error.clone(),
+            created: format_timestamp(&error.created_at),
+        }
+    }
+}
+
+/// Scan errors response for getScanErrors.
+#[derive(Debug, Serialize, Clone)]
+pub struct ScanErrorsResponse {
+    #[serde(rename = "@scanRunId")]
+    pub scan_run_id: i32,
+    #[serde(rename = "scanError", skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<ScanErrorResponse>,
+}
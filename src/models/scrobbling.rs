@@ -0,0 +1,129 @@
+//! Scrobble forwarding models.
+
+use chrono::NaiveDateTime;
+use serde::Serialize;
+
+use super::music::Song;
+
+/// An external service plays can be forwarded to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScrobbleService {
+    ListenBrainz,
+    LastFm,
+}
+
+impl ScrobbleService {
+    /// Name used in the API and stored in the database.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::ListenBrainz => "listenbrainz",
+            Self::LastFm => "lastfm",
+        }
+    }
+
+    /// Parse a service name ("listenbrainz" or "lastfm").
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "listenbrainz" => Some(Self::ListenBrainz),
+            "lastfm" => Some(Self::LastFm),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for ScrobbleService {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::ListenBrainz => "ListenBrainz",
+            Self::LastFm => "Last.fm",
+        })
+    }
+}
+
+/// A user's linked account on a scrobbling service.
+#[derive(Debug, Clone)]
+pub struct ScrobbleAccount {
+    pub user_id: i32,
+    pub service: ScrobbleService,
+    /// ListenBrainz user token or Last.fm session key.
+    pub token: String,
+    /// Username on the service, if known.
+    pub username: Option<String>,
+    pub linked_at: NaiveDateTime,
+}
+
+/// A completed play waiting in the outbox to be submitted to a service.
+#[derive(Debug, Clone)]
+pub struct PendingScrobble {
+    pub id: i32,
+    pub user_id: i32,
+    pub service: ScrobbleService,
+    pub song_id: i32,
+    pub played_at: NaiveDateTime,
+    /// Number of failed submissions so far.
+    pub attempts: i32,
+}
+
+/// Details of a track sent to a scrobbling service.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScrobbleTrack {
+    pub artist: String,
+    pub title: String,
+    pub album: Option<String>,
+    /// Duration in seconds.
+    pub duration: i32,
+    pub track_number: Option<i32>,
+    pub musicbrainz_id: Option<String>,
+}
+
+impl ScrobbleTrack {
+    /// Get the details of a song, or `None` if it has no artist, which
+    /// scrobbling services require.
+    pub fn from_song(song: &Song) -> Option<Self> {
+        let artist = song.artist_name.clone().filter(|a| !a.is_empty())?;
+        Some(Self {
+            artist,
+            title: song.title.clone(),
+            album: song.album_name.clone().filter(|a| !a.is_empty()),
+            duration: song.duration,
+            track_number: song.track_number,
+            musicbrainz_id: song.musicbrainz_id.clone(),
+        })
+    }
+}
+
+fn format_timestamp(dt: &NaiveDateTime) -> String {
+    dt.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string()
+}
+
+/// Linked account entry for getScrobbleAccounts.
+#[derive(Debug, Serialize, Clone)]
+pub struct ScrobbleAccountResponse {
+    #[serde(rename = "@service")]
+    pub service: String,
+    #[serde(rename = "@username", skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(rename = "@linkedAt")]
+    pub linked_at: String,
+    /// Plays waiting to be submitted.
+    #[serde(rename = "@pending")]
+    pub pending: i64,
+}
+
+impl ScrobbleAccountResponse {
+    pub fn from_account(account: &ScrobbleAccount, pending: i64) -> Self {
+        Self {
+            service: account.service.as_str().to_string(),
+            username: account.username.clone(),
+            linked_at: format_timestamp(&account.linked_at),
+            pending,
+        }
+    }
+}
+
+/// Linked accounts response for getScrobbleAccounts.
+#[derive(Debug, Serialize, Clone)]
+pub struct ScrobbleAccountsResponse {
+    #[serde(rename = "account", skip_serializing_if = "Vec::is_empty")]
+    pub accounts: Vec<ScrobbleAccountResponse>,
+}
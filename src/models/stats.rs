@@ -0,0 +1,164 @@
+//! Listening statistics models.
+
+use chrono::{NaiveDateTime, TimeDelta};
+use serde::Serialize;
+
+use super::music::{AlbumID3Response, Artist, ChildResponse};
+
+/// A named span of recent listening history.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatsPeriod {
+    /// The last 7 days.
+    Week,
+    /// The last 30 days.
+    Month,
+    /// The last 365 days.
+    Year,
+    /// All listening history.
+    All,
+}
+
+impl StatsPeriod {
+    /// Parse a period name ("week", "month", "year" or "all").
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "week" => Some(Self::Week),
+            "month" => Some(Self::Month),
+            "year" => Some(Self::Year),
+            "all" => Some(Self::All),
+            _ => None,
+        }
+    }
+
+    /// Start of the period ending at `now`, or `None` for all history.
+    pub fn start(self, now: NaiveDateTime) -> Option<NaiveDateTime> {
+        let days = match self {
+            Self::Week => 7,
+            Self::Month => 30,
+            Self::Year => 365,
+            Self::All => return None,
+        };
+        Some(now - TimeDelta::days(days))
+    }
+}
+
+/// Which plays statistics are computed over.
+#[derive(Debug, Clone, Default)]
+pub struct StatsFilter {
+    /// Only plays by this user; all users if `None`.
+    pub user_id: Option<i32>,
+    /// Only plays at or after this time.
+    pub from: Option<NaiveDateTime>,
+    /// Only plays before this time.
+    pub to: Option<NaiveDateTime>,
+}
+
+/// How much a user listened.
+#[derive(Debug, Clone)]
+pub struct UserListening {
+    pub user_id: i32,
+    pub username: String,
+    pub play_count: i64,
+    /// Total duration of the songs played, in seconds.
+    pub listening_time: i64,
+}
+
+fn format_timestamp(dt: &NaiveDateTime) -> String {
+    dt.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string()
+}
+
+/// Listening totals for a user in getListeningStats.
+#[derive(Debug, Serialize, Clone)]
+pub struct StatsUserResponse {
+    #[serde(rename = "@username")]
+    pub username: String,
+    #[serde(rename = "@playCount")]
+    pub play_count: i64,
+    #[serde(rename = "@listeningTime")]
+    pub listening_time: i64,
+}
+
+impl From<&UserListening> for StatsUserResponse {
+    fn from(listening: &UserListening) -> Self {
+        Self {
+            username: listening.username.clone(),
+            play_count: listening.play_count,
+            listening_time: listening.listening_time,
+        }
+    }
+}
+
+/// Top artist entry for getListeningStats.
+#[derive(Debug, Serialize, Clone)]
+pub struct StatsArtistResponse {
+    #[serde(rename = "@id")]
+    pub id: String,
+    #[serde(rename = "@name")]
+    pub name: String,
+    #[serde(rename = "@coverArt", skip_serializing_if = "Option::is_none")]
+    pub cover_art: Option<String>,
+    #[serde(rename = "@playCount")]
+    pub play_count: i64,
+}
+
+impl StatsArtistResponse {
+    pub fn from_artist(artist: &Artist, play_count: i64) -> Self {
+        Self {
+            id: artist.id.to_string(),
+            name: artist.name.clone(),
+            cover_art: artist.cover_art.clone(),
+            play_count,
+        }
+    }
+}
+
+/// Top genre entry for getListeningStats.
+#[derive(Debug, Serialize, Clone)]
+pub struct StatsGenreResponse {
+    #[serde(rename = "@name")]
+    pub name: String,
+    #[serde(rename = "@playCount")]
+    pub play_count: i64,
+}
+
+/// Listening statistics response for getListeningStats.
+///
+/// The play counts of albums and songs are plays within the range.
+#[derive(Debug, Serialize, Clone)]
+pub struct ListeningStatsResponse {
+    #[serde(rename = "@from", skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+    #[serde(rename = "@to", skip_serializing_if = "Option::is_none")]
+    pub to: Option<String>,
+    #[serde(rename = "@playCount")]
+    pub play_count: i64,
+    #[serde(rename = "@listeningTime")]
+    pub listening_time: i64,
+    #[serde(rename = "user", skip_serializing_if = "Vec::is_empty")]
+    pub users: Vec<StatsUserResponse>,
+    #[serde(rename = "artist", skip_serializing_if = "Vec::is_empty")]
+    pub artists: Vec<StatsArtistResponse>,
+    #[serde(rename = "album", skip_serializing_if = "Vec::is_empty")]
+    pub albums: Vec<AlbumID3Response>,
+    #[serde(rename = "song", skip_serializing_if = "Vec::is_empty")]
+    pub songs: Vec<ChildResponse>,
+    #[serde(rename = "genre", skip_serializing_if = "Vec::is_empty")]
+    pub genres: Vec<StatsGenreResponse>,
+}
+
+impl ListeningStatsResponse {
+    /// Create an empty response for the given range.
+    pub fn new(from: Option<&NaiveDateTime>, to: Option<&NaiveDateTime>) -> Self {
+        Self {
+            from: from.map(format_timestamp),
+            to: to.map(format_timestamp),
+            play_count: 0,
+            listening_time: 0,
+            users: Vec::new(),
+            artists: Vec::new(),
+            albums: Vec::new(),
+            songs: Vec::new(),
+            genres: Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,803 @@
+//! CUE sheet parsing.
+//!
+//! Splits single-file album rips into virtual tracks using a CUE sheet, either
+//! embedded in the file's `CUESHEET` tag or stored next to it as a `.cue` file.
+
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use lofty::config::ParseOptions;
+use lofty::file::AudioFile;
+use lofty::probe::Probe;
+
+use super::playlists::decode_text;
+
+/// A parsed CUE sheet.
+#[derive(Debug, Clone, Default)]
+pub struct CueSheet {
+    /// Album title.
+    pub title: Option<String>,
+    /// Album performer.
+    pub performer: Option<String>,
+    /// Genre from `REM GENRE`.
+    pub genre: Option<String>,
+    /// Year from `REM DATE`.
+    pub year: Option<u32>,
+    /// Audio files referenced by the sheet, in order.
+    pub files: Vec<CueFile>,
+}
+
+/// An audio file referenced by a CUE sheet.
+#[derive(Debug, Clone, Default)]
+pub struct CueFile {
+    /// File name as written in the sheet.
+    pub name: String,
+    /// Tracks stored in this file.
+    pub tracks: Vec<CueTrack>,
+}
+
+/// A single track in a CUE sheet.
+#[derive(Debug, Clone, Default)]
+pub struct CueTrack {
+    /// Track number.
+    pub number: u32,
+    /// Track title.
+    pub title: Option<String>,
+    /// Track performer.
+    pub performer: Option<String>,
+    /// Start offset within the file in milliseconds (`INDEX 01`).
+    pub start_ms: i64,
+}
+
+impl CueSheet {
+    /// Get the tracks stored in the given audio file.
+    ///
+    /// Sheets referencing a single file match regardless of its name, since
+    /// rips are often renamed without updating the sheet.
+    pub fn tracks_for_file(&self, file_name: &str) -> Option<&[CueTrack]> {
+        if let [only] = self.files.as_slice() {
+            return Some(&only.tracks);
+        }
+
+        self.files
+            .iter()
+            .find(|f| {
+                Path::new(&f.name.replace('\\', "/"))
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .is_some_and(|n| n.eq_ignore_ascii_case(file_name))
+            })
+            .map(|f| f.tracks.as_slice())
+    }
+}
+
+/// Find a sidecar CUE sheet for an audio file (`album.cue` or `album.flac.cue`).
+pub fn find_sidecar_cue(audio_path: &Path) -> Option<PathBuf> {
+    let with_stem = audio_path.with_extension("cue");
+    if with_stem.is_file() {
+        return Some(with_stem);
+    }
+
+    let mut with_name = audio_path.as_os_str().to_owned();
+    with_name.push(".cue");
+    let with_name = PathBuf::from(with_name);
+    with_name.is_file().then_some(with_name)
+}
+
+/// Read and parse a CUE sheet file.
+pub fn read_cue_file(path: &Path) -> std::io::Result<CueSheet> {
+    let bytes = fs::read(path)?;
+    Ok(parse_cue(&decode_text(&bytes)))
+}
+
+/// Parse CUE sheet content.
+pub fn parse_cue(content: &str) -> CueSheet {
+    let mut sheet = CueSheet::default();
+    let mut current_track: Option<CueTrack> = None;
+    let mut index_00: Option<i64> = None;
+
+    for line in content.lines() {
+        let line = line.trim();
+        let (command, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+
+        match command.to_ascii_uppercase().as_str() {
+            "FILE" => {
+                finish_track(&mut sheet, &mut current_track, &mut index_00);
+                sheet.files.push(CueFile {
+                    name: parse_file_name(rest),
+                    tracks: Vec::new(),
+                });
+            }
+            "TRACK" => {
+                finish_track(&mut sheet, &mut current_track, &mut index_00);
+                let number = rest
+                    .split_whitespace()
+                    .next()
+                    .and_then(|n| n.parse().ok())
+                    .unwrap_or(0);
+                current_track = Some(CueTrack {
+                    number,
+                    start_ms: -1,
+                    ..Default::default()
+                });
+            }
+            "TITLE" => match current_track.as_mut() {
+                Some(track) => track.title = Some(unquote(rest)),
+                None => sheet.title = Some(unquote(rest)),
+            },
+            "PERFORMER" => match current_track.as_mut() {
+                Some(track) => track.performer = Some(unquote(rest)),
+                None => sheet.performer = Some(unquote(rest)),
+            },
+            "INDEX" => {
+                let mut parts = rest.split_whitespace();
+                let number = parts.next().and_then(|n| n.parse::<u32>().ok());
+                let time = parts.next().and_then(parse_cue_time);
+                if let (Some(track), Some(number), Some(time)) =
+                    (current_track.as_mut(), number, time)
+                {
+                    match number {
+                        0 => index_00 = Some(time),
+                        1 => track.start_ms = time,
+                        _ => {}
+                    }
+                }
+            }
+            "REM" if current_track.is_none() => {
+                let (key, value) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+                match key.to_ascii_uppercase().as_str() {
+                    "GENRE" => sheet.genre = Some(unquote(value)),
+                    "DATE" => sheet.year = unquote(value).get(..4).and_then(|y| y.parse().ok()),
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    finish_track(&mut sheet, &mut current_track, &mut index_00);
+    sheet
+}
+
+/// Attach the track being parsed to the current file.
+fn finish_track(sheet: &mut CueSheet, track: &mut Option<CueTrack>, index_00: &mut Option<i64>) {
+    if let Some(mut track) = track.take() {
+        // Fall back to the pregap index when INDEX 01 is missing
+        if track.start_ms < 0 {
+            track.start_ms = index_00.unwrap_or(0);
+        }
+        if let Some(file) = sheet.files.last_mut() {
+            file.tracks.push(track);
+        }
+    }
+    *index_00 = None;
+}
+
+/// Parse the file name from a `FILE "name" WAVE` line.
+fn parse_file_name(rest: &str) -> String {
+    if let Some(quoted) = rest.strip_prefix('"') {
+        return quoted.split('"').next().unwrap_or_default().to_string();
+    }
+    // Unquoted: the file type is the last word
+    match rest.rsplit_once(char::is_whitespace) {
+        Some((name, _)) => name.trim().to_string(),
+        None => rest.to_string(),
+    }
+}
+
+/// Strip surrounding quotes from a value.
+fn unquote(value: &str) -> String {
+    let value = value.trim();
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value)
+        .to_string()
+}
+
+/// Parse a CUE timestamp (`mm:ss:ff`, 75 frames per second) into milliseconds.
+fn parse_cue_time(s: &str) -> Option<i64> {
+    let mut parts = s.split(':');
+    let mins: i64 = parts.next()?.parse().ok()?;
+    let secs: i64 = parts.next()?.parse().ok()?;
+    let frames: i64 = parts.next()?.parse().ok()?;
+    if parts.next().is_some() {
+        return None;
+    }
+    Some(mins * 60_000 + secs * 1000 + frames * 1000 / 75)
+}
+
+/// A playable range of an audio file.
+///
+/// CUE tracks are served as a byte range of their file, preceded by a rewritten
+/// container header where the format needs one to decode the range on its own.
+#[derive(Debug, Clone, Default)]
+pub struct AudioSlice {
+    /// Header bytes sent before the range.
+    pub header: Vec<u8>,
+    /// Start of the range in the file.
+    pub offset: u64,
+    /// Length of the range in bytes.
+    pub length: u64,
+}
+
+impl AudioSlice {
+    /// A slice covering an entire file.
+    pub fn whole_file(file_size: u64) -> Self {
+        Self {
+            header: Vec::new(),
+            offset: 0,
+            length: file_size,
+        }
+    }
+
+    /// Total number of bytes served for this slice.
+    pub fn len(&self) -> u64 {
+        self.header.len() as u64 + self.length
+    }
+
+    /// Whether the slice is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Largest WAV header (everything before the data chunk) copied into a slice.
+const MAX_WAV_HEADER: u64 = 1024 * 1024;
+
+/// How far to look for a frame boundary when aligning a slice.
+const FRAME_SEARCH_WINDOW: usize = 64 * 1024;
+
+/// Compute the slice of an audio file holding the time range `start_ms..end_ms`.
+///
+/// WAV slices are sample-exact with a patched header. FLAC slices start on the
+/// frame holding the start time and get a STREAMINFO header with the sample
+/// count cleared. MP3 slices are aligned to frame sync. MP3 and other formats
+/// are cut proportionally to their duration, which is only time-accurate for
+/// constant bitrate audio.
+pub fn slice_track(
+    path: &Path,
+    suffix: &str,
+    start_ms: i64,
+    end_ms: Option<i64>,
+) -> io::Result<AudioSlice> {
+    let mut file = File::open(path)?;
+    let file_size = file.metadata()?.len();
+    let start_ms = start_ms.max(0) as u64;
+    let end_ms = end_ms.map(|ms| ms.max(0) as u64);
+
+    let slice = match suffix {
+        "wav" => slice_wav(&mut file, file_size, start_ms, end_ms)?,
+        "flac" => slice_flac(&mut file, file_size, start_ms, end_ms)?,
+        _ => None,
+    };
+
+    match slice {
+        Some(slice) => Ok(slice),
+        None => slice_proportional(path, &mut file, file_size, suffix, start_ms, end_ms),
+    }
+}
+
+/// Slice a RIFF/WAVE file on block boundaries.
+fn slice_wav(
+    file: &mut File,
+    file_size: u64,
+    start_ms: u64,
+    end_ms: Option<u64>,
+) -> io::Result<Option<AudioSlice>> {
+    let mut riff = [0u8; 12];
+    if file.read_exact(&mut riff).is_err() || &riff[0..4] != b"RIFF" || &riff[8..12] != b"WAVE" {
+        return Ok(None);
+    }
+
+    let mut byte_rate = 0u64;
+    let mut block_align = 0u64;
+    let mut pos = 12u64;
+
+    loop {
+        let mut chunk = [0u8; 8];
+        file.seek(SeekFrom::Start(pos))?;
+        if file.read_exact(&mut chunk).is_err() {
+            return Ok(None);
+        }
+        let size = u32::from_le_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]) as u64;
+        let body = pos + 8;
+
+        match &chunk[0..4] {
+            b"fmt " => {
+                let mut fmt = [0u8; 16];
+                file.read_exact(&mut fmt)?;
+                byte_rate = u32::from_le_bytes([fmt[8], fmt[9], fmt[10], fmt[11]]) as u64;
+                block_align = u16::from_le_bytes([fmt[12], fmt[13]]) as u64;
+            }
+            b"data" => {
+                if byte_rate == 0 || block_align == 0 || body > MAX_WAV_HEADER {
+                    return Ok(None);
+                }
+                let data_len = size.min(file_size.saturating_sub(body));
+                let to_offset = |ms: u64| {
+                    let bytes = byte_rate * ms / 1000;
+                    (bytes - bytes % block_align).min(data_len)
+                };
+                let start = to_offset(start_ms);
+                let end = end_ms.map(to_offset).unwrap_or(data_len);
+                let length = end.saturating_sub(start);
+
+                let mut header = vec![0u8; body as usize];
+                file.seek(SeekFrom::Start(0))?;
+                file.read_exact(&mut header)?;
+
+                let riff_size = (body - 8 + length) as u32;
+                header[4..8].copy_from_slice(&riff_size.to_le_bytes());
+                let data_size = body as usize - 4;
+                header[data_size..].copy_from_slice(&(length as u32).to_le_bytes());
+
+                return Ok(Some(AudioSlice {
+                    header,
+                    offset: body + start,
+                    length,
+                }));
+            }
+            _ => {}
+        }
+
+        // Chunks are padded to an even size
+        pos = body + size + (size & 1);
+    }
+}
+
+/// Slice a FLAC file on frame boundaries.
+///
+/// Each cut point is the start of the frame holding the requested sample, found
+/// from the SEEKTABLE and the sample numbers in the frame headers, so tracks
+/// start at most one frame (typically under 100ms) early.
+fn slice_flac(
+    file: &mut File,
+    file_size: u64,
+    start_ms: u64,
+    end_ms: Option<u64>,
+) -> io::Result<Option<AudioSlice>> {
+    let mut magic = [0u8; 4];
+    if file.read_exact(&mut magic).is_err() || &magic != b"fLaC" {
+        return Ok(None);
+    }
+
+    let mut streaminfo: Option<[u8; 34]> = None;
+    let mut seek_points: Vec<(u64, u64)> = Vec::new();
+    let mut pos = 4u64;
+
+    loop {
+        let mut block = [0u8; 4];
+        file.seek(SeekFrom::Start(pos))?;
+        if file.read_exact(&mut block).is_err() {
+            return Ok(None);
+        }
+        let is_last = block[0] & 0x80 != 0;
+        let length = u32::from_be_bytes([0, block[1], block[2], block[3]]) as u64;
+
+        if block[0] & 0x7F == 0 && length == 34 {
+            let mut info = [0u8; 34];
+            file.read_exact(&mut info)?;
+            streaminfo = Some(info);
+        } else if block[0] & 0x7F == 3 {
+            seek_points = read_seek_table(file, length)?;
+        }
+
+        pos += 4 + length;
+        if is_last {
+            break;
+        }
+    }
+
+    let Some(mut info) = streaminfo else {
+        return Ok(None);
+    };
+
+    let sample_rate =
+        ((info[10] as u64) << 12) | ((info[11] as u64) << 4) | ((info[12] as u64) >> 4);
+    let total_samples = (((info[13] & 0x0F) as u64) << 32)
+        | u32::from_be_bytes([info[14], info[15], info[16], info[17]]) as u64;
+    if sample_rate == 0 || total_samples == 0 {
+        return Ok(None);
+    }
+
+    let stream = FlacStream {
+        audio_start: pos,
+        file_size,
+        block_size: u16::from_be_bytes([info[0], info[1]]) as u64,
+        total_samples,
+        seek_points,
+    };
+    let to_sample = |ms: u64| (ms * sample_rate / 1000).min(total_samples);
+
+    let start = stream.frame_containing(file, to_sample(start_ms))?;
+    let end = match end_ms {
+        Some(ms) => stream.frame_containing(file, to_sample(ms))?,
+        None => file_size,
+    };
+
+    // The slice's sample count and checksum are unknown
+    info[13] &= 0xF0;
+    info[14..18].fill(0);
+    info[18..34].fill(0);
+
+    let mut header = Vec::with_capacity(42);
+    header.extend_from_slice(b"fLaC");
+    header.extend_from_slice(&[0x80, 0, 0, 34]);
+    header.extend_from_slice(&info);
+
+    Ok(Some(AudioSlice {
+        header,
+        offset: start,
+        length: end.saturating_sub(start),
+    }))
+}
+
+/// Slice a file proportionally to its duration, skipping MP3 tags and aligning to MP3 frames.
+///
+/// Byte offsets are estimated from the time as a fraction of the file length,
+/// which is only time-accurate for constant bitrate audio. VBR files can start
+/// or end a few seconds off the CUE time.
+fn slice_proportional(
+    path: &Path,
+    file: &mut File,
+    file_size: u64,
+    suffix: &str,
+    start_ms: u64,
+    end_ms: Option<u64>,
+) -> io::Result<AudioSlice> {
+    let total_ms = Probe::open(path)
+        .and_then(|probe| probe.options(ParseOptions::new().read_tags(false)).read())
+        .map(|tagged| tagged.properties().duration().as_millis() as u64)
+        .unwrap_or(0);
+
+    let is_mp3 = suffix == "mp3";
+    let mut audio_start = 0u64;
+    let mut audio_end = file_size;
+
+    if is_mp3 {
+        // Skip an ID3v2 tag at the start
+        let mut id3 = [0u8; 10];
+        file.seek(SeekFrom::Start(0))?;
+        if file.read_exact(&mut id3).is_ok() && &id3[0..3] == b"ID3" {
+            let size = id3[6..10]
+                .iter()
+                .fold(0u64, |acc, &b| (acc << 7) | (b & 0x7F) as u64);
+            let footer = if id3[5] & 0x10 != 0 { 10 } else { 0 };
+            audio_start = (10 + size + footer).min(file_size);
+        }
+
+        // Skip an ID3v1 tag at the end
+        if file_size >= audio_start + 128 {
+            let mut tag = [0u8; 3];
+            file.seek(SeekFrom::Start(file_size - 128))?;
+            if file.read_exact(&mut tag).is_ok() && &tag == b"TAG" {
+                audio_end = file_size - 128;
+            }
+        }
+    }
+
+    if total_ms == 0 {
+        return Ok(AudioSlice {
+            header: Vec::new(),
+            offset: audio_start,
+            length: audio_end - audio_start,
+        });
+    }
+
+    let audio_len = audio_end - audio_start;
+    let to_offset = |ms: u64| audio_start + audio_len * ms.min(total_ms) / total_ms;
+
+    let mut start = to_offset(start_ms);
+    let mut end = end_ms.map(to_offset).unwrap_or(audio_end);
+    if is_mp3 {
+        start = find_frame(file, start, audio_end, is_mp3_sync)?;
+        if end < audio_end {
+            end = find_frame(file, end, audio_end, is_mp3_sync)?;
+        }
+    }
+
+    Ok(AudioSlice {
+        header: Vec::new(),
+        offset: start,
+        length: end.saturating_sub(start),
+    })
+}
+
+/// Read the seek points of a FLAC SEEKTABLE block as (sample, offset) pairs.
+fn read_seek_table(file: &mut File, length: u64) -> io::Result<Vec<(u64, u64)>> {
+    let mut table = vec![0u8; length as usize];
+    file.read_exact(&mut table)?;
+
+    Ok(table
+        .chunks_exact(18)
+        .map(|point| {
+            let sample = u64::from_be_bytes(point[0..8].try_into().unwrap());
+            let offset = u64::from_be_bytes(point[8..16].try_into().unwrap());
+            (sample, offset)
+        })
+        // Placeholder points use the maximum sample number
+        .filter(|(sample, _)| *sample != u64::MAX)
+        .collect())
+}
+
+/// Layout of a FLAC stream used to locate frames by sample number.
+struct FlacStream {
+    /// Offset of the first audio frame.
+    audio_start: u64,
+    file_size: u64,
+    /// Block size of fixed-blocksize streams, from STREAMINFO.
+    block_size: u64,
+    total_samples: u64,
+    /// Seek points as (sample, offset from the first frame).
+    seek_points: Vec<(u64, u64)>,
+}
+
+impl FlacStream {
+    /// Find the offset of the frame holding `target`.
+    ///
+    /// The SEEKTABLE narrows the search, which then bisects on the sample
+    /// numbers of the frames found after each midpoint.
+    fn frame_containing(&self, file: &mut File, target: u64) -> io::Result<u64> {
+        // Frame starts at `lo` hold samples <= target; frames at or after `hi` don't.
+        let mut lo = self.audio_start;
+        let mut hi = self.file_size;
+        for &(sample, offset) in &self.seek_points {
+            let point = self.audio_start + offset;
+            if point >= self.file_size {
+                break;
+            }
+            if sample <= target {
+                lo = lo.max(point);
+            } else {
+                hi = hi.min(point);
+                break;
+            }
+        }
+
+        while hi > lo + 1 {
+            let mid = lo + (hi - lo) / 2;
+            match self.next_frame(file, mid, hi)? {
+                Some((pos, sample)) if sample <= target => lo = pos,
+                _ => hi = mid,
+            }
+        }
+
+        Ok(lo)
+    }
+
+    /// Find the first valid frame header in `from..limit`, returning its offset and first sample.
+    fn next_frame(&self, file: &mut File, from: u64, limit: u64) -> io::Result<Option<(u64, u64)>> {
+        let mut pos = from;
+        while pos < limit {
+            let mut buf = vec![0u8; FRAME_SEARCH_WINDOW.min((self.file_size - pos) as usize)];
+            file.seek(SeekFrom::Start(pos))?;
+            let read = file.read(&mut buf)?;
+            if read < 2 {
+                return Ok(None);
+            }
+
+            for i in 0..read - 1 {
+                if pos + i as u64 >= limit {
+                    return Ok(None);
+                }
+                if is_flac_sync(buf[i], buf[i + 1])
+                    && let Some(sample) = self.parse_frame_header(&buf[i..read])
+                {
+                    return Ok(Some((pos + i as u64, sample)));
+                }
+            }
+
+            // Overlap windows so headers spanning the boundary are still found
+            pos += (read - 1).saturating_sub(MAX_FLAC_HEADER).max(1) as u64;
+        }
+        Ok(None)
+    }
+
+    /// Parse and CRC-check a frame header, returning the frame's first sample.
+    fn parse_frame_header(&self, header: &[u8]) -> Option<u64> {
+        if header.len() < 6 {
+            return None;
+        }
+        let variable_blocksize = header[1] & 0x01 != 0;
+        let block_size_code = header[2] >> 4;
+        let sample_rate_code = header[2] & 0x0F;
+        let channels = header[3] >> 4;
+        let sample_size = (header[3] >> 1) & 0x07;
+        if block_size_code == 0
+            || sample_rate_code == 0x0F
+            || channels > 10
+            || sample_size == 3
+            || header[3] & 0x01 != 0
+        {
+            return None;
+        }
+
+        // UTF-8 style coded frame or sample number
+        let first = header[4];
+        let (mut number, extra) = match first.leading_ones() {
+            0 => (first as u64, 0),
+            n @ 2..=7 => ((first as u16 & (0xFF >> (n + 1))) as u64, n - 1),
+            _ => return None,
+        };
+        let mut len = 5;
+        for _ in 0..extra {
+            let b = *header.get(len)?;
+            if b & 0xC0 != 0x80 {
+                return None;
+            }
+            number = (number << 6) | (b & 0x3F) as u64;
+            len += 1;
+        }
+
+        len += match block_size_code {
+            6 => 1,
+            7 => 2,
+            _ => 0,
+        };
+        len += match sample_rate_code {
+            12 => 1,
+            13 | 14 => 2,
+            _ => 0,
+        };
+
+        let crc = *header.get(len)?;
+        if crc8(&header[..len]) != crc {
+            return None;
+        }
+
+        let sample = if variable_blocksize {
+            number
+        } else {
+            number * self.block_size
+        };
+        (sample < self.total_samples).then_some(sample)
+    }
+}
+
+/// Longest possible FLAC frame header, in bytes.
+const MAX_FLAC_HEADER: usize = 16;
+
+/// CRC-8 (polynomial 0x07) used by FLAC frame headers.
+fn crc8(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |mut crc, &byte| {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            };
+        }
+        crc
+    })
+}
+
+/// Whether two bytes start a FLAC frame header.
+fn is_flac_sync(a: u8, b: u8) -> bool {
+    a == 0xFF && b & 0xFE == 0xF8
+}
+
+/// Whether two bytes start an MPEG audio frame header.
+fn is_mp3_sync(a: u8, b: u8) -> bool {
+    a == 0xFF && b & 0xE0 == 0xE0
+}
+
+/// Find the first frame sync at or after `from`, returning `from` if none is found nearby.
+fn find_frame(
+    file: &mut File,
+    from: u64,
+    limit: u64,
+    is_sync: fn(u8, u8) -> bool,
+) -> io::Result<u64> {
+    if from >= limit {
+        return Ok(limit);
+    }
+
+    let mut buf = vec![0u8; FRAME_SEARCH_WINDOW.min((limit - from) as usize)];
+    file.seek(SeekFrom::Start(from))?;
+    let read = file.read(&mut buf)?;
+
+    Ok(buf[..read]
+        .windows(2)
+        .position(|w| is_sync(w[0], w[1]))
+        .map(|i| from + i as u64)
+        .unwrap_or(from))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHEET: &str = r#"REM GENRE Rock
+REM DATE 1994
+PERFORMER "The Band"
+TITLE "Live Album"
+FILE "Live Album.flac" WAVE
+  TRACK 01 AUDIO
+    TITLE "Opener"
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    TITLE "Second"
+    PERFORMER "Guest"
+    INDEX 00 03:58:00
+    INDEX 01 04:00:37
+"#;
+
+    #[test]
+    fn test_parse_cue_time() {
+        assert_eq!(parse_cue_time("00:00:00"), Some(0));
+        assert_eq!(parse_cue_time("01:02:75"), Some(63_000));
+        assert_eq!(parse_cue_time("04:00:37"), Some(240_493));
+        assert_eq!(parse_cue_time("bad"), None);
+    }
+
+    #[test]
+    fn test_parse_cue() {
+        let sheet = parse_cue(SHEET);
+        assert_eq!(sheet.title.as_deref(), Some("Live Album"));
+        assert_eq!(sheet.performer.as_deref(), Some("The Band"));
+        assert_eq!(sheet.genre.as_deref(), Some("Rock"));
+        assert_eq!(sheet.year, Some(1994));
+        assert_eq!(sheet.files.len(), 1);
+        assert_eq!(sheet.files[0].name, "Live Album.flac");
+
+        let tracks = sheet.tracks_for_file("renamed.flac").unwrap();
+        assert_eq!(tracks.len(), 2);
+        assert_eq!(tracks[0].title.as_deref(), Some("Opener"));
+        assert_eq!(tracks[0].start_ms, 0);
+        assert_eq!(tracks[1].number, 2);
+        assert_eq!(tracks[1].performer.as_deref(), Some("Guest"));
+        assert_eq!(tracks[1].start_ms, 240_493);
+    }
+
+    /// Build a fixed-blocksize FLAC frame header for `frame_number`.
+    fn flac_frame(frame_number: u8, payload_len: usize) -> Vec<u8> {
+        // 4096-sample blocks, 44.1kHz, stereo, 16-bit
+        let mut frame = vec![0xFF, 0xF8, 0xC9, 0x18, frame_number];
+        frame.push(crc8(&frame));
+        frame.resize(frame.len() + payload_len, 0x55);
+        frame
+    }
+
+    #[test]
+    fn test_slice_flac_seeks_by_sample() {
+        let mut info = [0u8; 34];
+        info[0..2].copy_from_slice(&4096u16.to_be_bytes());
+        info[2..4].copy_from_slice(&4096u16.to_be_bytes());
+        // 44100 Hz, 2 channels, 16 bits, 40 * 4096 samples
+        let total_samples: u64 = 40 * 4096;
+        info[10] = (44100u32 >> 12) as u8;
+        info[11] = (44100u32 >> 4) as u8;
+        info[12] = ((44100u32 & 0x0F) << 4) as u8 | (1 << 1);
+        info[13] = 0xF0;
+        info[14..18].copy_from_slice(&(total_samples as u32).to_be_bytes());
+
+        let mut data = b"fLaC".to_vec();
+        data.extend_from_slice(&[0x80, 0, 0, 34]);
+        data.extend_from_slice(&info);
+
+        // Variable frame sizes so byte offsets aren't proportional to time
+        let mut offsets = Vec::new();
+        for n in 0..40u8 {
+            offsets.push(data.len() as u64);
+            let payload = if n < 20 { 200 } else { 4000 };
+            data.extend_from_slice(&flac_frame(n, payload));
+        }
+
+        let path =
+            std::env::temp_dir().join(format!("subsonic-cue-test-{}.flac", std::process::id()));
+        fs::write(&path, &data).unwrap();
+
+        // Frame 25 starts at 25 * 4096 / 44100 s = 2322ms
+        let slice = slice_track(&path, "flac", 2400, Some(3000)).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(slice.offset, offsets[25]);
+        // 3000ms is sample 132300, inside frame 32
+        assert_eq!(slice.offset + slice.length, offsets[32]);
+        assert_eq!(&slice.header[0..4], b"fLaC");
+    }
+}
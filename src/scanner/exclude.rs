@@ -0,0 +1,276 @@
+//! Scan exclusion rules.
+//!
+//! Directories containing a `.nomedia` or `.subsonicignore` marker are skipped
+//! entirely. `.ignore` files hold gitignore-style patterns relative to their
+//! directory, and each music folder can carry its own exclude patterns.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Marker files that exclude the directory containing them.
+pub const IGNORE_MARKERS: &[&str] = &[".nomedia", ".subsonicignore"];
+
+/// Name of gitignore-style pattern files.
+pub const IGNORE_FILE: &str = ".ignore";
+
+/// A single gitignore-style pattern.
+#[derive(Debug, Clone)]
+pub struct IgnorePattern {
+    glob: Vec<char>,
+    /// `!pattern`: re-include paths excluded by an earlier pattern.
+    negated: bool,
+    /// `pattern/`: only match directories.
+    dir_only: bool,
+    /// Patterns containing a `/` match the full relative path, others match any file name.
+    anchored: bool,
+}
+
+impl IgnorePattern {
+    /// Parse a pattern line, returning None for blank lines and comments.
+    pub fn parse(line: &str) -> Option<Self> {
+        let mut pattern = line.trim();
+        if pattern.is_empty() || pattern.starts_with('#') {
+            return None;
+        }
+
+        let negated = pattern.starts_with('!');
+        if negated {
+            pattern = &pattern[1..];
+        } else if let Some(escaped) = pattern.strip_prefix('\\') {
+            pattern = escaped;
+        }
+
+        let dir_only = pattern.ends_with('/');
+        let pattern = pattern.trim_end_matches('/');
+        let anchored = pattern.contains('/');
+        let pattern = pattern.trim_start_matches('/');
+        if pattern.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            glob: pattern.chars().collect(),
+            negated,
+            dir_only,
+            anchored,
+        })
+    }
+
+    /// Check whether a path relative to the pattern's base directory matches.
+    pub fn matches(&self, relative_path: &str, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+
+        let target = if self.anchored {
+            relative_path
+        } else {
+            relative_path.rsplit('/').next().unwrap_or(relative_path)
+        };
+        let target: Vec<char> = target.chars().collect();
+        glob_match(&self.glob, &target)
+    }
+}
+
+/// Parse a list of pattern lines.
+pub fn parse_patterns<'a>(lines: impl IntoIterator<Item = &'a str>) -> Vec<IgnorePattern> {
+    lines.into_iter().filter_map(IgnorePattern::parse).collect()
+}
+
+/// Exclusion state for a directory walk.
+///
+/// Tracks the `.ignore` files of the directories currently being walked so
+/// nested files only apply to their own subtree. A clone carries the state
+/// of the current directory, so its subtree can be walked separately.
+#[derive(Clone)]
+pub struct ExcludeRules {
+    root: PathBuf,
+    folder_patterns: Vec<IgnorePattern>,
+    /// (walk depth, directory, patterns) for each ancestor with an `.ignore` file.
+    stack: Vec<(usize, PathBuf, Vec<IgnorePattern>)>,
+}
+
+impl ExcludeRules {
+    /// Create rules for walking a music folder with its configured exclude patterns.
+    pub fn new(root: &Path, folder_patterns: &[String]) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            folder_patterns: parse_patterns(folder_patterns.iter().map(String::as_str)),
+            stack: Vec::new(),
+        }
+    }
+
+    /// Check whether an entry at the given walk depth is excluded.
+    ///
+    /// Entries must be visited in walk order, since this also drops the
+    /// `.ignore` patterns of directories that are no longer ancestors.
+    pub fn is_excluded(&mut self, path: &Path, depth: usize, is_dir: bool) -> bool {
+        while self.stack.last().is_some_and(|(d, _, _)| *d >= depth) {
+            self.stack.pop();
+        }
+
+        // Later patterns override earlier ones, with nested files overriding the folder config
+        let mut excluded = false;
+        let sources = std::iter::once((self.root.as_path(), &self.folder_patterns))
+            .chain(self.stack.iter().map(|(_, dir, p)| (dir.as_path(), p)));
+        for (base, patterns) in sources {
+            let Some(relative) = relative_path(path, base) else {
+                continue;
+            };
+            for pattern in patterns {
+                if pattern.matches(&relative, is_dir) {
+                    excluded = !pattern.negated;
+                }
+            }
+        }
+
+        excluded
+    }
+
+    /// Enter a directory, returning false if it holds a marker file and should be skipped.
+    pub fn enter_dir(&mut self, dir: &Path, depth: usize) -> bool {
+        if IGNORE_MARKERS.iter().any(|m| dir.join(m).exists()) {
+            return false;
+        }
+
+        if let Ok(content) = fs::read_to_string(dir.join(IGNORE_FILE)) {
+            let patterns = parse_patterns(content.lines());
+            if !patterns.is_empty() {
+                self.stack.push((depth, dir.to_path_buf(), patterns));
+            }
+        }
+
+        true
+    }
+}
+
+/// Get a path relative to a base directory, with `/` separators.
+fn relative_path(path: &Path, base: &Path) -> Option<String> {
+    let relative = path.strip_prefix(base).ok()?;
+    let parts: Vec<_> = relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect();
+    (!parts.is_empty()).then(|| parts.join("/"))
+}
+
+/// Match text against a glob supporting `*`, `**`, `?` and `[...]` classes.
+fn glob_match(pattern: &[char], text: &[char]) -> bool {
+    match pattern.first() {
+        None => text.is_empty(),
+        Some('*') if pattern.get(1) == Some(&'*') => {
+            let rest = &pattern[2..];
+            // `**/` also matches zero directories
+            if let Some(after_slash) = rest.strip_prefix(&['/'])
+                && glob_match(after_slash, text)
+            {
+                return true;
+            }
+            (0..=text.len()).any(|i| glob_match(rest, &text[i..]))
+        }
+        Some('*') => {
+            let rest = &pattern[1..];
+            for i in 0..=text.len() {
+                if glob_match(rest, &text[i..]) {
+                    return true;
+                }
+                if text.get(i) == Some(&'/') {
+                    break;
+                }
+            }
+            false
+        }
+        Some('?') => {
+            text.first().is_some_and(|&c| c != '/') && glob_match(&pattern[1..], &text[1..])
+        }
+        Some('[') => match match_class(&pattern[1..], text.first().copied()) {
+            Some((matched, consumed)) => {
+                matched && glob_match(&pattern[1 + consumed..], &text[1..])
+            }
+            // No closing bracket: treat `[` literally
+            None => text.first() == Some(&'[') && glob_match(&pattern[1..], &text[1..]),
+        },
+        Some(&c) => text.first() == Some(&c) && glob_match(&pattern[1..], &text[1..]),
+    }
+}
+
+/// Match a character against a `[...]` class (starting after the `[`).
+///
+/// Returns whether it matched and how many pattern characters the class used,
+/// or None if the class is not terminated.
+fn match_class(class: &[char], c: Option<char>) -> Option<(bool, usize)> {
+    let negated = matches!(class.first(), Some('!') | Some('^'));
+    let start = usize::from(negated);
+
+    // A `]` right after the opening bracket is literal
+    let end = class
+        .iter()
+        .enumerate()
+        .skip(start + 1)
+        .find(|(_, ch)| **ch == ']')
+        .map(|(i, _)| i)?;
+
+    let Some(c) = c.filter(|&c| c != '/') else {
+        return Some((false, end + 1));
+    };
+
+    let items = &class[start..end];
+    let mut matched = false;
+    let mut i = 0;
+    while i < items.len() {
+        if i + 2 < items.len() && items[i + 1] == '-' {
+            matched |= (items[i]..=items[i + 2]).contains(&c);
+            i += 3;
+        } else {
+            matched |= items[i] == c;
+            i += 1;
+        }
+    }
+
+    Some((matched != negated, end + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glob(pattern: &str, text: &str) -> bool {
+        let pattern: Vec<char> = pattern.chars().collect();
+        let text: Vec<char> = text.chars().collect();
+        glob_match(&pattern, &text)
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob("*.wav", "kick.wav"));
+        assert!(!glob("*.wav", "samples/kick.wav"));
+        assert!(glob("samples/**", "samples/drums/kick.wav"));
+        assert!(glob("**/@eaDir", "@eaDir"));
+        assert!(glob("**/@eaDir", "Artist/Album/@eaDir"));
+        assert!(glob("track?.mp3", "track1.mp3"));
+        assert!(glob("[!_]*", "Album"));
+        assert!(!glob("[!_]*", "_incoming"));
+        assert!(glob("disc[0-9]", "disc2"));
+    }
+
+    #[test]
+    fn test_pattern_rules() {
+        let patterns = parse_patterns(["# comment", "_incoming/", "*.tmp", "!keep.tmp", "/Live"]);
+        let excluded = |path: &str, is_dir: bool| {
+            let mut result = false;
+            for p in &patterns {
+                if p.matches(path, is_dir) {
+                    result = !p.negated;
+                }
+            }
+            result
+        };
+
+        assert!(excluded("Artist/_incoming", true));
+        assert!(!excluded("Artist/_incoming", false));
+        assert!(excluded("Artist/Album/file.tmp", false));
+        assert!(!excluded("Artist/Album/keep.tmp", false));
+        assert!(excluded("Live", true));
+        assert!(!excluded("Artist/Live", true));
+    }
+}
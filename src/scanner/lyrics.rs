@@ -6,7 +6,7 @@
 use std::path::Path;
 
 use lofty::file::TaggedFileExt;
-use lofty::tag::ItemKey;
+use lofty::tag::{ItemKey, Tag};
 
 /// Extracted lyrics from an audio file.
 #[derive(Debug, Clone)]
@@ -34,21 +34,26 @@ pub struct SyncedLine {
 ///
 /// Returns a list of extracted lyrics (may have multiple for different languages).
 pub fn extract_lyrics(path: &Path) -> Vec<ExtractedLyrics> {
-    let mut results = Vec::new();
-
     let tagged_file = match lofty::read_from_path(path) {
         Ok(f) => f,
-        Err(_) => return results,
+        Err(_) => return Vec::new(),
     };
 
     // Try primary tag first, then any available tag
-    let tag = match tagged_file
+    match tagged_file
         .primary_tag()
         .or_else(|| tagged_file.first_tag())
     {
-        Some(t) => t,
-        None => return results,
-    };
+        Some(tag) => extract_lyrics_from_tag(tag),
+        None => Vec::new(),
+    }
+}
+
+/// Extract lyrics from an already-read tag.
+///
+/// Used by the scanner so lyrics can be indexed without re-reading the file.
+pub fn extract_lyrics_from_tag(tag: &Tag) -> Vec<ExtractedLyrics> {
+    let mut results = Vec::new();
 
     // Try to get unsynchronized lyrics (USLT in ID3, LYRICS in Vorbis)
     // ItemKey::Lyrics is the standard key for unsynchronized lyrics
@@ -1,15 +1,22 @@
 //! Music library scanner.
 //!
 //! Walks music folders, reads audio file metadata, and populates the database.
+//! Files are read in parallel and written to the database in batches as they arrive,
+//! so memory use does not grow with the size of the library.
 //! Supports incremental scanning (only changed files) and auto-scan with configurable interval.
 
+pub mod cue;
+pub mod exclude;
 pub mod lyrics;
+pub mod nfo;
+pub mod playlists;
+pub mod smart_playlists;
 
 use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, mpsc};
 use std::time::{Duration, UNIX_EPOCH};
 
 use lofty::file::{AudioFile, TaggedFileExt};
@@ -19,8 +26,18 @@ use thiserror::Error;
 use tokio::sync::watch;
 use walkdir::WalkDir;
 
-use crate::db::{DbPool, MusicFolderRepository, MusicRepoError};
+use crate::db::{
+    DbConn, DbPool, DirectoryRepository, LyricsRepository, MusicFolderRepository, MusicRepoError,
+    NewDirectory, NewLyrics, PlaylistRepository, ScanHistoryRepository, ScannedDirectoryRepository,
+    UserRepository,
+};
 use crate::models::music::MusicFolder;
+use cue::{CueSheet, find_sidecar_cue, parse_cue, read_cue_file};
+use exclude::ExcludeRules;
+use lyrics::{ExtractedLyrics, extract_lyrics_from_tag};
+use nfo::{read_album_info, read_artist_info};
+use playlists::{PLAYLIST_EXTENSIONS, PlaylistImportConfig, SongPathIndex, read_playlist_file};
+use smart_playlists::{SMART_PLAYLIST_EXTENSION, read_smart_playlist_file};
 
 /// Errors that can occur during scanning.
 #[derive(Debug, Error)]
@@ -75,17 +92,23 @@ pub struct ScannedTrack {
     pub disc_number: Option<u32>,
     pub year: Option<u32>,
     pub genre: Option<String>,
+    /// MusicBrainz recording ID.
+    pub musicbrainz_id: Option<String>,
     pub duration_secs: u32,
     pub bit_rate: Option<u32>,
     pub bit_depth: Option<u8>,
     pub sample_rate: Option<u32>,
     pub channels: Option<u8>,
-    /// Embedded cover art data (bytes).
-    pub cover_art_data: Option<Vec<u8>>,
-    /// MIME type of the embedded cover art.
-    pub cover_art_mime: Option<String>,
+    /// ID of the embedded cover art, saved to the cover art cache when the file is read.
+    pub cover_art: Option<String>,
     /// File modification time (Unix timestamp in seconds).
     pub file_modified_at: Option<i64>,
+    /// Embedded lyrics, indexed into the lyrics table.
+    pub lyrics: Vec<ExtractedLyrics>,
+    /// Start offset within the file for tracks split by a CUE sheet.
+    pub cue_start_ms: Option<i64>,
+    /// End offset within the file for CUE tracks (None = end of file).
+    pub cue_end_ms: Option<i64>,
 }
 
 /// Result of scanning a music folder.
@@ -96,12 +119,70 @@ pub struct ScanResult {
     pub tracks_updated: usize,
     pub tracks_skipped: usize,
     pub tracks_removed: usize,
+    /// Songs whose files were moved or renamed, kept under their existing IDs.
+    pub tracks_moved: usize,
     pub tracks_failed: usize,
     pub artists_added: usize,
     pub albums_added: usize,
     pub cover_art_saved: usize,
+    pub playlists_imported: usize,
+    /// Files (or folders) that failed during the scan.
+    pub errors: Vec<ScanFileError>,
+    /// Whether the scan was cancelled before it finished.
+    pub cancelled: bool,
 }
 
+/// A file that failed during a scan.
+#[derive(Debug, Clone)]
+pub struct ScanFileError {
+    pub path: String,
+    pub error: String,
+}
+
+impl ScanFileError {
+    fn new(path: &Path, error: impl ToString) -> Self {
+        Self {
+            path: path.to_string_lossy().to_string(),
+            error: error.to_string(),
+        }
+    }
+}
+
+/// Files found while walking a music folder.
+struct DiscoveredFiles {
+    /// Audio files to read.
+    audio_files: Vec<PathBuf>,
+    /// Playlist files to import.
+    playlist_files: Vec<PathBuf>,
+    /// Audio files whose size and modification time match the database (incremental scans).
+    unchanged_files: Vec<String>,
+    /// Directories unchanged since the last scan, whose files were not looked at
+    /// (incremental scans skipping unchanged directories).
+    unchanged_dirs: HashSet<PathBuf>,
+    /// Every directory walked, with its modification time in nanoseconds.
+    directories: Vec<(String, i64)>,
+}
+
+/// A song already in the database, as needed to detect changes.
+#[derive(Debug, Clone, Copy)]
+struct ExistingSong {
+    /// File modification time (Unix timestamp in seconds).
+    file_modified_at: Option<i64>,
+    file_size: i64,
+}
+
+/// Result of reading one audio file: its tracks (several for CUE sheets) or the error.
+type ReadResult = Result<Vec<ScannedTrack>, ScanFileError>;
+
+/// Number of songs written per database transaction.
+const BATCH_SIZE: usize = 100;
+
+/// Number of read files that may wait for the database writer.
+///
+/// Readers block once the queue is full, so memory use is bounded by this and
+/// the batch size rather than by the size of the library.
+const READ_QUEUE_SIZE: usize = 2 * BATCH_SIZE;
+
 /// Shared state for tracking scan progress across API requests.
 ///
 /// This is designed to be shared across threads (wrapped in Arc) and
@@ -110,6 +191,8 @@ pub struct ScanResult {
 pub struct ScanState {
     /// Whether a scan is currently in progress.
     scanning: AtomicBool,
+    /// Whether the current (or last) scan was asked to stop.
+    cancelled: AtomicBool,
     /// Number of items scanned so far.
     count: AtomicU64,
     /// Total number of items to scan (0 if unknown/discovery phase).
@@ -118,6 +201,8 @@ pub struct ScanState {
     phase: std::sync::RwLock<ScanPhase>,
     /// Current folder being scanned (if any).
     current_folder: std::sync::RwLock<Option<String>>,
+    /// Mode of the running scan (if any).
+    mode: std::sync::RwLock<Option<ScanMode>>,
 }
 
 /// Scan phase for progress tracking.
@@ -132,6 +217,8 @@ pub enum ScanPhase {
     Processing,
     /// Cleaning up orphaned records.
     Cleaning,
+    /// Scan was cancelled (stopping, or stopped early).
+    Cancelled,
 }
 
 impl ScanPhase {
@@ -142,6 +229,7 @@ impl ScanPhase {
             ScanPhase::Discovering => "discovering",
             ScanPhase::Processing => "processing",
             ScanPhase::Cleaning => "cleaning",
+            ScanPhase::Cancelled => "cancelled",
         }
     }
 }
@@ -151,10 +239,12 @@ impl ScanState {
     pub fn new() -> Self {
         Self {
             scanning: AtomicBool::new(false),
+            cancelled: AtomicBool::new(false),
             count: AtomicU64::new(0),
             total: AtomicU64::new(0),
             phase: std::sync::RwLock::new(ScanPhase::Idle),
             current_folder: std::sync::RwLock::new(None),
+            mode: std::sync::RwLock::new(None),
         }
     }
 
@@ -174,20 +264,52 @@ impl ScanState {
     }
 
     /// Get the current scan phase.
+    ///
+    /// Reports `Cancelled` once a cancel is requested, until the next scan starts.
     pub fn get_phase(&self) -> ScanPhase {
+        if self.is_cancelled() {
+            return ScanPhase::Cancelled;
+        }
         self.phase.read().unwrap().clone()
     }
 
+    /// Check if the current scan has been asked to stop.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Ask the running scan to stop. Returns false if no scan is in progress.
+    ///
+    /// The scanner stops at the next batch or folder boundary, keeping work
+    /// already committed.
+    pub fn cancel(&self) -> bool {
+        if !self.is_scanning() {
+            return false;
+        }
+        self.cancelled.store(true, Ordering::SeqCst);
+        true
+    }
+
     /// Get the current folder being scanned.
     pub fn get_current_folder(&self) -> Option<String> {
         self.current_folder.read().unwrap().clone()
     }
 
+    /// Get the mode of the running scan.
+    pub fn get_mode(&self) -> Option<ScanMode> {
+        *self.mode.read().unwrap()
+    }
+
     /// Try to start a scan. Returns false if a scan is already in progress.
     pub fn try_start(&self) -> bool {
-        self.scanning
+        let started = self
+            .scanning
             .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
-            .is_ok()
+            .is_ok();
+        if started {
+            self.cancelled.store(false, Ordering::SeqCst);
+        }
+        started
     }
 
     /// Mark the scan as complete.
@@ -195,6 +317,7 @@ impl ScanState {
         self.scanning.store(false, Ordering::SeqCst);
         *self.phase.write().unwrap() = ScanPhase::Idle;
         *self.current_folder.write().unwrap() = None;
+        *self.mode.write().unwrap() = None;
     }
 
     /// Reset the count to 0.
@@ -204,10 +327,12 @@ impl ScanState {
 
     /// Reset all progress state for a new scan.
     pub fn reset(&self) {
+        self.cancelled.store(false, Ordering::SeqCst);
         self.count.store(0, Ordering::SeqCst);
         self.total.store(0, Ordering::SeqCst);
         *self.phase.write().unwrap() = ScanPhase::Idle;
         *self.current_folder.write().unwrap() = None;
+        *self.mode.write().unwrap() = None;
     }
 
     /// Increment the count by 1 and return the new value.
@@ -234,6 +359,11 @@ impl ScanState {
     pub fn set_current_folder(&self, folder: Option<String>) {
         *self.current_folder.write().unwrap() = folder;
     }
+
+    /// Set the mode of the running scan.
+    pub fn set_mode(&self, mode: ScanMode) {
+        *self.mode.write().unwrap() = Some(mode);
+    }
 }
 
 /// Default cover art cache directory.
@@ -242,6 +372,9 @@ const COVER_ART_CACHE_DIR: &str = ".cache/subsonic/covers";
 /// Default auto-scan interval (5 minutes).
 const DEFAULT_AUTO_SCAN_INTERVAL_SECS: u64 = 300;
 
+/// How often a running scan records that it is still working.
+pub const SCAN_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+
 /// Scan mode controlling how files are scanned.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum ScanMode {
@@ -252,16 +385,54 @@ pub enum ScanMode {
     Incremental,
 }
 
+impl ScanMode {
+    /// Name recorded in the scan history.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScanMode::Full => "full",
+            ScanMode::Incremental => "incremental",
+        }
+    }
+}
+
+/// What started a scan, recorded in the scan history.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ScanTrigger {
+    /// The `scan` CLI command.
+    #[default]
+    Cli,
+    /// The startScan API endpoint.
+    Api,
+    /// The periodic auto-scanner.
+    Auto,
+}
+
+impl ScanTrigger {
+    /// Name recorded in the scan history.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScanTrigger::Cli => "cli",
+            ScanTrigger::Api => "api",
+            ScanTrigger::Auto => "auto",
+        }
+    }
+}
+
 /// Music library scanner.
 pub struct Scanner {
     pool: DbPool,
     cover_art_dir: PathBuf,
+    playlist_import: PlaylistImportConfig,
+    trigger: ScanTrigger,
+    skip_unchanged_dirs: bool,
 }
 
 /// Auto-scanner that runs periodic scans in the background.
 pub struct AutoScanner {
     pool: DbPool,
     cover_art_dir: PathBuf,
+    playlist_import: PlaylistImportConfig,
+    skip_unchanged_dirs: bool,
     interval: Duration,
     scan_state: Arc<ScanState>,
     shutdown_tx: Option<watch::Sender<bool>>,
@@ -278,6 +449,9 @@ impl Scanner {
         Self {
             pool,
             cover_art_dir,
+            playlist_import: PlaylistImportConfig::default(),
+            trigger: ScanTrigger::default(),
+            skip_unchanged_dirs: false,
         }
     }
 
@@ -286,7 +460,82 @@ impl Scanner {
         Self {
             pool,
             cover_art_dir,
+            playlist_import: PlaylistImportConfig::default(),
+            trigger: ScanTrigger::default(),
+            skip_unchanged_dirs: false,
+        }
+    }
+
+    /// Set how playlist files found in music folders are imported.
+    pub fn with_playlist_import(mut self, config: PlaylistImportConfig) -> Self {
+        self.playlist_import = config;
+        self
+    }
+
+    /// Set what started the scan, for the scan history.
+    pub fn with_trigger(mut self, trigger: ScanTrigger) -> Self {
+        self.trigger = trigger;
+        self
+    }
+
+    /// Skip directories whose modification time hasn't changed in incremental
+    /// scans, without looking at their files.
+    ///
+    /// Directory mtimes only change when entries are added, removed or
+    /// renamed, so files retagged in place are missed until a full scan.
+    pub fn with_skip_unchanged_dirs(mut self, skip: bool) -> Self {
+        self.skip_unchanged_dirs = skip;
+        self
+    }
+
+    /// Run a scan and record it in the scan history.
+    ///
+    /// Failing to record history is logged but never fails the scan itself.
+    fn record_run(
+        &self,
+        mode: ScanMode,
+        scan: impl FnOnce() -> Result<ScanResult, ScanError>,
+    ) -> Result<ScanResult, ScanError> {
+        let history = ScanHistoryRepository::new(self.pool.clone());
+        let run_id = match history.start_run(mode.as_str(), self.trigger.as_str()) {
+            Ok(id) => Some(id),
+            Err(e) => {
+                eprintln!("Warning: Failed to record scan run: {}", e);
+                None
+            }
+        };
+
+        // Keep the run marked as live until the scan returns
+        let result = std::thread::scope(|scope| {
+            let (done_tx, done_rx) = mpsc::channel::<()>();
+            if let Some(run_id) = run_id {
+                let history = &history;
+                scope.spawn(move || {
+                    while let Err(mpsc::RecvTimeoutError::Timeout) =
+                        done_rx.recv_timeout(SCAN_HEARTBEAT_INTERVAL)
+                    {
+                        if let Err(e) = history.heartbeat(run_id) {
+                            eprintln!("Warning: Failed to record scan run: {}", e);
+                        }
+                    }
+                });
+            }
+            let result = scan();
+            drop(done_tx);
+            result
+        });
+
+        if let Some(run_id) = run_id {
+            let recorded = match &result {
+                Ok(stats) => history.finish_run(run_id, stats, None),
+                Err(e) => history.finish_run(run_id, &ScanResult::default(), Some(&e.to_string())),
+            };
+            if let Err(e) = recorded {
+                eprintln!("Warning: Failed to record scan run: {}", e);
+            }
         }
+
+        result
     }
 
     /// Ensure cover art cache directory exists.
@@ -297,8 +546,8 @@ impl Scanner {
         Ok(())
     }
 
-    /// Save cover art to cache and return the cover art ID.
-    fn save_cover_art(&self, data: &[u8], mime: &str) -> Result<String, ScanError> {
+    /// Save cover art to the cache directory and return the cover art ID.
+    fn save_cover_art(cover_art_dir: &Path, data: &[u8], mime: &str) -> Result<String, ScanError> {
         use md5::{Digest, Md5};
 
         // Generate hash-based ID for the cover art
@@ -316,11 +565,24 @@ impl Scanner {
         };
 
         let filename = format!("{}.{}", hash, ext);
-        let filepath = self.cover_art_dir.join(&filename);
+        let filepath = cover_art_dir.join(&filename);
 
-        // Only write if file doesn't already exist (same content = same hash)
+        // Only write if file doesn't already exist (same content = same hash).
+        // Write to a temporary file first so readers never see a partial image;
+        // reader threads saving the same picture each use their own.
         if !filepath.exists() {
-            fs::write(&filepath, data)?;
+            static TMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+            let tmp_path = cover_art_dir.join(format!(
+                ".{}.{}.{}.tmp",
+                filename,
+                std::process::id(),
+                TMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+            ));
+            fs::write(&tmp_path, data)?;
+            if let Err(e) = fs::rename(&tmp_path, &filepath) {
+                let _ = fs::remove_file(&tmp_path);
+                return Err(e.into());
+            }
         }
 
         // Return just the hash as the cover art ID
@@ -429,9 +691,32 @@ impl Scanner {
             return Err(ScanError::NoMusicFolders);
         }
 
+        if let Some(ref s) = state {
+            s.set_mode(mode);
+        }
+
+        self.record_run(mode, || self.scan_folders(&folders, state, mode))
+    }
+
+    /// Scan the given music folders, then clean up removed songs and orphans.
+    fn scan_folders(
+        &self,
+        folders: &[MusicFolder],
+        state: Option<Arc<ScanState>>,
+        mode: ScanMode,
+    ) -> Result<ScanResult, ScanError> {
         let mut total_result = ScanResult::default();
 
-        for folder in &folders {
+        // Removals are deferred until every folder is scanned, so songs moved
+        // to a folder scanned later keep their IDs
+        let mut missing_paths: Vec<String> = Vec::new();
+
+        for folder in folders {
+            if state.as_ref().is_some_and(|s| s.is_cancelled()) {
+                total_result.cancelled = true;
+                break;
+            }
+
             // Update scan state with current folder
             if let Some(ref s) = state {
                 s.set_current_folder(Some(folder.name.clone()));
@@ -441,24 +726,52 @@ impl Scanner {
                 "Scanning folder: {} ({}) [mode: {:?}]",
                 folder.name, folder.path, mode
             );
-            match self.scan_folder_with_options(folder, state.clone(), mode) {
-                Ok(result) => {
+            match self.scan_folder_inner(folder, state.clone(), mode) {
+                Ok((result, missing)) => {
+                    missing_paths.extend(missing);
                     total_result.tracks_found += result.tracks_found;
                     total_result.tracks_added += result.tracks_added;
                     total_result.tracks_updated += result.tracks_updated;
                     total_result.tracks_skipped += result.tracks_skipped;
                     total_result.tracks_removed += result.tracks_removed;
+                    total_result.tracks_moved += result.tracks_moved;
                     total_result.tracks_failed += result.tracks_failed;
                     total_result.artists_added += result.artists_added;
                     total_result.albums_added += result.albums_added;
                     total_result.cover_art_saved += result.cover_art_saved;
+                    total_result.playlists_imported += result.playlists_imported;
+                    total_result.errors.extend(result.errors);
+                    total_result.cancelled |= result.cancelled;
                 }
                 Err(e) => {
                     eprintln!("Error scanning folder {}: {}", folder.name, e);
+                    total_result
+                        .errors
+                        .push(ScanFileError::new(Path::new(&folder.path), e));
                 }
             }
         }
 
+        // A cancelled scan has not seen every folder, so leave removals and
+        // orphan cleanup to the next complete scan
+        if total_result.cancelled || state.as_ref().is_some_and(|s| s.is_cancelled()) {
+            total_result.cancelled = true;
+            println!("Scan cancelled");
+            self.update_directories(folders);
+            return Ok(total_result);
+        }
+
+        if !missing_paths.is_empty() {
+            println!(
+                "Removing {} deleted files from database",
+                missing_paths.len()
+            );
+            match self.remove_deleted_songs(&missing_paths) {
+                Ok(removed) => total_result.tracks_removed += removed,
+                Err(e) => eprintln!("Warning: Failed to remove deleted files: {}", e),
+            }
+        }
+
         // Clean up orphaned artists and albums after scanning all folders
         if let Some(ref s) = state {
             s.set_phase(ScanPhase::Cleaning);
@@ -469,6 +782,12 @@ impl Scanner {
             eprintln!("Warning: Failed to cleanup orphaned records: {}", e);
         }
 
+        if let Err(e) = self.update_info_files() {
+            eprintln!("Warning: Failed to read artist and album info files: {}", e);
+        }
+
+        self.update_directories(folders);
+
         Ok(total_result)
     }
 
@@ -482,6 +801,16 @@ impl Scanner {
         &self,
         folder_id: i32,
         mode: ScanMode,
+    ) -> Result<ScanResult, ScanError> {
+        self.scan_folder_by_id_with_options(folder_id, None, mode)
+    }
+
+    /// Scan a specific music folder by ID with optional progress tracking and scan mode.
+    pub fn scan_folder_by_id_with_options(
+        &self,
+        folder_id: i32,
+        state: Option<Arc<ScanState>>,
+        mode: ScanMode,
     ) -> Result<ScanResult, ScanError> {
         let folder_repo = MusicFolderRepository::new(self.pool.clone());
         let folder = folder_repo
@@ -492,7 +821,12 @@ impl Scanner {
             "Scanning folder: {} ({}) [mode: {:?}]",
             folder.name, folder.path, mode
         );
-        self.scan_folder_with_options(&folder, None, mode)
+        if let Some(ref s) = state {
+            s.set_mode(mode);
+            s.set_current_folder(Some(folder.name.clone()));
+        }
+
+        self.record_run(mode, || self.scan_folder_with_options(&folder, state, mode))
     }
 
     /// Scan a single music folder with optional progress tracking and scan mode.
@@ -502,6 +836,38 @@ impl Scanner {
         state: Option<Arc<ScanState>>,
         mode: ScanMode,
     ) -> Result<ScanResult, ScanError> {
+        let (mut result, missing_paths) = self.scan_folder_inner(folder, state, mode)?;
+
+        if !missing_paths.is_empty() && !result.cancelled {
+            println!(
+                "  Removing {} deleted files from database",
+                missing_paths.len()
+            );
+            result.tracks_removed = self.remove_deleted_songs(&missing_paths)?;
+        }
+
+        if !result.cancelled
+            && let Err(e) = self.update_info_files()
+        {
+            eprintln!(
+                "  Warning: Failed to read artist and album info files: {}",
+                e
+            );
+        }
+
+        self.update_directories(std::slice::from_ref(folder));
+
+        Ok(result)
+    }
+
+    /// Scan a single music folder without removing songs whose files are gone.
+    /// Returns the scan result and the paths of songs missing from disk.
+    fn scan_folder_inner(
+        &self,
+        folder: &MusicFolder,
+        state: Option<Arc<ScanState>>,
+        mode: ScanMode,
+    ) -> Result<(ScanResult, Vec<String>), ScanError> {
         let mut result = ScanResult::default();
         let folder_path = Path::new(&folder.path);
 
@@ -514,80 +880,470 @@ impl Scanner {
             s.set_phase(ScanPhase::Discovering);
         }
 
-        // Get existing songs in this folder for incremental scanning
-        let existing_songs = self.get_existing_songs(folder.id)?;
+        // Get existing songs and directories in this folder for incremental scanning
+        let mut existing_songs = self.get_existing_songs(folder.id)?;
+        let dir_repo = ScannedDirectoryRepository::new(self.pool.clone());
+        let known_dirs = dir_repo.find_by_folder(folder.id)?;
+
+        // Walk the folder first, so progress has a total while files are read
+        let DiscoveredFiles {
+            audio_files,
+            playlist_files,
+            unchanged_files,
+            unchanged_dirs,
+            directories,
+        } = self.discover_files(folder_path, folder, mode, &existing_songs, &known_dirs);
+        let mut discovered_paths: HashSet<String> = audio_files
+            .iter()
+            .map(|p| p.to_string_lossy().to_string())
+            .collect();
 
-        // Collect all audio files on disk
-        let (tracks, discovered_paths) = self.discover_tracks_with_paths(folder_path, folder)?;
-        result.tracks_found = tracks.len();
+        // Songs in unchanged directories and unchanged files are kept without reading tags
+        let unchanged_songs: Vec<String> = existing_songs
+            .keys()
+            .filter(|path| {
+                Path::new(path)
+                    .parent()
+                    .is_some_and(|dir| unchanged_dirs.contains(dir))
+            })
+            .cloned()
+            .chain(unchanged_files)
+            .collect();
+        let unchanged = unchanged_songs.len();
+        result.tracks_found += unchanged;
+        result.tracks_skipped += unchanged;
+        discovered_paths.extend(unchanged_songs);
+
+        if state.as_ref().is_some_and(|s| s.is_cancelled()) {
+            result.cancelled = true;
+            return Ok((result, Vec::new()));
+        }
 
-        // Set total count now that we know how many files to process
+        // Add to total (accumulates across folders)
         if let Some(ref s) = state {
-            // Add to total (accumulates across folders)
             let current_total = s.get_total();
-            s.set_total(current_total + tracks.len() as u64);
+            s.set_total(current_total + (audio_files.len() + unchanged) as u64);
+            s.set_count(s.get_count() + unchanged as u64);
             s.set_phase(ScanPhase::Processing);
         }
 
-        println!("  Found {} audio files on disk", tracks.len());
+        println!("  Found {} audio files to read", audio_files.len());
+        if unchanged > 0 {
+            println!("  Skipping {} unchanged songs", unchanged);
+        }
+
+        self.ensure_cover_art_dir()?;
+        let mut writer = TrackWriter::new(self, folder, mode, state.clone())?;
+
+        // Files are read in parallel and written in batches as they arrive
+        result.cancelled = std::thread::scope(|scope| -> Result<bool, ScanError> {
+            let (tx, rx) = mpsc::sync_channel::<ReadResult>(READ_QUEUE_SIZE);
+            let audio_files = &audio_files;
+            scope.spawn(move || self.read_tracks(audio_files, folder, tx));
+
+            let mut batch: Vec<ScannedTrack> = Vec::with_capacity(BATCH_SIZE);
+            // Dropping the receiver on return stops the readers
+            for read in rx {
+                match read {
+                    Ok(file_tracks) => {
+                        // Files split by a CUE sheet are stored as virtual tracks instead
+                        if file_tracks.len() > 1
+                            && let Some(ref s) = state
+                        {
+                            s.set_total(s.get_total() + file_tracks.len() as u64 - 1);
+                        }
+                        for track in file_tracks.iter().filter(|t| t.cue_start_ms.is_some()) {
+                            let path = track.path.to_string_lossy();
+                            if let Some((file_path, _)) = path.rsplit_once('#') {
+                                discovered_paths.remove(file_path);
+                            }
+                            discovered_paths.insert(path.to_string());
+                        }
+                        batch.extend(file_tracks);
+                    }
+                    Err(e) => {
+                        result.tracks_failed += 1;
+                        result.errors.push(e);
+                        if let Some(ref s) = state {
+                            s.increment_count();
+                        }
+                    }
+                }
+
+                if batch.len() >= BATCH_SIZE {
+                    // Stop between batches when cancelled, keeping committed batches
+                    if state.as_ref().is_some_and(|s| s.is_cancelled()) {
+                        return Ok(true);
+                    }
+                    let tracks = std::mem::take(&mut batch);
+                    self.write_batch(&mut writer, tracks, &mut existing_songs, &mut result)?;
+                }
+            }
+
+            if state.as_ref().is_some_and(|s| s.is_cancelled()) {
+                return Ok(true);
+            }
+            self.write_batch(&mut writer, batch, &mut existing_songs, &mut result)?;
+            Ok(false)
+        })?;
+
+        // Update album song counts and durations
+        writer.finish()?;
+
+        if result.tracks_moved > 0 {
+            println!("  Detected {} moved files", result.tracks_moved);
+        }
+
+        if result.cancelled {
+            return Ok((result, Vec::new()));
+        }
 
         // Find deleted files (in database but not on disk)
-        let deleted_paths: Vec<_> = existing_songs
+        let missing_paths: Vec<_> = existing_songs
             .keys()
             .filter(|path| !discovered_paths.contains(*path))
             .cloned()
             .collect();
 
-        if !deleted_paths.is_empty() {
-            println!(
-                "  Removing {} deleted files from database",
-                deleted_paths.len()
+        // Import playlist files now that their songs are in the database
+        match self.import_playlists(folder, &playlist_files, &unchanged_dirs) {
+            Ok(imported) => result.playlists_imported = imported,
+            Err(e) => eprintln!("  Warning: Failed to import playlists: {}", e),
+        }
+
+        // Remember directory mtimes, leaving out directories with failed files
+        // and their parents so those files are retried on the next scan
+        let failed_dirs: HashSet<&Path> = result
+            .errors
+            .iter()
+            .filter_map(|e| Path::new(&e.path).parent())
+            .flat_map(Path::ancestors)
+            .collect();
+        let directories: Vec<(String, i64)> = directories
+            .into_iter()
+            .filter(|(dir, _)| !failed_dirs.contains(Path::new(dir)))
+            .collect();
+        if let Err(e) = dir_repo.replace_for_folder(folder.id, &directories) {
+            eprintln!("  Warning: Failed to record scanned directories: {}", e);
+        }
+
+        Ok((result, missing_paths))
+    }
+
+    /// Write a batch of read tracks, first keeping the IDs of any songs that were moved.
+    fn write_batch(
+        &self,
+        writer: &mut TrackWriter,
+        tracks: Vec<ScannedTrack>,
+        existing_songs: &mut HashMap<String, ExistingSong>,
+        result: &mut ScanResult,
+    ) -> Result<(), ScanError> {
+        if tracks.is_empty() {
+            return Ok(());
+        }
+
+        result.tracks_found += tracks.len();
+        result.tracks_moved += self.relocate_moved_songs(writer.folder, &tracks, existing_songs)?;
+        writer.write(tracks, existing_songs, result)
+    }
+
+    /// Detect new tracks that are existing songs moved or renamed on disk, and
+    /// point those songs at their new paths so stars, ratings, play counts and
+    /// playlist entries are kept.
+    ///
+    /// A song matches a new track when its old file is gone and it has the same
+    /// duration, file size and either MusicBrainz recording ID or tags. Moved
+    /// songs are added to `existing_songs` under their new path.
+    fn relocate_moved_songs(
+        &self,
+        folder: &MusicFolder,
+        tracks: &[ScannedTrack],
+        existing_songs: &mut HashMap<String, ExistingSong>,
+    ) -> Result<usize, ScanError> {
+        use crate::db::schema::songs;
+        use diesel::prelude::*;
+
+        type Candidate = (
+            i32,
+            String,
+            Option<String>,
+            String,
+            Option<String>,
+            Option<String>,
+            Option<i32>,
+            i32,
+            i64,
+            Option<i64>,
+        );
+
+        let new_tracks: Vec<&ScannedTrack> = tracks
+            .iter()
+            .filter(|t| !existing_songs.contains_key(t.path.to_string_lossy().as_ref()))
+            .collect();
+        if new_tracks.is_empty() {
+            return Ok(0);
+        }
+
+        let mut conn = self.pool.get().map_err(MusicRepoError::Pool)?;
+
+        // Candidates may come from any music folder; narrow them down by size first
+        let sizes: Vec<i64> = new_tracks
+            .iter()
+            .map(|t| t.file_size as i64)
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        let mut candidates: Vec<Candidate> = Vec::new();
+        for chunk in sizes.chunks(500) {
+            candidates.extend(
+                songs::table
+                    .filter(songs::file_size.eq_any(chunk))
+                    .select((
+                        songs::id,
+                        songs::path,
+                        songs::musicbrainz_id,
+                        songs::title,
+                        songs::artist_name,
+                        songs::album_name,
+                        songs::track_number,
+                        songs::duration,
+                        songs::file_size,
+                        songs::file_modified_at,
+                    ))
+                    .load::<Candidate>(&mut conn)
+                    .map_err(MusicRepoError::Database)?,
             );
-            result.tracks_removed = self.remove_deleted_songs(&deleted_paths)?;
         }
 
-        // Process tracks and populate database
-        let (
-            artists_added,
-            albums_added,
-            tracks_added,
-            tracks_updated,
-            tracks_skipped,
-            tracks_failed,
-            cover_art_saved,
-        ) = self.process_tracks_with_options(folder, tracks, &existing_songs, state, mode)?;
-
-        result.artists_added = artists_added;
-        result.albums_added = albums_added;
-        result.tracks_added = tracks_added;
-        result.tracks_updated = tracks_updated;
-        result.tracks_skipped = tracks_skipped;
-        result.tracks_failed = tracks_failed;
-        result.cover_art_saved = cover_art_saved;
+        let same_text = |a: Option<&str>, b: Option<&str>| match (a, b) {
+            (Some(a), Some(b)) => a.eq_ignore_ascii_case(b),
+            (None, None) => true,
+            _ => false,
+        };
 
-        Ok(result)
+        let mut claimed: HashSet<i32> = HashSet::new();
+        let mut moved = 0;
+
+        for track in new_tracks {
+            let new_path = track.path.to_string_lossy().to_string();
+
+            let found = candidates.iter().find(
+                |(id, path, mbid, title, artist, album, track_number, duration, size, _)| {
+                    if claimed.contains(id)
+                        || *size != track.file_size as i64
+                        || *duration != track.duration_secs as i32
+                    {
+                        return false;
+                    }
+
+                    let same_recording = match (mbid, &track.musicbrainz_id) {
+                        (Some(a), Some(b)) => a == b,
+                        _ => {
+                            title.eq_ignore_ascii_case(&track.title)
+                                && same_text(artist.as_deref(), track.artist.as_deref())
+                                && same_text(album.as_deref(), track.album.as_deref())
+                                && *track_number == track.track_number.map(|n| n as i32)
+                        }
+                    };
+                    if !same_recording {
+                        return false;
+                    }
+
+                    // The old file must be gone, otherwise this is a copy
+                    let old_file = path.rsplit_once('#').map_or(path.as_str(), |(f, _)| f);
+                    !Path::new(old_file).exists()
+                },
+            );
+
+            let Some((id, old_path, .., size, mtime)) = found else {
+                continue;
+            };
+
+            diesel::update(songs::table.filter(songs::id.eq(id)))
+                .set((
+                    songs::path.eq(&new_path),
+                    songs::parent_path.eq(&track.parent_path),
+                    songs::music_folder_id.eq(folder.id),
+                    songs::updated_at.eq(diesel::dsl::now),
+                ))
+                .execute(&mut conn)
+                .map_err(MusicRepoError::Database)?;
+
+            claimed.insert(*id);
+            existing_songs.remove(old_path);
+            existing_songs.insert(
+                new_path,
+                ExistingSong {
+                    file_modified_at: *mtime,
+                    file_size: *size,
+                },
+            );
+            moved += 1;
+        }
+
+        Ok(moved)
+    }
+
+    /// Import playlist files (including `.nsp` smart playlists) found in a folder
+    /// and remove playlists whose files are gone.
+    ///
+    /// Playlists in `unchanged_dirs` were not walked and are kept as they are.
+    /// Returns the number of playlists created or updated.
+    fn import_playlists(
+        &self,
+        folder: &MusicFolder,
+        playlist_files: &[PathBuf],
+        unchanged_dirs: &HashSet<PathBuf>,
+    ) -> Result<usize, ScanError> {
+        use crate::db::schema::songs;
+        use diesel::prelude::*;
+
+        let playlist_repo = PlaylistRepository::new(self.pool.clone());
+
+        // Drop imported playlists from this folder whose files no longer exist
+        let discovered: HashSet<String> = playlist_files
+            .iter()
+            .map(|p| p.to_string_lossy().to_string())
+            .collect();
+        let folder_prefix = Path::new(&folder.path);
+        let stale: Vec<String> = playlist_repo
+            .find_imported_source_paths()?
+            .into_iter()
+            .filter(|p| {
+                let path = Path::new(p);
+                path.starts_with(folder_prefix)
+                    && !discovered.contains(p)
+                    && !path
+                        .parent()
+                        .is_some_and(|dir| unchanged_dirs.contains(dir))
+            })
+            .collect();
+        if !stale.is_empty() {
+            println!("  Removing {} deleted playlists", stale.len());
+            playlist_repo.delete_imported_playlists(&stale)?;
+        }
+
+        if playlist_files.is_empty() {
+            return Ok(0);
+        }
+
+        let user_repo = UserRepository::new(self.pool.clone());
+        let owner = match &self.playlist_import.owner {
+            Some(username) => user_repo.find_by_username(username),
+            None => user_repo.find_first_admin(),
+        };
+        let owner = match owner {
+            Ok(Some(user)) => user,
+            Ok(None) => {
+                eprintln!("  Warning: No playlist owner found, skipping playlist import");
+                return Ok(0);
+            }
+            Err(e) => {
+                eprintln!("  Warning: Failed to look up playlist owner: {}", e);
+                return Ok(0);
+            }
+        };
+
+        // Playlists may reference songs in any music folder
+        let mut conn = self.pool.get().map_err(MusicRepoError::Pool)?;
+        let song_paths: Vec<(String, i32)> = songs::table
+            .select((songs::path, songs::id))
+            .load(&mut conn)
+            .map_err(MusicRepoError::Database)?;
+        let index = SongPathIndex::new(song_paths);
+
+        let mut imported = 0;
+        for path in playlist_files {
+            let is_smart = path
+                .extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|e| e.eq_ignore_ascii_case(SMART_PLAYLIST_EXTENSION));
+            if is_smart {
+                let smart = match read_smart_playlist_file(path) {
+                    Ok(smart) => smart,
+                    Err(e) => {
+                        eprintln!("  Warning: Failed to read {}: {}", path.display(), e);
+                        continue;
+                    }
+                };
+                playlist_repo.sync_smart_playlist(
+                    owner.id,
+                    &path.to_string_lossy(),
+                    &smart.name,
+                    smart.comment.as_deref(),
+                    self.playlist_import.public,
+                    &smart.definition,
+                )?;
+                imported += 1;
+                continue;
+            }
+
+            let parsed = match read_playlist_file(path) {
+                Ok(parsed) => parsed,
+                Err(e) => {
+                    eprintln!("  Warning: Failed to read {}: {}", path.display(), e);
+                    continue;
+                }
+            };
+
+            let playlist_dir = path.parent().unwrap_or(folder_prefix);
+            let song_ids: Vec<i32> = parsed
+                .entries
+                .iter()
+                .filter_map(|entry| index.resolve(entry, playlist_dir))
+                .collect();
+
+            if song_ids.len() < parsed.entries.len() {
+                eprintln!(
+                    "  Warning: {} of {} entries in {} did not match a song",
+                    parsed.entries.len() - song_ids.len(),
+                    parsed.entries.len(),
+                    path.display()
+                );
+            }
+
+            playlist_repo.sync_imported_playlist(
+                owner.id,
+                &path.to_string_lossy(),
+                &parsed.name,
+                self.playlist_import.public,
+                &song_ids,
+            )?;
+            imported += 1;
+        }
+
+        println!("  Imported {} playlists", imported);
+
+        Ok(imported)
     }
 
-    /// Get existing songs in a folder from the database.
-    /// Returns a map of path -> (id, file_modified_at).
+    /// Get existing songs in a folder from the database, keyed by path.
     fn get_existing_songs(
         &self,
         folder_id: i32,
-    ) -> Result<HashMap<String, (i32, Option<i64>)>, ScanError> {
+    ) -> Result<HashMap<String, ExistingSong>, ScanError> {
         use crate::db::schema::songs;
         use diesel::prelude::*;
 
         let mut conn = self.pool.get().map_err(MusicRepoError::Pool)?;
 
-        let existing: Vec<(i32, String, Option<i64>)> = songs::table
+        let existing: Vec<(String, Option<i64>, i64)> = songs::table
             .filter(songs::music_folder_id.eq(folder_id))
-            .select((songs::id, songs::path, songs::file_modified_at))
+            .select((songs::path, songs::file_modified_at, songs::file_size))
             .load(&mut conn)
             .map_err(MusicRepoError::Database)?;
 
         Ok(existing
             .into_iter()
-            .map(|(id, path, mtime)| (path, (id, mtime)))
+            .map(|(path, file_modified_at, file_size)| {
+                (
+                    path,
+                    ExistingSong {
+                        file_modified_at,
+                        file_size,
+                    },
+                )
+            })
             .collect())
     }
 
@@ -628,69 +1384,482 @@ impl Scanner {
         Ok(())
     }
 
-    /// Discover all audio files in a directory, also returning the set of discovered paths.
-    /// Uses parallel processing for metadata reading.
-    fn discover_tracks_with_paths(
-        &self,
-        folder_path: &Path,
-        folder: &MusicFolder,
-    ) -> Result<(Vec<ScannedTrack>, HashSet<String>), ScanError> {
-        // First, collect all audio file paths (fast, sequential walk)
-        let audio_files: Vec<PathBuf> = WalkDir::new(folder_path)
-            .follow_links(true)
-            .into_iter()
-            .filter_map(|e| e.ok())
-            .filter(|entry| entry.path().is_file())
-            .filter_map(|entry| {
-                let path = entry.into_path();
-                let ext = path
-                    .extension()
-                    .and_then(|e| e.to_str())
-                    .map(|e| e.to_lowercase());
+    /// Update artist biographies and album notes from the info files next to their songs.
+    ///
+    /// Albums read `album.nfo` from their directory. Artists read `artist.nfo`
+    /// or `biography.txt` from the directory holding all of their albums.
+    /// Details missing from the info files are left as they are, and
+    /// MusicBrainz IDs from info files only fill in IDs missing from tags.
+    fn update_info_files(&self) -> Result<(), ScanError> {
+        use crate::db::schema::{albums, artists, songs};
+        use diesel::prelude::*;
 
-                match ext {
-                    Some(ext) if AUDIO_EXTENSIONS.contains(&ext.as_str()) => Some(path),
-                    _ => None,
+        let folders = MusicFolderRepository::new(self.pool.clone()).find_enabled()?;
+        let mut conn = self.pool.get().map_err(MusicRepoError::Pool)?;
+
+        // Directories of each album, and candidate directories of each artist
+        let mut album_dirs: HashMap<i32, PathBuf> = HashMap::new();
+        let mut artist_dirs: HashMap<i32, Vec<PathBuf>> = HashMap::new();
+        for folder in &folders {
+            let rows: Vec<(i32, Option<i32>, String)> = songs::table
+                .inner_join(albums::table)
+                .filter(songs::music_folder_id.eq(folder.id))
+                .select((albums::id, albums::artist_id, songs::parent_path))
+                .distinct()
+                .order((albums::id.asc(), songs::parent_path.asc()))
+                .load(&mut conn)
+                .map_err(MusicRepoError::Database)?;
+
+            let folder_path = Path::new(&folder.path);
+            let mut folder_artist_dirs: HashMap<i32, Vec<PathBuf>> = HashMap::new();
+            for (album_id, artist_id, parent_path) in rows {
+                let dir = PathBuf::from(parent_path);
+                // Loose files at the folder root say nothing about artist directories
+                if let Some(artist_id) = artist_id
+                    && !dir.as_os_str().is_empty()
+                {
+                    folder_artist_dirs
+                        .entry(artist_id)
+                        .or_default()
+                        .push(dir.clone());
                 }
-            })
+                album_dirs
+                    .entry(album_id)
+                    .or_insert_with(|| folder_path.join(dir));
+            }
+
+            // An artist's directory holds all of their albums: a single album
+            // directory or its parent, or else the albums' common ancestor
+            for (artist_id, mut dirs) in folder_artist_dirs {
+                dirs.sort();
+                dirs.dedup();
+                let candidates: Vec<PathBuf> = match dirs.as_slice() {
+                    [dir] => dir.ancestors().take(2).map(Path::to_path_buf).collect(),
+                    [first, rest @ ..] => {
+                        let common: PathBuf = first
+                            .components()
+                            .enumerate()
+                            .take_while(|(i, component)| {
+                                rest.iter()
+                                    .all(|dir| dir.components().nth(*i) == Some(*component))
+                            })
+                            .map(|(_, component)| component)
+                            .collect();
+                        vec![common]
+                    }
+                    [] => Vec::new(),
+                };
+                artist_dirs.entry(artist_id).or_default().extend(
+                    candidates
+                        .into_iter()
+                        .filter(|dir| !dir.as_os_str().is_empty())
+                        .map(|dir| folder_path.join(dir)),
+                );
+            }
+        }
+
+        // Read every info file before writing, so the database is not locked
+        // while files are read
+        let album_infos: Vec<(i32, nfo::AlbumNfo)> = album_dirs
+            .iter()
+            .filter_map(|(album_id, dir)| Some((*album_id, read_album_info(dir)?)))
             .collect();
 
-        // Build paths set
-        let paths: HashSet<String> = audio_files
+        let mut read_dirs: HashMap<&Path, Option<nfo::ArtistNfo>> = HashMap::new();
+        let artist_infos: Vec<(i32, nfo::ArtistNfo)> = artist_dirs
             .iter()
-            .map(|p| p.to_string_lossy().to_string())
+            .filter_map(|(artist_id, dirs)| {
+                let info = dirs.iter().find_map(|dir| {
+                    read_dirs
+                        .entry(dir.as_path())
+                        .or_insert_with(|| read_artist_info(dir))
+                        .clone()
+                })?;
+                Some((*artist_id, info))
+            })
             .collect();
 
-        // Read metadata in parallel using rayon
-        let folder_path_str = folder.path.clone();
-        let tracks: Vec<ScannedTrack> = audio_files
-            .par_iter()
-            .filter_map(|path| {
-                let extension = path
-                    .extension()
-                    .and_then(|e| e.to_str())
-                    .map(|e| e.to_lowercase())
-                    .unwrap_or_default();
+        // Only the details an info file supplies are written, leaving values
+        // from other sources in place
+        let now = chrono::Utc::now().naive_utc();
+        for chunk in album_infos.chunks(BATCH_SIZE) {
+            conn.transaction(|conn| {
+                for (album_id, info) in chunk {
+                    if let Some(ref notes) = info.notes {
+                        diesel::update(
+                            albums::table
+                                .find(album_id)
+                                .filter(albums::notes.is_not(notes)),
+                        )
+                        .set((albums::notes.eq(notes), albums::updated_at.eq(now)))
+                        .execute(conn)?;
+                    }
+                    if let Some(ref url) = info.image_url {
+                        diesel::update(
+                            albums::table
+                                .find(album_id)
+                                .filter(albums::album_image_url.is_not(url)),
+                        )
+                        .set((albums::album_image_url.eq(url), albums::updated_at.eq(now)))
+                        .execute(conn)?;
+                    }
+                    if let Some(ref mbid) = info.musicbrainz_id {
+                        diesel::update(
+                            albums::table
+                                .find(album_id)
+                                .filter(albums::musicbrainz_id.is_null()),
+                        )
+                        .set(albums::musicbrainz_id.eq(mbid))
+                        .execute(conn)?;
+                    }
+                }
+                Ok::<_, diesel::result::Error>(())
+            })
+            .map_err(MusicRepoError::Database)?;
+        }
 
-                match Self::read_track_metadata_static(path, &extension, &folder_path_str) {
-                    Ok(track) => Some(track),
-                    Err(e) => {
-                        eprintln!("  Warning: Failed to read {}: {}", path.display(), e);
-                        None
+        for chunk in artist_infos.chunks(BATCH_SIZE) {
+            conn.transaction(|conn| {
+                for (artist_id, info) in chunk {
+                    if let Some(ref biography) = info.biography {
+                        diesel::update(
+                            artists::table
+                                .find(artist_id)
+                                .filter(artists::biography.is_not(biography)),
+                        )
+                        .set((
+                            artists::biography.eq(biography),
+                            artists::updated_at.eq(now),
+                        ))
+                        .execute(conn)?;
+                    }
+                    if let Some(ref url) = info.image_url {
+                        diesel::update(
+                            artists::table
+                                .find(artist_id)
+                                .filter(artists::artist_image_url.is_not(url)),
+                        )
+                        .set((
+                            artists::artist_image_url.eq(url),
+                            artists::updated_at.eq(now),
+                        ))
+                        .execute(conn)?;
+                    }
+                    if let Some(ref mbid) = info.musicbrainz_id {
+                        diesel::update(
+                            artists::table
+                                .find(artist_id)
+                                .filter(artists::musicbrainz_id.is_null()),
+                        )
+                        .set(artists::musicbrainz_id.eq(mbid))
+                        .execute(conn)?;
                     }
                 }
+                Ok::<_, diesel::result::Error>(())
+            })
+            .map_err(MusicRepoError::Database)?;
+        }
+
+        Ok(())
+    }
+
+    /// Rebuild the directory tree of each folder for folder-based browsing.
+    fn update_directories(&self, folders: &[MusicFolder]) {
+        for folder in folders {
+            if let Err(e) = self.update_folder_directories(folder) {
+                eprintln!(
+                    "Warning: Failed to update directories of {}: {}",
+                    folder.name, e
+                );
+            }
+        }
+    }
+
+    /// Sync a folder's directory tree with the directories holding its songs.
+    ///
+    /// The tree is built from the songs table rather than the walk, so
+    /// directories skipped by incremental scans stay in it. A directory's
+    /// cover art is the first among its songs, else that of its first
+    /// subdirectory with cover art.
+    fn update_folder_directories(&self, folder: &MusicFolder) -> Result<(), ScanError> {
+        use crate::db::schema::songs;
+        use diesel::prelude::*;
+
+        let song_dirs: Vec<(String, Option<String>)> = {
+            let mut conn = self.pool.get().map_err(MusicRepoError::Pool)?;
+            songs::table
+                .filter(songs::music_folder_id.eq(folder.id))
+                .select((songs::parent_path, songs::cover_art))
+                .order((
+                    songs::parent_path.asc(),
+                    songs::disc_number.asc(),
+                    songs::track_number.asc(),
+                ))
+                .load(&mut conn)
+                .map_err(MusicRepoError::Database)?
+        };
+
+        let mut cover_arts: HashMap<PathBuf, Option<String>> = HashMap::new();
+        for (parent_path, cover_art) in song_dirs {
+            let entry = cover_arts.entry(PathBuf::from(parent_path)).or_default();
+            if entry.is_none() {
+                *entry = cover_art;
+            }
+        }
+
+        // Directories holding only subdirectories have no songs of their own
+        let song_dir_paths: Vec<PathBuf> = cover_arts.keys().cloned().collect();
+        for path in song_dir_paths {
+            for ancestor in path.ancestors().skip(1) {
+                if ancestor.as_os_str().is_empty() {
+                    break;
+                }
+                cover_arts.entry(ancestor.to_path_buf()).or_default();
+            }
+        }
+
+        // Files at the root belong to the music folder itself
+        cover_arts.remove(Path::new(""));
+
+        // Hand cover art up from the deepest directories, in name order
+        let mut deepest_first: Vec<PathBuf> = cover_arts.keys().cloned().collect();
+        deepest_first.sort_by(|a, b| {
+            b.components()
+                .count()
+                .cmp(&a.components().count())
+                .then_with(|| a.cmp(b))
+        });
+        for path in &deepest_first {
+            let Some(cover_art) = cover_arts.get(path).cloned().flatten() else {
+                continue;
+            };
+            if let Some(parent) = path.parent()
+                && let Some(parent_cover_art) = cover_arts.get_mut(parent)
+                && parent_cover_art.is_none()
+            {
+                *parent_cover_art = Some(cover_art);
+            }
+        }
+
+        // Parents must be synced before their children
+        let mut new_directories: Vec<NewDirectory> = deepest_first
+            .into_iter()
+            .map(|path| NewDirectory {
+                parent_path: path
+                    .parent()
+                    .filter(|parent| !parent.as_os_str().is_empty())
+                    .map(|parent| parent.to_string_lossy().to_string()),
+                name: path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+                cover_art: cover_arts.remove(&path).flatten(),
+                path: path.to_string_lossy().to_string(),
             })
             .collect();
+        new_directories.sort_by_key(|dir| Path::new(&dir.path).components().count());
 
-        Ok((tracks, paths))
+        DirectoryRepository::new(self.pool.clone()).sync_folder(folder.id, &new_directories)?;
+        Ok(())
+    }
+
+    /// Walk a music folder for audio and playlist files, skipping excluded paths.
+    ///
+    /// Incremental scans skip files whose size and modification time match the
+    /// database without reading their tags. With `skip_unchanged_dirs`, they also
+    /// skip directories whose modification time matches `known_dirs` without
+    /// listing them, only checking the known subdirectories for changes.
+    fn discover_files(
+        &self,
+        folder_path: &Path,
+        folder: &MusicFolder,
+        mode: ScanMode,
+        existing_songs: &HashMap<String, ExistingSong>,
+        known_dirs: &HashMap<String, i64>,
+    ) -> DiscoveredFiles {
+        let incremental = mode == ScanMode::Incremental;
+        let skip_dirs = incremental && self.skip_unchanged_dirs;
+        let mut audio_files: Vec<PathBuf> = Vec::new();
+        let mut playlist_files: Vec<PathBuf> = Vec::new();
+        let mut unchanged_files: Vec<String> = Vec::new();
+        let mut unchanged_dirs: HashSet<PathBuf> = HashSet::new();
+        let mut directories: Vec<(String, i64)> = Vec::new();
+
+        // Virtual tracks split from single-file rips by a CUE sheet, by file path
+        let mut cue_songs: HashMap<&str, Vec<(&String, &ExistingSong)>> = HashMap::new();
+        if incremental {
+            for (path, song) in existing_songs {
+                if let Some((file_path, _)) = path.rsplit_once('#') {
+                    cue_songs.entry(file_path).or_default().push((path, song));
+                }
+            }
+        }
+
+        // Subdirectories recorded by the last scan, by parent
+        let mut known_subdirs: HashMap<&Path, Vec<&Path>> = HashMap::new();
+        if skip_dirs {
+            for dir in known_dirs.keys() {
+                let dir = Path::new(dir);
+                if let Some(parent) = dir.parent() {
+                    known_subdirs.entry(parent).or_default().push(dir);
+                }
+            }
+        }
+
+        // Subtrees to walk with their depth and exclusion state: the folder
+        // itself, then the subdirectories of unchanged directories
+        let mut subtrees = vec![(
+            folder_path.to_path_buf(),
+            0,
+            ExcludeRules::new(folder_path, &folder.exclude_patterns),
+        )];
+
+        while let Some((root, root_depth, mut rules)) = subtrees.pop() {
+            let mut walker = WalkDir::new(&root).follow_links(true).into_iter();
+            while let Some(entry) = walker.next() {
+                let Ok(entry) = entry else {
+                    continue;
+                };
+                let depth = root_depth + entry.depth();
+                let is_dir = entry.file_type().is_dir();
+
+                // Skip excluded entries, and directories holding a marker file
+                if rules.is_excluded(entry.path(), depth, is_dir)
+                    || (is_dir && !rules.enter_dir(entry.path(), depth))
+                {
+                    if is_dir {
+                        walker.skip_current_dir();
+                    }
+                    continue;
+                }
+
+                if is_dir {
+                    let modified = entry
+                        .metadata()
+                        .ok()
+                        .and_then(|m| m.modified().ok())
+                        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                        .map(|d| d.as_nanos() as i64);
+                    if let Some(modified) = modified {
+                        let dir = entry.path().to_string_lossy().to_string();
+                        if skip_dirs && known_dirs.get(&dir) == Some(&modified) {
+                            // Same entries as last time: only its subdirectories can have changed
+                            walker.skip_current_dir();
+                            for subdir in known_subdirs.get(entry.path()).into_iter().flatten() {
+                                subtrees.push((subdir.to_path_buf(), depth + 1, rules.clone()));
+                            }
+                            unchanged_dirs.insert(entry.path().to_path_buf());
+                        }
+                        directories.push((dir, modified));
+                    }
+                    continue;
+                }
+                if !entry.file_type().is_file() {
+                    continue;
+                }
+
+                let ext = entry
+                    .path()
+                    .extension()
+                    .and_then(|e| e.to_str())
+                    .map(|e| e.to_lowercase());
+
+                match ext {
+                    Some(ext) if AUDIO_EXTENSIONS.contains(&ext.as_str()) => {
+                        // Compare size and mtime before reading any tags
+                        let path_str = entry.path().to_string_lossy().to_string();
+                        if incremental && let Ok(metadata) = entry.metadata() {
+                            let modified = modified_secs(&metadata);
+                            if let Some(existing) = existing_songs.get(&path_str) {
+                                if modified.is_some()
+                                    && existing.file_modified_at == modified
+                                    && existing.file_size == metadata.len() as i64
+                                {
+                                    unchanged_files.push(path_str);
+                                    continue;
+                                }
+                            } else if let Some(tracks) = cue_songs.get(path_str.as_str()) {
+                                // Virtual tracks only share the later of the file's
+                                // and its sidecar CUE sheet's mtimes
+                                let cue_modified = find_sidecar_cue(entry.path())
+                                    .and_then(|cue| fs::metadata(cue).ok())
+                                    .and_then(|m| modified_secs(&m));
+                                let modified = modified.max(cue_modified);
+                                if modified.is_some()
+                                    && tracks
+                                        .iter()
+                                        .all(|(_, song)| song.file_modified_at == modified)
+                                {
+                                    unchanged_files
+                                        .extend(tracks.iter().map(|(path, _)| (*path).clone()));
+                                    continue;
+                                }
+                            }
+                        }
+                        audio_files.push(entry.into_path());
+                    }
+                    Some(ext)
+                        if PLAYLIST_EXTENSIONS.contains(&ext.as_str())
+                            || ext == SMART_PLAYLIST_EXTENSION =>
+                    {
+                        playlist_files.push(entry.into_path())
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        DiscoveredFiles {
+            audio_files,
+            playlist_files,
+            unchanged_files,
+            unchanged_dirs,
+            directories,
+        }
+    }
+
+    /// Read metadata for audio files in parallel, sending each file's tracks to the writer.
+    ///
+    /// Stops early once the receiving end is dropped.
+    fn read_tracks(
+        &self,
+        audio_files: &[PathBuf],
+        folder: &MusicFolder,
+        tx: mpsc::SyncSender<ReadResult>,
+    ) {
+        let _ = audio_files.par_iter().try_for_each_with(tx, |tx, path| {
+            let extension = path
+                .extension()
+                .and_then(|e| e.to_str())
+                .map(|e| e.to_lowercase())
+                .unwrap_or_default();
+
+            let read = Self::read_track_metadata_static(
+                path,
+                &extension,
+                &folder.path,
+                &self.cover_art_dir,
+            )
+            .map_err(|e| {
+                eprintln!("  Warning: Failed to read {}: {}", path.display(), e);
+                ScanFileError::new(path, e)
+            });
+            tx.send(read)
+        });
     }
 
     /// Static version of read_track_metadata for use with rayon (no &self needed).
+    ///
+    /// Returns one track per file, or one virtual track per CUE sheet entry when
+    /// the file is a single-file rip with an embedded or sidecar CUE sheet.
+    /// Embedded cover art is saved to `cover_art_dir` right away so the picture
+    /// bytes are not kept with the track.
     fn read_track_metadata_static(
         path: &Path,
         extension: &str,
         folder_path: &str,
-    ) -> Result<ScannedTrack, Box<dyn std::error::Error + Send + Sync>> {
+        cover_art_dir: &Path,
+    ) -> Result<Vec<ScannedTrack>, Box<dyn std::error::Error + Send + Sync>> {
         let metadata = fs::metadata(path)?;
         let file_size = metadata.len();
 
@@ -727,6 +1896,16 @@ impl Scanner {
             .primary_tag()
             .or_else(|| tagged_file.first_tag());
 
+        // Embedded CUE sheet (Vorbis comment or APE item)
+        let embedded_cue = tag.and_then(|tag| {
+            tag.items().find_map(|item| match item.key() {
+                ItemKey::Unknown(key) if key.eq_ignore_ascii_case("cuesheet") => {
+                    item.value().text().map(|s| s.to_string())
+                }
+                _ => None,
+            })
+        });
+
         let (
             title,
             artist,
@@ -736,25 +1915,28 @@ impl Scanner {
             disc_number,
             year,
             genre,
-            cover_art_data,
-            cover_art_mime,
+            musicbrainz_id,
+            cover_art,
+            lyrics,
         ) = if let Some(tag) = tag {
-            // Extract embedded cover art (first picture)
-            let (art_data, art_mime) = tag
-                .pictures()
-                .first()
-                .map(|p| {
-                    let mime = match p.mime_type() {
-                        Some(lofty::picture::MimeType::Png) => "image/png",
-                        Some(lofty::picture::MimeType::Jpeg) => "image/jpeg",
-                        Some(lofty::picture::MimeType::Gif) => "image/gif",
-                        Some(lofty::picture::MimeType::Bmp) => "image/bmp",
-                        Some(lofty::picture::MimeType::Tiff) => "image/tiff",
-                        _ => "image/jpeg", // Default to JPEG
-                    };
-                    (Some(p.data().to_vec()), Some(mime.to_string()))
-                })
-                .unwrap_or((None, None));
+            // Save embedded cover art (first picture)
+            let cover_art = tag.pictures().first().and_then(|p| {
+                let mime = match p.mime_type() {
+                    Some(lofty::picture::MimeType::Png) => "image/png",
+                    Some(lofty::picture::MimeType::Jpeg) => "image/jpeg",
+                    Some(lofty::picture::MimeType::Gif) => "image/gif",
+                    Some(lofty::picture::MimeType::Bmp) => "image/bmp",
+                    Some(lofty::picture::MimeType::Tiff) => "image/tiff",
+                    _ => "image/jpeg", // Default to JPEG
+                };
+                match Self::save_cover_art(cover_art_dir, p.data(), mime) {
+                    Ok(hash) => Some(hash),
+                    Err(e) => {
+                        eprintln!("  Warning: Failed to save cover art: {}", e);
+                        None
+                    }
+                }
+            });
 
             (
                 tag.title().map(|s| s.to_string()),
@@ -765,11 +1947,25 @@ impl Scanner {
                 tag.disk(),
                 tag.year(),
                 tag.genre().map(|s| s.to_string()),
-                art_data,
-                art_mime,
+                tag.get_string(&ItemKey::MusicBrainzRecordingId)
+                    .map(|s| s.to_string()),
+                cover_art,
+                extract_lyrics_from_tag(tag),
             )
         } else {
-            (None, None, None, None, None, None, None, None, None, None)
+            (
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                Vec::new(),
+            )
         };
 
         // Use filename as title if no tag
@@ -795,7 +1991,7 @@ impl Scanner {
         }
         .to_string();
 
-        Ok(ScannedTrack {
+        let track = ScannedTrack {
             path: path.to_path_buf(),
             parent_path,
             file_size,
@@ -809,38 +2005,176 @@ impl Scanner {
             disc_number,
             year,
             genre,
+            musicbrainz_id,
             duration_secs,
             bit_rate,
             bit_depth,
             sample_rate,
             channels,
-            cover_art_data,
-            cover_art_mime,
+            cover_art,
             file_modified_at,
+            lyrics,
+            cue_start_ms: None,
+            cue_end_ms: None,
+        };
+
+        // Prefer an embedded CUE sheet, falling back to a sidecar .cue file
+        let cue = match embedded_cue {
+            Some(text) => Some((parse_cue(&text), None)),
+            None => find_sidecar_cue(path).and_then(|cue_path| {
+                let cue_mtime = fs::metadata(&cue_path)
+                    .and_then(|m| m.modified())
+                    .ok()
+                    .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                    .map(|d| d.as_secs() as i64);
+                match read_cue_file(&cue_path) {
+                    Ok(sheet) => Some((sheet, cue_mtime)),
+                    Err(e) => {
+                        eprintln!("  Warning: Failed to read {}: {}", cue_path.display(), e);
+                        None
+                    }
+                }
+            }),
+        };
+
+        Ok(match cue {
+            Some((sheet, cue_mtime)) => Self::split_cue_tracks(track, &sheet, cue_mtime),
+            None => vec![track],
         })
     }
 
-    /// Process scanned tracks and populate the database with options.
-    /// Returns (artists_added, albums_added, tracks_added, tracks_updated, tracks_skipped, tracks_failed, cover_art_saved)
-    #[allow(clippy::type_complexity)]
-    fn process_tracks_with_options(
-        &self,
-        folder: &MusicFolder,
-        tracks: Vec<ScannedTrack>,
-        existing_songs: &HashMap<String, (i32, Option<i64>)>,
-        state: Option<Arc<ScanState>>,
-        mode: ScanMode,
-    ) -> Result<(usize, usize, usize, usize, usize, usize, usize), ScanError> {
-        use crate::db::schema::{albums, artists, songs};
+    /// Split a single-file rip into virtual tracks using its CUE sheet.
+    ///
+    /// Each virtual track is stored under `<file path>#<track number>` with its
+    /// offsets in the file. Returns the file as-is if the sheet has no usable tracks.
+    fn split_cue_tracks(
+        track: ScannedTrack,
+        sheet: &CueSheet,
+        cue_mtime: Option<i64>,
+    ) -> Vec<ScannedTrack> {
+        let file_name = track
+            .path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or_default();
+        let total_ms = track.duration_secs as i64 * 1000;
+
+        let cue_tracks: Vec<_> = sheet
+            .tracks_for_file(file_name)
+            .unwrap_or_default()
+            .iter()
+            .filter(|t| t.start_ms < total_ms || total_ms == 0)
+            .collect();
+        if cue_tracks.is_empty() {
+            return vec![track];
+        }
+
+        // Re-scan the virtual tracks when either the audio file or the sheet changes
+        let file_modified_at = track.file_modified_at.max(cue_mtime);
+
+        cue_tracks
+            .iter()
+            .enumerate()
+            .map(|(i, cue_track)| {
+                let end_ms = cue_tracks.get(i + 1).map(|next| next.start_ms);
+                let length_ms = end_ms.unwrap_or(total_ms) - cue_track.start_ms;
+                let file_size = if total_ms > 0 {
+                    (track.file_size as i128 * length_ms.max(0) as i128 / total_ms as i128) as u64
+                } else {
+                    track.file_size
+                };
+
+                ScannedTrack {
+                    path: PathBuf::from(format!(
+                        "{}#{}",
+                        track.path.to_string_lossy(),
+                        cue_track.number
+                    )),
+                    file_size,
+                    title: cue_track
+                        .title
+                        .clone()
+                        .unwrap_or_else(|| format!("Track {}", cue_track.number)),
+                    artist: cue_track
+                        .performer
+                        .clone()
+                        .or_else(|| sheet.performer.clone())
+                        .or_else(|| track.artist.clone()),
+                    album: sheet.title.clone().or_else(|| track.album.clone()),
+                    album_artist: sheet
+                        .performer
+                        .clone()
+                        .or_else(|| track.album_artist.clone()),
+                    track_number: Some(cue_track.number),
+                    year: sheet.year.or(track.year),
+                    genre: sheet.genre.clone().or_else(|| track.genre.clone()),
+                    duration_secs: (length_ms.max(0) / 1000) as u32,
+                    file_modified_at,
+                    musicbrainz_id: None,
+                    lyrics: Vec::new(),
+                    cue_start_ms: Some(cue_track.start_ms),
+                    cue_end_ms: end_ms,
+                    ..track.clone()
+                }
+            })
+            .collect()
+    }
+
+    /// Update album statistics (song count, duration) based on songs.
+    fn update_album_stats(&self, conn: &mut diesel::SqliteConnection) -> Result<(), ScanError> {
         use diesel::prelude::*;
 
-        // Ensure cover art directory exists
-        self.ensure_cover_art_dir()?;
+        // This updates each album's song_count and duration based on its songs
+        diesel::sql_query(
+            r#"
+            UPDATE albums SET
+                song_count = (SELECT COUNT(*) FROM songs WHERE songs.album_id = albums.id),
+                duration = (SELECT COALESCE(SUM(duration), 0) FROM songs WHERE songs.album_id = albums.id),
+                updated_at = CURRENT_TIMESTAMP
+            "#,
+        )
+        .execute(conn)
+        .map_err(MusicRepoError::Database)?;
 
-        let mut conn = self.pool.get().map_err(MusicRepoError::Pool)?;
+        Ok(())
+    }
+}
+
+/// Writes scanned tracks of a music folder to the database batch by batch.
+///
+/// Artist, album and cover art lookups are kept across batches so each
+/// batch only touches the rows it needs.
+struct TrackWriter<'a> {
+    scanner: &'a Scanner,
+    folder: &'a MusicFolder,
+    mode: ScanMode,
+    state: Option<Arc<ScanState>>,
+    conn: DbConn,
+    /// Artist name -> ID.
+    artist_cache: HashMap<String, i32>,
+    /// (Album name, artist ID) -> album ID.
+    album_cache: HashMap<(String, Option<i32>), i32>,
+    /// Album ID -> cover art ID.
+    album_cover_art_cache: HashMap<i32, Option<String>>,
+    /// Cover art ID of external cover art per directory (None = already checked, none found).
+    dir_cover_art_cache: HashMap<PathBuf, Option<String>>,
+}
+
+impl<'a> TrackWriter<'a> {
+    /// Create a writer, pre-loading existing artists and albums.
+    fn new(
+        scanner: &'a Scanner,
+        folder: &'a MusicFolder,
+        mode: ScanMode,
+        state: Option<Arc<ScanState>>,
+    ) -> Result<Self, ScanError> {
+        use crate::db::schema::{albums, artists};
+        use diesel::prelude::*;
+
+        let mut conn = scanner.pool.get().map_err(MusicRepoError::Pool)?;
 
         // Pre-load all existing artists into cache (much faster than individual lookups)
-        let mut artist_cache: HashMap<String, i32> = artists::table
+        let artist_cache: HashMap<String, i32> = artists::table
             .select((artists::name, artists::id))
             .load::<(String, i32)>(&mut conn)
             .map_err(MusicRepoError::Database)?
@@ -848,7 +2182,7 @@ impl Scanner {
             .collect();
 
         // Pre-load all existing albums into cache
-        let mut album_cache: HashMap<(String, Option<i32>), i32> = albums::table
+        let album_cache: HashMap<(String, Option<i32>), i32> = albums::table
             .select((albums::name, albums::artist_id, albums::id))
             .load::<(String, Option<i32>, i32)>(&mut conn)
             .map_err(MusicRepoError::Database)?
@@ -857,85 +2191,92 @@ impl Scanner {
             .collect();
 
         // Pre-load album cover art hashes
-        let mut album_cover_art_cache: HashMap<i32, Option<String>> = albums::table
+        let album_cover_art_cache: HashMap<i32, Option<String>> = albums::table
             .select((albums::id, albums::cover_art))
             .load::<(i32, Option<String>)>(&mut conn)
             .map_err(MusicRepoError::Database)?
             .into_iter()
             .collect();
 
-        // Cache for external cover art per directory (None = already checked, no cover art found)
-        let mut dir_cover_art_cache: HashMap<PathBuf, Option<(Vec<u8>, String)>> = HashMap::new();
-
-        let mut artists_added = 0;
-        let mut albums_added = 0;
-        let mut tracks_added = 0;
-        let mut tracks_updated = 0;
-        let mut tracks_skipped = 0;
-        let tracks_failed = 0;
-        let mut cover_art_saved = 0;
-
-        // Collect unique new artists and albums first (avoid duplicate inserts)
-        let mut new_artists: HashSet<String> = HashSet::new();
+        Ok(Self {
+            scanner,
+            folder,
+            mode,
+            state,
+            conn,
+            artist_cache,
+            album_cache,
+            album_cover_art_cache,
+            dir_cover_art_cache: HashMap::new(),
+        })
+    }
 
-        // First pass: collect all unique new artists
-        for track in &tracks {
-            let path_str = track.path.to_string_lossy().to_string();
+    /// Write a batch of tracks, updating the counters in `result`.
+    fn write(
+        &mut self,
+        tracks: Vec<ScannedTrack>,
+        existing_songs: &HashMap<String, ExistingSong>,
+        result: &mut ScanResult,
+    ) -> Result<(), ScanError> {
+        use crate::db::schema::{albums, artists, songs};
+        use diesel::prelude::*;
 
-            // Skip unchanged files in incremental mode
-            if mode == ScanMode::Incremental
-                && let Some((_, stored_mtime)) = existing_songs.get(&path_str)
-                && let (Some(stored), Some(current)) = (stored_mtime, track.file_modified_at)
-                && *stored == current
+        // Skip unchanged files in incremental mode
+        let mut changed: Vec<ScannedTrack> = Vec::with_capacity(tracks.len());
+        for track in tracks {
+            let path_str = track.path.to_string_lossy();
+            if self.mode == ScanMode::Incremental
+                && let Some(existing) = existing_songs.get(path_str.as_ref())
+                && let (Some(stored), Some(current)) =
+                    (existing.file_modified_at, track.file_modified_at)
+                && stored == current
             {
+                result.tracks_skipped += 1;
+                if let Some(ref state) = self.state {
+                    state.increment_count();
+                }
                 continue;
             }
-
-            let artist_name = track
-                .album_artist
-                .as_ref()
-                .or(track.artist.as_ref())
-                .cloned();
-
-            if let Some(ref name) = artist_name
-                && !artist_cache.contains_key(name)
-            {
-                new_artists.insert(name.clone());
-            }
+            changed.push(track);
         }
 
+        // Collect unique new artists first (avoid duplicate inserts)
+        let new_artists: HashSet<String> = changed
+            .iter()
+            .filter_map(|t| t.album_artist.as_ref().or(t.artist.as_ref()))
+            .filter(|name| !self.artist_cache.contains_key(*name))
+            .cloned()
+            .collect();
+
         // Batch insert new artists in a transaction
         if !new_artists.is_empty() {
-            conn.transaction::<_, diesel::result::Error, _>(|conn| {
-                for name in &new_artists {
-                    diesel::insert_into(artists::table)
-                        .values(artists::name.eq(name))
-                        .on_conflict_do_nothing()
-                        .execute(conn)?;
-                }
-                Ok(())
-            })
-            .map_err(MusicRepoError::Database)?;
+            self.conn
+                .transaction::<_, diesel::result::Error, _>(|conn| {
+                    for name in &new_artists {
+                        diesel::insert_into(artists::table)
+                            .values(artists::name.eq(name))
+                            .on_conflict_do_nothing()
+                            .execute(conn)?;
+                    }
+                    Ok(())
+                })
+                .map_err(MusicRepoError::Database)?;
 
             // Reload artist cache to get new IDs
             let new_artist_ids: Vec<(String, i32)> = artists::table
                 .filter(artists::name.eq_any(&new_artists))
                 .select((artists::name, artists::id))
-                .load(&mut conn)
+                .load(&mut self.conn)
                 .map_err(MusicRepoError::Database)?;
 
             for (name, id) in new_artist_ids {
-                if !artist_cache.contains_key(&name) {
-                    artists_added += 1;
+                if !self.artist_cache.contains_key(&name) {
+                    result.artists_added += 1;
                 }
-                artist_cache.insert(name, id);
+                self.artist_cache.insert(name, id);
             }
         }
 
-        // Batch size for song inserts (SQLite has a limit of ~999 variables per query)
-        const BATCH_SIZE: usize = 100;
-
-        // Prepare tracks to process
         struct PreparedTrack {
             track: ScannedTrack,
             path_str: String,
@@ -945,26 +2286,11 @@ impl Scanner {
             is_update: bool,
         }
 
-        let mut prepared_tracks: Vec<PreparedTrack> = Vec::with_capacity(tracks.len());
-
-        // Second pass: resolve albums and prepare tracks
-        for track in tracks {
+        // Resolve albums and cover art
+        let mut prepared_tracks: Vec<PreparedTrack> = Vec::with_capacity(changed.len());
+        for track in changed {
             let path_str = track.path.to_string_lossy().to_string();
 
-            // For incremental scan, check if file has changed
-            if mode == ScanMode::Incremental
-                && let Some((_, stored_mtime)) = existing_songs.get(&path_str)
-                && let (Some(stored), Some(current)) = (stored_mtime, track.file_modified_at)
-                && *stored == current
-            {
-                // File hasn't changed, skip processing
-                tracks_skipped += 1;
-                if let Some(ref state) = state {
-                    state.increment_count();
-                }
-                continue;
-            }
-
             // Get artist ID from cache
             let artist_name = track
                 .album_artist
@@ -974,13 +2300,13 @@ impl Scanner {
 
             let artist_id = artist_name
                 .as_ref()
-                .and_then(|name| artist_cache.get(name).copied());
+                .and_then(|name| self.artist_cache.get(name).copied());
 
             // Get or create album
             let album_id = if let Some(ref album_name) = track.album {
                 let cache_key = (album_name.clone(), artist_id);
 
-                if let Some(&id) = album_cache.get(&cache_key) {
+                if let Some(&id) = self.album_cache.get(&cache_key) {
                     Some(id)
                 } else {
                     // Insert new album
@@ -993,7 +2319,7 @@ impl Scanner {
                             albums::genre.eq(&track.genre),
                         ))
                         .on_conflict_do_nothing()
-                        .execute(&mut conn)
+                        .execute(&mut self.conn)
                         .map_err(MusicRepoError::Database)?;
 
                     // Get the album ID
@@ -1008,16 +2334,16 @@ impl Scanner {
 
                     let album_row: Option<(i32, Option<String>)> = query
                         .select((albums::id, albums::cover_art))
-                        .first(&mut conn)
+                        .first(&mut self.conn)
                         .optional()
                         .map_err(MusicRepoError::Database)?;
 
                     if let Some((id, existing_cover)) = album_row {
-                        if !album_cache.contains_key(&cache_key) {
-                            albums_added += 1;
+                        if !self.album_cache.contains_key(&cache_key) {
+                            result.albums_added += 1;
                         }
-                        album_cache.insert(cache_key, id);
-                        album_cover_art_cache.insert(id, existing_cover);
+                        self.album_cache.insert(cache_key, id);
+                        self.album_cover_art_cache.insert(id, existing_cover);
                         Some(id)
                     } else {
                         None
@@ -1028,58 +2354,9 @@ impl Scanner {
             };
 
             // Handle cover art
-            let album_cover_art_id = if let Some(album_id) = album_id {
-                let existing_cover_art = album_cover_art_cache.get(&album_id).cloned().flatten();
-
-                if existing_cover_art.is_none() {
-                    let art_source: Option<(Vec<u8>, String)> =
-                        if let (Some(art_data), Some(art_mime)) =
-                            (&track.cover_art_data, &track.cover_art_mime)
-                        {
-                            Some((art_data.clone(), art_mime.clone()))
-                        } else if let Some(parent_dir) = track.path.parent() {
-                            let parent_buf = parent_dir.to_path_buf();
-                            if let Some(cached) = dir_cover_art_cache.get(&parent_buf) {
-                                cached.clone()
-                            } else {
-                                let found = self.find_external_cover_art(parent_dir);
-                                dir_cover_art_cache.insert(parent_buf, found.clone());
-                                found
-                            }
-                        } else {
-                            None
-                        };
-
-                    if let Some((art_data, art_mime)) = art_source {
-                        match self.save_cover_art(&art_data, &art_mime) {
-                            Ok(cover_art_hash) => {
-                                if let Err(e) =
-                                    diesel::update(albums::table.filter(albums::id.eq(album_id)))
-                                        .set(albums::cover_art.eq(&cover_art_hash))
-                                        .execute(&mut conn)
-                                {
-                                    eprintln!("  Warning: Failed to update album cover art: {}", e);
-                                    None
-                                } else {
-                                    album_cover_art_cache
-                                        .insert(album_id, Some(cover_art_hash.clone()));
-                                    cover_art_saved += 1;
-                                    Some(cover_art_hash)
-                                }
-                            }
-                            Err(e) => {
-                                eprintln!("  Warning: Failed to save cover art: {}", e);
-                                None
-                            }
-                        }
-                    } else {
-                        None
-                    }
-                } else {
-                    existing_cover_art
-                }
-            } else {
-                None
+            let album_cover_art_id = match album_id {
+                Some(album_id) => self.album_cover_art(album_id, &track, result),
+                None => None,
             };
 
             let is_update = existing_songs.contains_key(&path_str);
@@ -1094,10 +2371,13 @@ impl Scanner {
             });
         }
 
-        // Process songs in batches within transactions
-        for batch in prepared_tracks.chunks(BATCH_SIZE) {
-            conn.transaction::<_, diesel::result::Error, _>(|conn| {
-                for prepared in batch {
+        // Write songs in one transaction
+        let folder_id = self.folder.id;
+        let mut batch_errors: Vec<ScanFileError> = Vec::new();
+
+        self.conn
+            .transaction::<_, diesel::result::Error, _>(|conn| {
+                for prepared in &prepared_tracks {
                     let result = if prepared.is_update {
                         diesel::update(songs::table.filter(songs::path.eq(&prepared.path_str)))
                             .set((
@@ -1118,8 +2398,11 @@ impl Scanner {
                                 songs::disc_number.eq(prepared.track.disc_number.map(|d| d as i32)),
                                 songs::year.eq(prepared.track.year.map(|y| y as i32)),
                                 songs::genre.eq(&prepared.track.genre),
+                                songs::musicbrainz_id.eq(&prepared.track.musicbrainz_id),
                                 songs::cover_art.eq(&prepared.cover_art),
                                 songs::file_modified_at.eq(prepared.track.file_modified_at),
+                                songs::cue_start_ms.eq(prepared.track.cue_start_ms),
+                                songs::cue_end_ms.eq(prepared.track.cue_end_ms),
                                 songs::updated_at.eq(diesel::dsl::now),
                             ))
                             .execute(conn)
@@ -1131,7 +2414,7 @@ impl Scanner {
                                 songs::artist_id.eq(prepared.artist_id),
                                 songs::artist_name.eq(&prepared.track.artist),
                                 songs::album_name.eq(&prepared.track.album),
-                                songs::music_folder_id.eq(folder.id),
+                                songs::music_folder_id.eq(folder_id),
                                 songs::path.eq(&prepared.path_str),
                                 songs::parent_path.eq(&prepared.track.parent_path),
                                 songs::file_size.eq(prepared.track.file_size as i64),
@@ -1148,16 +2431,47 @@ impl Scanner {
                                 songs::disc_number.eq(prepared.track.disc_number.map(|d| d as i32)),
                                 songs::year.eq(prepared.track.year.map(|y| y as i32)),
                                 songs::genre.eq(&prepared.track.genre),
+                                songs::musicbrainz_id.eq(&prepared.track.musicbrainz_id),
                                 songs::cover_art.eq(&prepared.cover_art),
                                 songs::file_modified_at.eq(prepared.track.file_modified_at),
+                                songs::cue_start_ms.eq(prepared.track.cue_start_ms),
+                                songs::cue_end_ms.eq(prepared.track.cue_end_ms),
                             ))
                             .execute(conn)
                     };
 
                     match result {
-                        Ok(_) => {}
+                        Ok(_) => {
+                            // Refresh the indexed lyrics for this song
+                            let lyrics_result = songs::table
+                                .filter(songs::path.eq(&prepared.path_str))
+                                .select(songs::id)
+                                .first::<i32>(conn)
+                                .and_then(|song_id| {
+                                    let entries: Vec<NewLyrics> = prepared
+                                        .track
+                                        .lyrics
+                                        .iter()
+                                        .map(|l| NewLyrics {
+                                            song_id,
+                                            lang: l.lang.as_deref(),
+                                            description: l.description.as_deref(),
+                                            synced: l.synced,
+                                            text: &l.text,
+                                        })
+                                        .collect();
+                                    LyricsRepository::replace_for_song(conn, song_id, &entries)
+                                });
+                            if let Err(e) = lyrics_result {
+                                eprintln!(
+                                    "  Failed to store lyrics for {}: {}",
+                                    prepared.path_str, e
+                                );
+                            }
+                        }
                         Err(e) => {
                             eprintln!("  Failed to insert {}: {}", prepared.path_str, e);
+                            batch_errors.push(ScanFileError::new(&prepared.track.path, e));
                         }
                     }
                 }
@@ -1165,50 +2479,86 @@ impl Scanner {
             })
             .map_err(MusicRepoError::Database)?;
 
-            // Update counters and progress
-            for prepared in batch {
-                if prepared.is_update {
-                    tracks_updated += 1;
-                } else {
-                    tracks_added += 1;
-                }
-                if let Some(ref state) = state {
-                    state.increment_count();
-                }
+        // Update counters and progress
+        let failed_paths: HashSet<String> = batch_errors.iter().map(|e| e.path.clone()).collect();
+        for prepared in &prepared_tracks {
+            if failed_paths.contains(&prepared.path_str) {
+                result.tracks_failed += 1;
+            } else if prepared.is_update {
+                result.tracks_updated += 1;
+            } else {
+                result.tracks_added += 1;
+            }
+            if let Some(ref state) = self.state {
+                state.increment_count();
             }
         }
+        result.errors.extend(batch_errors);
 
-        // Update album song counts and durations
-        self.update_album_stats(&mut conn)?;
-
-        Ok((
-            artists_added,
-            albums_added,
-            tracks_added,
-            tracks_updated,
-            tracks_skipped,
-            tracks_failed,
-            cover_art_saved,
-        ))
+        Ok(())
     }
 
-    /// Update album statistics (song count, duration) based on songs.
-    fn update_album_stats(&self, conn: &mut diesel::SqliteConnection) -> Result<(), ScanError> {
+    /// Get an album's cover art, setting it from the track's embedded art or
+    /// its directory's cover image if the album has none yet.
+    fn album_cover_art(
+        &mut self,
+        album_id: i32,
+        track: &ScannedTrack,
+        result: &mut ScanResult,
+    ) -> Option<String> {
+        use crate::db::schema::albums;
         use diesel::prelude::*;
 
-        // This updates each album's song_count and duration based on its songs
-        diesel::sql_query(
-            r#"
-            UPDATE albums SET
-                song_count = (SELECT COUNT(*) FROM songs WHERE songs.album_id = albums.id),
-                duration = (SELECT COALESCE(SUM(duration), 0) FROM songs WHERE songs.album_id = albums.id),
-                updated_at = CURRENT_TIMESTAMP
-            "#,
-        )
-        .execute(conn)
-        .map_err(MusicRepoError::Database)?;
+        if let Some(existing) = self.album_cover_art_cache.get(&album_id).cloned().flatten() {
+            return Some(existing);
+        }
 
-        Ok(())
+        let cover_art = match (&track.cover_art, track.path.parent()) {
+            (Some(embedded), _) => Some(embedded.clone()),
+            (None, Some(parent_dir)) => self.external_cover_art(parent_dir),
+            (None, None) => None,
+        }?;
+
+        if let Err(e) = diesel::update(albums::table.filter(albums::id.eq(album_id)))
+            .set(albums::cover_art.eq(&cover_art))
+            .execute(&mut self.conn)
+        {
+            eprintln!("  Warning: Failed to update album cover art: {}", e);
+            return None;
+        }
+
+        self.album_cover_art_cache
+            .insert(album_id, Some(cover_art.clone()));
+        result.cover_art_saved += 1;
+        Some(cover_art)
+    }
+
+    /// Find and save the cover image of a directory, checking each directory once.
+    fn external_cover_art(&mut self, dir: &Path) -> Option<String> {
+        if let Some(cached) = self.dir_cover_art_cache.get(dir) {
+            return cached.clone();
+        }
+
+        let cover_art = self
+            .scanner
+            .find_external_cover_art(dir)
+            .and_then(|(data, mime)| {
+                match Scanner::save_cover_art(&self.scanner.cover_art_dir, &data, &mime) {
+                    Ok(hash) => Some(hash),
+                    Err(e) => {
+                        eprintln!("  Warning: Failed to save cover art: {}", e);
+                        None
+                    }
+                }
+            });
+        self.dir_cover_art_cache
+            .insert(dir.to_path_buf(), cover_art.clone());
+        cover_art
+    }
+
+    /// Finish writing, updating album song counts and durations.
+    fn finish(mut self) -> Result<(), ScanError> {
+        self.scanner.update_album_stats(&mut self.conn)
     }
 }
 
@@ -1222,6 +2572,8 @@ impl AutoScanner {
         Self {
             pool,
             cover_art_dir,
+            playlist_import: PlaylistImportConfig::default(),
+            skip_unchanged_dirs: true,
             interval: Duration::from_secs(DEFAULT_AUTO_SCAN_INTERVAL_SECS),
             scan_state,
             shutdown_tx: None,
@@ -1237,12 +2589,29 @@ impl AutoScanner {
         Self {
             pool,
             cover_art_dir,
+            playlist_import: PlaylistImportConfig::default(),
+            skip_unchanged_dirs: true,
             interval: Duration::from_secs(interval_secs),
             scan_state,
             shutdown_tx: None,
         }
     }
 
+    /// Set how playlist files found in music folders are imported.
+    pub fn with_playlist_import(mut self, config: PlaylistImportConfig) -> Self {
+        self.playlist_import = config;
+        self
+    }
+
+    /// Skip unchanged directories in auto-scans (see [`Scanner::with_skip_unchanged_dirs`]).
+    ///
+    /// On by default, so periodic scans of an unchanged library don't list
+    /// every directory or check every file.
+    pub fn with_skip_unchanged_dirs(mut self, skip: bool) -> Self {
+        self.skip_unchanged_dirs = skip;
+        self
+    }
+
     /// Start the auto-scanner in the background.
     /// Returns a handle that can be used to stop the scanner.
     pub fn start(&mut self) -> AutoScanHandle {
@@ -1251,11 +2620,22 @@ impl AutoScanner {
 
         let pool = self.pool.clone();
         let cover_art_dir = self.cover_art_dir.clone();
+        let playlist_import = self.playlist_import.clone();
+        let skip_unchanged_dirs = self.skip_unchanged_dirs;
         let interval = self.interval;
         let scan_state = self.scan_state.clone();
 
         tokio::spawn(async move {
-            Self::run_scan_loop(pool, cover_art_dir, interval, scan_state, shutdown_rx).await;
+            Self::run_scan_loop(
+                pool,
+                cover_art_dir,
+                playlist_import,
+                skip_unchanged_dirs,
+                interval,
+                scan_state,
+                shutdown_rx,
+            )
+            .await;
         });
 
         AutoScanHandle { shutdown_tx }
@@ -1265,6 +2645,8 @@ impl AutoScanner {
     async fn run_scan_loop(
         pool: DbPool,
         cover_art_dir: PathBuf,
+        playlist_import: PlaylistImportConfig,
+        skip_unchanged_dirs: bool,
         interval: Duration,
         scan_state: Arc<ScanState>,
         mut shutdown_rx: watch::Receiver<bool>,
@@ -1297,10 +2679,14 @@ impl AutoScanner {
             // Run the scan in a blocking task since it uses diesel
             let pool_clone = pool.clone();
             let cover_art_dir_clone = cover_art_dir.clone();
+            let playlist_import_clone = playlist_import.clone();
             let scan_state_clone = scan_state.clone();
 
             let result = tokio::task::spawn_blocking(move || {
-                let scanner = Scanner::with_cover_art_dir(pool_clone, cover_art_dir_clone);
+                let scanner = Scanner::with_cover_art_dir(pool_clone, cover_art_dir_clone)
+                    .with_playlist_import(playlist_import_clone)
+                    .with_trigger(ScanTrigger::Auto)
+                    .with_skip_unchanged_dirs(skip_unchanged_dirs);
                 scanner.scan_all_with_options(Some(scan_state_clone), ScanMode::Incremental)
             })
             .await;
@@ -1308,6 +2694,14 @@ impl AutoScanner {
             scan_state.finish();
 
             match result {
+                Ok(Ok(stats)) if stats.cancelled => {
+                    tracing::info!(
+                        "Auto-scan cancelled: found={}, added={}, updated={}",
+                        stats.tracks_found,
+                        stats.tracks_added,
+                        stats.tracks_updated
+                    );
+                }
                 Ok(Ok(stats)) => {
                     tracing::info!(
                         "Auto-scan complete: found={}, added={}, updated={}, skipped={}, removed={}, failed={}",
@@ -1335,6 +2729,15 @@ impl AutoScanner {
     }
 }
 
+/// Get a file's modification time as a Unix timestamp in seconds.
+fn modified_secs(metadata: &fs::Metadata) -> Option<i64> {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs() as i64)
+}
+
 /// Handle for controlling the auto-scanner.
 pub struct AutoScanHandle {
     shutdown_tx: watch::Sender<bool>,
@@ -1346,3 +2749,427 @@ impl AutoScanHandle {
         let _ = self.shutdown_tx.send(true);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::SystemTime;
+
+    use diesel::prelude::*;
+
+    use super::*;
+    use crate::db::schema::songs;
+    use crate::db::{
+        DbConfig, NewUser, ScrobbleRepository, StarredRepository, UserRepository, run_migrations,
+    };
+    use crate::models::music::NewMusicFolder;
+
+    /// Scanner over an empty `library` directory, with the database and cover
+    /// art stored next to it. Returns the scanner and the library path.
+    fn setup(name: &str) -> (Scanner, PathBuf) {
+        let dir = std::env::temp_dir().join(format!(
+            "subsonic-scan-test-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        let library = dir.join("library");
+        fs::create_dir_all(&library).unwrap();
+
+        let pool = DbConfig::new(dir.join("test.db").to_string_lossy())
+            .build_pool()
+            .unwrap();
+        run_migrations(&mut pool.get().unwrap()).unwrap();
+        MusicFolderRepository::new(pool.clone())
+            .create(&NewMusicFolder::new("Music", library.to_string_lossy()))
+            .unwrap();
+        UserRepository::new(pool.clone())
+            .create(&NewUser::admin("admin", "hash", "secret"))
+            .unwrap();
+
+        (
+            Scanner::with_cover_art_dir(pool, dir.join("covers")),
+            library,
+        )
+    }
+
+    /// Write a one second WAV file tagged with the given title, by "Artist"
+    /// on "Album".
+    fn write_wav(path: &Path, title: &str) {
+        let samples = vec![0x80u8; 8000];
+
+        let mut info = b"INFO".to_vec();
+        for (id, value) in [(b"INAM", title), (b"IART", "Artist"), (b"IPRD", "Album")] {
+            let mut value = value.as_bytes().to_vec();
+            value.push(0);
+            if value.len() % 2 == 1 {
+                value.push(0);
+            }
+            info.extend_from_slice(id);
+            info.extend_from_slice(&(value.len() as u32).to_le_bytes());
+            info.extend_from_slice(&value);
+        }
+
+        let mut body = b"WAVE".to_vec();
+        body.extend_from_slice(b"fmt ");
+        body.extend_from_slice(&16u32.to_le_bytes());
+        // PCM, mono, 8000 Hz, 8000 bytes/s, 1 byte blocks, 8 bits
+        body.extend_from_slice(&[1, 0, 1, 0]);
+        body.extend_from_slice(&8000u32.to_le_bytes());
+        body.extend_from_slice(&8000u32.to_le_bytes());
+        body.extend_from_slice(&[1, 0, 8, 0]);
+        body.extend_from_slice(b"data");
+        body.extend_from_slice(&(samples.len() as u32).to_le_bytes());
+        body.extend_from_slice(&samples);
+        body.extend_from_slice(b"LIST");
+        body.extend_from_slice(&(info.len() as u32).to_le_bytes());
+        body.extend_from_slice(&info);
+
+        let mut data = b"RIFF".to_vec();
+        data.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        data.extend_from_slice(&body);
+
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, data).unwrap();
+    }
+
+    fn song_paths(scanner: &Scanner) -> Vec<String> {
+        let mut paths: Vec<String> = songs::table
+            .select(songs::path)
+            .load(&mut scanner.pool.get().unwrap())
+            .unwrap();
+        paths.sort();
+        paths
+    }
+
+    fn set_modified(path: &Path, time: SystemTime) {
+        fs::File::open(path).unwrap().set_modified(time).unwrap();
+    }
+
+    #[test]
+    fn test_unchanged_directories_are_not_listed() {
+        let (scanner, library) = setup("unchanged-dirs");
+        let scanner = scanner.with_skip_unchanged_dirs(true);
+        let album = library.join("Artist").join("Album");
+        write_wav(&album.join("one.wav"), "One");
+        write_wav(&album.join("two.wav"), "Two");
+        fs::write(album.join("album.m3u"), "one.wav\ntwo.wav\n").unwrap();
+
+        let first = scanner
+            .scan_all_with_options(None, ScanMode::Incremental)
+            .unwrap();
+        assert_eq!(first.tracks_added, 2);
+        assert_eq!(first.playlists_imported, 1);
+
+        // A file added without changing the directory's mtime is never seen
+        let album_mtime = fs::metadata(&album).unwrap().modified().unwrap();
+        write_wav(&album.join("hidden.wav"), "Hidden");
+        set_modified(&album, album_mtime);
+
+        let second = scanner
+            .scan_all_with_options(None, ScanMode::Incremental)
+            .unwrap();
+        assert_eq!(second.tracks_found, 2);
+        assert_eq!(second.tracks_skipped, 2);
+        assert_eq!(second.tracks_added + second.tracks_updated, 0);
+        assert_eq!(second.tracks_removed, 0);
+        assert_eq!(song_paths(&scanner).len(), 2);
+        // Playlists in skipped directories are kept
+        let playlists = PlaylistRepository::new(scanner.pool.clone())
+            .find_imported_source_paths()
+            .unwrap();
+        assert_eq!(playlists.len(), 1);
+
+        // A changed directory below unchanged ones is still walked
+        set_modified(&album, album_mtime + Duration::from_secs(1));
+        let third = scanner
+            .scan_all_with_options(None, ScanMode::Incremental)
+            .unwrap();
+        assert_eq!(third.tracks_added, 1);
+        assert_eq!(song_paths(&scanner).len(), 3);
+    }
+
+    #[test]
+    fn test_unchanged_cue_rip_is_not_reread() {
+        let (scanner, library) = setup("cue-rip");
+        let rip = library.join("Album").join("rip.wav");
+        write_wav(&rip, "Rip");
+        let cue = rip.with_extension("cue");
+        fs::write(
+            &cue,
+            "FILE \"rip.wav\" WAVE\n\
+             TRACK 01 AUDIO\n  TITLE \"First\"\n  INDEX 01 00:00:00\n\
+             TRACK 02 AUDIO\n  TITLE \"Second\"\n  INDEX 01 00:00:40\n",
+        )
+        .unwrap();
+        let rip_path = rip.to_string_lossy().to_string();
+        let virtual_paths = vec![format!("{}#1", rip_path), format!("{}#2", rip_path)];
+
+        let first = scanner
+            .scan_all_with_options(None, ScanMode::Incremental)
+            .unwrap();
+        assert_eq!(first.tracks_added, 2);
+        assert_eq!(song_paths(&scanner), virtual_paths);
+
+        let second = scanner
+            .scan_all_with_options(None, ScanMode::Incremental)
+            .unwrap();
+        assert_eq!(second.tracks_skipped, 2);
+        assert_eq!(second.tracks_updated + second.tracks_removed, 0);
+        assert_eq!(song_paths(&scanner), virtual_paths);
+
+        // Editing the sheet re-reads the rip
+        let cue_mtime = fs::metadata(&cue).unwrap().modified().unwrap();
+        set_modified(&cue, cue_mtime + Duration::from_secs(10));
+        let third = scanner
+            .scan_all_with_options(None, ScanMode::Incremental)
+            .unwrap();
+        assert_eq!(third.tracks_skipped, 0);
+        assert_eq!(third.tracks_updated, 2);
+        assert_eq!(song_paths(&scanner), virtual_paths);
+    }
+
+    fn song_id(scanner: &Scanner, path: &Path) -> Option<i32> {
+        songs::table
+            .filter(songs::path.eq(path.to_string_lossy()))
+            .select(songs::id)
+            .first(&mut scanner.pool.get().unwrap())
+            .optional()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_moved_file_keeps_song_and_user_data() {
+        let (scanner, library) = setup("moved");
+        let old_path = library.join("Old").join("song.wav");
+        write_wav(&old_path, "Song");
+        scanner
+            .scan_all_with_options(None, ScanMode::Incremental)
+            .unwrap();
+        let id = song_id(&scanner, &old_path).unwrap();
+
+        let user = UserRepository::new(scanner.pool.clone())
+            .find_by_username("admin")
+            .unwrap()
+            .unwrap();
+        StarredRepository::new(scanner.pool.clone())
+            .star_song(user.id, id)
+            .unwrap();
+        let scrobbles = ScrobbleRepository::new(scanner.pool.clone());
+        scrobbles.scrobble(user.id, id, None, true).unwrap();
+
+        // Moved to another directory and renamed
+        let new_path = library.join("New").join("renamed.wav");
+        fs::create_dir_all(new_path.parent().unwrap()).unwrap();
+        fs::rename(&old_path, &new_path).unwrap();
+
+        let result = scanner
+            .scan_all_with_options(None, ScanMode::Incremental)
+            .unwrap();
+        assert_eq!(result.tracks_moved, 1);
+        assert_eq!(result.tracks_added + result.tracks_removed, 0);
+        assert_eq!(song_id(&scanner, &new_path), Some(id));
+        assert_eq!(song_id(&scanner, &old_path), None);
+        assert!(
+            StarredRepository::new(scanner.pool.clone())
+                .is_song_starred(user.id, id)
+                .unwrap()
+        );
+        let stats = scrobbles.get_song_play_stats_batch(user.id, &[id]).unwrap();
+        assert_eq!(stats[&id].play_count, 1);
+
+        // A copy next to a file that still exists is a new song
+        let copy_path = library.join("New").join("copy.wav");
+        fs::copy(&new_path, &copy_path).unwrap();
+        let result = scanner
+            .scan_all_with_options(None, ScanMode::Incremental)
+            .unwrap();
+        assert_eq!(result.tracks_moved, 0);
+        assert_eq!(result.tracks_added, 1);
+        assert_eq!(song_id(&scanner, &new_path), Some(id));
+        assert_ne!(song_id(&scanner, &copy_path), Some(id));
+    }
+
+    #[test]
+    fn test_scan_history_records_outcome_and_failed_files() {
+        let (scanner, library) = setup("history");
+        write_wav(&library.join("good.wav"), "Good");
+        let broken = library.join("broken.wav");
+        fs::write(&broken, b"not audio").unwrap();
+        let history = ScanHistoryRepository::new(scanner.pool.clone());
+
+        let result = scanner.scan_all_with_options(None, ScanMode::Full).unwrap();
+        assert_eq!(result.tracks_failed, 1);
+        let run = &history.find_recent(1).unwrap()[0];
+        assert_eq!(run.status, "completed");
+        assert_eq!(run.mode, "full");
+        assert_eq!(run.tracks_added, 1);
+        assert_eq!(run.tracks_failed, 1);
+        assert!(run.finished_at.is_some());
+        let errors = history.find_errors(run.id).unwrap();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, broken.to_string_lossy());
+
+        // A scan cancelled before it writes anything is recorded as cancelled
+        let state = Arc::new(ScanState::new());
+        assert!(state.try_start());
+        state.cancel();
+        let result = scanner
+            .scan_all_with_options(Some(state), ScanMode::Incremental)
+            .unwrap();
+        assert!(result.cancelled);
+        let run = &history.find_recent(1).unwrap()[0];
+        assert_eq!(run.status, "cancelled");
+        assert_eq!(run.tracks_added, 0);
+    }
+
+    #[test]
+    fn test_abort_stale_runs_keeps_live_scans() {
+        use crate::db::schema::scan_runs;
+
+        let (scanner, _) = setup("stale-runs");
+        let history = ScanHistoryRepository::new(scanner.pool.clone());
+        let live = history.start_run("full", "cli").unwrap();
+        let exited = history.start_run("full", "api").unwrap();
+        let silent = history.start_run("full", "auto").unwrap();
+
+        let mut conn = scanner.pool.get().unwrap();
+        diesel::update(scan_runs::table.filter(scan_runs::id.eq(exited)))
+            .set(scan_runs::pid.eq(i32::MAX))
+            .execute(&mut conn)
+            .unwrap();
+        diesel::update(scan_runs::table.filter(scan_runs::id.eq(silent)))
+            .set(
+                scan_runs::heartbeat_at
+                    .eq(chrono::Utc::now().naive_utc() - chrono::TimeDelta::hours(1)),
+            )
+            .execute(&mut conn)
+            .unwrap();
+
+        assert_eq!(history.abort_stale_runs().unwrap(), 2);
+        let status = |id| history.find_by_id(id).unwrap().unwrap().status;
+        assert_eq!(status(live), "running");
+        assert_eq!(status(exited), "aborted");
+        assert_eq!(status(silent), "aborted");
+
+        // A heartbeat keeps a long scan live
+        history.heartbeat(live).unwrap();
+        assert_eq!(history.abort_stale_runs().unwrap(), 0);
+    }
+
+    /// Check that every album's song count matches its songs.
+    fn assert_album_counts(scanner: &Scanner) {
+        use crate::db::schema::albums;
+
+        let mut conn = scanner.pool.get().unwrap();
+        let albums: Vec<(i32, i32)> = albums::table
+            .select((albums::id, albums::song_count))
+            .load(&mut conn)
+            .unwrap();
+        for (album_id, song_count) in albums {
+            let songs: i64 = songs::table
+                .filter(songs::album_id.eq(album_id))
+                .count()
+                .get_result(&mut conn)
+                .unwrap();
+            assert_eq!(i64::from(song_count), songs);
+        }
+    }
+
+    #[test]
+    fn test_cancelled_scan_keeps_whole_batches() {
+        let (scanner, library) = setup("cancel-batches");
+        let files = 5 * BATCH_SIZE;
+        for i in 0..files {
+            let path = library.join(format!("{:03}.wav", i));
+            if i % 50 == 7 {
+                fs::write(&path, b"not audio").unwrap();
+            } else {
+                write_wav(&path, &format!("Track {}", i));
+            }
+        }
+        let broken = files / 50;
+
+        // Cancel as soon as the first batch is being written (failed files
+        // count towards progress when they are read)
+        let state = Arc::new(ScanState::new());
+        assert!(state.try_start());
+        let result = std::thread::scope(|scope| {
+            let watcher = state.clone();
+            scope.spawn(move || {
+                while watcher.get_count() <= broken as u64 {
+                    std::hint::spin_loop();
+                }
+                watcher.cancel();
+            });
+            scanner
+                .scan_all_with_options(Some(state.clone()), ScanMode::Incremental)
+                .unwrap()
+        });
+        assert!(result.cancelled);
+
+        // Only whole batches were committed, with album stats to match
+        let written = song_paths(&scanner).len();
+        assert_eq!(written, result.tracks_added);
+        assert!(written > 0 && written < files - broken);
+        assert_eq!(written % BATCH_SIZE, 0);
+        assert_album_counts(&scanner);
+        // Directories aren't recorded, so the next scan walks them again
+        let folder = MusicFolderRepository::new(scanner.pool.clone())
+            .find_all()
+            .unwrap()
+            .remove(0);
+        assert!(
+            ScannedDirectoryRepository::new(scanner.pool.clone())
+                .find_by_folder(folder.id)
+                .unwrap()
+                .is_empty()
+        );
+
+        let result = scanner
+            .scan_all_with_options(None, ScanMode::Incremental)
+            .unwrap();
+        assert!(!result.cancelled);
+        assert_eq!(result.tracks_added, files - broken - written);
+        assert_eq!(result.tracks_failed, broken);
+        assert_eq!(song_paths(&scanner).len(), files - broken);
+        assert_album_counts(&scanner);
+    }
+
+    #[test]
+    fn test_changed_excludes_apply_to_unchanged_directories() {
+        let (scanner, library) = setup("excludes");
+        let scanner = scanner.with_skip_unchanged_dirs(true);
+        write_wav(&library.join("Keep").join("keep.wav"), "Keep");
+        write_wav(&library.join("Skip").join("skip.wav"), "Skip");
+        let folders = MusicFolderRepository::new(scanner.pool.clone());
+        let folder_id = folders.find_all().unwrap()[0].id;
+
+        assert!(
+            folders
+                .set_exclude_patterns(folder_id, &["Skip/".to_string()])
+                .unwrap()
+        );
+        let result = scanner
+            .scan_all_with_options(None, ScanMode::Incremental)
+            .unwrap();
+        assert_eq!(result.tracks_added, 1);
+
+        // Nothing changed on disk, but the directory is no longer excluded
+        assert!(folders.set_exclude_patterns(folder_id, &[]).unwrap());
+        assert!(
+            folders
+                .find_by_id(folder_id)
+                .unwrap()
+                .unwrap()
+                .exclude_patterns
+                .is_empty()
+        );
+        let result = scanner
+            .scan_all_with_options(None, ScanMode::Incremental)
+            .unwrap();
+        assert_eq!(result.tracks_added, 1);
+        assert_eq!(song_paths(&scanner).len(), 2);
+
+        assert!(!folders.set_exclude_patterns(folder_id + 1, &[]).unwrap());
+    }
+}
@@ -0,0 +1,260 @@
+//! Artist and album info files.
+//!
+//! Reads biographies and album notes, with any MusicBrainz IDs and image URLs
+//! they reference, from Kodi-style `artist.nfo`/`album.nfo` files and plain
+//! `biography.txt` files kept next to the music.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use quick_xml::Reader;
+use quick_xml::escape::resolve_predefined_entity;
+use quick_xml::events::Event;
+
+use super::playlists::decode_text;
+
+/// Kodi artist info file name.
+pub const ARTIST_NFO: &str = "artist.nfo";
+
+/// Kodi album info file name.
+pub const ALBUM_NFO: &str = "album.nfo";
+
+/// Plain-text artist biography file name.
+pub const BIOGRAPHY_TXT: &str = "biography.txt";
+
+/// Artist details read from `artist.nfo` and `biography.txt`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ArtistNfo {
+    pub biography: Option<String>,
+    pub musicbrainz_id: Option<String>,
+    pub image_url: Option<String>,
+}
+
+/// Album details read from `album.nfo`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AlbumNfo {
+    pub notes: Option<String>,
+    pub musicbrainz_id: Option<String>,
+    pub image_url: Option<String>,
+}
+
+/// Read the artist info files in a directory, if it has any.
+///
+/// `biography.txt` is used when `artist.nfo` has no biography of its own.
+pub fn read_artist_info(dir: &Path) -> Option<ArtistNfo> {
+    let nfo = read_info_file(&dir.join(ARTIST_NFO)).map(|content| parse_artist_nfo(&content));
+    let biography = read_info_file(&dir.join(BIOGRAPHY_TXT))
+        .map(|content| content.trim().to_string())
+        .filter(|text| !text.is_empty());
+
+    if nfo.is_none() && biography.is_none() {
+        return None;
+    }
+
+    let mut info = nfo.unwrap_or_default();
+    if info.biography.is_none() {
+        info.biography = biography;
+    }
+    Some(info)
+}
+
+/// Read the album info file in a directory, if it has one.
+pub fn read_album_info(dir: &Path) -> Option<AlbumNfo> {
+    read_info_file(&dir.join(ALBUM_NFO)).map(|content| parse_album_nfo(&content))
+}
+
+fn read_info_file(path: &Path) -> Option<String> {
+    fs::read(path).ok().map(|bytes| decode_text(&bytes))
+}
+
+/// Parse `artist.nfo` content.
+///
+/// Besides Kodi's XML format, this accepts "URL" nfo files that only hold a
+/// MusicBrainz artist link.
+pub fn parse_artist_nfo(content: &str) -> ArtistNfo {
+    let fields = parse_fields(content);
+
+    ArtistNfo {
+        biography: first_field(&fields, &["biography"]),
+        musicbrainz_id: first_field(&fields, &["musicbrainzartistid"])
+            .filter(|id| is_mbid(id))
+            .or_else(|| find_musicbrainz_link(content, "artist")),
+        image_url: first_image_url(&fields),
+    }
+}
+
+/// Parse `album.nfo` content.
+///
+/// Besides Kodi's XML format, this accepts "URL" nfo files that only hold a
+/// MusicBrainz release link.
+pub fn parse_album_nfo(content: &str) -> AlbumNfo {
+    let fields = parse_fields(content);
+
+    AlbumNfo {
+        notes: first_field(&fields, &["review", "description", "plot", "outline"]),
+        musicbrainz_id: first_field(&fields, &["musicbrainzalbumid"])
+            .filter(|id| is_mbid(id))
+            .or_else(|| find_musicbrainz_link(content, "release")),
+        image_url: first_image_url(&fields),
+    }
+}
+
+/// Collect the text of the root element's children, keyed by lowercase name.
+///
+/// Nested elements such as `<fanart><thumb>` are ignored, and parsing stops
+/// at the first malformed element, keeping what was read before it.
+fn parse_fields(content: &str) -> HashMap<String, Vec<String>> {
+    let mut fields: HashMap<String, Vec<String>> = HashMap::new();
+    let mut reader = Reader::from_str(content);
+    let mut depth = 0usize;
+    let mut current: Option<(String, String)> = None;
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) => {
+                depth += 1;
+                if depth == 2 {
+                    let name = String::from_utf8_lossy(e.local_name().as_ref()).to_lowercase();
+                    current = Some((name, String::new()));
+                }
+            }
+            Ok(Event::End(_)) => {
+                if depth == 2
+                    && let Some((name, text)) = current.take()
+                {
+                    fields
+                        .entry(name)
+                        .or_default()
+                        .push(text.trim().to_string());
+                }
+                depth = depth.saturating_sub(1);
+            }
+            Ok(Event::Text(e)) if depth == 2 => {
+                if let (Some((_, text)), Ok(s)) = (current.as_mut(), e.xml_content()) {
+                    text.push_str(&s);
+                }
+            }
+            Ok(Event::CData(e)) if depth == 2 => {
+                if let (Some((_, text)), Ok(s)) = (current.as_mut(), e.decode()) {
+                    text.push_str(&s);
+                }
+            }
+            Ok(Event::GeneralRef(e)) if depth == 2 => {
+                if let Some((_, text)) = current.as_mut() {
+                    if let Ok(Some(ch)) = e.resolve_char_ref() {
+                        text.push(ch);
+                    } else if let Ok(name) = e.decode()
+                        && let Some(resolved) = resolve_predefined_entity(&name)
+                    {
+                        text.push_str(resolved);
+                    }
+                }
+            }
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+    }
+
+    fields
+}
+
+/// Get the first non-empty value among the given field names.
+fn first_field(fields: &HashMap<String, Vec<String>>, names: &[&str]) -> Option<String> {
+    names
+        .iter()
+        .filter_map(|name| fields.get(*name))
+        .flatten()
+        .find(|value| !value.is_empty())
+        .cloned()
+}
+
+/// Get the first `<thumb>` that is a web URL rather than a local file.
+fn first_image_url(fields: &HashMap<String, Vec<String>>) -> Option<String> {
+    fields
+        .get("thumb")?
+        .iter()
+        .find(|value| value.starts_with("http://") || value.starts_with("https://"))
+        .cloned()
+}
+
+/// Find the MBID in a `musicbrainz.org/<entity>/<mbid>` link.
+fn find_musicbrainz_link(content: &str, entity: &str) -> Option<String> {
+    let marker = format!("musicbrainz.org/{}/", entity);
+    content.match_indices(&marker).find_map(|(start, _)| {
+        let id = content.get(start + marker.len()..start + marker.len() + 36)?;
+        is_mbid(id).then(|| id.to_lowercase())
+    })
+}
+
+/// Check whether a string is a MusicBrainz identifier (a UUID).
+fn is_mbid(id: &str) -> bool {
+    id.len() == 36
+        && id.char_indices().all(|(i, c)| match i {
+            8 | 13 | 18 | 23 => c == '-',
+            _ => c.is_ascii_hexdigit(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_artist_nfo() {
+        let content = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes" ?>
+<artist>
+    <name>Artist</name>
+    <musicBrainzArtistID>b10bbbfc-cf9e-42e0-be17-e2c3e1d2600d</musicBrainzArtistID>
+    <biography>Formed in 1960 &amp; split in 1970.</biography>
+    <thumb aspect="thumb">folder.jpg</thumb>
+    <thumb aspect="thumb">https://example.com/artist.jpg</thumb>
+    <fanart><thumb>https://example.com/fanart.jpg</thumb></fanart>
+</artist>"#;
+
+        let info = parse_artist_nfo(content);
+        assert_eq!(
+            info.biography.as_deref(),
+            Some("Formed in 1960 & split in 1970.")
+        );
+        assert_eq!(
+            info.musicbrainz_id.as_deref(),
+            Some("b10bbbfc-cf9e-42e0-be17-e2c3e1d2600d")
+        );
+        assert_eq!(
+            info.image_url.as_deref(),
+            Some("https://example.com/artist.jpg")
+        );
+    }
+
+    #[test]
+    fn test_parse_album_nfo() {
+        let content = r#"<album>
+    <title>Album</title>
+    <musicbrainzalbumid>not-an-id</musicbrainzalbumid>
+    <review><![CDATA[Recorded <live>.]]></review>
+</album>
+https://musicbrainz.org/release/0B2B5B46-2A45-4C5D-8F0A-1E2D3C4B5A69"#;
+
+        let info = parse_album_nfo(content);
+        assert_eq!(info.notes.as_deref(), Some("Recorded <live>."));
+        assert_eq!(
+            info.musicbrainz_id.as_deref(),
+            Some("0b2b5b46-2a45-4c5d-8f0a-1e2d3c4b5a69")
+        );
+        assert_eq!(info.image_url, None);
+    }
+
+    #[test]
+    fn test_parse_url_nfo() {
+        let content =
+            "https://musicbrainz.org/release-group/0b2b5b46-2a45-4c5d-8f0a-1e2d3c4b5a69\n";
+        assert_eq!(parse_album_nfo(content), AlbumNfo::default());
+
+        let content = "https://musicbrainz.org/artist/b10bbbfc-cf9e-42e0-be17-e2c3e1d2600d";
+        assert_eq!(
+            parse_artist_nfo(content).musicbrainz_id.as_deref(),
+            Some("b10bbbfc-cf9e-42e0-be17-e2c3e1d2600d")
+        );
+    }
+}
@@ -0,0 +1,288 @@
+//! Playlist file import.
+//!
+//! Parses `.m3u`, `.m3u8` and `.pls` files found in music folders and resolves
+//! their entries against the paths of scanned songs.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// Supported playlist file extensions.
+pub const PLAYLIST_EXTENSIONS: &[&str] = &["m3u", "m3u8", "pls"];
+
+/// Settings for importing playlist files found in music folders.
+#[derive(Debug, Clone, Default)]
+pub struct PlaylistImportConfig {
+    /// Username that owns imported playlists. Defaults to the first admin user.
+    pub owner: Option<String>,
+    /// Whether imported playlists are public (visible to all users).
+    pub public: bool,
+}
+
+/// A playlist file parsed from disk.
+#[derive(Debug, Clone)]
+pub struct ParsedPlaylist {
+    /// Playlist name (from `#PLAYLIST:` or the file name).
+    pub name: String,
+    /// Raw entries in playlist order.
+    pub entries: Vec<String>,
+}
+
+/// Read and parse a playlist file.
+pub fn read_playlist_file(path: &Path) -> std::io::Result<ParsedPlaylist> {
+    let bytes = fs::read(path)?;
+    let content = decode_text(&bytes);
+
+    let is_pls = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("pls"));
+
+    let (name, entries) = if is_pls {
+        (None, parse_pls(&content))
+    } else {
+        parse_m3u(&content)
+    };
+
+    let name = name.unwrap_or_else(|| {
+        path.file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("Playlist")
+            .to_string()
+    });
+
+    Ok(ParsedPlaylist { name, entries })
+}
+
+/// Decode playlist or CUE text, falling back to Latin-1 for legacy files.
+pub(crate) fn decode_text(bytes: &[u8]) -> String {
+    let bytes = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bytes);
+    match std::str::from_utf8(bytes) {
+        Ok(s) => s.to_string(),
+        Err(_) => bytes.iter().map(|&b| b as char).collect(),
+    }
+}
+
+/// Parse M3U/M3U8 content into an optional name and its entries.
+pub fn parse_m3u(content: &str) -> (Option<String>, Vec<String>) {
+    let mut name = None;
+    let mut entries = Vec::new();
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(rest) = line.strip_prefix('#') {
+            if let Some(title) = rest.strip_prefix("PLAYLIST:") {
+                let title = title.trim();
+                if !title.is_empty() {
+                    name = Some(title.to_string());
+                }
+            }
+            continue;
+        }
+        entries.push(line.to_string());
+    }
+
+    (name, entries)
+}
+
+/// Parse PLS content into its entries, ordered by their `FileN` index.
+pub fn parse_pls(content: &str) -> Vec<String> {
+    let mut files: Vec<(u32, String)> = Vec::new();
+
+    for line in content.lines() {
+        let line = line.trim();
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let key = key.trim();
+        let value = value.trim();
+        if let Some(index) = key
+            .get(..4)
+            .filter(|prefix| prefix.eq_ignore_ascii_case("file"))
+            .and_then(|_| key[4..].parse::<u32>().ok())
+            && !value.is_empty()
+        {
+            files.push((index, value.to_string()));
+        }
+    }
+
+    files.sort_by_key(|(index, _)| *index);
+    files.into_iter().map(|(_, file)| file).collect()
+}
+
+/// Index of scanned song paths used to resolve playlist entries.
+pub struct SongPathIndex {
+    by_path: HashMap<String, i32>,
+    by_file_name: HashMap<String, Vec<(String, i32)>>,
+}
+
+impl SongPathIndex {
+    /// Build an index from (path, song_id) pairs.
+    pub fn new(songs: impl IntoIterator<Item = (String, i32)>) -> Self {
+        let mut by_path = HashMap::new();
+        let mut by_file_name: HashMap<String, Vec<(String, i32)>> = HashMap::new();
+
+        for (path, id) in songs {
+            if let Some(file_name) = Path::new(&path).file_name().and_then(|n| n.to_str()) {
+                by_file_name
+                    .entry(file_name.to_lowercase())
+                    .or_default()
+                    .push((path.clone(), id));
+            }
+            by_path.insert(path, id);
+        }
+
+        Self {
+            by_path,
+            by_file_name,
+        }
+    }
+
+    /// Resolve a playlist entry to a song ID.
+    ///
+    /// Absolute entries are matched directly, relative entries are resolved
+    /// against the playlist's directory. Entries that don't match exactly
+    /// (such as Windows paths from another machine) fall back to the single
+    /// song whose path shares the longest trailing run of path components.
+    pub fn resolve(&self, entry: &str, playlist_dir: &Path) -> Option<i32> {
+        let entry = entry.trim();
+        if entry.is_empty() || entry.contains("://") {
+            return None;
+        }
+
+        let normalized = entry.replace('\\', "/");
+        let is_windows_absolute = normalized.as_bytes().get(1) == Some(&b':');
+
+        if !is_windows_absolute {
+            let candidate = if normalized.starts_with('/') {
+                PathBuf::from(&normalized)
+            } else {
+                playlist_dir.join(&normalized)
+            };
+            let candidate = normalize_path(&candidate);
+            if let Some(&id) = self.by_path.get(candidate.to_string_lossy().as_ref()) {
+                return Some(id);
+            }
+        }
+
+        self.resolve_by_suffix(&normalized)
+    }
+
+    /// Match an entry by its trailing path components.
+    ///
+    /// At least one parent directory must match besides the file name, and the
+    /// entry is left unresolved when several songs share the best match.
+    fn resolve_by_suffix(&self, normalized: &str) -> Option<i32> {
+        let entry_parts: Vec<String> = normalized
+            .split('/')
+            .filter(|p| !p.is_empty() && *p != "." && *p != "..")
+            .map(|p| p.to_lowercase())
+            .collect();
+        let file_name = entry_parts.last()?;
+        let candidates = self.by_file_name.get(file_name)?;
+
+        let mut best: Option<(usize, i32)> = None;
+        let mut tied = false;
+        for (path, id) in candidates {
+            let matched = path
+                .to_lowercase()
+                .split('/')
+                .rev()
+                .zip(entry_parts.iter().rev())
+                .take_while(|(a, b)| a == *b)
+                .count();
+            match best {
+                Some((best_matched, _)) if matched < best_matched => {}
+                Some((best_matched, _)) if matched == best_matched => tied = true,
+                _ => {
+                    best = Some((matched, *id));
+                    tied = false;
+                }
+            }
+        }
+
+        match best {
+            Some((matched, id)) if matched >= 2 && !tied => Some(id),
+            _ => None,
+        }
+    }
+}
+
+/// Lexically normalize a path, resolving `.` and `..` components.
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut result = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                result.pop();
+            }
+            other => result.push(other),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_m3u() {
+        let content = "#EXTM3U\n#PLAYLIST:Road Trip\n#EXTINF:123,Artist - Song\nsong1.mp3\n\nsub/song2.flac\n";
+        let (name, entries) = parse_m3u(content);
+        assert_eq!(name.as_deref(), Some("Road Trip"));
+        assert_eq!(entries, vec!["song1.mp3", "sub/song2.flac"]);
+    }
+
+    #[test]
+    fn test_parse_pls() {
+        let content =
+            "[playlist]\nFile2=b.mp3\nTitle2=B\nFile1=a.mp3\nNumberOfEntries=2\nVersion=2\n";
+        assert_eq!(parse_pls(content), vec!["a.mp3", "b.mp3"]);
+    }
+
+    #[test]
+    fn test_resolve_entries() {
+        let index = SongPathIndex::new(vec![
+            ("/music/Artist/Album/01 Song.flac".to_string(), 1),
+            ("/music/Other/Album/01 Song.flac".to_string(), 2),
+            ("/music/Artist/Album/02 Next.flac".to_string(), 3),
+        ]);
+        let dir = Path::new("/music/Playlists");
+
+        assert_eq!(
+            index.resolve("/music/Artist/Album/01 Song.flac", dir),
+            Some(1)
+        );
+        assert_eq!(index.resolve("../Other/Album/01 Song.flac", dir), Some(2));
+        assert_eq!(
+            index.resolve("D:\\Music\\Artist\\Album\\02 Next.flac", dir),
+            Some(3)
+        );
+        assert_eq!(index.resolve("http://radio.example/stream", dir), None);
+        assert_eq!(index.resolve("missing.mp3", dir), None);
+    }
+
+    #[test]
+    fn test_resolve_ambiguous_suffix() {
+        let index = SongPathIndex::new(vec![
+            ("/music/Artist/First/01 - Intro.flac".to_string(), 1),
+            ("/music/Other/Second/01 - Intro.flac".to_string(), 2),
+            ("/music/Artist/Third/track01.mp3".to_string(), 3),
+        ]);
+        let dir = Path::new("/music/Playlists");
+
+        // Only the file name matches: never guess.
+        assert_eq!(index.resolve("C:\\Rips\\01 - Intro.flac", dir), None);
+        assert_eq!(index.resolve("E:\\Elsewhere\\track01.mp3", dir), None);
+        // Parent directory disambiguates.
+        assert_eq!(
+            index.resolve("C:\\Rips\\Second\\01 - Intro.flac", dir),
+            Some(2)
+        );
+    }
+}
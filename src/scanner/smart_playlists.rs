@@ -0,0 +1,658 @@
+//! Smart playlist rules.
+//!
+//! Smart playlists are defined by rules rather than fixed entries, using the
+//! JSON format of Navidrome's `.nsp` files:
+//!
+//! ```json
+//! {
+//!   "name": "Jazz favourites",
+//!   "all": [
+//!     {"is": {"genre": "Jazz"}},
+//!     {"gt": {"rating": 3}},
+//!     {"notInTheLast": {"lastPlayed": 90}}
+//!   ],
+//!   "sort": "random",
+//!   "limit": 100
+//! }
+//! ```
+//!
+//! Rules are combined with `all` or `any`, which can be nested. Rules on
+//! ratings, play counts and stars apply to the playlist's owner.
+
+use std::fs;
+use std::path::Path;
+
+use chrono::{NaiveDate, NaiveDateTime, TimeDelta, Utc};
+use serde_json::{Map, Value};
+use thiserror::Error;
+
+use super::playlists::decode_text;
+
+/// File extension of smart playlist files found in music folders.
+pub const SMART_PLAYLIST_EXTENSION: &str = "nsp";
+
+/// Default number of seconds the songs of a smart playlist are kept before
+/// its rules are evaluated again.
+pub const DEFAULT_REFRESH_TTL_SECONDS: i64 = 60;
+
+/// Errors in a smart playlist definition.
+#[derive(Debug, Error)]
+pub enum SmartPlaylistError {
+    #[error("Invalid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("{0}")]
+    Invalid(String),
+}
+
+fn invalid<T>(message: impl Into<String>) -> Result<T, SmartPlaylistError> {
+    Err(SmartPlaylistError::Invalid(message.into()))
+}
+
+/// A song attribute rules can test and playlists can be sorted by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Title,
+    Album,
+    Artist,
+    Genre,
+    FileType,
+    FilePath,
+    Year,
+    TrackNumber,
+    DiscNumber,
+    /// Duration in seconds.
+    Duration,
+    /// Bit rate in kbps.
+    BitRate,
+    /// File size in bytes.
+    Size,
+    /// The owner's rating, 0 if unrated.
+    Rating,
+    /// The owner's number of plays.
+    PlayCount,
+    DateAdded,
+    DateModified,
+    /// When the owner last played the song.
+    LastPlayed,
+    /// When the owner starred the song.
+    DateLoved,
+    /// Whether the owner starred the song.
+    Loved,
+}
+
+/// The kind of values a field holds, which decides the operators it supports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FieldKind {
+    Text,
+    Number,
+    Date,
+    Bool,
+}
+
+impl Field {
+    /// Parse a field name, ignoring case (`lastPlayed` or `lastplayed`).
+    pub fn parse(name: &str) -> Option<Self> {
+        let field = match name.to_ascii_lowercase().as_str() {
+            "title" => Self::Title,
+            "album" => Self::Album,
+            "artist" => Self::Artist,
+            "genre" => Self::Genre,
+            "filetype" | "suffix" => Self::FileType,
+            "filepath" | "path" => Self::FilePath,
+            "year" => Self::Year,
+            "tracknumber" => Self::TrackNumber,
+            "discnumber" => Self::DiscNumber,
+            "duration" => Self::Duration,
+            "bitrate" => Self::BitRate,
+            "size" => Self::Size,
+            "rating" => Self::Rating,
+            "playcount" => Self::PlayCount,
+            "dateadded" => Self::DateAdded,
+            "datemodified" => Self::DateModified,
+            "lastplayed" => Self::LastPlayed,
+            "dateloved" => Self::DateLoved,
+            "loved" => Self::Loved,
+            _ => return None,
+        };
+        Some(field)
+    }
+
+    fn kind(self) -> FieldKind {
+        match self {
+            Self::Title
+            | Self::Album
+            | Self::Artist
+            | Self::Genre
+            | Self::FileType
+            | Self::FilePath => FieldKind::Text,
+            Self::Year
+            | Self::TrackNumber
+            | Self::DiscNumber
+            | Self::Duration
+            | Self::BitRate
+            | Self::Size
+            | Self::Rating
+            | Self::PlayCount => FieldKind::Number,
+            Self::DateAdded | Self::DateModified | Self::LastPlayed | Self::DateLoved => {
+                FieldKind::Date
+            }
+            Self::Loved => FieldKind::Bool,
+        }
+    }
+}
+
+/// A test on a text field. Matching ignores case.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TextCondition {
+    Is(String),
+    IsNot(String),
+    Contains(String),
+    NotContains(String),
+    StartsWith(String),
+    EndsWith(String),
+}
+
+/// A test on a number field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumberCondition {
+    Is(i64),
+    IsNot(i64),
+    Gt(i64),
+    Lt(i64),
+    /// Between two values, inclusive.
+    InTheRange(i64, i64),
+}
+
+/// A test on a date field. Relative conditions count days back from when
+/// the rules are evaluated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateCondition {
+    Before(NaiveDateTime),
+    After(NaiveDateTime),
+    /// Within the last number of days.
+    InTheLast(i64),
+    /// Not within the last number of days, including never.
+    NotInTheLast(i64),
+    /// Between two dates, inclusive.
+    InTheRange(NaiveDateTime, NaiveDateTime),
+}
+
+impl DateCondition {
+    /// Start of the span of a relative condition evaluated at `now`.
+    pub fn since(days: i64, now: NaiveDateTime) -> NaiveDateTime {
+        now - TimeDelta::days(days)
+    }
+}
+
+/// The test a rule applies to its field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Condition {
+    Text(TextCondition),
+    Number(NumberCondition),
+    Date(DateCondition),
+    Bool(bool),
+}
+
+/// A single rule, such as "genre is Jazz".
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    pub field: Field,
+    pub condition: Condition,
+}
+
+/// Rules combined into the criteria songs must meet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Criteria {
+    /// Songs must meet all of the criteria.
+    All(Vec<Criteria>),
+    /// Songs must meet at least one of the criteria.
+    Any(Vec<Criteria>),
+    Rule(Rule),
+}
+
+/// What songs are ordered by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortBy {
+    Field(Field),
+    Random,
+}
+
+/// One key of a smart playlist's order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SortKey {
+    pub by: SortBy,
+    pub descending: bool,
+}
+
+/// A parsed smart playlist definition.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SmartPlaylistRules {
+    /// Playlist name, if the definition has one.
+    pub name: Option<String>,
+    pub comment: Option<String>,
+    pub criteria: Criteria,
+    /// Order of the songs, by title if empty.
+    pub sort: Vec<SortKey>,
+    /// Maximum number of songs.
+    pub limit: Option<i64>,
+    /// Number of matching songs to skip.
+    pub offset: Option<i64>,
+}
+
+impl SmartPlaylistRules {
+    /// Parse a smart playlist definition from JSON.
+    pub fn parse(json: &str) -> Result<Self, SmartPlaylistError> {
+        let value: Value = serde_json::from_str(json)?;
+        let Some(object) = value.as_object() else {
+            return invalid("A smart playlist must be a JSON object");
+        };
+
+        let criteria = match (object.get("all"), object.get("any")) {
+            (Some(_), Some(_)) => return invalid("Use either \"all\" or \"any\", not both"),
+            (Some(rules), None) => Criteria::All(parse_criteria_list(rules)?),
+            (None, Some(rules)) => Criteria::Any(parse_criteria_list(rules)?),
+            (None, None) => return invalid("A smart playlist needs \"all\" or \"any\" rules"),
+        };
+
+        let descending = match object.get("order").map(|o| o.as_str()) {
+            None => false,
+            Some(Some(order)) if order.eq_ignore_ascii_case("asc") => false,
+            Some(Some(order)) if order.eq_ignore_ascii_case("desc") => true,
+            Some(_) => return invalid("\"order\" must be \"asc\" or \"desc\""),
+        };
+        let sort = match object.get("sort") {
+            None => Vec::new(),
+            Some(Value::String(sort)) => parse_sort(sort, descending)?,
+            Some(_) => return invalid("\"sort\" must be a string"),
+        };
+
+        Ok(Self {
+            name: optional_string(object, "name")?,
+            comment: optional_string(object, "comment")?,
+            criteria,
+            sort,
+            limit: optional_count(object, "limit")?,
+            offset: optional_count(object, "offset")?,
+        })
+    }
+}
+
+/// A smart playlist file parsed from disk.
+#[derive(Debug, Clone)]
+pub struct SmartPlaylistFile {
+    /// Playlist name (from the definition or the file name).
+    pub name: String,
+    pub comment: Option<String>,
+    /// The definition's JSON, as stored with the playlist.
+    pub definition: String,
+}
+
+/// Read and validate a smart playlist file.
+pub fn read_smart_playlist_file(path: &Path) -> Result<SmartPlaylistFile, SmartPlaylistError> {
+    let bytes = fs::read(path).map_err(|e| SmartPlaylistError::Invalid(e.to_string()))?;
+    let definition = decode_text(&bytes);
+    let rules = SmartPlaylistRules::parse(&definition)?;
+
+    let name = rules.name.unwrap_or_else(|| {
+        path.file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("Smart Playlist")
+            .to_string()
+    });
+
+    Ok(SmartPlaylistFile {
+        name,
+        comment: rules.comment,
+        definition,
+    })
+}
+
+fn optional_string(
+    object: &Map<String, Value>,
+    key: &str,
+) -> Result<Option<String>, SmartPlaylistError> {
+    match object.get(key) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(s)) => Ok(Some(s.trim().to_string()).filter(|s| !s.is_empty())),
+        Some(_) => invalid(format!("\"{}\" must be a string", key)),
+    }
+}
+
+fn optional_count(
+    object: &Map<String, Value>,
+    key: &str,
+) -> Result<Option<i64>, SmartPlaylistError> {
+    match object.get(key) {
+        None | Some(Value::Null) => Ok(None),
+        Some(value) => match value.as_i64() {
+            Some(n) if n >= 0 => Ok(Some(n)),
+            _ => invalid(format!("\"{}\" must be a non-negative number", key)),
+        },
+    }
+}
+
+fn parse_criteria_list(value: &Value) -> Result<Vec<Criteria>, SmartPlaylistError> {
+    let Some(items) = value.as_array() else {
+        return invalid("\"all\" and \"any\" must be lists of rules");
+    };
+    items.iter().map(parse_criteria).collect()
+}
+
+/// Parse a nested `all`/`any` group or a rule like `{"is": {"genre": "Jazz"}}`.
+fn parse_criteria(value: &Value) -> Result<Criteria, SmartPlaylistError> {
+    let Some((key, body)) = value
+        .as_object()
+        .filter(|o| o.len() == 1)
+        .and_then(|o| o.iter().next())
+    else {
+        return invalid(format!("Invalid rule: {}", value));
+    };
+
+    match key.to_ascii_lowercase().as_str() {
+        "all" => return Ok(Criteria::All(parse_criteria_list(body)?)),
+        "any" => return Ok(Criteria::Any(parse_criteria_list(body)?)),
+        _ => {}
+    }
+
+    let Some((field_name, operand)) = body
+        .as_object()
+        .filter(|o| o.len() == 1)
+        .and_then(|o| o.iter().next())
+    else {
+        return invalid(format!("Invalid rule: {}", value));
+    };
+    let Some(field) = Field::parse(field_name) else {
+        return invalid(format!("Unknown field: {}", field_name));
+    };
+
+    let condition = parse_condition(field, key, operand)?;
+    Ok(Criteria::Rule(Rule { field, condition }))
+}
+
+fn parse_condition(
+    field: Field,
+    operator: &str,
+    operand: &Value,
+) -> Result<Condition, SmartPlaylistError> {
+    let op = operator.to_ascii_lowercase();
+    let unsupported = || {
+        invalid(format!(
+            "Operator {} does not apply to {:?}",
+            operator, field
+        ))
+    };
+
+    match field.kind() {
+        FieldKind::Text => {
+            let value = text_operand(operand)?;
+            let condition = match op.as_str() {
+                "is" => TextCondition::Is(value),
+                "isnot" => TextCondition::IsNot(value),
+                "contains" => TextCondition::Contains(value),
+                "notcontains" => TextCondition::NotContains(value),
+                "startswith" => TextCondition::StartsWith(value),
+                "endswith" => TextCondition::EndsWith(value),
+                _ => return unsupported(),
+            };
+            Ok(Condition::Text(condition))
+        }
+        FieldKind::Number => {
+            let condition = match op.as_str() {
+                "is" => NumberCondition::Is(number_operand(operand)?),
+                "isnot" => NumberCondition::IsNot(number_operand(operand)?),
+                "gt" => NumberCondition::Gt(number_operand(operand)?),
+                "lt" => NumberCondition::Lt(number_operand(operand)?),
+                "intherange" => {
+                    let (from, to) = range_operand(operand, number_operand)?;
+                    NumberCondition::InTheRange(from.min(to), from.max(to))
+                }
+                _ => return unsupported(),
+            };
+            Ok(Condition::Number(condition))
+        }
+        FieldKind::Date => {
+            let condition = match op.as_str() {
+                "before" => DateCondition::Before(date_operand(operand)?),
+                "after" => DateCondition::After(date_operand(operand)?),
+                "inthelast" => DateCondition::InTheLast(days_operand(operand)?),
+                "notinthelast" => DateCondition::NotInTheLast(days_operand(operand)?),
+                "intherange" => {
+                    let (from, to) = range_operand(operand, date_operand)?;
+                    // The range runs to the end of its last day
+                    if from
+                        .max(to)
+                        .checked_add_signed(TimeDelta::days(1))
+                        .is_none()
+                    {
+                        return invalid(format!("Date out of range: {}", operand));
+                    }
+                    DateCondition::InTheRange(from.min(to), from.max(to))
+                }
+                _ => return unsupported(),
+            };
+            Ok(Condition::Date(condition))
+        }
+        FieldKind::Bool => {
+            let value = match operand {
+                Value::Bool(b) => *b,
+                Value::String(s) if s.eq_ignore_ascii_case("true") => true,
+                Value::String(s) if s.eq_ignore_ascii_case("false") => false,
+                _ => return invalid(format!("Expected true or false, got {}", operand)),
+            };
+            match op.as_str() {
+                "is" => Ok(Condition::Bool(value)),
+                "isnot" => Ok(Condition::Bool(!value)),
+                _ => unsupported(),
+            }
+        }
+    }
+}
+
+fn text_operand(operand: &Value) -> Result<String, SmartPlaylistError> {
+    match operand {
+        Value::String(s) => Ok(s.clone()),
+        Value::Number(n) => Ok(n.to_string()),
+        _ => invalid(format!("Expected text, got {}", operand)),
+    }
+}
+
+fn number_operand(operand: &Value) -> Result<i64, SmartPlaylistError> {
+    let number = match operand {
+        Value::Number(n) => n.as_i64().or_else(|| n.as_f64().map(|f| f.round() as i64)),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    };
+    number.map_or_else(
+        || invalid(format!("Expected a number, got {}", operand)),
+        Ok,
+    )
+}
+
+/// Parse a number of days, which must reach back to a representable date.
+fn days_operand(operand: &Value) -> Result<i64, SmartPlaylistError> {
+    let days = number_operand(operand)?;
+    let representable = days >= 0
+        && TimeDelta::try_days(days)
+            .and_then(|span| Utc::now().naive_utc().checked_sub_signed(span))
+            .is_some();
+    if !representable {
+        return invalid(format!("Expected a number of days, got {}", operand));
+    }
+    Ok(days)
+}
+
+/// Parse a date like `2024-01-31` (midnight) or `2024-01-31T12:00:00`.
+fn date_operand(operand: &Value) -> Result<NaiveDateTime, SmartPlaylistError> {
+    let date = operand.as_str().map(str::trim).and_then(|s| {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d")
+            .ok()
+            .and_then(|d| d.and_hms_opt(0, 0, 0))
+            .or_else(|| NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S").ok())
+            .or_else(|| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").ok())
+    });
+    date.map_or_else(|| invalid(format!("Expected a date, got {}", operand)), Ok)
+}
+
+fn range_operand<T>(
+    operand: &Value,
+    parse: fn(&Value) -> Result<T, SmartPlaylistError>,
+) -> Result<(T, T), SmartPlaylistError> {
+    match operand.as_array().map(Vec::as_slice) {
+        Some([from, to]) => Ok((parse(from)?, parse(to)?)),
+        _ => invalid(format!("Expected a list of two values, got {}", operand)),
+    }
+}
+
+/// Parse a sort like `"-rating,title"` or `"random"`. A `-` prefix sorts a
+/// field descending; `descending` reverses every key.
+fn parse_sort(sort: &str, descending: bool) -> Result<Vec<SortKey>, SmartPlaylistError> {
+    sort.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|key| {
+            let (name, reversed) = match key.strip_prefix('-') {
+                Some(name) => (name.trim(), true),
+                None => (key.strip_prefix('+').unwrap_or(key).trim(), false),
+            };
+            let by = if name.eq_ignore_ascii_case("random") {
+                SortBy::Random
+            } else {
+                match Field::parse(name) {
+                    Some(field) => SortBy::Field(field),
+                    None => return invalid(format!("Unknown sort field: {}", name)),
+                }
+            };
+            Ok(SortKey {
+                by,
+                descending: reversed != descending,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDateTime {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d")
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_parse_rules() {
+        let rules = SmartPlaylistRules::parse(
+            r#"{
+                "name": "Jazz",
+                "all": [
+                    {"is": {"Genre": "Jazz"}},
+                    {"gt": {"rating": 3}},
+                    {"notInTheLast": {"lastPlayed": 90}},
+                    {"any": [
+                        {"inTheRange": {"year": [1969, 1959]}},
+                        {"is": {"loved": true}}
+                    ]}
+                ],
+                "sort": "random",
+                "limit": 100
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(rules.name.as_deref(), Some("Jazz"));
+        assert_eq!(rules.limit, Some(100));
+        assert_eq!(
+            rules.sort,
+            vec![SortKey {
+                by: SortBy::Random,
+                descending: false
+            }]
+        );
+        assert_eq!(
+            rules.criteria,
+            Criteria::All(vec![
+                Criteria::Rule(Rule {
+                    field: Field::Genre,
+                    condition: Condition::Text(TextCondition::Is("Jazz".into())),
+                }),
+                Criteria::Rule(Rule {
+                    field: Field::Rating,
+                    condition: Condition::Number(NumberCondition::Gt(3)),
+                }),
+                Criteria::Rule(Rule {
+                    field: Field::LastPlayed,
+                    condition: Condition::Date(DateCondition::NotInTheLast(90)),
+                }),
+                Criteria::Any(vec![
+                    Criteria::Rule(Rule {
+                        field: Field::Year,
+                        condition: Condition::Number(NumberCondition::InTheRange(1959, 1969)),
+                    }),
+                    Criteria::Rule(Rule {
+                        field: Field::Loved,
+                        condition: Condition::Bool(true),
+                    }),
+                ]),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_dates_and_sort() {
+        let rules = SmartPlaylistRules::parse(
+            r#"{"any": [{"after": {"dateAdded": "2024-01-31"}},
+                        {"isNot": {"loved": true}}],
+                "sort": "-playCount, title", "order": "desc"}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            rules.criteria,
+            Criteria::Any(vec![
+                Criteria::Rule(Rule {
+                    field: Field::DateAdded,
+                    condition: Condition::Date(DateCondition::After(date("2024-01-31"))),
+                }),
+                Criteria::Rule(Rule {
+                    field: Field::Loved,
+                    condition: Condition::Bool(false),
+                }),
+            ])
+        );
+        assert_eq!(
+            rules.sort,
+            vec![
+                SortKey {
+                    by: SortBy::Field(Field::PlayCount),
+                    descending: false
+                },
+                SortKey {
+                    by: SortBy::Field(Field::Title),
+                    descending: true
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_rules() {
+        for json in [
+            "[]",
+            "{}",
+            r#"{"all": [{"is": {"mood": "happy"}}]}"#,
+            r#"{"all": [{"contains": {"year": 1990}}]}"#,
+            r#"{"all": [{"gt": {"rating": "high"}}]}"#,
+            r#"{"all": [{"before": {"dateAdded": "yesterday"}}]}"#,
+            r#"{"all": [{"is": {"genre": "Jazz", "year": 1990}}]}"#,
+            r#"{"all": [], "sort": "mood"}"#,
+            r#"{"all": [], "limit": -1}"#,
+            r#"{"all": [{"inTheLast": {"lastPlayed": 100000000}}]}"#,
+            r#"{"all": [{"notInTheLast": {"lastPlayed": 9223372036854775807}}]}"#,
+        ] {
+            assert!(SmartPlaylistRules::parse(json).is_err(), "{}", json);
+        }
+    }
+}